@@ -0,0 +1,2020 @@
+//! Request and notification handling for the language server.
+
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::call_hierarchy::{self, Node};
+use crate::code_actions;
+use crate::commands::{self, RenamePrefixRequest, SetOptionRequest};
+use crate::completion::{self, CompletionOptions, IncludeScope};
+use crate::configuration::{
+    merged_annotations, resolve_snippets, IncludeOptions, Snippet, SnippetCache, WorkspaceCache,
+};
+use crate::diagnostics::{check_platform, is_deployment_lint, limit_diagnostics, DiagnosticReport};
+use crate::document_store::DocumentStore;
+use crate::file_utilities::{get_workspace_config_files, include_path, normalize_uri, path_to_uri, uri_to_path};
+use crate::formatter::{self, FormattingOptions};
+use crate::grammar;
+use crate::hover;
+use crate::inlay_hints;
+use crate::json::Value;
+use crate::json_object;
+use crate::language_types::annotations::{parse_version, Annotation};
+use crate::language_types::{Element, ParsedConfiguration};
+use crate::linked_editing;
+use crate::log_event;
+use crate::logging::Level;
+use crate::lsp_types::{
+    CodeAction, CodeActionKind, CodeLens, Command, Diagnostic, DiagnosticSeverity, FromJson, Hover, Location,
+    LspResult, MessageType, Position, Range, ResponseError, SymbolInformation, TextDocumentContentChangeEvent,
+    TextDocumentSyncKind, TextEdit, ToJson, WorkspaceEdit,
+};
+use crate::parser::object_parser::is_valid_identifier;
+use crate::parser::parse_annotations;
+use crate::selection_ranges;
+use crate::semantic_tokens;
+use crate::settings::{ServerSettings, ValidationMode};
+use crate::snapshot::ParseSnapshot;
+use crate::structure;
+use crate::symbols::{self, symbol_locations, BlockEntry, SymbolEntry, SymbolTable};
+use crate::syslog_ng;
+use crate::trace::{trace_message, SampleMessage};
+use crate::transport::{read_message, Client};
+
+pub struct Backend {
+    client: Client,
+    documents: DocumentStore,
+    snippets: SnippetCache,
+    workspace_cache: WorkspaceCache,
+    workspace_folders: Vec<PathBuf>,
+    settings: ServerSettings,
+    /// The client can expand snippet placeholders in completion items.
+    snippet_support: bool,
+    /// The client lets the server register file watchers.
+    watched_files_registration: bool,
+    /// The client can create files as part of a workspace edit.
+    create_files_support: bool,
+    /// Analysis of the current revision of every open document, keyed by
+    /// its URI.
+    snapshots: HashMap<String, Rc<ParseSnapshot>>,
+    /// Results of `syslog-ng --syntax-only` per open document, from when it
+    /// was last opened or saved.
+    external_reports: HashMap<String, DiagnosticReport>,
+    /// Configuration version of the syslog-ng binary, once asked for.
+    binary_version: Option<Option<String>>,
+    shutdown_requested: bool,
+}
+
+impl Backend {
+    pub fn new(client: Client) -> Self {
+        Backend {
+            client,
+            documents: DocumentStore::default(),
+            snippets: SnippetCache::default(),
+            workspace_cache: WorkspaceCache::default(),
+            workspace_folders: Vec::new(),
+            settings: ServerSettings::default(),
+            snippet_support: false,
+            watched_files_registration: false,
+            create_files_support: false,
+            snapshots: HashMap::new(),
+            external_reports: HashMap::new(),
+            binary_version: None,
+            shutdown_requested: false,
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> LspResult<Value> {
+        let folders = params["workspaceFolders"].as_array().into_iter().flatten();
+        self.workspace_folders = folders
+            .filter_map(|f| f["uri"].as_str().and_then(uri_to_path))
+            .collect();
+        if self.workspace_folders.is_empty() {
+            self.workspace_folders
+                .extend(params["rootUri"].as_str().and_then(uri_to_path));
+        }
+        self.settings.update(&params["initializationOptions"]);
+        self.snippet_support = params["capabilities"]["textDocument"]["completion"]["completionItem"]["snippetSupport"]
+            .as_bool()
+            .unwrap_or(false);
+        self.watched_files_registration = params["capabilities"]["workspace"]["didChangeWatchedFiles"]
+            ["dynamicRegistration"]
+            .as_bool()
+            .unwrap_or(false);
+        let workspace_edit = &params["capabilities"]["workspace"]["workspaceEdit"];
+        self.create_files_support = workspace_edit["documentChanges"].as_bool().unwrap_or(false)
+            && workspace_edit["resourceOperations"]
+                .as_array()
+                .is_some_and(|operations| operations.iter().any(|o| o.as_str() == Some("create")));
+
+        Ok(json_object! {
+            "capabilities" => json_object! {
+                "textDocumentSync" => json_object! {
+                    "openClose" => true,
+                    "change" => TextDocumentSyncKind::Incremental as u32,
+                    "save" => json_object! { "includeText" => false },
+                },
+                "hoverProvider" => true,
+                "completionProvider" => json_object! {
+                    "triggerCharacters" => completion::TRIGGER_CHARACTERS.to_vec(),
+                    "resolveProvider" => true,
+                },
+                "documentSymbolProvider" => true,
+                "inlayHintProvider" => true,
+                "selectionRangeProvider" => true,
+                "linkedEditingRangeProvider" => true,
+                "callHierarchyProvider" => true,
+                "documentFormattingProvider" => true,
+                "documentRangeFormattingProvider" => true,
+                "codeActionProvider" => json_object! { "codeActionKinds" => vec!["quickfix", "refactor.extract", "source.fixAll"] },
+                "codeLensProvider" => json_object! { "resolveProvider" => false },
+                "workspaceSymbolProvider" => true,
+                "semanticTokensProvider" => json_object! {
+                    "legend" => json_object! {
+                        "tokenTypes" => semantic_tokens::TOKEN_TYPES.to_vec(),
+                        "tokenModifiers" => semantic_tokens::TOKEN_MODIFIERS.to_vec(),
+                    },
+                    "full" => true,
+                    "range" => true,
+                },
+                "definitionProvider" => true,
+                "referencesProvider" => true,
+                "documentHighlightProvider" => true,
+                "renameProvider" => json_object! { "prepareProvider" => true },
+                "executeCommandProvider" => json_object! {
+                    "commands" => commands::COMMANDS.to_vec(),
+                },
+                "workspace" => json_object! {
+                    "workspaceFolders" => json_object! {
+                        "supported" => true,
+                        "changeNotifications" => true,
+                    },
+                },
+            },
+            "serverInfo" => json_object! {
+                "name" => env!("CARGO_PKG_NAME"),
+                "version" => env!("CARGO_PKG_VERSION"),
+            },
+        })
+    }
+
+    fn initialized(&mut self, _params: &Value) {
+        self.client
+            .log_message(MessageType::Info, "syslog-ng language server initialized");
+        if self.watched_files_registration {
+            // Snippets are named `*.conf` by convention; other files are
+            // still handled when the client reports them.
+            self.client.register_capability(
+                "watch-configuration-files",
+                "workspace/didChangeWatchedFiles",
+                json_object! { "watchers" => vec![json_object! { "globPattern" => "**/*.conf" }] },
+            );
+        }
+    }
+
+    fn shutdown(&mut self) -> LspResult<Value> {
+        self.shutdown_requested = true;
+        Ok(Value::Null)
+    }
+
+    fn did_change_configuration(&mut self, params: &Value) {
+        let previous = self.settings.clone();
+        self.settings.update(&params["settings"]["syslogng"]);
+        if previous.syslogng_binary != self.settings.syslogng_binary {
+            self.binary_version = None;
+        }
+        if previous.external_validation != self.settings.external_validation
+            || (self.settings.external_validation && previous.syslogng_binary != self.settings.syslogng_binary)
+        {
+            let uris: Vec<String> = self.documents.uris().map(str::to_string).collect();
+            for uri in uris {
+                self.run_external_validation(&uri);
+                self.publish_diagnostics(&uri);
+            }
+        }
+        // Different include settings can pull in different snippets.
+        if previous.include_path_map != self.settings.include_path_map
+            || previous.read_outside_includes != self.settings.read_outside_includes
+            || previous.include_root != self.settings.include_root
+            || previous.max_include_depth != self.settings.max_include_depth
+        {
+            let uris: Vec<String> = self.documents.uris().map(str::to_string).collect();
+            for uri in uris {
+                self.update_diagnostics(&uri);
+            }
+        } else if previous.validation_mode != self.settings.validation_mode
+            || previous.deployment_lints != self.settings.deployment_lints
+            || previous.max_diagnostics_per_file != self.settings.max_diagnostics_per_file
+            || previous.target_platform != self.settings.target_platform
+        {
+            let mut uris: Vec<String> = self.snapshots.keys().cloned().collect();
+            for snapshot in self.snapshots.values() {
+                uris.extend(snapshot.diagnostics.related.keys().cloned());
+            }
+            self.publish_all(uris);
+        }
+    }
+
+    fn did_open(&mut self, params: &Value) {
+        let document = &params["textDocument"];
+        let (Some(uri), Some(text)) = (document["uri"].as_str(), document["text"].as_str()) else {
+            return;
+        };
+        // Opening a document again replaces its text, which documents
+        // including it have to see as after a change.
+        let reopened = self.documents.is_open(uri);
+        self.documents.open(uri, text.to_string(), document["version"].as_i64());
+        self.run_external_validation(uri);
+        self.update_diagnostics(uri);
+        if reopened {
+            self.update_includers(uri);
+        }
+    }
+
+    fn did_change(&mut self, params: &Value) {
+        let Some(uri) = params["textDocument"]["uri"].as_str() else {
+            return;
+        };
+        // Changes are relative to the text produced by the previous one.
+        let changes: Vec<TextDocumentContentChangeEvent> = params["contentChanges"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(TextDocumentContentChangeEvent::from_json)
+            .collect();
+        // The snapshot of the previous revision, unless the buffer moved on
+        // without one being taken.
+        let previous = self
+            .snapshots
+            .get(uri)
+            .filter(|s| self.documents.get(uri).is_some_and(|d| d.text == s.text))
+            .cloned();
+        let version = params["textDocument"]["version"].as_i64();
+        if !self.documents.change(uri, &changes, version) {
+            return;
+        }
+        match previous {
+            Some(previous) => {
+                let load = |path: &Path| self.documents.load_text(path);
+                let started = Instant::now();
+                let snapshot = previous.apply_edit(&changes, version, &load, &self.snippets, &self.include_options());
+                self.install_snapshot(snapshot, started);
+            }
+            None => self.update_diagnostics(uri),
+        }
+        self.update_includers(uri);
+    }
+
+    fn did_save(&mut self, params: &Value) {
+        if let Some(uri) = params["textDocument"]["uri"].as_str() {
+            self.run_external_validation(uri);
+            self.update_diagnostics(uri);
+        }
+    }
+
+    /// Folders added to or removed from a multi-root workspace. They decide
+    /// which files are part of the workspace and where relative include
+    /// settings point, so every open document is analysed again.
+    fn did_change_workspace_folders(&mut self, params: &Value) {
+        let event = &params["event"];
+        let folders = |key: &str| -> Vec<PathBuf> {
+            let folders = event[key].as_array().into_iter().flatten();
+            folders
+                .filter_map(|f| f["uri"].as_str().and_then(uri_to_path))
+                .collect()
+        };
+        let removed = folders("removed");
+        self.workspace_folders.retain(|f| !removed.contains(f));
+        for folder in folders("added") {
+            if !self.workspace_folders.contains(&folder) {
+                self.workspace_folders.push(folder);
+            }
+        }
+        let uris: Vec<String> = self.documents.uris().map(str::to_string).collect();
+        for uri in uris {
+            self.update_diagnostics(&uri);
+        }
+    }
+
+    /// Files changed on disk by something other than the editor. The open
+    /// documents including a changed or deleted file are re-analysed; a
+    /// created or deleted one can also change what an include wildcard or
+    /// directory matches, so those re-analyse every document with includes.
+    fn did_change_watched_files(&mut self, params: &Value) {
+        let mut affected = Vec::new();
+        for change in params["changes"].as_array().into_iter().flatten() {
+            // 1 is created, 2 changed, 3 deleted; a rename is a deletion
+            // and a creation.
+            let change_type = change["type"].as_i64();
+            if let (Some(3), Some(path)) = (change_type, change["uri"].as_str().and_then(uri_to_path)) {
+                self.workspace_cache.remove(&path);
+            }
+            // The editor buffer wins over the file on disk.
+            let Some(uri) = change["uri"].as_str().filter(|uri| !self.documents.is_open(uri)) else {
+                continue;
+            };
+            for (source, snapshot) in &self.snapshots {
+                let included = snapshot.include_graph.iter().any(|s| s.uri == uri);
+                if included || (change_type != Some(2) && snapshot.has_includes()) {
+                    affected.push(source.clone());
+                }
+            }
+        }
+        affected.sort();
+        affected.dedup();
+        for uri in affected {
+            self.update_diagnostics(&uri);
+        }
+    }
+
+    /// Checks `uri` with `syslog-ng --syntax-only` if enabled. The file is
+    /// checked in place when neither its buffer nor those of the snippets it
+    /// includes differ from the files on disk. Otherwise the document and
+    /// its snippets are copied to a [`syslog_ng::TemporaryTree`], buffers as
+    /// they are in the editor, and the copy is checked. Errors in other
+    /// files, such as included snippets, are kept for those files.
+    fn run_external_validation(&mut self, uri: &str) {
+        let previous = self.external_reports.remove(uri);
+        let (Some(document), Some(path)) = (self.documents.get(uri), uri_to_path(uri)) else {
+            return;
+        };
+        if !self.settings.external_validation {
+            if let Some(previous) = previous {
+                self.publish_all(previous.related.into_keys().collect());
+            }
+            return;
+        }
+        let binary = self
+            .settings
+            .syslogng_binary
+            .as_deref()
+            .unwrap_or(syslog_ng::DEFAULT_BINARY);
+        // The `@include` lines are enough to find the snippets, and are
+        // there even when the document does not parse.
+        let annotations = ParsedConfiguration {
+            annotations: parse_annotations(&document.text),
+            ..ParsedConfiguration::default()
+        };
+        let load = |path: &Path| self.documents.load_text(path);
+        let snippets = resolve_snippets(uri, &annotations, &load, &self.snippets, &self.include_options());
+        let unsaved = |path: &Path, text: &str| !std::fs::read(path).is_ok_and(|bytes| bytes == text.as_bytes());
+        let modified = unsaved(&path, &document.text)
+            || snippets.iter().any(|snippet| {
+                self.documents
+                    .get(&snippet.uri)
+                    .is_some_and(|open| unsaved(&snippet.path, &open.text))
+            });
+        let tree = if modified {
+            let mut files = vec![(path.clone(), document.text.clone())];
+            files.extend(
+                snippets
+                    .iter()
+                    .filter_map(|snippet| Some((snippet.path.clone(), load(&snippet.path)?.text))),
+            );
+            match syslog_ng::TemporaryTree::create(&files) {
+                Ok(tree) => Some(tree),
+                Err(error) => {
+                    self.client.log_message(
+                        MessageType::Warning,
+                        format!("cannot copy {} for the syntax check: {}", path.display(), error),
+                    );
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        let checked = tree
+            .as_ref()
+            .and_then(|t| t.copy(&path))
+            .unwrap_or_else(|| path.clone());
+        let result = syslog_ng::syntax_check(binary, &checked);
+        let errors = match result {
+            Ok(errors) => errors,
+            Err(message) => {
+                self.client.log_message(MessageType::Warning, message);
+                return;
+            }
+        };
+        let mut report = DiagnosticReport::default();
+        for error in errors {
+            let mut diagnostic = Diagnostic::new(error.range, DiagnosticSeverity::Error, error.message);
+            diagnostic.source = syslog_ng::SYNTAX_CHECK_SOURCE.to_string();
+            let original = tree
+                .as_ref()
+                .map_or_else(|| error.path.clone(), |t| t.original(&error.path));
+            if original == path {
+                report.own.push(diagnostic);
+            } else {
+                report
+                    .related
+                    .entry(path_to_uri(&original))
+                    .or_default()
+                    .push(diagnostic);
+            }
+        }
+        let mut affected: Vec<String> = report.related.keys().cloned().collect();
+        affected.extend(previous.into_iter().flat_map(|p| p.related.into_keys()));
+        self.external_reports.insert(uri.to_string(), report);
+        self.publish_all(affected);
+    }
+
+    fn did_close(&mut self, params: &Value) {
+        if let Some(uri) = params["textDocument"]["uri"].as_str() {
+            self.documents.close(uri);
+            let mut affected: Vec<String> = self
+                .snapshots
+                .remove(uri)
+                .into_iter()
+                .flat_map(|s| s.diagnostics.related.keys().cloned().collect::<Vec<_>>())
+                .collect();
+            affected.extend(
+                self.external_reports
+                    .remove(uri)
+                    .into_iter()
+                    .flat_map(|r| r.related.into_keys()),
+            );
+            self.evict_snippets();
+            self.publish_diagnostics(uri);
+            self.publish_all(affected);
+        }
+    }
+
+    /// Takes a new snapshot of `uri` and republishes it together with every
+    /// file whose diagnostics it contributed to, before or after this change.
+    fn update_diagnostics(&mut self, uri: &str) {
+        let Some(document) = self.documents.get(uri) else {
+            return;
+        };
+        let load = |path: &Path| self.documents.load_text(path);
+        let started = Instant::now();
+        let snapshot = ParseSnapshot::build(
+            uri,
+            document.text.clone(),
+            document.version,
+            &load,
+            &self.snippets,
+            &self.include_options(),
+        );
+        self.install_snapshot(snapshot, started);
+    }
+
+    /// Makes `snapshot` the current one of its document, taken since
+    /// `started`, and republishes the diagnostics it affects.
+    fn install_snapshot(&mut self, snapshot: ParseSnapshot, started: Instant) {
+        log_event!(
+            Level::Debug,
+            "analysed {} version={:?} parsed={} snippets={} diagnostics={} took={:?}",
+            snapshot.uri,
+            snapshot.version,
+            snapshot.ast.is_ok(),
+            snapshot.include_graph.len(),
+            snapshot.diagnostics.own.len(),
+            started.elapsed()
+        );
+        let uri = snapshot.uri.clone();
+        let mut affected: Vec<String> = snapshot.diagnostics.related.keys().cloned().collect();
+        if let Some(previous) = self.snapshots.insert(uri.clone(), Rc::new(snapshot)) {
+            affected.extend(previous.diagnostics.related.keys().cloned());
+        }
+        self.evict_snippets();
+        self.publish_diagnostics(&uri);
+        self.publish_all(affected);
+    }
+
+    fn include_options(&self) -> IncludeOptions {
+        IncludeOptions {
+            workspace_folders: self.workspace_folders.clone(),
+            path_map: self
+                .settings
+                .include_path_map
+                .iter()
+                .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to)))
+                .collect(),
+            read_outside_workspace: self.settings.read_outside_includes,
+            include_root: self.settings.include_root.as_ref().map(PathBuf::from),
+            max_depth: self.settings.max_include_depth,
+        }
+    }
+
+    /// Forgets the snippets no open document includes any more.
+    fn evict_snippets(&self) {
+        self.snippets.retain(|uri| {
+            self.snapshots
+                .values()
+                .any(|s| s.include_graph.iter().any(|i| i.uri == uri))
+        });
+    }
+
+    /// Re-analyses the open documents that include `uri`, since their
+    /// merged view changed with it.
+    fn update_includers(&mut self, uri: &str) {
+        let key = normalize_uri(uri);
+        let includers: Vec<String> = self
+            .snapshots
+            .iter()
+            .filter(|(source, snapshot)| {
+                source.as_str() != uri && snapshot.include_graph.iter().any(|s| normalize_uri(&s.uri) == key)
+            })
+            .map(|(source, _)| source.clone())
+            .collect();
+        for includer in includers {
+            self.update_diagnostics(&includer);
+        }
+    }
+
+    fn publish_all(&self, mut uris: Vec<String>) {
+        uris.sort();
+        uris.dedup();
+        for uri in uris {
+            self.publish_diagnostics(&uri);
+        }
+    }
+
+    /// Publishes the current diagnostics of `uri`; an empty list clears
+    /// whatever the editor is still showing from an earlier revision.
+    fn publish_diagnostics(&self, uri: &str) {
+        let reports = self.snapshots.iter().map(|(source, s)| (source, &s.diagnostics));
+        let mut own = Vec::new();
+        let mut related = Vec::new();
+        for (source, report) in reports.chain(&self.external_reports) {
+            if source == uri {
+                own.extend(report.own.iter().cloned());
+            } else {
+                related.extend(report.related.get(uri).into_iter().flatten().cloned());
+            }
+        }
+        let mut diagnostics = own;
+        diagnostics.extend(related);
+        let parsed = self.snapshots.get(uri).map(|s| s.partial()).or_else(|| {
+            self.snapshots
+                .values()
+                .flat_map(|s| &s.include_graph)
+                .find(|snippet| snippet.uri == uri)
+                .and_then(|snippet| snippet.parsed.as_ref().ok())
+        });
+        apply_settings(&self.settings, parsed, &mut diagnostics);
+        let version = self.documents.version(uri);
+        self.client.publish_diagnostics(uri, &diagnostics, version);
+    }
+
+    /// Current text of `uri`, from the editor buffer or from disk.
+    fn document_text(&self, uri: &str) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(document) => Some(document.text.clone()),
+            None => uri_to_path(uri).and_then(|path| self.documents.load_text(&path).map(|l| l.text)),
+        }
+    }
+
+    /// The snapshot of the current revision of `uri` if it is open,
+    /// otherwise one taken of the file on disk.
+    fn snapshot(&self, uri: &str) -> Option<Rc<ParseSnapshot>> {
+        if let Some(snapshot) = self.snapshots.get(uri) {
+            return Some(snapshot.clone());
+        }
+        let load = |path: &Path| self.documents.load_text(path);
+        ParseSnapshot::from_disk(uri, &load, &self.snippets, &self.include_options()).map(Rc::new)
+    }
+
+    /// Every configuration file of the workspace, the open documents and the
+    /// snippets they include, parsed from their current text. Files that
+    /// fail to parse are returned separately by URI.
+    fn parse_workspace_files(&self) -> (Vec<(String, ParsedConfiguration)>, Vec<String>) {
+        let (parsed, failed, _) = self.parse_workspace_files_within(None);
+        (parsed, failed)
+    }
+
+    /// When the time budget of interactive requests runs out.
+    fn request_deadline(&self) -> Option<Instant> {
+        let budget = self.settings.request_time_budget_ms;
+        (budget > 0).then(|| Instant::now() + Duration::from_millis(budget))
+    }
+
+    /// [`Self::parse_workspace_files`], but files on disk that are not
+    /// cached yet are skipped once `deadline` has passed and parsed in the
+    /// background instead. The flag tells whether every file was included.
+    fn parse_workspace_files_within(
+        &self,
+        deadline: Option<Instant>,
+    ) -> (Vec<(String, ParsedConfiguration)>, Vec<String>, bool) {
+        let mut pending: Vec<String> = self
+            .workspace_folders
+            .iter()
+            .flat_map(|folder| get_workspace_config_files(folder))
+            .map(|path| path_to_uri(&path))
+            .chain(self.documents.uris().map(str::to_string))
+            .collect();
+        // A file is parsed once however its URI is spelled, and an open
+        // document under the spelling of the client.
+        let open: HashMap<String, &str> = self.documents.uris().map(|uri| (normalize_uri(uri), uri)).collect();
+        let mut seen = HashSet::new();
+        let mut parsed = Vec::new();
+        let mut failed = Vec::new();
+        let mut skipped = Vec::new();
+        while let Some(uri) = pending.pop() {
+            let key = normalize_uri(&uri);
+            let uri = open.get(&key).map_or(uri, |open| open.to_string());
+            if !seen.insert(key) {
+                continue;
+            }
+            // Open documents come with the snippets they include.
+            if let Some(snapshot) = self.snapshots.get(&uri) {
+                match snapshot.parsed() {
+                    Some(config) => {
+                        pending.extend(snapshot.include_graph.iter().map(|s| s.uri.clone()));
+                        parsed.push((uri, config.clone()));
+                    }
+                    None => failed.push(uri),
+                }
+                continue;
+            }
+            let result = match uri_to_path(&uri) {
+                Some(path) => match self.workspace_cache.get(&path) {
+                    Some(cached) => Some(cached),
+                    None if deadline.is_some_and(|d| Instant::now() >= d) => {
+                        skipped.push(path);
+                        continue;
+                    }
+                    None => self.workspace_cache.load(&path),
+                },
+                None => None,
+            };
+            match result {
+                Some(Ok(config)) => parsed.push((uri, config)),
+                _ => failed.push(uri),
+            }
+        }
+        parsed.sort_by(|a, b| a.0.cmp(&b.0));
+        failed.sort();
+        let complete = skipped.is_empty();
+        self.workspace_cache.prefetch(skipped);
+        (parsed, failed, complete)
+    }
+
+    fn completion(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let Some(snapshot) = self.snapshot(&uri) else {
+            return Ok(Value::Null);
+        };
+        // Only a trigger character typed by the user (triggerKind 2) narrows the items.
+        let trigger = match params["context"]["triggerKind"].as_i64() {
+            Some(2) => params["context"]["triggerCharacter"]
+                .as_str()
+                .and_then(|c| c.chars().next()),
+            _ => None,
+        };
+        let options = CompletionOptions {
+            trigger,
+            closing_syntax: self.snippet_support && self.settings.insert_closing_syntax,
+            verbosity: self.settings.completion_verbosity,
+            version: self.grammar_version(&uri, &snapshot),
+            platform: self.settings.target_platform,
+        };
+        let (files, _, complete) = self.parse_workspace_files_within(self.request_deadline());
+        // Worked out only when object identifiers are offered.
+        let visible = OnceCell::new();
+        let is_visible = |file: &str| {
+            visible
+                .get_or_init(|| self.visible_files(&snapshot, &files))
+                .contains(file)
+        };
+        let scope = IncludeScope {
+            uri: &uri,
+            visible: &is_visible,
+        };
+        let items = completion::completions(&snapshot, position, &files, &scope, &options).to_json();
+        if complete {
+            Ok(items)
+        } else {
+            // The client asks again as the user keeps typing, by when the
+            // remaining files have been parsed in the background.
+            Ok(json_object! { "isIncomplete" => true, "items" => items })
+        }
+    }
+
+    /// Adds the documentation left out of the completion list.
+    fn resolve_completion(&self, params: &Value) -> LspResult<Value> {
+        let mut item = params.clone();
+        if let Some(documentation) = completion::resolve_documentation(&params["data"]) {
+            item.insert(
+                "documentation",
+                json_object! { "kind" => "markdown", "value" => documentation },
+            );
+        }
+        Ok(item)
+    }
+
+    fn hover(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let Some(snapshot) = self.snapshot(&uri) else {
+            return Ok(Value::Null);
+        };
+        let parsed = snapshot.partial();
+        if let Some(include) = hover::include_at(parsed, position) {
+            let hover = Hover {
+                contents: hover::include_report(&uri, include, &snapshot.include_graph, &self.workspace_folders),
+                range: Some(include.range),
+            };
+            return Ok(hover.to_json());
+        }
+        let mut result = hover::hover(parsed, position);
+        if let Some(literal) = hover::persist_name_at(parsed, position) {
+            let (files, _, complete) = self.parse_workspace_files_within(self.request_deadline());
+            let mut users = hover::persist_name_users(literal, &uri, &files);
+            if !complete {
+                users.push_str("\n\n*Not every workspace file was read in time; hover again for the full list.*");
+            }
+            match &mut result {
+                Some(hover) => {
+                    hover.contents.push_str("\n\n");
+                    hover.contents.push_str(&users);
+                }
+                None => {
+                    result = Some(Hover {
+                        contents: users,
+                        range: Some(literal.range),
+                    })
+                }
+            }
+        }
+        Ok(result.map_or(Value::Null, |h| h.to_json()))
+    }
+
+    /// The files whose objects the document of `snapshot` can refer to: the
+    /// document, what it includes and, for a snippet, the files of `files`
+    /// including it together with everything they include.
+    fn visible_files(&self, snapshot: &ParseSnapshot, files: &[(String, ParsedConfiguration)]) -> HashSet<String> {
+        let mut visible: HashSet<String> = snapshot.include_graph.iter().map(|s| s.uri.clone()).collect();
+        visible.insert(snapshot.uri.clone());
+        let load = |path: &Path| self.documents.load_text(path);
+        let include_options = self.include_options();
+        for (uri, parsed) in files {
+            if *uri == snapshot.uri || !parsed.annotations.iter().any(|a| matches!(a, Annotation::Include(_))) {
+                continue;
+            }
+            let snippets: Vec<String> = match self.snapshots.get(uri) {
+                Some(includer) => includer.include_graph.iter().map(|s| s.uri.clone()).collect(),
+                None => resolve_snippets(uri, parsed, &load, &self.snippets, &include_options)
+                    .into_iter()
+                    .map(|s| s.uri)
+                    .collect(),
+            };
+            if snippets.contains(&snapshot.uri) {
+                visible.insert(uri.clone());
+                visible.extend(snippets);
+            }
+        }
+        visible
+    }
+
+    /// The `@include` directives of workspace files that pull `uri` in.
+    fn includers(&self, uri: &str) -> Vec<Location> {
+        let (files, _) = self.parse_workspace_files();
+        let load = |path: &Path| self.documents.load_text(path);
+        let include_options = self.include_options();
+        let mut includers: Vec<Location> = Vec::new();
+        for (file_uri, parsed) in &files {
+            let snippets = resolve_snippets(file_uri, parsed, &load, &self.snippets, &include_options);
+            for snippet in snippets.into_iter().filter(|s| s.uri == uri) {
+                if !includers.contains(&snippet.included_from) {
+                    includers.push(snippet.included_from);
+                }
+            }
+        }
+        includers
+    }
+
+    /// An "included from" lens at the top of a snippet for every
+    /// `@include` directive that pulls it in, a syntax check lens on
+    /// `@version`, the files a glob or directory `@include` expands to
+    /// and the reference count of every object definition.
+    fn code_lens(&self, params: &Value) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let mut lenses: Vec<CodeLens> = self
+            .includers(uri)
+            .into_iter()
+            .map(|location| CodeLens {
+                range: Range::default(),
+                command: Command {
+                    title: format!(
+                        "included from {}:{}",
+                        symbols::display_name(&location.uri, &self.workspace_folders),
+                        location.range.start.line + 1
+                    ),
+                    command: commands::SHOW_INCLUDER.to_string(),
+                    arguments: vec![location.uri.clone().into(), location.range.to_json()],
+                },
+            })
+            .collect();
+        let snapshot = self.snapshot(uri);
+        let Some(parsed) = snapshot.as_deref().and_then(ParseSnapshot::parsed) else {
+            return Ok(lenses.to_json());
+        };
+        for annotation in &parsed.annotations {
+            if let Annotation::Version(version) = annotation {
+                lenses.push(CodeLens {
+                    range: version.range,
+                    command: Command {
+                        title: "Run syntax check".to_string(),
+                        command: commands::CHECK_CONFIGURATION.to_string(),
+                        arguments: vec![uri.into()],
+                    },
+                });
+            }
+        }
+        let include_graph = snapshot.as_deref().map_or(&[][..], |s| &s.include_graph);
+        for include in parsed.annotations.iter().filter_map(|a| match a {
+            Annotation::Include(include) => Some(include),
+            _ => None,
+        }) {
+            // Directly included files, in the order syslog-ng reads them.
+            let included: Vec<&Snippet> = include_graph
+                .iter()
+                .filter(|s| s.included_from.uri == uri && s.included_from.range == include.range)
+                .collect();
+            let is_single_file = match included.as_slice() {
+                [snippet] => snippet.path.file_name() == Path::new(&include.path).file_name(),
+                _ => false,
+            };
+            if is_single_file {
+                continue;
+            }
+            let locations: Vec<Location> = included
+                .iter()
+                .map(|s| Location {
+                    uri: s.uri.clone(),
+                    range: Range::default(),
+                })
+                .collect();
+            lenses.push(CodeLens {
+                range: include.range,
+                command: Command {
+                    title: match locations.len() {
+                        0 => "matches no files".to_string(),
+                        1 => "expands to 1 file".to_string(),
+                        n => format!("expands to {} files", n),
+                    },
+                    command: commands::SHOW_REFERENCES.to_string(),
+                    arguments: vec![uri.into(), include.range.start.to_json(), locations.to_json()],
+                },
+            });
+        }
+        let (files, _) = self.parse_workspace_files();
+        for (kind, id) in parsed.definitions() {
+            let references = symbol_locations(&files, kind, &id.name, false);
+            lenses.push(CodeLens {
+                range: id.range,
+                command: Command {
+                    title: match references.len() {
+                        1 => "1 reference".to_string(),
+                        n => format!("{} references", n),
+                    },
+                    command: commands::SHOW_REFERENCES.to_string(),
+                    arguments: vec![uri.into(), id.range.start.to_json(), references.to_json()],
+                },
+            });
+        }
+        Ok(lenses.to_json())
+    }
+
+    /// Quick fixes for the problems in the requested range, `@version` for
+    /// a main configuration missing it, moving the selected objects to a
+    /// new file and fixing every auto-fixable problem of the document.
+    /// Limited to the kinds in `context.only` when the client sends it.
+    fn code_action(&self, params: &Value) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let range =
+            Range::from_json(&params["range"]).ok_or_else(|| ResponseError::invalid_params("expected a range"))?;
+        let Some(snapshot) = self.snapshot(uri) else {
+            return Ok(Value::Null);
+        };
+        let mut actions = code_actions::quick_fixes(uri, &snapshot.text, range);
+        actions.extend(self.include_fixes(&snapshot, range));
+        // Snippets get their version from the file including them.
+        if snapshot.ast.is_ok() && self.merged_version(uri).is_none() && self.includers(uri).is_empty() {
+            let version = self.settings.syslogng_version.as_deref();
+            actions.push(code_actions::add_version(
+                uri,
+                version.unwrap_or(code_actions::DEFAULT_VERSION),
+            ));
+        }
+        actions.extend(self.extract_action(&snapshot, range));
+        let only: Option<Vec<&str>> = params["context"]["only"]
+            .as_array()
+            .map(|kinds| kinds.iter().filter_map(Value::as_str).collect());
+        let requested = |kind: CodeActionKind| {
+            only.as_ref().is_none_or(|only| {
+                only.iter()
+                    .any(|o| kind.as_str() == *o || kind.as_str().starts_with(&format!("{}.", o)))
+            })
+        };
+        // Unasked, only offered when it does more than a single quick fix.
+        if let Some((fix_all, fixes)) = code_actions::fix_all(uri, &snapshot.text) {
+            if only.is_some() || fixes > 1 {
+                actions.push(fix_all);
+            }
+        }
+        actions.retain(|action| requested(action.kind));
+        Ok(actions.to_json())
+    }
+
+    /// "Extract to `conf.d/<name>.conf`" for the top-level objects in the
+    /// selection, named after the first of them.
+    fn extract_action(&self, snapshot: &ParseSnapshot, range: Range) -> Option<CodeAction> {
+        let (Some(parsed), Some(from)) = (snapshot.parsed(), uri_to_path(&snapshot.uri)) else {
+            return None;
+        };
+        if !self.create_files_support {
+            return None;
+        }
+        let objects = code_actions::extractable_objects(parsed, range);
+        let first = objects.first()?;
+        let name = first.id.as_ref().map_or(first.kind.keyword(), |id| id.name.as_str());
+        let directory = from.parent()?.join("conf.d");
+        // Never overwrite: a file on disk or open in the editor.
+        let target = (1..)
+            .map(|n| match n {
+                1 => directory.join(format!("{}.conf", name)),
+                _ => directory.join(format!("{}-{}.conf", name, n)),
+            })
+            .find(|path| !path.exists() && !self.documents.is_open(&path_to_uri(path)))?;
+        let path = include_path(&from, &target);
+        code_actions::extract_to_include(&snapshot.uri, &snapshot.text, &objects, &path_to_uri(&target), &path)
+    }
+
+    /// "Add `@include`" for the references in `range` to objects the
+    /// document cannot see, one per workspace file defining the object.
+    fn include_fixes(&self, snapshot: &ParseSnapshot, range: Range) -> Vec<CodeAction> {
+        let (Some(parsed), Some(from)) = (snapshot.parsed(), uri_to_path(&snapshot.uri)) else {
+            return Vec::new();
+        };
+        let unresolved: Vec<_> = parsed
+            .references
+            .iter()
+            .filter(|r| r.range.overlaps(&range) && snapshot.symbols.definitions(r.kind, &r.name).is_empty())
+            .collect();
+        if unresolved.is_empty() {
+            return Vec::new();
+        }
+        let (files, _) = self.parse_workspace_files();
+        let visible = self.visible_files(snapshot, &files);
+        let mut actions: Vec<CodeAction> = Vec::new();
+        for reference in unresolved {
+            let files = files.iter().map(|(uri, parsed)| (uri.as_str(), parsed));
+            let definitions = symbols::definition_locations(files, reference.kind, &reference.name);
+            // Defined by a file including this one.
+            if definitions.iter().any(|l| visible.contains(&l.uri)) {
+                continue;
+            }
+            let preferred = definitions.len() == 1;
+            for location in definitions {
+                let Some(target) = uri_to_path(&location.uri) else {
+                    continue;
+                };
+                let path = include_path(&from, &target);
+                let mut action = code_actions::add_include(&snapshot.uri, &snapshot.text, parsed, &path);
+                action.is_preferred = preferred;
+                if !actions.iter().any(|a| a.title == action.title) {
+                    actions.push(action);
+                }
+            }
+        }
+        actions
+    }
+
+    /// The first `@version` of the configuration `uri` and its includes.
+    fn merged_version(&self, uri: &str) -> Option<String> {
+        let snapshot = self.snapshot(uri)?;
+        let version = merged_annotations(uri, snapshot.parsed()?, &snapshot.include_graph)
+            .into_iter()
+            .find_map(|(_, annotation)| match annotation {
+                Annotation::Version(version) => Some(version.version.clone()),
+                _ => None,
+            });
+        version
+    }
+
+    /// The `@version` of `uri` or, for a snippet, of a file including it.
+    fn annotated_version(&self, uri: &str) -> Option<String> {
+        self.merged_version(uri)
+            .or_else(|| self.includers(uri).iter().find_map(|l| self.merged_version(&l.uri)))
+    }
+
+    /// The configuration version completion offers drivers for: the
+    /// annotated one, also while the document does not parse, else the
+    /// one in the settings or of the binary if already known.
+    fn grammar_version(&self, uri: &str, snapshot: &ParseSnapshot) -> Option<(u32, u32)> {
+        let own = || {
+            parse_annotations(&snapshot.text).into_iter().find_map(|a| match a {
+                Annotation::Version(version) => version.number(),
+                _ => None,
+            })
+        };
+        let configured = || {
+            let version = self.settings.syslogng_version.clone();
+            version.or_else(|| self.binary_version.clone().flatten())
+        };
+        self.annotated_version(uri)
+            .and_then(|v| parse_version(&v))
+            .or_else(own)
+            .or_else(|| configured().and_then(|v| parse_version(&v)))
+    }
+
+    /// `syslogng/effectiveVersion`: the configuration version assumed for
+    /// a document and where it came from, together with the grammar
+    /// tables in use.
+    fn effective_version(&mut self, params: &Value) -> LspResult<Value> {
+        let annotated = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(|uri| self.annotated_version(uri));
+        let (version, source) = if let Some(version) = annotated {
+            (Some(version), Some("annotation"))
+        } else if let Some(version) = self.settings.syslogng_version.clone() {
+            (Some(version), Some("settings"))
+        } else {
+            let binary = self
+                .settings
+                .syslogng_binary
+                .as_deref()
+                .unwrap_or(syslog_ng::DEFAULT_BINARY);
+            let detected = self
+                .binary_version
+                .get_or_insert_with(|| syslog_ng::detect_config_version(binary))
+                .clone();
+            let source = detected.as_ref().map(|_| "binary");
+            (detected, source)
+        };
+        Ok(json_object! {
+            "version" => version.map_or(Value::Null, Value::from),
+            "source" => source.map_or(Value::Null, Value::from),
+            "grammar" => json_object! {
+                "source" => "built-in",
+                "version" => env!("CARGO_PKG_VERSION"),
+                "drivers" => grammar::DRIVERS.len(),
+                "globalOptions" => grammar::GLOBAL_OPTIONS.len(),
+                "filterFunctions" => grammar::FILTER_FUNCTIONS.len(),
+            },
+        })
+    }
+
+    fn formatting(&self, params: &Value, with_range: bool) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let range = if with_range {
+            Some(Range::from_json(&params["range"]).ok_or_else(|| ResponseError::invalid_params("expected a range"))?)
+        } else {
+            None
+        };
+        let Some(text) = self.document_text(uri) else {
+            return Ok(Value::Null);
+        };
+        let options = FormattingOptions::from_lsp(&params["options"]);
+        // A document with syntax errors is left alone rather than failing the request.
+        let edits = formatter::formatting_edits(&text, range, &options).unwrap_or_default();
+        Ok(edits.to_json())
+    }
+
+    fn inlay_hint(&self, params: &Value) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let range =
+            Range::from_json(&params["range"]).ok_or_else(|| ResponseError::invalid_params("expected a range"))?;
+        let snapshot = self.snapshot(uri);
+        let Some(parsed) = snapshot.as_deref().map(ParseSnapshot::partial) else {
+            return Ok(Value::Null);
+        };
+        Ok(inlay_hints::inlay_hints(parsed, range).to_json())
+    }
+
+    fn prepare_call_hierarchy(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let snapshot = self.snapshot(&uri);
+        let Some(parsed) = snapshot.as_deref().and_then(ParseSnapshot::parsed) else {
+            return Ok(Value::Null);
+        };
+        let item = call_hierarchy::prepare(&uri, parsed, position);
+        Ok(item.map_or(Value::Null, |item| vec![item].to_json()))
+    }
+
+    /// The incoming or outgoing calls of a call hierarchy item, searched for
+    /// in the whole workspace.
+    fn hierarchy_calls(&self, params: &Value, incoming: bool) -> LspResult<Value> {
+        let item = &params["item"];
+        let node =
+            Node::from_item(item).ok_or_else(|| ResponseError::invalid_params("expected a call hierarchy item"))?;
+        let (files, _) = self.parse_workspace_files();
+        let calls = if incoming {
+            call_hierarchy::incoming_calls(&node, &files)
+        } else {
+            let selection = Range::from_json(&item["selectionRange"]).unwrap_or_default();
+            call_hierarchy::outgoing_calls(&node, selection, &files)
+        };
+        Ok(Value::Array(calls.iter().map(|c| c.to_json(incoming)).collect()))
+    }
+
+    fn linked_editing_range(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let ranges = self
+            .document_text(&uri)
+            .and_then(|text| linked_editing::linked_editing_ranges(&text, position));
+        Ok(ranges.map_or(Value::Null, |r| r.to_json()))
+    }
+
+    fn selection_range(&self, params: &Value) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let positions: Vec<Position> = params["positions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Position::from_json)
+            .collect();
+        let Some(snapshot) = self.snapshot(uri) else {
+            return Ok(Value::Null);
+        };
+        let parsed = snapshot.partial();
+        let ranges: Vec<_> = positions
+            .into_iter()
+            .map(|position| selection_ranges::selection_range(&snapshot.text, parsed, position))
+            .collect();
+        Ok(ranges.to_json())
+    }
+
+    fn document_symbol(&self, params: &Value) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let snapshot = self.snapshot(uri);
+        let Some(parsed) = snapshot.as_deref().map(ParseSnapshot::partial) else {
+            return Ok(Value::Null);
+        };
+        Ok(symbols::document_symbols(parsed).to_json())
+    }
+
+    /// `syslogng/documentStructure`: the node tree of [`structure`] for an
+    /// open document, with the syntax error keeping it from parsing. The
+    /// directives are still listed then.
+    fn document_structure(&self, params: &Value) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let Some(snapshot) = self.snapshot(uri) else {
+            return Ok(Value::Null);
+        };
+        let (nodes, syntax_error) = match &snapshot.ast {
+            Ok(parsed) => (structure::document_structure(parsed), Value::Null),
+            Err(error) => (
+                structure::annotation_nodes(&parse_annotations(&snapshot.text)),
+                json_object! { "message" => error.kind.to_string(), "range" => error.range.to_json() },
+            ),
+        };
+        Ok(json_object! {
+            "uri" => uri,
+            "version" => snapshot.version.map_or(Value::Null, Value::from),
+            "nodes" => nodes.to_json(),
+            "syntaxError" => syntax_error,
+        })
+    }
+
+    fn semantic_tokens(&self, params: &Value, with_range: bool) -> LspResult<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a text document"))?;
+        let range = match with_range {
+            true => Some(
+                Range::from_json(&params["range"]).ok_or_else(|| ResponseError::invalid_params("expected a range"))?,
+            ),
+            false => None,
+        };
+        let Some(text) = self.document_text(uri) else {
+            return Ok(Value::Null);
+        };
+        Ok(json_object! { "data" => semantic_tokens::semantic_tokens(&text, range) })
+    }
+
+    fn workspace_symbol(&self, params: &Value) -> LspResult<Value> {
+        let query = params["query"].as_str().unwrap_or_default();
+        let (files, _) = self.parse_workspace_files();
+        let table = SymbolTable::build(&files, &self.workspace_folders);
+        let symbols: Vec<SymbolInformation> = table
+            .search(query)
+            .map(SymbolEntry::to_symbol_information)
+            .chain(table.search_blocks(query).map(BlockEntry::to_symbol_information))
+            .collect();
+        Ok(symbols.to_json())
+    }
+
+    fn references(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let snapshot = self.snapshot(&uri);
+        let Some(parsed) = snapshot.as_deref().map(ParseSnapshot::partial) else {
+            return Ok(Value::Null);
+        };
+        let Some((kind, name, _)) = parsed.symbol_at(position) else {
+            return Ok(Value::Null);
+        };
+        let include_declaration = params["context"]["includeDeclaration"].as_bool().unwrap_or(true);
+        let (files, _) = self.parse_workspace_files();
+        Ok(symbol_locations(&files, kind, name, include_declaration).to_json())
+    }
+
+    /// The definition of the identifier under the cursor. The document and
+    /// the files it includes are searched first, so that a name defined in
+    /// an included snippet resolves there; the rest of the workspace after.
+    fn definition(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let snapshot = self.snapshot(&uri);
+        let Some(parsed) = snapshot.as_deref().map(ParseSnapshot::partial) else {
+            return Ok(Value::Null);
+        };
+        let Some((kind, name, _)) = parsed.symbol_at(position) else {
+            return Ok(self.block_definition(snapshot.as_deref(), parsed, position).to_json());
+        };
+        let mut locations = snapshot
+            .as_deref()
+            .map(|s| s.symbols.definitions(kind, name))
+            .unwrap_or_default();
+        if locations.is_empty() {
+            let (files, _) = self.parse_workspace_files();
+            locations = symbols::definition_locations(files.iter().map(|(u, p)| (u.as_str(), p)), kind, name);
+        }
+        Ok(locations.to_json())
+    }
+
+    /// The definition of the block called by the driver under `position`,
+    /// looked up like [`Self::definition`] does objects.
+    fn block_definition(
+        &self,
+        snapshot: Option<&ParseSnapshot>,
+        parsed: &ParsedConfiguration,
+        position: Position,
+    ) -> Vec<Location> {
+        let Some(Element::Call { calls, .. }) = parsed.element_at(position) else {
+            return Vec::new();
+        };
+        let [driver] = calls.as_slice() else {
+            return Vec::new();
+        };
+        let name = &driver.name.name;
+        let locations = snapshot.map(|s| s.symbols.block_definitions(name)).unwrap_or_default();
+        if !locations.is_empty() {
+            return locations;
+        }
+        let (files, _) = self.parse_workspace_files();
+        SymbolTable::build(&files, &self.workspace_folders).block_definitions(name)
+    }
+
+    fn document_highlight(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let snapshot = self.snapshot(&uri);
+        let Some(parsed) = snapshot.as_deref().map(ParseSnapshot::partial) else {
+            return Ok(Value::Null);
+        };
+        Ok(symbols::document_highlights(parsed, position).to_json())
+    }
+
+    fn prepare_rename(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let snapshot = self.snapshot(&uri);
+        let Some(parsed) = snapshot.as_deref().and_then(ParseSnapshot::parsed) else {
+            return Ok(Value::Null);
+        };
+        Ok(match parsed.symbol_at(position) {
+            Some((_, name, range)) => json_object! { "range" => range.to_json(), "placeholder" => name },
+            None => Value::Null,
+        })
+    }
+
+    fn rename(&self, params: &Value) -> LspResult<Value> {
+        let (uri, position) = text_document_position(params)?;
+        let new_name = params["newName"]
+            .as_str()
+            .ok_or_else(|| ResponseError::invalid_params("expected a new name"))?;
+        let snapshot = self.snapshot(&uri);
+        let Some(parsed) = snapshot.as_deref().and_then(ParseSnapshot::parsed) else {
+            return Err(ResponseError::new(
+                ResponseError::REQUEST_FAILED,
+                "the document has syntax errors",
+            ));
+        };
+        let (kind, name, _) = parsed
+            .symbol_at(position)
+            .ok_or_else(|| ResponseError::new(ResponseError::REQUEST_FAILED, "no identifier at this position"))?;
+        if !is_valid_identifier(new_name) {
+            return Err(ResponseError::new(
+                ResponseError::REQUEST_FAILED,
+                format!("`{}` is not a valid identifier", new_name),
+            ));
+        }
+
+        let (files, skipped) = self.parse_workspace_files();
+        let taken = files
+            .iter()
+            .flat_map(|(_, config)| config.definitions())
+            .any(|(k, id)| k == kind && id.name == new_name);
+        if taken && new_name != name {
+            return Err(ResponseError::new(
+                ResponseError::REQUEST_FAILED,
+                format!("a {} named `{}` already exists", kind, new_name),
+            ));
+        }
+        if !skipped.is_empty() {
+            self.client.show_message(
+                MessageType::Warning,
+                format!(
+                    "{} was not renamed in files with syntax errors: {}",
+                    name,
+                    skipped.join(", ")
+                ),
+            );
+        }
+
+        // Files outside the workspace, e.g. under `/etc`, are only analysed.
+        let include_options = self.include_options();
+        let (locations, read_only): (Vec<_>, Vec<_>) = symbol_locations(&files, kind, name, true)
+            .into_iter()
+            .partition(|l| uri_to_path(&l.uri).is_none_or(|path| include_options.is_in_workspace(&path)));
+        if !read_only.is_empty() {
+            let mut uris: Vec<&str> = read_only.iter().map(|l| l.uri.as_str()).collect();
+            uris.dedup();
+            self.client.show_message(
+                MessageType::Warning,
+                format!(
+                    "{} was not renamed in files outside the workspace: {}",
+                    name,
+                    uris.join(", ")
+                ),
+            );
+        }
+
+        let mut edit = WorkspaceEdit::default();
+        for location in locations {
+            edit.push(
+                &location.uri,
+                TextEdit {
+                    range: location.range,
+                    new_text: new_name.to_string(),
+                },
+            );
+        }
+        Ok(edit.to_json())
+    }
+
+    fn execute_command(&mut self, params: &Value) -> LspResult<Value> {
+        let command = params["command"].as_str().unwrap_or_default();
+        let arguments = &params["arguments"];
+        match command {
+            commands::SET_OPTION_EVERYWHERE => {
+                let request = SetOptionRequest::from_arguments(arguments)
+                    .ok_or_else(|| ResponseError::invalid_params("expected an option name and a value"))?;
+                let (files, skipped) = self.parse_workspace_files();
+                let edit = commands::set_option_everywhere(&request, &files);
+                if !edit.changes.is_empty() {
+                    self.client
+                        .apply_edit(&format!("Set {}({})", request.option, request.value), &edit);
+                }
+                Ok(json_object! {
+                    "edit" => edit.to_json(),
+                    "changedFiles" => edit.changes.len(),
+                    "edits" => edit.edit_count(),
+                    "skippedFiles" => skipped,
+                })
+            }
+            commands::PREVIEW_FORMAT => {
+                let (uri, options) = commands::preview_format_arguments(arguments)
+                    .ok_or_else(|| ResponseError::invalid_params("expected a document URI"))?;
+                let text = self
+                    .document_text(&uri)
+                    .ok_or_else(|| ResponseError::invalid_params(format!("cannot read {}", uri)))?;
+                commands::preview_format(&uri, &text, &options).ok_or_else(|| {
+                    ResponseError::new(
+                        ResponseError::REQUEST_FAILED,
+                        "the document has syntax errors and cannot be formatted",
+                    )
+                })
+            }
+            commands::RENAME_PREFIX => {
+                let request = RenamePrefixRequest::from_arguments(arguments)
+                    .ok_or_else(|| ResponseError::invalid_params("expected the old and the new prefix"))?;
+                let (files, skipped) = self.parse_workspace_files();
+                let include_options = self.include_options();
+                let read_only =
+                    |uri: &str| uri_to_path(uri).is_some_and(|path| !include_options.is_in_workspace(&path));
+                let (renames, edit, kept) = commands::rename_prefix(&request, &files, read_only)
+                    .map_err(|message| ResponseError::new(ResponseError::REQUEST_FAILED, message))?;
+                if !request.preview && !edit.changes.is_empty() {
+                    self.client
+                        .apply_edit(&format!("Rename {}* to {}*", request.from, request.to), &edit);
+                }
+                Ok(json_object! {
+                    "renames" => renames.to_json(),
+                    "kept" => kept.to_json(),
+                    "edit" => edit.to_json(),
+                    "changedFiles" => edit.changes.len(),
+                    "edits" => edit.edit_count(),
+                    "skippedFiles" => skipped,
+                })
+            }
+            commands::CHECK_CONFIGURATION => {
+                let uri = arguments[0]
+                    .as_str()
+                    .ok_or_else(|| ResponseError::invalid_params("expected a document URI"))?;
+                let snapshot = self
+                    .snapshot(uri)
+                    .ok_or_else(|| ResponseError::invalid_params(format!("cannot read {}", uri)))?;
+                let report = &snapshot.diagnostics;
+                let (errors, warnings, files) =
+                    count_problems(std::iter::once(&report.own).chain(report.related.values()));
+                let checked = 1 + snapshot.include_graph.len();
+                let (typ, message) = match (errors, warnings) {
+                    (0, 0) => (
+                        MessageType::Info,
+                        format!("Syntax check passed: no problems in {} file(s)", checked),
+                    ),
+                    (0, _) => (
+                        MessageType::Warning,
+                        format!("Syntax check: {} warning(s) in {} file(s)", warnings, files),
+                    ),
+                    _ => (
+                        MessageType::Error,
+                        format!(
+                            "Syntax check: {} error(s) and {} warning(s) in {} file(s)",
+                            errors, warnings, files
+                        ),
+                    ),
+                };
+                self.client.show_message(typ, message);
+                if self.documents.get(uri).is_some() {
+                    self.update_diagnostics(uri);
+                }
+                Ok(json_object! {
+                    "errors" => errors,
+                    "warnings" => warnings,
+                    "files" => files,
+                    "checkedFiles" => checked,
+                })
+            }
+            commands::VALIDATE_CONFIGURATION => Ok(self.validate_configuration()),
+            commands::SHOW_RESOLVED_CONFIG => {
+                let uri = arguments[0]
+                    .as_str()
+                    .ok_or_else(|| ResponseError::invalid_params("expected a document URI"))?;
+                let snapshot = self
+                    .snapshot(uri)
+                    .ok_or_else(|| ResponseError::invalid_params(format!("cannot read {}", uri)))?;
+                let parsed = snapshot.ast.as_ref().map_err(|error| {
+                    ResponseError::new(
+                        ResponseError::REQUEST_FAILED,
+                        format!("the document has syntax errors: {}", error),
+                    )
+                })?;
+                let load = |path: &Path| self.documents.load_text(path);
+                let snippets = &snapshot.include_graph;
+                let resolved = commands::resolved_configuration(
+                    uri,
+                    &snapshot.text,
+                    parsed,
+                    snippets,
+                    &load,
+                    &self.workspace_folders,
+                );
+                Ok(json_object! {
+                    "text" => resolved,
+                    "files" => 1 + snippets.len(),
+                })
+            }
+            commands::TRACE_MESSAGE => {
+                let (Some(uri), Some(sample)) = (arguments[0].as_str(), SampleMessage::from_json(&arguments[1])) else {
+                    return Err(ResponseError::invalid_params("expected a document URI and a message"));
+                };
+                let snapshot = self
+                    .snapshot(uri)
+                    .ok_or_else(|| ResponseError::invalid_params(format!("cannot read {}", uri)))?;
+                let parsed = snapshot.ast.as_ref().map_err(|error| {
+                    ResponseError::new(
+                        ResponseError::REQUEST_FAILED,
+                        format!("the document has syntax errors: {}", error),
+                    )
+                })?;
+                Ok(trace_message(uri, parsed, &snapshot.include_graph, &sample).to_json())
+            }
+            commands::SHOW_INCLUDER => {
+                let (Some(uri), Some(range)) = (arguments[0].as_str(), Range::from_json(&arguments[1])) else {
+                    return Err(ResponseError::invalid_params("expected a document URI and a range"));
+                };
+                self.client.show_document(uri, range);
+                Ok(Value::Null)
+            }
+            _ => Err(ResponseError::invalid_params(format!("unknown command: {}", command))),
+        }
+    }
+
+    /// Analyses every configuration file of the workspace and every file
+    /// they include, each as if it were open, and publishes the diagnostics
+    /// of all of them as they are published while editing.
+    fn validate_configuration(&mut self) -> Value {
+        let mut pending: Vec<String> = self
+            .workspace_folders
+            .iter()
+            .flat_map(|folder| get_workspace_config_files(folder))
+            .map(|path| path_to_uri(&path))
+            .chain(self.documents.uris().map(str::to_string))
+            .collect();
+        // By [`normalize_uri`], so that a file is analysed once however
+        // its URI is spelt; published under the URI it was first found by,
+        // the one of the editor for open documents.
+        let mut checked: HashMap<String, (String, Rc<ParseSnapshot>)> = HashMap::new();
+        let mut diagnostics: HashMap<String, Vec<Diagnostic>> = HashMap::new();
+        while let Some(uri) = pending.pop() {
+            let key = normalize_uri(&uri);
+            if checked.contains_key(&key) {
+                continue;
+            }
+            let Some(snapshot) = self.snapshot(&uri) else {
+                continue;
+            };
+            let report = &snapshot.diagnostics;
+            pending.extend(snapshot.include_graph.iter().map(|s| s.uri.clone()));
+            for (file, file_diagnostics) in std::iter::once((&uri, &report.own)).chain(&report.related) {
+                // Problems spanning includes are found from every file involved.
+                let known = diagnostics.entry(normalize_uri(file)).or_default();
+                for diagnostic in file_diagnostics {
+                    if !known.contains(diagnostic) {
+                        known.push(diagnostic.clone());
+                    }
+                }
+            }
+            checked.insert(key, (uri, snapshot));
+        }
+
+        let mut published: Vec<(&String, &String, &Rc<ParseSnapshot>)> = checked
+            .iter()
+            .map(|(key, (uri, snapshot))| (key, uri, snapshot))
+            .collect();
+        published.sort_by(|a, b| a.1.cmp(b.1));
+        let mut per_file = Vec::new();
+        for (key, uri, snapshot) in published {
+            let mut file_diagnostics = diagnostics.remove(key).unwrap_or_default();
+            apply_settings(&self.settings, Some(snapshot.partial()), &mut file_diagnostics);
+            self.client
+                .publish_diagnostics(uri, &file_diagnostics, self.documents.version(uri));
+            per_file.push(file_diagnostics);
+        }
+        let (errors, warnings, files) = count_problems(per_file.iter());
+        json_object! {
+            "errors" => errors,
+            "warnings" => warnings,
+            "files" => files,
+            "checkedFiles" => checked.len(),
+        }
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Value) -> LspResult<Value> {
+        match method {
+            "initialize" => self.initialize(params),
+            "shutdown" => self.shutdown(),
+            "workspace/executeCommand" => self.execute_command(params),
+            "textDocument/completion" => self.completion(params),
+            "completionItem/resolve" => self.resolve_completion(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/codeLens" => self.code_lens(params),
+            "textDocument/codeAction" => self.code_action(params),
+            "syslogng/effectiveVersion" => self.effective_version(params),
+            "syslogng/documentStructure" => self.document_structure(params),
+            "textDocument/formatting" => self.formatting(params, false),
+            "textDocument/rangeFormatting" => self.formatting(params, true),
+            "textDocument/inlayHint" => self.inlay_hint(params),
+            "textDocument/selectionRange" => self.selection_range(params),
+            "textDocument/linkedEditingRange" => self.linked_editing_range(params),
+            "textDocument/prepareCallHierarchy" => self.prepare_call_hierarchy(params),
+            "callHierarchy/incomingCalls" => self.hierarchy_calls(params, true),
+            "callHierarchy/outgoingCalls" => self.hierarchy_calls(params, false),
+            "textDocument/documentSymbol" => self.document_symbol(params),
+            "workspace/symbol" => self.workspace_symbol(params),
+            "textDocument/semanticTokens/full" => self.semantic_tokens(params, false),
+            "textDocument/semanticTokens/range" => self.semantic_tokens(params, true),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/documentHighlight" => self.document_highlight(params),
+            "textDocument/prepareRename" => self.prepare_rename(params),
+            "textDocument/rename" => self.rename(params),
+            _ => Err(ResponseError::new(
+                ResponseError::METHOD_NOT_FOUND,
+                format!("method not found: {}", method),
+            )),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) {
+        match method {
+            "initialized" => self.initialized(params),
+            "textDocument/didOpen" => self.did_open(params),
+            "textDocument/didChange" => self.did_change(params),
+            "textDocument/didSave" => self.did_save(params),
+            "textDocument/didClose" => self.did_close(params),
+            "workspace/didChangeConfiguration" => self.did_change_configuration(params),
+            "workspace/didChangeWatchedFiles" => self.did_change_watched_files(params),
+            "workspace/didChangeWorkspaceFolders" => self.did_change_workspace_folders(params),
+            _ => {}
+        }
+    }
+
+    /// Handles one incoming message. Returns `false` once the client sent `exit`.
+    pub fn handle_message(&mut self, message: &Value) -> bool {
+        let Some(method) = message["method"].as_str() else {
+            // Responses to server-initiated requests are not used.
+            return true;
+        };
+        if method == "exit" {
+            return false;
+        }
+        let params = &message["params"];
+        if self.settings.log_document_content {
+            log_event!(Level::Trace, "received {}", message);
+        } else {
+            log_event!(Level::Trace, "received {}", redact_document_content(message));
+        }
+        let started = Instant::now();
+        match message.get("id") {
+            Some(id) => {
+                let mut response = json_object! { "jsonrpc" => "2.0", "id" => id.clone() };
+                let result = panic::catch_unwind(AssertUnwindSafe(|| self.handle_request(method, params)))
+                    .unwrap_or_else(|payload| {
+                        Err(ResponseError::new(
+                            ResponseError::INTERNAL_ERROR,
+                            panic_message(&payload),
+                        ))
+                    });
+                log_event!(
+                    Level::Debug,
+                    "request {} id={} ok={} took={:?}",
+                    method,
+                    id,
+                    result.is_ok(),
+                    started.elapsed()
+                );
+                match result {
+                    Ok(result) => response.insert("result", result),
+                    Err(error) => {
+                        if error.code == ResponseError::INTERNAL_ERROR {
+                            self.log_internal_error(method, params, &error.message);
+                        } else {
+                            log_event!(Level::Warn, "request {} failed: {}", method, error.message);
+                        }
+                        response.insert("error", error.to_json())
+                    }
+                }
+                self.client.send(&response);
+            }
+            None => {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| self.handle_notification(method, params)));
+                if let Err(payload) = result {
+                    self.log_internal_error(method, params, &panic_message(&payload));
+                }
+                log_event!(Level::Debug, "notification {} took={:?}", method, started.elapsed());
+            }
+        }
+        true
+    }
+
+    /// Logs what is needed to reproduce a failure: the document, its
+    /// revision and, unless disabled, the text around the position or
+    /// change the message was about.
+    fn log_internal_error(&self, method: &str, params: &Value, error: &str) {
+        let mut report = format!("internal error while handling {}: {}", method, error);
+        if let Some(uri) = params["textDocument"]["uri"].as_str() {
+            report.push_str(&format!("\ndocument: {}", uri));
+            if let Some(version) = self.documents.version(uri) {
+                report.push_str(&format!(" (version {})", version));
+            }
+            let line = Position::from_json(&params["position"])
+                .or_else(|| Position::from_json(&params["contentChanges"][0]["range"]["start"]))
+                .map(|p| p.line);
+            if let Some(line) = line {
+                report.push_str(&format!("\nline: {}", line + 1));
+            }
+            if self.settings.log_document_content {
+                if let Some(document) = self.documents.get(uri) {
+                    report.push_str("\nexcerpt:\n");
+                    report.push_str(&excerpt(&document.text, line.unwrap_or(0)));
+                }
+            }
+        }
+        self.client.log_message(MessageType::Error, report);
+    }
+}
+
+fn panic_message(payload: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panic".to_string()
+    }
+}
+
+/// `message` without the document text that notifications carry, the
+/// `text` of `didOpen` and `didSave` and the `contentChanges` of
+/// `didChange`, for a log that is to keep configurations out.
+fn redact_document_content(message: &Value) -> Value {
+    let mut message = message.clone();
+    if let Value::Object(fields) = &mut message {
+        if let Some(Value::Object(params)) = fields.get_mut("params") {
+            if let Some(Value::Object(document)) = params.get_mut("textDocument") {
+                if document.contains_key("text") {
+                    document.insert("text".to_string(), Value::from(REDACTED));
+                }
+            }
+            for key in ["text", "contentChanges"] {
+                if params.contains_key(key) {
+                    params.insert(key.to_string(), Value::from(REDACTED));
+                }
+            }
+        }
+    }
+    message
+}
+
+const REDACTED: &str = "[redacted]";
+
+/// Lines of `text` around `line`, cut off after a few hundred bytes so a
+/// crash log stays readable.
+fn excerpt(text: &str, line: u32) -> String {
+    const CONTEXT_LINES: usize = 3;
+    const MAX_LEN: usize = 800;
+    let line = line as usize;
+    let lines: Vec<&str> = text
+        .lines()
+        .skip(line.saturating_sub(CONTEXT_LINES))
+        .take(2 * CONTEXT_LINES + 1)
+        .collect();
+    let mut excerpt = lines.join("\n");
+    if excerpt.len() > MAX_LEN {
+        let mut end = MAX_LEN;
+        while !excerpt.is_char_boundary(end) {
+            end -= 1;
+        }
+        excerpt.truncate(end);
+        excerpt.push_str(" [...]");
+    }
+    excerpt
+}
+
+/// Adds the warnings for drivers of another platform than the configured
+/// one to the `diagnostics` published for a file, `parsed` its contents,
+/// and drops those the settings leave out.
+fn apply_settings(settings: &ServerSettings, parsed: Option<&ParsedConfiguration>, diagnostics: &mut Vec<Diagnostic>) {
+    if let Some(platform) = settings.target_platform {
+        diagnostics.extend(parsed.into_iter().flat_map(|parsed| check_platform(parsed, platform)));
+    }
+    match settings.validation_mode {
+        ValidationMode::Full => {}
+        ValidationMode::Syntax => diagnostics
+            .retain(|d| d.code.as_deref() == Some("syntax-error") || d.source == syslog_ng::SYNTAX_CHECK_SOURCE),
+        ValidationMode::Off => diagnostics.clear(),
+    }
+    if !settings.deployment_lints {
+        diagnostics.retain(|d| !is_deployment_lint(d));
+    }
+    if settings.max_diagnostics_per_file > 0 {
+        limit_diagnostics(diagnostics, settings.max_diagnostics_per_file);
+    }
+}
+
+/// Errors, warnings and the number of files with either among the
+/// diagnostics of each file.
+fn count_problems<'a>(per_file: impl Iterator<Item = &'a Vec<Diagnostic>>) -> (usize, usize, usize) {
+    let (mut errors, mut warnings, mut files) = (0, 0, 0);
+    for diagnostics in per_file {
+        let file_errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Error)
+            .count();
+        let file_warnings = diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Warning)
+            .count();
+        errors += file_errors;
+        warnings += file_warnings;
+        files += usize::from(file_errors + file_warnings > 0);
+    }
+    (errors, warnings, files)
+}
+
+/// `textDocument` URI and `position` of a position-based request.
+fn text_document_position(params: &Value) -> LspResult<(String, Position)> {
+    let uri = params["textDocument"]["uri"].as_str();
+    let position = Position::from_json(&params["position"]);
+    match (uri, position) {
+        (Some(uri), Some(position)) => Ok((uri.to_string(), position)),
+        _ => Err(ResponseError::invalid_params("expected a text document and a position")),
+    }
+}
+
+/// Serves the protocol until `exit`, returning the process exit code.
+pub fn run<R: BufRead>(reader: &mut R, writer: Box<dyn Write + Send>) -> i32 {
+    let mut backend = Backend::new(Client::new(writer));
+    loop {
+        match read_message(reader) {
+            Ok(Some(message)) => {
+                if !backend.handle_message(&message) {
+                    return if backend.shutdown_requested { 0 } else { 1 };
+                }
+            }
+            Ok(None) => return 1,
+            Err(error) => {
+                backend
+                    .client
+                    .log_message(MessageType::Error, format!("failed to read message: {}", error));
+                if error.kind() != std::io::ErrorKind::InvalidData {
+                    return 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::json;
+
+    /// Collects what the backend sends to the client.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Recorder {
+        /// The messages sent so far, which are then forgotten.
+        fn take(&self) -> Vec<Value> {
+            let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+            let mut reader = bytes.as_slice();
+            std::iter::from_fn(|| read_message(&mut reader).unwrap()).collect()
+        }
+    }
+
+    /// A workspace folder holding `files`, removed first if a previous run
+    /// left it behind.
+    fn workspace(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sng-lsp-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, text) in files {
+            fs::write(dir.join(file), text).unwrap();
+        }
+        dir
+    }
+
+    fn start(folder: &Path, options: &str) -> (Backend, Recorder) {
+        let recorder = Recorder::default();
+        let mut backend = Backend::new(Client::new(Box::new(recorder.clone())));
+        let initialize = format!(
+            r#"{{"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": {{"rootUri": "{}", "capabilities": {{}}, "initializationOptions": {}}}}}"#,
+            path_to_uri(folder),
+            options
+        );
+        backend.handle_message(&json::parse(&initialize).unwrap());
+        recorder.take();
+        (backend, recorder)
+    }
+
+    /// Runs the validation command, returning its summary and the
+    /// diagnostics it published by URI.
+    fn validate(backend: &mut Backend, recorder: &Recorder) -> (Value, Vec<(String, Vec<Value>)>) {
+        let request = format!(
+            r#"{{"jsonrpc": "2.0", "id": 2, "method": "workspace/executeCommand",
+                "params": {{"command": "{}", "arguments": []}}}}"#,
+            commands::VALIDATE_CONFIGURATION
+        );
+        backend.handle_message(&json::parse(&request).unwrap());
+        let mut summary = Value::Null;
+        let mut published = Vec::new();
+        for message in recorder.take() {
+            if message["id"].as_i64() == Some(2) {
+                summary = message["result"].clone();
+            } else if message["method"].as_str() == Some("textDocument/publishDiagnostics") {
+                let params = &message["params"];
+                let diagnostics = params["diagnostics"].as_array().cloned().unwrap_or_default();
+                published.push((params["uri"].as_str().unwrap().to_string(), diagnostics));
+            }
+        }
+        (summary, published)
+    }
+
+    fn codes(diagnostics: &[Value]) -> Vec<&str> {
+        diagnostics.iter().filter_map(|d| d["code"].as_str()).collect()
+    }
+
+    const UNPRIVILEGED: &str = "@version: 4.2\noptions { user(\"syslog\"); };\n\
+        source s { systemd-journal(); network(port(514)); };\nlog { source(s); };\n";
+
+    #[test]
+    fn validation_publishes_what_editing_would() {
+        let folder = workspace("validate-settings", &[("syslog-ng.conf", UNPRIVILEGED)]);
+        let (mut backend, recorder) = start(&folder, r#"{"targetPlatform": "bsd"}"#);
+        let (summary, published) = validate(&mut backend, &recorder);
+        assert_eq!(published.len(), 1);
+        let codes = codes(&published[0].1);
+        assert!(codes.contains(&"wrong-platform"), "{:?}", codes);
+        assert!(!codes.contains(&"privileged-port"), "{:?}", codes);
+        assert_eq!(summary["warnings"].as_u64(), Some(published[0].1.len() as u64));
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn validation_counts_what_it_publishes() {
+        let folder = workspace("validate-limit", &[("syslog-ng.conf", UNPRIVILEGED)]);
+        let options = r#"{"targetPlatform": "bsd", "deploymentLints": true, "maxDiagnosticsPerFile": 1}"#;
+        let (mut backend, recorder) = start(&folder, options);
+        let (summary, published) = validate(&mut backend, &recorder);
+        assert_eq!(codes(&published[0].1).len(), 2, "{:?}", published[0].1);
+        assert_eq!(codes(&published[0].1)[0], "too-many-diagnostics");
+        assert_eq!(summary["warnings"].as_u64(), Some(1));
+
+        let (mut backend, recorder) = start(&folder, r#"{"validationMode": "off"}"#);
+        let (summary, published) = validate(&mut backend, &recorder);
+        assert!(published[0].1.is_empty());
+        assert_eq!(summary["warnings"].as_u64(), Some(0));
+        assert_eq!(summary["files"].as_u64(), Some(0));
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn validation_checks_a_file_once_however_its_uri_is_spelt() {
+        let folder = workspace(
+            "validate-spelling",
+            &[("syslog-ng.conf", "source s { file(\"/x\") }\n")],
+        );
+        let (mut backend, recorder) = start(&folder, "{}");
+        let uri = path_to_uri(&folder.join("syslog-ng.conf")).replace("syslog-ng", "syslog%2Dng");
+        let open = format!(
+            r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen",
+                "params": {{"textDocument": {{"uri": "{}", "version": 1, "text": "source s {{ file(\"/x\") }}\n"}}}}}}"#,
+            uri
+        );
+        backend.handle_message(&json::parse(&open).unwrap());
+        recorder.take();
+        let (summary, published) = validate(&mut backend, &recorder);
+        assert_eq!(summary["checkedFiles"].as_u64(), Some(1));
+        assert_eq!(summary["errors"].as_u64(), Some(1));
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].0, uri);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn redacts_the_document_content_of_notifications() {
+        let open = json::parse(
+            r#"{"method": "textDocument/didOpen",
+                "params": {"textDocument": {"uri": "file:///a.conf", "version": 1, "text": "secret"}}}"#,
+        )
+        .unwrap();
+        let redacted = redact_document_content(&open);
+        assert_eq!(redacted["params"]["textDocument"]["text"].as_str(), Some(REDACTED));
+        assert_eq!(
+            redacted["params"]["textDocument"]["uri"].as_str(),
+            Some("file:///a.conf")
+        );
+
+        let change = json::parse(
+            r#"{"method": "textDocument/didChange",
+                "params": {"textDocument": {"uri": "file:///a.conf", "version": 2},
+                           "contentChanges": [{"text": "secret"}]}}"#,
+        )
+        .unwrap();
+        let redacted = redact_document_content(&change);
+        assert!(!redacted.to_string().contains("secret"));
+        assert_eq!(redacted["params"]["textDocument"]["version"].as_i64(), Some(2));
+
+        let hover = json::parse(r#"{"id": 1, "method": "textDocument/hover", "params": {"position": {}}}"#).unwrap();
+        assert_eq!(redact_document_content(&hover), hover);
+    }
+}
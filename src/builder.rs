@@ -0,0 +1,289 @@
+//! Construction of configurations from Rust: the builders put together the
+//! same AST [`crate::parser`] produces, which
+//! [`ToSource`](crate::language_types::source::ToSource) turns into
+//! syslog-ng syntax.
+//!
+//! ```text
+//! let text = ConfigBuilder::new()
+//!     .version("4.8")
+//!     .object(ObjectBuilder::new(ObjectKind::Source, "s_net")
+//!         .driver(DriverBuilder::new("network").option(DriverBuilder::new("port").number(514))))
+//!     .object(ObjectBuilder::filter(Some("f_errors"), not(call(DriverBuilder::new("level").word("debug..notice")))))
+//!     .object(ObjectBuilder::log().reference(ObjectKind::Source, "s_net").reference(ObjectKind::Filter, "f_errors"))
+//!     .build()?;
+//! ```
+//!
+//! Nodes built here have empty ranges; parse the built text for positions.
+
+use std::fmt::Display;
+
+use crate::formatter::{format_document, FormattingOptions};
+use crate::language_types::annotations::{Annotation, DefineAnnotation, IncludeAnnotation, VersionAnnotation};
+use crate::language_types::filter_expression::{Comparison, Expression, FilterExpression};
+use crate::language_types::objects::{
+    Driver, Identifier, Literal, LiteralKind, Object, ObjectBody, ObjectKind, Parameter, Statement,
+};
+use crate::language_types::source::ToSource;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::Range;
+use crate::parser::references::collect_references;
+use crate::parser::{parse_conf, SngSyntaxError};
+
+fn identifier(name: &str) -> Identifier {
+    Identifier {
+        name: name.to_string(),
+        range: Range::default(),
+    }
+}
+
+fn literal(kind: LiteralKind, value: String) -> Literal {
+    Literal {
+        kind,
+        value,
+        raw: None,
+        range: Range::default(),
+    }
+}
+
+/// A quoted value, `"text"`.
+pub fn string(value: impl Into<String>) -> Literal {
+    literal(LiteralKind::String, value.into())
+}
+
+/// A bare value such as `tcp` or an object identifier.
+pub fn word(value: impl Into<String>) -> Literal {
+    literal(LiteralKind::Word, value.into())
+}
+
+pub fn number(value: impl Display) -> Literal {
+    literal(LiteralKind::Number, value.to_string())
+}
+
+/// A filter function as an expression, e.g. `facility(mail)`.
+pub fn call(function: DriverBuilder) -> Expression {
+    Expression::Call(function.build())
+}
+
+pub fn not(operand: Expression) -> Expression {
+    Expression::Not {
+        keyword: identifier("not"),
+        operand: Box::new(operand),
+    }
+}
+
+pub fn and(left: Expression, right: Expression) -> Expression {
+    binary("and", left, right)
+}
+
+pub fn or(left: Expression, right: Expression) -> Expression {
+    binary("or", left, right)
+}
+
+fn binary(operator: &str, left: Expression, right: Expression) -> Expression {
+    Expression::Binary {
+        operator: identifier(operator),
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+/// `"${PID}" == 1` and the like; `operator` is one of
+/// [`crate::language_types::filter_expression::NUMERIC_COMPARISONS`] or
+/// [`crate::language_types::filter_expression::STRING_COMPARISONS`].
+pub fn compare(left: Literal, operator: &str, right: Literal) -> Expression {
+    Expression::Comparison(Comparison {
+        left,
+        operator: identifier(operator),
+        right,
+    })
+}
+
+/// A driver, or an option when given to [`DriverBuilder::option`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriverBuilder {
+    driver: Driver,
+}
+
+impl DriverBuilder {
+    pub fn new(name: &str) -> Self {
+        DriverBuilder {
+            driver: Driver {
+                name: identifier(name),
+                parameters: Vec::new(),
+                location: Range::default(),
+            },
+        }
+    }
+
+    pub fn value(mut self, value: Literal) -> Self {
+        self.driver.parameters.push(Parameter::Literal(value));
+        self
+    }
+
+    pub fn string(self, value: impl Into<String>) -> Self {
+        self.value(string(value))
+    }
+
+    pub fn word(self, value: impl Into<String>) -> Self {
+        self.value(word(value))
+    }
+
+    pub fn number(self, value: impl Display) -> Self {
+        self.value(number(value))
+    }
+
+    pub fn option(mut self, option: DriverBuilder) -> Self {
+        self.driver.parameters.push(Parameter::Option(option.driver));
+        self
+    }
+
+    pub fn build(self) -> Driver {
+        self.driver
+    }
+}
+
+/// A top-level object, or an inline one when given to
+/// [`ObjectBuilder::object`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectBuilder {
+    object: Object,
+}
+
+impl ObjectBuilder {
+    /// An object with an identifier, `source s_net { ... };`.
+    pub fn new(kind: ObjectKind, id: &str) -> Self {
+        let mut builder = ObjectBuilder::anonymous(kind);
+        builder.object.id = Some(identifier(id));
+        builder
+    }
+
+    /// An object without one: `log`, `options` or an inline object of a
+    /// log path.
+    pub fn anonymous(kind: ObjectKind) -> Self {
+        ObjectBuilder {
+            object: Object {
+                kind,
+                keyword_range: Range::default(),
+                id: None,
+                body: ObjectBody::Statements(Vec::new()),
+                location: Range::default(),
+            },
+        }
+    }
+
+    /// `filter f_name { expression };`, or an inline filter without `id`.
+    pub fn filter(id: Option<&str>, expression: Expression) -> Self {
+        let mut builder = ObjectBuilder::anonymous(ObjectKind::Filter);
+        builder.object.id = id.map(identifier);
+        builder.object.body = ObjectBody::Expression(Box::new(FilterExpression {
+            root: expression,
+            tokens: Vec::new(),
+        }));
+        builder
+    }
+
+    pub fn log() -> Self {
+        ObjectBuilder::anonymous(ObjectKind::Log)
+    }
+
+    pub fn options() -> Self {
+        ObjectBuilder::anonymous(ObjectKind::Options)
+    }
+
+    fn push(mut self, statement: Statement) -> Self {
+        if let ObjectBody::Statements(statements) = &mut self.object.body {
+            statements.push(statement);
+        }
+        self
+    }
+
+    /// A driver, or a global option of `options { ... };`. Ignored by
+    /// filters, which have an expression instead.
+    pub fn driver(self, driver: DriverBuilder) -> Self {
+        self.push(Statement::Driver(driver.driver))
+    }
+
+    /// An inline object of a log path.
+    pub fn object(self, object: ObjectBuilder) -> Self {
+        self.push(Statement::Object(object.object))
+    }
+
+    /// `source(s_net);` and the like in a log path.
+    pub fn reference(self, kind: ObjectKind, id: &str) -> Self {
+        self.driver(DriverBuilder::new(kind.keyword()).word(id))
+    }
+
+    pub fn build(self) -> Object {
+        self.object
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigBuilder {
+    configuration: ParsedConfiguration,
+}
+
+impl ConfigBuilder {
+    pub fn new() -> Self {
+        ConfigBuilder::default()
+    }
+
+    /// `@version: 4.8`, written first whatever the order of the calls.
+    pub fn version(mut self, version: &str) -> Self {
+        let annotations = &mut self.configuration.annotations;
+        annotations.retain(|a| !matches!(a, Annotation::Version(_)));
+        annotations.insert(
+            0,
+            Annotation::Version(VersionAnnotation {
+                version: version.to_string(),
+                range: Range::default(),
+                value_range: Range::default(),
+            }),
+        );
+        self
+    }
+
+    pub fn include(mut self, path: &str) -> Self {
+        self.configuration
+            .annotations
+            .push(Annotation::Include(IncludeAnnotation {
+                path: path.to_string(),
+                range: Range::default(),
+                path_range: Range::default(),
+            }));
+        self
+    }
+
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.configuration
+            .annotations
+            .push(Annotation::Define(DefineAnnotation {
+                name: name.to_string(),
+                value: value.to_string(),
+                range: Range::default(),
+                name_range: Range::default(),
+            }));
+        self
+    }
+
+    pub fn object(mut self, object: ObjectBuilder) -> Self {
+        self.configuration.objects.push(object.object);
+        self
+    }
+
+    /// The configuration as the parser would return it, without positions.
+    pub fn configuration(&self) -> ParsedConfiguration {
+        let mut configuration = self.configuration.clone();
+        configuration.references = collect_references(&configuration.objects);
+        configuration
+    }
+
+    /// The configuration as formatted text. Fails with the error the parser
+    /// reports for it when a name given to a builder cannot be written,
+    /// e.g. an identifier containing spaces.
+    pub fn build(&self) -> Result<String, SngSyntaxError> {
+        let text = self.configuration.to_source();
+        parse_conf(&text)?;
+        Ok(format_document(&text, &FormattingOptions::default()).unwrap_or(text))
+    }
+}
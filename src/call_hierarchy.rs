@@ -0,0 +1,316 @@
+//! Message flow as a call hierarchy (`textDocument/prepareCallHierarchy`).
+//!
+//! Log paths are the calls: a source "calls" the log paths that read from
+//! it, and a log path calls the filters, parsers, rewrites, destinations and
+//! nested log paths its messages pass through. Incoming calls go the other
+//! way, so expanding outgoing calls from a source shows where its messages
+//! end up and expanding incoming calls from a destination where they come
+//! from.
+
+use crate::json::Value;
+use crate::json_object;
+use crate::language_types::objects::{LiteralKind, Object, ObjectKind, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{CallHierarchyCall, CallHierarchyItem, FromJson, Position, Range};
+use crate::parser::object_parser::match_object_kind;
+use crate::symbols::symbol_kind;
+
+/// What an item stands for, recovered from the item the client sends back.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Object {
+        kind: ObjectKind,
+        name: String,
+    },
+    Log {
+        uri: String,
+        range: Range,
+    },
+    /// An object defined inline in a log path; it has no further calls.
+    Inline,
+}
+
+impl Node {
+    pub fn from_item(item: &Value) -> Option<Node> {
+        let data = &item["data"];
+        match data["kind"].as_str()? {
+            "log" => Some(Node::Log {
+                uri: item["uri"].as_str()?.to_string(),
+                range: Range::from_json(&item["range"])?,
+            }),
+            "inline" => Some(Node::Inline),
+            keyword => Some(Node::Object {
+                kind: match_object_kind(keyword)?,
+                name: data["name"].as_str()?.to_string(),
+            }),
+        }
+    }
+}
+
+/// A step of a log path, in the order messages pass through them.
+enum Element<'a> {
+    Reference {
+        kind: ObjectKind,
+        name: &'a str,
+        range: Range,
+    },
+    Inline(&'a Object),
+    Log(&'a Object),
+}
+
+fn elements(log: &Object) -> Vec<Element<'_>> {
+    let mut elements = Vec::new();
+    push_elements(log.statements(), &mut elements);
+    elements
+}
+
+/// The branches of an `if` block and the channels of a junction count as
+/// steps of the enclosing log path, filters called in the conditions of
+/// the branches included.
+fn push_elements<'a>(statements: &'a [Statement], elements: &mut Vec<Element<'a>>) {
+    for statement in statements {
+        match statement {
+            Statement::Object(inline) if inline.kind == ObjectKind::Log => elements.push(Element::Log(inline)),
+            Statement::Object(inline) => elements.push(Element::Inline(inline)),
+            Statement::Conditional(conditional) => {
+                for branch in &conditional.branches {
+                    let filters = branch.condition.iter().flat_map(|c| c.filter_references());
+                    elements.extend(filters.map(|name| Element::Reference {
+                        kind: ObjectKind::Filter,
+                        name: &name.value,
+                        range: name.range,
+                    }));
+                    push_elements(&branch.statements, elements);
+                }
+            }
+            Statement::Junction(junction) => {
+                for channel in &junction.channels {
+                    push_elements(&channel.statements, elements);
+                }
+            }
+            Statement::Driver(driver) => {
+                let Some(kind) = match_object_kind(&driver.name.name).filter(|k| k.has_id()) else {
+                    continue;
+                };
+                let names = driver.literals().filter(|l| l.kind == LiteralKind::Word);
+                elements.extend(names.map(|l| Element::Reference {
+                    kind,
+                    name: &l.value,
+                    range: l.range,
+                }));
+            }
+        }
+    }
+}
+
+/// Every log path of `files`, nested ones included, with its file and the
+/// log path it is nested in.
+fn log_paths(files: &[(String, ParsedConfiguration)]) -> Vec<(&str, &Object, Option<&Object>)> {
+    fn collect<'a>(
+        uri: &'a str,
+        log: &'a Object,
+        parent: Option<&'a Object>,
+        logs: &mut Vec<(&'a str, &'a Object, Option<&'a Object>)>,
+    ) {
+        logs.push((uri, log, parent));
+        for element in elements(log) {
+            if let Element::Log(nested) = element {
+                collect(uri, nested, Some(log), logs);
+            }
+        }
+    }
+    let mut logs = Vec::new();
+    for (uri, parsed) in files {
+        for log in parsed.get_objects_by_kind(ObjectKind::Log) {
+            collect(uri, log, None, &mut logs);
+        }
+    }
+    logs
+}
+
+/// The item for the identifier under `position`, or for the log path whose
+/// `log` keyword is there.
+pub fn prepare(uri: &str, parsed: &ParsedConfiguration, position: Position) -> Option<CallHierarchyItem> {
+    if let Some((kind, name, range)) = parsed.symbol_at(position) {
+        if kind == ObjectKind::Template {
+            return None;
+        }
+        let files = [(uri.to_string(), parsed.clone())];
+        return Some(object_item(kind, name, &files).unwrap_or_else(|| undefined_item(kind, name, uri, range)));
+    }
+    log_paths(&[(uri.to_string(), parsed.clone())])
+        .into_iter()
+        .find(|(_, log, _)| log.keyword_range.contains(position))
+        .map(|(_, log, _)| log_item(uri, log))
+}
+
+/// The items that send messages to `node`.
+pub fn incoming_calls(node: &Node, files: &[(String, ParsedConfiguration)]) -> Vec<CallHierarchyCall> {
+    let mut calls = Vec::new();
+    match node {
+        Node::Object { kind, .. } if *kind == ObjectKind::Source => {}
+        Node::Object { kind, name } => {
+            for (uri, log, _) in log_paths(files) {
+                for element in elements(log) {
+                    if let Element::Reference {
+                        kind: k,
+                        name: n,
+                        range,
+                    } = element
+                    {
+                        if k == *kind && n == name {
+                            push_call(&mut calls, log_item(uri, log), range);
+                        }
+                    }
+                }
+            }
+        }
+        Node::Log { uri, range } => {
+            let Some((_, log, parent)) = find_log(files, uri, *range) else {
+                return calls;
+            };
+            if let Some(parent) = parent {
+                push_call(&mut calls, log_item(uri, parent), log.keyword_range);
+            }
+            for element in elements(log) {
+                match element {
+                    Element::Reference { kind, name, range } if kind == ObjectKind::Source => {
+                        let item =
+                            object_item(kind, name, files).unwrap_or_else(|| undefined_item(kind, name, uri, range));
+                        let selection = item.selection_range;
+                        push_call(&mut calls, item, selection);
+                    }
+                    Element::Inline(inline) if inline.kind == ObjectKind::Source => {
+                        push_call(&mut calls, inline_item(uri, inline), inline.keyword_range);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Node::Inline => {}
+    }
+    calls
+}
+
+/// The items `node` sends messages to; `selection` is the selection range
+/// of the item `node` was recovered from.
+pub fn outgoing_calls(
+    node: &Node,
+    selection: Range,
+    files: &[(String, ParsedConfiguration)],
+) -> Vec<CallHierarchyCall> {
+    let mut calls = Vec::new();
+    match node {
+        Node::Object { kind, name } if *kind == ObjectKind::Source => {
+            for (uri, log, _) in log_paths(files) {
+                let reads = elements(log)
+                    .iter()
+                    .any(|e| matches!(e, Element::Reference { kind: k, name: n, .. } if k == kind && n == name));
+                if reads {
+                    push_call(&mut calls, log_item(uri, log), selection);
+                }
+            }
+        }
+        Node::Object { .. } | Node::Inline => {}
+        Node::Log { uri, range } => {
+            let Some((_, log, _)) = find_log(files, uri, *range) else {
+                return calls;
+            };
+            for element in elements(log) {
+                match element {
+                    Element::Reference { kind, name, range } if kind != ObjectKind::Source => {
+                        let to =
+                            object_item(kind, name, files).unwrap_or_else(|| undefined_item(kind, name, uri, range));
+                        push_call(&mut calls, to, range);
+                    }
+                    Element::Inline(inline) if inline.kind != ObjectKind::Source => {
+                        push_call(&mut calls, inline_item(uri, inline), inline.keyword_range);
+                    }
+                    Element::Log(nested) => push_call(&mut calls, log_item(uri, nested), nested.keyword_range),
+                    _ => {}
+                }
+            }
+        }
+    }
+    calls
+}
+
+/// Adds a call to `item` at `range`, merged with an earlier call to the same item.
+fn push_call(calls: &mut Vec<CallHierarchyCall>, item: CallHierarchyItem, range: Range) {
+    match calls
+        .iter_mut()
+        .find(|c| c.item.uri == item.uri && c.item.range == item.range)
+    {
+        Some(call) => call.from_ranges.push(range),
+        None => calls.push(CallHierarchyCall {
+            item,
+            from_ranges: vec![range],
+        }),
+    }
+}
+
+fn find_log<'a>(
+    files: &'a [(String, ParsedConfiguration)],
+    uri: &str,
+    range: Range,
+) -> Option<(&'a str, &'a Object, Option<&'a Object>)> {
+    log_paths(files)
+        .into_iter()
+        .find(|(u, log, _)| *u == uri && log.location == range)
+}
+
+/// The item of the definition of the `kind` object `name`.
+fn object_item(kind: ObjectKind, name: &str, files: &[(String, ParsedConfiguration)]) -> Option<CallHierarchyItem> {
+    files.iter().find_map(|(uri, parsed)| {
+        let object = parsed
+            .get_objects_by_kind(kind)
+            .find(|o| o.id.as_ref().is_some_and(|id| id.name == name))?;
+        Some(CallHierarchyItem {
+            name: name.to_string(),
+            kind: symbol_kind(kind),
+            detail: Some(kind.to_string()),
+            uri: uri.clone(),
+            range: object.location,
+            selection_range: object.id.as_ref()?.range,
+            data: json_object! { "kind" => kind.keyword(), "name" => name },
+        })
+    })
+}
+
+/// An item for a reference to an object that is defined nowhere, located at
+/// the reference.
+fn undefined_item(kind: ObjectKind, name: &str, uri: &str, range: Range) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: name.to_string(),
+        kind: symbol_kind(kind),
+        detail: Some(format!("undefined {}", kind)),
+        uri: uri.to_string(),
+        range,
+        selection_range: range,
+        data: json_object! { "kind" => kind.keyword(), "name" => name },
+    }
+}
+
+fn log_item(uri: &str, log: &Object) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: "log".to_string(),
+        kind: symbol_kind(ObjectKind::Log),
+        detail: Some(format!("line {}", log.location.start.line + 1)),
+        uri: uri.to_string(),
+        range: log.location,
+        selection_range: log.keyword_range,
+        data: json_object! { "kind" => "log" },
+    }
+}
+
+fn inline_item(uri: &str, object: &Object) -> CallHierarchyItem {
+    CallHierarchyItem {
+        name: object.kind.to_string(),
+        kind: symbol_kind(object.kind),
+        detail: Some("inline".to_string()),
+        uri: uri.to_string(),
+        range: object.location,
+        selection_range: object.keyword_range,
+        data: json_object! { "kind" => "inline" },
+    }
+}
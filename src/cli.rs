@@ -0,0 +1,514 @@
+//! Command line modes: `lsp-syslog-ng check [--fix] [--deployment-lints]
+//! [--format human|json] <file or directory>...` reports the diagnostics the
+//! language server would publish for each file, for use without an editor
+//! such as in CI; with `--fix` it first applies the preferred quick fixes to
+//! the file on disk, and `--deployment-lints` adds the checks of how the
+//! configuration will run, like privileged ports bound by an unprivileged
+//! syslog-ng.
+//! `--changed-from <revision>` and `--stdin-paths` limit the report to the
+//! files changed since a git revision or listed on standard input, for
+//! commit hooks; the paths given, the current directory by default, are
+//! still read to find the files including them.
+//! `lsp-syslog-ng --listen <address>` serves a client connecting over a
+//! socket instead of stdio.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::backend;
+use crate::code_actions::apply_safe_fixes;
+use crate::configuration::{resolve_snippets, IncludeOptions, SnippetCache};
+use crate::diagnostics::{
+    analyse_configuration, analyse_file, invalid_utf8_warning, is_deployment_lint, syntax_error_report,
+};
+use crate::document_store::LoadedText;
+use crate::file_utilities::{get_workspace_config_files, path_to_uri, real_path, uri_to_path};
+use crate::json::Value;
+use crate::json_object;
+use crate::lsp_types::{Diagnostic, DiagnosticSeverity};
+use crate::parser::{parse_conf, parse_conf_recovering};
+
+const USAGE: &str = "usage: lsp-syslog-ng check [--fix] [--deployment-lints] [--format human|json] \
+[--changed-from <revision>] [--stdin-paths] <file or directory>...";
+const LISTEN_USAGE: &str = "usage: lsp-syslog-ng --listen tcp://<host>:<port> | unix:<path>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Human,
+    Json,
+}
+
+/// The diagnostics of one file, in the order they are reported.
+struct FileReport {
+    path: PathBuf,
+    uri: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Runs `check` with the arguments following it. Returns the exit code:
+/// 0 when no file has errors, 1 when some do and 2 on invalid usage.
+pub fn check(args: &[String]) -> i32 {
+    let mut fix = false;
+    let mut deployment_lints = false;
+    let mut format = Format::Human;
+    let mut changed_from = None;
+    let mut stdin_paths = false;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--fix" => {
+                fix = true;
+                continue;
+            }
+            "--deployment-lints" => {
+                deployment_lints = true;
+                continue;
+            }
+            "--stdin-paths" => {
+                stdin_paths = true;
+                continue;
+            }
+            "--changed-from" => match args.next() {
+                Some(revision) => {
+                    changed_from = Some(revision.clone());
+                    continue;
+                }
+                None => {
+                    eprintln!("`--changed-from` takes a git revision\n{}", USAGE);
+                    return 2;
+                }
+            },
+            _ if arg.starts_with("--changed-from=") => {
+                changed_from = arg.strip_prefix("--changed-from=").map(str::to_string);
+                continue;
+            }
+            "--format" => args.next().map(String::as_str),
+            _ if arg.starts_with("--format=") => arg.strip_prefix("--format="),
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{}`\n{}", arg, USAGE);
+                return 2;
+            }
+            _ => {
+                paths.push(PathBuf::from(arg));
+                continue;
+            }
+        };
+        format = match value {
+            Some("human") => Format::Human,
+            Some("json") => Format::Json,
+            _ => {
+                eprintln!("`--format` takes `human` or `json`\n{}", USAGE);
+                return 2;
+            }
+        };
+    }
+
+    // The real paths of the files to report on; `None` for all of them.
+    let mut listed: Option<HashSet<PathBuf>> = None;
+    let mut extra = Vec::new();
+    if changed_from.is_some() || stdin_paths {
+        if let Some(revision) = &changed_from {
+            match changed_files(revision) {
+                Ok(files) => extra.extend(files),
+                Err(error) => {
+                    eprintln!("cannot list the files changed from `{}`: {}", revision, error);
+                    return 2;
+                }
+            }
+        }
+        if stdin_paths {
+            let lines = io::stdin().lock().lines().map_while(Result::ok);
+            extra.extend(lines.filter(|l| !l.trim().is_empty()).map(|l| PathBuf::from(l.trim())));
+        }
+        listed = Some(extra.iter().map(|path| real_path(path)).collect());
+        if paths.is_empty() {
+            paths.push(PathBuf::from("."));
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        return 2;
+    }
+    let is_listed = |path: &Path| listed.as_ref().is_none_or(|listed| listed.contains(&real_path(path)));
+    let is_listed_uri = |uri: &str| uri_to_path(uri).is_some_and(|path| is_listed(&path));
+
+    // A directory stands for the configuration files below it. Those
+    // another file of the directory includes get only the checks of the
+    // file alone; the rest of their diagnostics are reported through the
+    // including file, where their references resolve.
+    let mut files = Vec::new();
+    if !extra.is_empty() || listed.is_none() {
+        for path in &paths {
+            if path.is_dir() {
+                files.extend(get_workspace_config_files(path).into_iter().map(|file| (file, true)));
+            } else {
+                files.push((path.clone(), false));
+            }
+        }
+    }
+    // Listed files outside the paths given are checked on their own.
+    let known: HashSet<PathBuf> = files.iter().map(|(file, _)| real_path(file)).collect();
+    files.extend(
+        extra
+            .into_iter()
+            .filter(|file| !known.contains(&real_path(file)))
+            .map(|file| (file, false)),
+    );
+
+    let cache = SnippetCache::default();
+    let options = IncludeOptions::default();
+    let load = |path: &Path| fs::read(path).ok().map(LoadedText::decode);
+    let (mut errors, mut fixes, mut fixed_files) = (0, 0, 0);
+    let mut checked = Vec::new();
+    for (path, in_directory) in files {
+        let reported = is_listed(&path);
+        let Some(mut loaded) = load(&path) else {
+            if reported {
+                eprintln!("{}: cannot read file", path.display());
+                errors += 1;
+            }
+            continue;
+        };
+        if fix && reported {
+            match fix_file(&path, &mut loaded) {
+                Ok(Some(applied)) if !applied.is_empty() => {
+                    if format == Format::Human {
+                        for title in &applied {
+                            println!("{}: fixed: {}", path.display(), title);
+                        }
+                    }
+                    fixes += applied.len();
+                    fixed_files += 1;
+                }
+                Ok(Some(_)) => {}
+                Ok(None) => eprintln!("{}: not valid UTF-8, fixes not applied", path.display()),
+                Err(error) => {
+                    eprintln!("{}: cannot write fixes: {}", path.display(), error);
+                    errors += 1;
+                    continue;
+                }
+            }
+        }
+        // Includes are resolved relative to the file, which needs an absolute path.
+        let uri = path_to_uri(&real_path(&path));
+        let parsed = parse_conf(&loaded.text);
+        // Syntax errors of an included file are only reported on the file
+        // itself, like the checks of the file alone; the rest comes from
+        // the files including it.
+        let standalone = !in_directory || parsed.is_err();
+        let lints = match &parsed {
+            Ok(parsed) if !standalone && reported => analyse_file(parsed),
+            _ => Vec::new(),
+        };
+        let mut report = match &parsed {
+            Ok(parsed) => {
+                let snippets = resolve_snippets(&uri, parsed, &load, &cache, &options);
+                // The analysis is the slow part; skip it where it cannot
+                // report on a listed file.
+                if !reported && !snippets.iter().any(|snippet| is_listed(&snippet.path)) {
+                    continue;
+                }
+                analyse_configuration(&uri, parsed, &snippets)
+            }
+            Err(_) if !reported => continue,
+            Err(_) => syntax_error_report(&parse_conf_recovering(&loaded.text).1),
+        };
+        // Included files get the warning from their includer, the file
+        // itself from here.
+        if let Some(position) = loaded.invalid_utf8 {
+            report.own.push(invalid_utf8_warning(position));
+        }
+        checked.push((path, uri, standalone, report, lints));
+    }
+
+    let included: HashSet<&str> = checked
+        .iter()
+        .flat_map(|(_, uri, _, report, _)| report.snippets.iter().filter(move |s| *s != uri))
+        .map(String::as_str)
+        .collect();
+    let mut files: Vec<FileReport> = Vec::new();
+    for (path, uri, standalone, report, lints) in &checked {
+        if !standalone && included.contains(uri.as_str()) {
+            if is_listed_uri(uri) {
+                push_diagnostics(&mut files, uri, || path.clone(), lints);
+            }
+            continue;
+        }
+        if is_listed_uri(uri) {
+            push_diagnostics(&mut files, uri, || path.clone(), &report.own);
+        }
+        let mut related: Vec<_> = report.related.iter().filter(|(uri, _)| is_listed_uri(uri)).collect();
+        related.sort_by(|a, b| a.0.cmp(b.0));
+        for (related_uri, diagnostics) in related {
+            let path = || uri_to_path(related_uri).unwrap_or_else(|| PathBuf::from(related_uri));
+            push_diagnostics(&mut files, related_uri, path, diagnostics);
+        }
+    }
+    if !deployment_lints {
+        for file in &mut files {
+            file.diagnostics.retain(|d| !is_deployment_lint(d));
+        }
+    }
+
+    let all = || files.iter().flat_map(|f| &f.diagnostics);
+    errors += all().filter(|d| d.severity == DiagnosticSeverity::Error).count();
+    let warnings = all().filter(|d| d.severity == DiagnosticSeverity::Warning).count();
+    match format {
+        Format::Human => {
+            for file in &files {
+                for diagnostic in &file.diagnostics {
+                    println!("{}", describe(&file.path, diagnostic));
+                }
+            }
+            if fix {
+                println!("{} fixes applied in {} files", fixes, fixed_files);
+            }
+            println!("{} errors, {} warnings", errors, warnings);
+        }
+        Format::Json => {
+            let files: Vec<Value> = files.iter().map(file_to_json).collect();
+            let mut output = json_object! {
+                "files" => files,
+                "errors" => errors as u64,
+                "warnings" => warnings as u64,
+            };
+            if fix {
+                output.insert("fixes", fixes as u64);
+            }
+            println!("{}", output);
+        }
+    }
+    if errors > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+/// The `.conf` files that differ between `revision` and the working tree,
+/// as `git diff` lists them, leaving out deleted ones.
+/// Applies the preferred fixes of `loaded` to the file at `path` and to
+/// `loaded`, returning their titles. A file that is not valid UTF-8 is
+/// left alone and gives `None`: writing back the text read would replace
+/// its invalid bytes.
+fn fix_file(path: &Path, loaded: &mut LoadedText) -> io::Result<Option<Vec<String>>> {
+    if loaded.invalid_utf8.is_some() {
+        return Ok(None);
+    }
+    let (text, applied) = apply_safe_fixes(&loaded.text);
+    if !applied.is_empty() {
+        fs::write(path, &text)?;
+        loaded.text = text;
+    }
+    Ok(Some(applied))
+}
+
+fn changed_files(revision: &str) -> io::Result<Vec<PathBuf>> {
+    let top = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim_end());
+    let names = git(&["diff", "--name-only", "-z", "--diff-filter=d", revision, "--"])?;
+    let current = std::env::current_dir()?;
+    Ok(names
+        .split('\0')
+        .filter(|name| name.ends_with(".conf"))
+        .map(|name| {
+            let path = top.join(name);
+            path.strip_prefix(&current).map(Path::to_path_buf).unwrap_or(path)
+        })
+        .collect())
+}
+
+/// The output of `git args...`, or its error message when it fails.
+fn git(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Appends `diagnostics` to the report of `uri`, skipping those already
+/// reported through another file including the same snippet.
+fn push_diagnostics(
+    files: &mut Vec<FileReport>,
+    uri: &str,
+    path: impl FnOnce() -> PathBuf,
+    diagnostics: &[Diagnostic],
+) {
+    let index = match files.iter().position(|f| f.uri == uri) {
+        Some(index) => index,
+        None => {
+            files.push(FileReport {
+                path: path(),
+                uri: uri.to_string(),
+                diagnostics: Vec::new(),
+            });
+            files.len() - 1
+        }
+    };
+    let file = &mut files[index];
+    for diagnostic in diagnostics {
+        if !file.diagnostics.contains(diagnostic) {
+            file.diagnostics.push(diagnostic.clone());
+        }
+    }
+}
+
+fn severity_name(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::Error => "error",
+        DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Information => "info",
+        DiagnosticSeverity::Hint => "hint",
+    }
+}
+
+/// `path:line:column: severity: message [code]`, with 1-based positions.
+fn describe(path: &Path, diagnostic: &Diagnostic) -> String {
+    let mut line = format!(
+        "{}:{}:{}: {}: {}",
+        path.display(),
+        diagnostic.range.start.line + 1,
+        diagnostic.range.start.character + 1,
+        severity_name(diagnostic.severity),
+        diagnostic.message
+    );
+    if let Some(code) = &diagnostic.code {
+        line.push_str(&format!(" [{}]", code));
+    }
+    line
+}
+
+/// A file and its diagnostics for `--format json`, with the same 1-based
+/// positions as the human-readable output.
+fn file_to_json(file: &FileReport) -> Value {
+    let diagnostics: Vec<Value> = file
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let range = diagnostic.range;
+            let mut value = json_object! {
+                "line" => range.start.line + 1,
+                "column" => range.start.character + 1,
+                "endLine" => range.end.line + 1,
+                "endColumn" => range.end.character + 1,
+                "severity" => severity_name(diagnostic.severity),
+                "message" => &diagnostic.message,
+            };
+            if let Some(code) = &diagnostic.code {
+                value.insert("code", code);
+            }
+            value
+        })
+        .collect();
+    json_object! {
+        "path" => file.path.display().to_string(),
+        "diagnostics" => diagnostics,
+    }
+}
+
+/// Where `--listen` waits for its client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// `tcp://127.0.0.1:9257`; port 0 picks a free one.
+    Tcp(String),
+    /// `unix:/run/lsp-syslog-ng.sock`
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    pub fn parse(address: &str) -> Option<Endpoint> {
+        if let Some(address) = address.strip_prefix("tcp://") {
+            (!address.is_empty()).then(|| Endpoint::Tcp(address.to_string()))
+        } else if let Some(path) = address.strip_prefix("unix:") {
+            let path = path.strip_prefix("//").unwrap_or(path);
+            (!path.is_empty()).then(|| Endpoint::Unix(PathBuf::from(path)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Runs the language server for the first client connecting to `address`.
+/// Returns the exit code of the session, or 2 when the address is invalid
+/// or cannot be listened on.
+pub fn listen(address: Option<&str>) -> i32 {
+    let Some(endpoint) = address.and_then(Endpoint::parse) else {
+        eprintln!("{}", LISTEN_USAGE);
+        return 2;
+    };
+    match serve(&endpoint) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("cannot listen on {}: {}", address.unwrap_or_default(), error);
+            2
+        }
+    }
+}
+
+fn serve(endpoint: &Endpoint) -> io::Result<i32> {
+    match endpoint {
+        Endpoint::Tcp(address) => {
+            let listener = TcpListener::bind(address)?;
+            // With the actual port, for clients that asked for port 0.
+            eprintln!("listening on tcp://{}", listener.local_addr()?);
+            let (stream, _) = listener.accept()?;
+            Ok(backend::run(&mut BufReader::new(stream.try_clone()?), Box::new(stream)))
+        }
+        #[cfg(unix)]
+        Endpoint::Unix(path) => {
+            let listener = UnixListener::bind(path)?;
+            eprintln!("listening on unix:{}", path.display());
+            let accepted = listener.accept();
+            // The socket file outlives the listener otherwise.
+            let _ = fs::remove_file(path);
+            let (stream, _) = accepted?;
+            Ok(backend::run(&mut BufReader::new(stream.try_clone()?), Box::new(stream)))
+        }
+        #[cfg(not(unix))]
+        Endpoint::Unix(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported on this system",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("sng-lsp-{}-{}.conf", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn fixes_the_file_on_disk() {
+        let path = scratch_file("fix-file", b"source s { network(port(514) transport(\"udp\")) };\n");
+        let mut loaded = LoadedText::decode(fs::read(&path).unwrap());
+        let applied = fix_file(&path, &mut loaded).unwrap().unwrap();
+        assert!(!applied.is_empty());
+        assert_eq!(fs::read_to_string(&path).unwrap(), loaded.text);
+        assert!(parse_conf(&loaded.text).is_ok());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn leaves_files_that_are_not_valid_utf8_unfixed() {
+        let bytes = b"source s { network(port(514) transport(\"udp\")) };\n# caf\xe9\n";
+        let path = scratch_file("fix-latin1", bytes);
+        let mut loaded = LoadedText::decode(fs::read(&path).unwrap());
+        assert_eq!(fix_file(&path, &mut loaded).unwrap(), None);
+        assert_eq!(fs::read(&path).unwrap(), bytes);
+        fs::remove_file(&path).unwrap();
+    }
+}
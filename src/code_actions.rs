@@ -0,0 +1,438 @@
+//! Quick fixes and refactorings for `textDocument/codeAction`.
+//!
+//! Syntax fixes are derived from the parse error of the document itself
+//! rather than from the diagnostics the client sends back, since the
+//! structured error says what was expected where.
+
+use crate::diagnostics::{
+    bare_driver_diagnostic, bare_drivers, deprecated_driver_diagnostic, deprecated_drivers, syntax_error_to_diagnostic,
+    unknown_time_zone_diagnostic, unknown_time_zones, unquoted_template_diagnostic, unquoted_templates,
+};
+use crate::document_store::{offset_to_position, position_to_offset};
+use crate::language_types::annotations::Annotation;
+use crate::language_types::objects::{Object, Parameter};
+use crate::language_types::source::quote;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{CodeAction, CodeActionKind, Diagnostic, Position, Range, TextEdit, WorkspaceEdit};
+use crate::parser::lexer::{tokenize, Token, TokenKind};
+use crate::parser::{parse_conf, SngSyntaxError, SngSyntaxErrorKind};
+
+/// Version written by the "add `@version`" fix when the settings do not
+/// name one.
+pub const DEFAULT_VERSION: &str = "4.0";
+
+/// Fixes for the problems of `text` overlapping `range`.
+pub fn quick_fixes(uri: &str, text: &str, range: Range) -> Vec<CodeAction> {
+    match parse_conf(text) {
+        Ok(parsed) => {
+            let mut actions = bare_driver_fixes(uri, text, &parsed, range);
+            actions.extend(time_zone_fixes(uri, &parsed, range));
+            actions.extend(deprecated_driver_fixes(uri, &parsed, range));
+            actions.extend(quoting_fixes(uri, &parsed, range));
+            actions
+        }
+        Err(error) if error.range.overlaps(&range) => syntax_error_fixes(uri, text, &error),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// "Wrap in `source { ... };`" and the like for drivers written straight
+/// into a log path; preferred when only one kind of object takes them.
+fn bare_driver_fixes(uri: &str, text: &str, parsed: &ParsedConfiguration, range: Range) -> Vec<CodeAction> {
+    bare_drivers(parsed)
+        .into_iter()
+        .filter(|(driver, _)| driver.location.overlaps(&range))
+        .flat_map(|(driver, kinds)| {
+            let start = position_to_offset(text, driver.location.start);
+            let end = position_to_offset(text, driver.location.end);
+            let diagnostic = bare_driver_diagnostic(driver, &kinds);
+            let preferred = kinds.len() == 1;
+            kinds.into_iter().map(move |kind| {
+                let mut action = replacement(
+                    uri,
+                    format!("Wrap in `{} {{ ... }};`", kind),
+                    driver.location,
+                    format!("{} {{ {}; }}", kind, &text[start..end]),
+                    vec![diagnostic.clone()],
+                );
+                action.is_preferred = preferred;
+                action
+            })
+        })
+        .collect()
+}
+
+/// Replaces a misspelt time zone by the one it most likely stands for.
+fn time_zone_fixes(uri: &str, parsed: &ParsedConfiguration, range: Range) -> Vec<CodeAction> {
+    unknown_time_zones(parsed)
+        .into_iter()
+        // A zone coming from a define is fixed at the define.
+        .filter(|(literal, _)| literal.range.overlaps(&range) && literal.raw.is_none())
+        .filter_map(|(literal, suggestion)| {
+            let zone = suggestion?;
+            let mut action = replacement(
+                uri,
+                format!("Change to `{}`", zone),
+                literal.range,
+                quote(zone),
+                vec![unknown_time_zone_diagnostic(literal, suggestion)],
+            );
+            action.is_preferred = true;
+            Some(action)
+        })
+        .collect()
+}
+
+/// Rewrites `tcp(...)` to `network(... transport(tcp))` and the like.
+fn deprecated_driver_fixes(uri: &str, parsed: &ParsedConfiguration, range: Range) -> Vec<CodeAction> {
+    deprecated_drivers(parsed)
+        .into_iter()
+        .filter(|(driver, _)| driver.location.overlaps(&range))
+        .filter_map(|(driver, options)| {
+            let options = options?;
+            let diagnostic = deprecated_driver_diagnostic(driver, Some(&options));
+            let mut action = replacement(
+                uri,
+                format!("Rewrite `{}()` as `network()`", driver.name.name),
+                driver.name.range,
+                "network".to_string(),
+                vec![diagnostic],
+            );
+            if !options.is_empty() {
+                // After the last parameter, or inside the empty parentheses.
+                let (at, new_text) = match driver.parameters.last() {
+                    Some(Parameter::Literal(literal)) => (literal.range.end, format!(" {}", options.join(" "))),
+                    Some(Parameter::Option(option)) => (option.location.end, format!(" {}", options.join(" "))),
+                    None => {
+                        let end = driver.location.end;
+                        (
+                            Position::new(end.line, end.character.saturating_sub(1)),
+                            options.join(" "),
+                        )
+                    }
+                };
+                action.edit.push(
+                    uri,
+                    TextEdit {
+                        range: Range::new(at, at),
+                        new_text,
+                    },
+                );
+            }
+            action.is_preferred = true;
+            Some(action)
+        })
+        .collect()
+}
+
+/// Quotes a bare inline template such as `template($HOST)`; syslog-ng
+/// reads it the same way, without the doubt of it naming a template.
+fn quoting_fixes(uri: &str, parsed: &ParsedConfiguration, range: Range) -> Vec<CodeAction> {
+    unquoted_templates(parsed)
+        .into_iter()
+        .filter(|literal| literal.range.overlaps(&range) && literal.raw.is_none())
+        .map(|literal| {
+            let mut action = replacement(
+                uri,
+                format!("Quote `{}`", literal.value),
+                literal.range,
+                quote(&literal.value),
+                vec![unquoted_template_diagnostic(literal)],
+            );
+            action.is_preferred = true;
+            action
+        })
+        .collect()
+}
+
+/// Upper bound on the fixes [`apply_safe_fixes`] applies to one text.
+const MAX_FIXES: usize = 100;
+
+/// Applies the preferred fixes of `text` one at a time, re-parsing after
+/// each since fixing one syntax error reveals the next. Returns the fixed
+/// text and the titles of the applied fixes, in order.
+pub fn apply_safe_fixes(text: &str) -> (String, Vec<String>) {
+    let everything = Range::new(Position::default(), Position::new(u32::MAX, u32::MAX));
+    let mut text = text.to_string();
+    let mut applied = Vec::new();
+    while applied.len() < MAX_FIXES {
+        let Some(fix) = quick_fixes("", &text, everything).into_iter().find(|a| a.is_preferred) else {
+            break;
+        };
+        let mut edits: Vec<&TextEdit> = fix.edit.changes.values().flatten().collect();
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.range.start));
+        for edit in edits {
+            let start = position_to_offset(&text, edit.range.start);
+            let end = position_to_offset(&text, edit.range.end).max(start);
+            text.replace_range(start..end, &edit.new_text);
+        }
+        applied.push(fix.title);
+    }
+    (text, applied)
+}
+
+/// "Fix all auto-fixable problems": the fixes [`apply_safe_fixes`] would
+/// apply, as one edit. Applying them in turn keeps them from conflicting,
+/// as each one is computed on the text the previous ones produced. Returns
+/// the action and the number of fixes it applies, `None` when nothing can
+/// be fixed.
+pub fn fix_all(uri: &str, text: &str) -> Option<(CodeAction, usize)> {
+    let (fixed, applied) = apply_safe_fixes(text);
+    if applied.is_empty() {
+        return None;
+    }
+    // One edit over the part that changed, from the first to the last
+    // differing byte.
+    let prefix = text
+        .char_indices()
+        .zip(fixed.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(text.len().min(fixed.len()), |((i, _), _)| i);
+    let suffix = text[prefix..]
+        .chars()
+        .rev()
+        .zip(fixed[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+    let range = Range::new(
+        offset_to_position(text, prefix),
+        offset_to_position(text, text.len() - suffix),
+    );
+    let new_text = fixed[prefix..fixed.len() - suffix].to_string();
+    let title = match applied.len() {
+        1 => "Fix all auto-fixable problems (1 fix)".to_string(),
+        n => format!("Fix all auto-fixable problems ({} fixes)", n),
+    };
+    let mut action = replacement(uri, title, range, new_text, Vec::new());
+    action.kind = CodeActionKind::SourceFixAll;
+    Some((action, applied.len()))
+}
+
+/// Adds `@version` as the first line. Only the main configuration needs
+/// one, which is for the caller to decide.
+pub fn add_version(uri: &str, version: &str) -> CodeAction {
+    let mut action = replacement(
+        uri,
+        format!("Add `@version: {}`", version),
+        Range::default(),
+        format!("@version: {}\n", version),
+        Vec::new(),
+    );
+    action.is_preferred = true;
+    action
+}
+
+/// Adds `@include "path"` on a new line after the last `@include`, or
+/// after `@version` if there is none, or as the first line.
+pub fn add_include(uri: &str, text: &str, parsed: &ParsedConfiguration, path: &str) -> CodeAction {
+    let include = format!("@include {}", quote(path));
+    let annotations = &parsed.annotations;
+    let after = annotations
+        .iter()
+        .rev()
+        .find(|a| matches!(a, Annotation::Include(_)))
+        .or_else(|| annotations.iter().find(|a| matches!(a, Annotation::Version(_))));
+    let (at, new_text) = match after {
+        Some(annotation) => {
+            // After a trailing comment too.
+            let start = position_to_offset(text, annotation.range().end);
+            let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+            (offset_to_position(text, end), format!("\n{}", include))
+        }
+        None => (Position::default(), format!("{}\n", include)),
+    };
+    replacement(
+        uri,
+        format!("Add `{}`", include),
+        Range::new(at, at),
+        new_text,
+        Vec::new(),
+    )
+}
+
+/// The top-level objects a non-empty selection `range` touches, which
+/// [`extract_to_include`] can move; empty if a directive sits between them,
+/// since it would move along.
+pub fn extractable_objects(parsed: &ParsedConfiguration, range: Range) -> Vec<&Object> {
+    if range.start == range.end {
+        return Vec::new();
+    }
+    let objects: Vec<&Object> = parsed.objects.iter().filter(|o| o.location.overlaps(&range)).collect();
+    let (Some(first), Some(last)) = (objects.first(), objects.last()) else {
+        return Vec::new();
+    };
+    let span = Range::new(first.location.start, last.location.end);
+    if parsed.annotations.iter().any(|a| a.range().overlaps(&span)) {
+        return Vec::new();
+    }
+    objects
+}
+
+/// Moves `objects`, as returned by [`extractable_objects`], to the new file
+/// `target_uri` and leaves `@include "path"` in their place, so everything
+/// after them still sees them defined.
+pub fn extract_to_include(
+    uri: &str,
+    text: &str,
+    objects: &[&Object],
+    target_uri: &str,
+    path: &str,
+) -> Option<CodeAction> {
+    let (first, last) = (objects.first()?, objects.last()?);
+    let mut start = position_to_offset(text, first.location.start);
+    let mut end = position_to_offset(text, last.location.end);
+    // Whole lines, so neither file keeps stray indentation or blank lines.
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    if text[line_start..start].trim().is_empty() {
+        start = line_start;
+    }
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i);
+    if text[end..line_end].trim().is_empty() {
+        end = (line_end + 1).min(text.len());
+    }
+    let mut moved = text[start..end].to_string();
+    if !moved.ends_with('\n') {
+        moved.push('\n');
+    }
+    // A directive runs to the end of its line.
+    let include = format!("@include {}", quote(path));
+    let before = if start == line_start { "" } else { "\n" };
+    let new_text = format!("{}{}\n", before, include);
+    let range = Range::new(offset_to_position(text, start), offset_to_position(text, end));
+
+    let mut action = replacement(uri, format!("Extract to `{}`", path), range, new_text, Vec::new());
+    action.kind = CodeActionKind::RefactorExtract;
+    action.edit.create_files.push(target_uri.to_string());
+    action.edit.push(
+        target_uri,
+        TextEdit {
+            range: Range::default(),
+            new_text: moved,
+        },
+    );
+    Some(action)
+}
+
+fn syntax_error_fixes(uri: &str, text: &str, error: &SngSyntaxError) -> Vec<CodeAction> {
+    let expected = match &error.kind {
+        SngSyntaxErrorKind::UnexpectedToken { expected, .. } | SngSyntaxErrorKind::UnexpectedEndOfFile { expected } => {
+            expected.as_str()
+        }
+        _ => return Vec::new(),
+    };
+    let Ok(tokens) = tokenize(text) else {
+        return Vec::new();
+    };
+    // The missing token belongs right after the last one before the error.
+    let before: Vec<&Token> = tokens.iter().take_while(|t| t.range.end <= error.range.start).collect();
+    let Some(previous) = before.last() else {
+        return Vec::new();
+    };
+    let at = Range::new(previous.range.end, previous.range.end);
+    let diagnostics = vec![syntax_error_to_diagnostic(error)];
+    let mut action = match expected {
+        "`;`" | "`;` after `}`" => replacement(uri, "Insert missing `;`".to_string(), at, ";".to_string(), diagnostics),
+        "`)`" | "an option, a value or `)`" => {
+            let depth = before.iter().fold(0i32, |depth, token| match token.kind {
+                TokenKind::LParen => depth + 1,
+                TokenKind::RParen => depth - 1,
+                _ => depth,
+            });
+            if depth <= 0 {
+                return Vec::new();
+            }
+            let title = match depth {
+                1 => "Close unbalanced parenthesis".to_string(),
+                _ => format!("Close {} unbalanced parentheses", depth),
+            };
+            replacement(uri, title, at, ")".repeat(depth as usize), diagnostics)
+        }
+        _ => return Vec::new(),
+    };
+    action.is_preferred = true;
+    vec![action]
+}
+
+fn replacement(uri: &str, title: String, range: Range, new_text: String, diagnostics: Vec<Diagnostic>) -> CodeAction {
+    let mut edit = WorkspaceEdit::default();
+    edit.push(uri, TextEdit { range, new_text });
+    CodeAction {
+        title,
+        kind: CodeActionKind::QuickFix,
+        diagnostics,
+        edit,
+        is_preferred: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inserts_missing_semicolons() {
+        let (fixed, applied) = apply_safe_fixes("log { source(s); destination(d) }\n");
+        assert_eq!(fixed, "log { source(s); destination(d); };\n");
+        assert_eq!(applied, ["Insert missing `;`", "Insert missing `;`"]);
+    }
+
+    #[test]
+    fn rewrites_deprecated_network_drivers() {
+        let (fixed, applied) = apply_safe_fixes("source s { udp6(port(514)); };\n");
+        assert_eq!(
+            fixed,
+            "source s { network(port(514) transport(udp) ip-protocol(6)); };\n"
+        );
+        assert_eq!(applied, ["Rewrite `udp6()` as `network()`"]);
+    }
+
+    #[test]
+    fn keeps_deprecated_drivers_with_tls() {
+        let text = "source s { tcp(port(6514) tls(key-file(\"/k\"))); };\n";
+        assert_eq!(apply_safe_fixes(text), (text.to_string(), Vec::new()));
+    }
+
+    #[test]
+    fn quotes_templates() {
+        let (fixed, applied) = apply_safe_fixes("destination d { file(\"/x\" template($HOST)); };\n");
+        assert_eq!(fixed, "destination d { file(\"/x\" template(\"$HOST\")); };\n");
+        assert_eq!(applied, ["Quote `$HOST`"]);
+    }
+
+    #[test]
+    fn applies_fixes_revealed_by_earlier_ones() {
+        let (fixed, applied) = apply_safe_fixes(
+            "source s { tcp(ip(\"0.0.0.0\") port(514)) };\ndestination d { file(\"/x\" template($HOST)); };\n",
+        );
+        assert_eq!(
+            fixed,
+            "source s { network(ip(\"0.0.0.0\") port(514) transport(tcp)); };\n\
+             destination d { file(\"/x\" template(\"$HOST\")); };\n"
+        );
+        assert_eq!(
+            applied,
+            ["Insert missing `;`", "Rewrite `tcp()` as `network()`", "Quote `$HOST`"]
+        );
+    }
+
+    #[test]
+    fn fixes_everything_in_one_edit() {
+        let text = "@version: 4.8\nsource s { udp(port(514)) };\nlog { source(s); };\n";
+        let (action, count) = fix_all("file:///x.conf", text).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(action.title, "Fix all auto-fixable problems (2 fixes)");
+        assert_eq!(action.kind, CodeActionKind::SourceFixAll);
+        let edits = &action.edit.changes["file:///x.conf"];
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].range, Range::new(Position::new(1, 11), Position::new(1, 25)));
+        assert_eq!(edits[0].new_text, "network(port(514) transport(udp));");
+    }
+
+    #[test]
+    fn leaves_valid_text_alone() {
+        let text = "source s { internal(); };\nlog { source(s); };\n";
+        assert_eq!(apply_safe_fixes(text), (text.to_string(), Vec::new()));
+        assert!(fix_all("file:///x.conf", text).is_none());
+    }
+}
@@ -0,0 +1,390 @@
+//! `workspace/executeCommand` implementations.
+
+use std::path::PathBuf;
+
+use crate::configuration::{Snippet, TextLoader};
+use crate::diff::unified_diff;
+use crate::document_store::position_to_offset;
+use crate::formatter::{format_document, FormattingOptions};
+use crate::grammar::normalize_name;
+use crate::json::Value;
+use crate::json_object;
+use crate::language_types::annotations::Annotation;
+use crate::language_types::objects::{Driver, Object, ObjectKind, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Position, Range, TextEdit, ToJson, WorkspaceEdit};
+use crate::parser::object_parser::{is_valid_identifier, match_object_kind};
+use crate::parser::substitute_defines;
+use crate::symbols::{display_name, symbol_locations};
+
+pub const SET_OPTION_EVERYWHERE: &str = "syslogng.setOptionEverywhere";
+pub const PREVIEW_FORMAT: &str = "syslogng.previewFormat";
+/// Opens the `@include` directive that pulls a snippet in; used by the
+/// "included from" code lenses. Arguments: `[uri, range]`.
+pub const SHOW_INCLUDER: &str = "syslogng.showIncluder";
+
+/// Renames every object identifier starting with a prefix.
+pub const RENAME_PREFIX: &str = "syslogng.renamePrefix";
+
+/// Analyses a configuration together with its includes and reports the
+/// outcome in a message; used by the `@version` code lens. Arguments: `[uri]`.
+pub const CHECK_CONFIGURATION: &str = "syslogng.checkConfiguration";
+
+/// Analyses every configuration file of the workspace and the files they
+/// include, publishes their diagnostics and returns a summary. Takes no
+/// arguments.
+pub const VALIDATE_CONFIGURATION: &str = "syslog-ng.validateConfiguration";
+
+/// The configuration with includes and `@define` names resolved, as
+/// syslog-ng reads it. Arguments: `[uri]`.
+pub const SHOW_RESOLVED_CONFIG: &str = "syslog-ng.showResolvedConfig";
+
+/// The log paths, filters, rewrites and destinations a sample message goes
+/// through, see [`crate::trace`]. Arguments: `[uri, message]`.
+pub const TRACE_MESSAGE: &str = "syslogng.traceMessage";
+
+/// Reference list of VS Code, run by the client itself and therefore not
+/// in [`COMMANDS`]. Arguments: `[uri, position, locations]`.
+pub const SHOW_REFERENCES: &str = "editor.action.showReferences";
+
+pub const COMMANDS: &[&str] = &[
+    SET_OPTION_EVERYWHERE,
+    PREVIEW_FORMAT,
+    SHOW_INCLUDER,
+    RENAME_PREFIX,
+    CHECK_CONFIGURATION,
+    VALIDATE_CONFIGURATION,
+    SHOW_RESOLVED_CONFIG,
+    TRACE_MESSAGE,
+];
+
+/// Arguments of [`SET_OPTION_EVERYWHERE`]: either one object
+/// `{ "option", "value", "addWhereAbsent", "drivers" }` or the positional
+/// form `[option, value, addWhereAbsent?]`.
+#[derive(Debug, Clone)]
+pub struct SetOptionRequest {
+    pub option: String,
+    pub value: String,
+    /// Add the option to drivers that do not set it yet. Only drivers named in
+    /// `drivers` are touched, since not every driver accepts every option.
+    pub add_where_absent: bool,
+    pub drivers: Vec<String>,
+}
+
+impl SetOptionRequest {
+    pub fn from_arguments(arguments: &Value) -> Option<Self> {
+        let first = &arguments[0];
+        if first.as_object().is_some() {
+            return Some(SetOptionRequest {
+                option: first["option"].as_str()?.to_string(),
+                value: value_text(&first["value"])?,
+                add_where_absent: first["addWhereAbsent"].as_bool().unwrap_or(false),
+                drivers: first["drivers"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|d| d.as_str().map(str::to_string))
+                    .collect(),
+            });
+        }
+        Some(SetOptionRequest {
+            option: first.as_str()?.to_string(),
+            value: value_text(&arguments[1])?,
+            add_where_absent: arguments[2].as_bool().unwrap_or(false),
+            drivers: Vec::new(),
+        })
+    }
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Builds the edit that sets `request.option` on every driver in `files`.
+pub fn set_option_everywhere(request: &SetOptionRequest, files: &[(String, ParsedConfiguration)]) -> WorkspaceEdit {
+    let mut edit = WorkspaceEdit::default();
+    let option = normalize_name(&request.option);
+    let new_text = format!("{}({})", request.option, request.value);
+
+    for (uri, parsed) in files {
+        let mut drivers = Vec::new();
+        for object in &parsed.objects {
+            collect_drivers(object, &mut drivers);
+        }
+        for driver in drivers {
+            let existing: Vec<&Driver> = driver
+                .options()
+                .filter(|o| normalize_name(&o.name.name) == option)
+                .collect();
+            for found in &existing {
+                edit.push(
+                    uri,
+                    TextEdit {
+                        range: found.location,
+                        new_text: new_text.clone(),
+                    },
+                );
+            }
+            let wanted = request
+                .drivers
+                .iter()
+                .any(|d| normalize_name(d) == normalize_name(&driver.name.name));
+            if existing.is_empty() && request.add_where_absent && wanted {
+                // Insert right before the closing parenthesis.
+                let end = driver.location.end;
+                let before_paren = Position::new(end.line, end.character.saturating_sub(1));
+                let separator = if driver.parameters.is_empty() { "" } else { " " };
+                edit.push(
+                    uri,
+                    TextEdit {
+                        range: Range::new(before_paren, before_paren),
+                        new_text: format!("{}{}", separator, new_text),
+                    },
+                );
+            }
+        }
+    }
+    edit
+}
+
+/// Drivers of `object` and of its inline objects. Log path elements and
+/// global options are not drivers and are skipped.
+fn collect_drivers<'a>(object: &'a Object, drivers: &mut Vec<&'a Driver>) {
+    for statement in object.flat_statements() {
+        match statement {
+            Statement::Driver(driver) => {
+                if !matches!(object.kind, ObjectKind::Log | ObjectKind::Options) {
+                    drivers.push(driver);
+                }
+            }
+            Statement::Object(inline) => collect_drivers(inline, drivers),
+            Statement::Conditional(_) | Statement::Junction(_) => {}
+        }
+    }
+}
+
+/// Arguments of [`PREVIEW_FORMAT`]: `[uri]` or
+/// `[{ "uri", "tabSize", "insertSpaces" }]`.
+pub fn preview_format_arguments(arguments: &Value) -> Option<(String, FormattingOptions)> {
+    let first = &arguments[0];
+    if let Some(uri) = first.as_str() {
+        return Some((uri.to_string(), FormattingOptions::default()));
+    }
+    Some((first["uri"].as_str()?.to_string(), FormattingOptions::from_lsp(first)))
+}
+
+/// The formatter's changes to `text` as a unified diff, or `None` if the
+/// document cannot be formatted because it has syntax errors.
+pub fn preview_format(uri: &str, text: &str, options: &FormattingOptions) -> Option<Value> {
+    let formatted = format_document(text, options)?;
+    let diff = unified_diff(text, &formatted, uri, &format!("{} (formatted)", uri), 3);
+    Some(json_object! {
+        "changed" => !diff.is_empty(),
+        "diff" => diff,
+    })
+}
+
+/// The text syslog-ng reads for `uri`: every `@include` replaced by the
+/// files it resolves to, in include order and between comments naming
+/// them, and `` `name` `` replaced by the value of the `@define` before
+/// it. Names defined nowhere are left as they are.
+pub fn resolved_configuration(
+    uri: &str,
+    text: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    load: &TextLoader,
+    workspace_folders: &[PathBuf],
+) -> String {
+    let resolver = ConfigResolver {
+        snippets,
+        load,
+        workspace_folders,
+    };
+    let mut defines = Vec::new();
+    let mut resolved = String::new();
+    resolver.append(uri, text, parsed, &mut defines, &mut resolved);
+    resolved
+}
+
+struct ConfigResolver<'a, 'b> {
+    snippets: &'a [Snippet],
+    load: &'a TextLoader<'b>,
+    workspace_folders: &'a [PathBuf],
+}
+
+impl ConfigResolver<'_, '_> {
+    fn append(
+        &self,
+        uri: &str,
+        text: &str,
+        parsed: &ParsedConfiguration,
+        defines: &mut Vec<(String, String)>,
+        resolved: &mut String,
+    ) {
+        let mut offset = 0;
+        for annotation in &parsed.annotations {
+            let start = position_to_offset(text, annotation.range().start);
+            let end = position_to_offset(text, annotation.range().end);
+            resolved.push_str(&substitute_defines(&text[offset..start], defines));
+            offset = end;
+            match annotation {
+                Annotation::Include(include) => {
+                    resolved.push_str(&format!("# @include \"{}\"", include.path));
+                    let included = self
+                        .snippets
+                        .iter()
+                        .filter(|s| s.included_from.uri == uri && s.included_from.range == include.range);
+                    for snippet in included {
+                        self.append_snippet(snippet, defines, resolved);
+                    }
+                }
+                Annotation::Define(define) => {
+                    resolved.push_str(&text[start..end]);
+                    defines.retain(|(name, _)| *name != define.name);
+                    defines.push((define.name.clone(), define.value.clone()));
+                }
+                _ => resolved.push_str(&text[start..end]),
+            }
+        }
+        resolved.push_str(&substitute_defines(&text[offset..], defines));
+    }
+
+    fn append_snippet(&self, snippet: &Snippet, defines: &mut Vec<(String, String)>, resolved: &mut String) {
+        let name = display_name(&snippet.uri, self.workspace_folders);
+        let Some(loaded) = (self.load)(&snippet.path) else {
+            resolved.push_str(&format!("\n# {} could not be read", name));
+            return;
+        };
+        resolved.push_str(&format!("\n# begin {}\n", name));
+        match &snippet.parsed {
+            Ok(parsed) => self.append(&snippet.uri, &loaded.text, parsed, defines, resolved),
+            Err(error) => {
+                resolved.push_str(&format!("# syntax error, includes not resolved: {}\n", error));
+                resolved.push_str(&substitute_defines(&loaded.text, defines));
+            }
+        }
+        if !resolved.ends_with('\n') {
+            resolved.push('\n');
+        }
+        resolved.push_str(&format!("# end {}", name));
+    }
+}
+
+/// Arguments of [`RENAME_PREFIX`]: either one object
+/// `{ "from", "to", "kind", "preview" }` or the positional form `[from, to]`.
+#[derive(Debug, Clone)]
+pub struct RenamePrefixRequest {
+    pub from: String,
+    pub to: String,
+    /// Only rename objects of this kind.
+    pub kind: Option<ObjectKind>,
+    /// Only compute the edit, without applying it.
+    pub preview: bool,
+}
+
+impl RenamePrefixRequest {
+    pub fn from_arguments(arguments: &Value) -> Option<Self> {
+        let first = &arguments[0];
+        if first.as_object().is_some() {
+            let kind = match first["kind"].as_str() {
+                Some(kind) => Some(match_object_kind(kind)?),
+                None => None,
+            };
+            return Some(RenamePrefixRequest {
+                from: first["from"].as_str()?.to_string(),
+                to: first["to"].as_str()?.to_string(),
+                kind,
+                preview: first["preview"].as_bool().unwrap_or(false),
+            });
+        }
+        Some(RenamePrefixRequest {
+            from: first.as_str()?.to_string(),
+            to: arguments[1].as_str()?.to_string(),
+            kind: None,
+            preview: false,
+        })
+    }
+}
+
+/// An identifier [`rename_prefix`] renames.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PrefixRename {
+    pub kind: ObjectKind,
+    pub from: String,
+    pub to: String,
+}
+
+impl ToJson for PrefixRename {
+    fn to_json(&self) -> Value {
+        json_object! { "kind" => self.kind.keyword(), "from" => &self.from, "to" => &self.to }
+    }
+}
+
+/// The renames and the edit performing them. Identifiers that appear in a
+/// `read_only` file are kept, since renaming them would leave dangling
+/// references there; they are returned as the third element.
+pub fn rename_prefix(
+    request: &RenamePrefixRequest,
+    files: &[(String, ParsedConfiguration)],
+    read_only: impl Fn(&str) -> bool,
+) -> Result<(Vec<PrefixRename>, WorkspaceEdit, Vec<PrefixRename>), String> {
+    if request.from.is_empty() {
+        return Err("the prefix to replace is empty".to_string());
+    }
+    let mut renames: Vec<PrefixRename> = files
+        .iter()
+        .flat_map(|(_, parsed)| parsed.definitions())
+        .filter(|(kind, id)| request.kind.is_none_or(|k| k == *kind) && id.name.starts_with(&request.from))
+        .map(|(kind, id)| PrefixRename {
+            kind,
+            from: id.name.clone(),
+            to: format!("{}{}", request.to, &id.name[request.from.len()..]),
+        })
+        .collect();
+    renames.sort();
+    renames.dedup();
+
+    let existing: Vec<(ObjectKind, &str)> = files
+        .iter()
+        .flat_map(|(_, parsed)| parsed.definitions())
+        .map(|(kind, id)| (kind, id.name.as_str()))
+        .collect();
+    for rename in &renames {
+        if !is_valid_identifier(&rename.to) {
+            return Err(format!("`{}` is not a valid identifier", rename.to));
+        }
+        let renamed_away = |name: &str| renames.iter().any(|r| r.kind == rename.kind && r.from == name);
+        if existing
+            .iter()
+            .any(|(k, n)| *k == rename.kind && *n == rename.to && !renamed_away(n))
+        {
+            return Err(format!("a {} named `{}` already exists", rename.kind, rename.to));
+        }
+    }
+
+    let mut edit = WorkspaceEdit::default();
+    let mut kept = Vec::new();
+    for rename in &renames {
+        let locations = symbol_locations(files, rename.kind, &rename.from, true);
+        if locations.iter().any(|l| read_only(&l.uri)) {
+            kept.push(rename.clone());
+            continue;
+        }
+        for location in locations {
+            edit.push(
+                &location.uri,
+                TextEdit {
+                    range: location.range,
+                    new_text: rename.to.clone(),
+                },
+            );
+        }
+    }
+    renames.retain(|r| !kept.contains(r));
+    Ok((renames, edit, kept))
+}
@@ -0,0 +1,1076 @@
+//! `textDocument/completion`.
+//!
+//! The document is usually incomplete while the user types, so the context
+//! is worked out from the tokens before the cursor rather than from the AST.
+
+use std::collections::BTreeMap;
+
+use crate::document_store::{offset_to_position, position_to_offset};
+use crate::file_utilities::{include_path, uri_to_path};
+use crate::grammar::{self, BranchKeyword, DriverInfo, LogFlag, MacroInfo, OptionInfo, Platform};
+use crate::hover;
+use crate::json::Value;
+use crate::json_object;
+use crate::language_types::objects::ObjectKind;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{CompletionItem, CompletionItemKind, InsertTextFormat, Position, Range, TextEdit};
+use crate::parser::lexer::{tokenize, Token, TokenKind};
+use crate::parser::object_parser::match_object_kind;
+use crate::parser::parse_conf_recovering;
+use crate::settings::CompletionVerbosity;
+use crate::snapshot::ParseSnapshot;
+use crate::time_zones::TIME_ZONES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompletionOptions {
+    /// The character whose typing triggered the completion, if any.
+    pub trigger: Option<char>,
+    /// Insert the closing syntax of drivers and blocks as a snippet. Only
+    /// set it when the client supports snippets.
+    pub closing_syntax: bool,
+    pub verbosity: CompletionVerbosity,
+    /// Configuration version of the document; drivers it does not have yet
+    /// are left out. Everything is offered when it is unknown.
+    pub version: Option<(u32, u32)>,
+    /// Drivers of other platforms are left out; everything is offered when
+    /// it is unknown.
+    pub platform: Option<Platform>,
+}
+
+/// Characters that trigger completion when typed.
+pub const TRIGGER_CHARACTERS: &[&str] = &["(", "\"", "@", "{", "`", "$"];
+
+/// Where the cursor is, as far as completion is concerned.
+#[derive(Debug, Clone, PartialEq)]
+enum Context {
+    /// Between top-level objects.
+    Root,
+    /// On an `@` annotation line.
+    Annotation,
+    /// At the start of a statement in the body of an object. Branches of
+    /// `if` blocks count as log paths, their conditions as filters.
+    ObjectBody(ObjectKind),
+    /// Right after the `}` of an `if` or `elif` branch.
+    AfterBranch,
+    /// At the start of a channel in the body of a junction.
+    Junction,
+    /// Inside the parentheses of a call; `calls` runs from the statement of
+    /// the object body to the innermost call.
+    Call { context: ObjectKind, calls: Vec<String> },
+    /// Inside a string literal opened by `quote`, `closed` when the closing
+    /// quote follows the cursor.
+    String { quote: char, closed: bool },
+    /// Nothing sensible to offer, e.g. while typing an identifier.
+    None,
+}
+
+enum Frame {
+    Object(ObjectKind),
+    /// The body of an `if`, `elif` or `else` branch.
+    Branch {
+        is_else: bool,
+    },
+    /// The body of a junction, holding its channels.
+    Junction,
+    Paren(Option<String>),
+}
+
+impl Frame {
+    /// The kind of object whose statements go here; `None` for parentheses.
+    fn object_kind(&self) -> Option<ObjectKind> {
+        match self {
+            Frame::Object(kind) => Some(*kind),
+            Frame::Branch { .. } => Some(ObjectKind::Log),
+            Frame::Junction | Frame::Paren(_) => None,
+        }
+    }
+}
+
+/// Something the grammar allows at a position in a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrammarItem {
+    /// A top-level object keyword.
+    Keyword(ObjectKind),
+    /// `@version` or `@include`.
+    Annotation(&'static str),
+    /// `if`, `elif` or `else` in a log path, `junction` and `channel`.
+    Branch(&'static BranchKeyword),
+    Driver(&'static DriverInfo),
+    /// A global option, a statement of `options { ... };`.
+    GlobalOption(&'static OptionInfo),
+    /// An option inside a driver or an inner block.
+    Option(&'static OptionInfo),
+    FilterFunction(&'static OptionInfo),
+    /// One of the values an option accepts, e.g. `yes`.
+    Value(&'static str),
+    /// A flag in `flags()` of a log path.
+    LogFlag(&'static LogFlag),
+    /// A zone name for an option taking a time zone; quoted when inserted.
+    TimeZone(&'static str),
+    /// The identifier of a defined object, e.g. in `source(s_local)`, and
+    /// the `@include` path of the file defining it if the document does not
+    /// include that file yet.
+    ObjectId(ObjectKind, String, Option<String>),
+    /// A `block` of the context of the object body, with its parameters
+    /// that have no default.
+    Block {
+        name: String,
+        context: String,
+        required: Vec<String>,
+    },
+    /// A variable substituted as `` `name` ``, with its value or, for the
+    /// built-in ones, a description.
+    Define {
+        name: String,
+        detail: String,
+    },
+    /// A built-in macro in a quoted string, after `$` or, when `braced`,
+    /// after `${`.
+    Macro {
+        info: &'static MacroInfo,
+        braced: bool,
+    },
+}
+
+/// The grammar items valid at `position` of `text`, e.g. for completion.
+/// Object identifiers are looked up in `text` and in `files`.
+pub fn possible_insertions(
+    text: &str,
+    position: Position,
+    files: &[(String, ParsedConfiguration)],
+) -> Vec<GrammarItem> {
+    let (current, _) = parse_conf_recovering(text);
+    insertion_point(text, position_to_offset(text, position), &current, files, None)
+        .map(|(_, _, items)| items)
+        .unwrap_or_default()
+}
+
+/// The files whose objects a document can refer to, see [`completions`].
+pub struct IncludeScope<'a> {
+    pub uri: &'a str,
+    pub visible: &'a dyn Fn(&str) -> bool,
+}
+
+/// Completion items at `position` of the document `snapshot` was taken of.
+/// Identifiers of objects defined only in `files` outside `scope` are
+/// offered too, marked with the file to include.
+pub fn completions(
+    snapshot: &ParseSnapshot,
+    position: Position,
+    files: &[(String, ParsedConfiguration)],
+    scope: &IncludeScope,
+    options: &CompletionOptions,
+) -> Vec<CompletionItem> {
+    let text = snapshot.text.as_str();
+    let offset = snapshot.offset(position);
+    let Some((replace_start, context, items)) = insertion_point(text, offset, snapshot.partial(), files, Some(scope))
+    else {
+        return Vec::new();
+    };
+    // Completing `fi|("x")` must not add a second pair of parentheses.
+    let options = &CompletionOptions {
+        closing_syntax: options.closing_syntax && !text[offset..].starts_with(['(', '`']),
+        ..*options
+    };
+    let builder = ItemBuilder {
+        range: Range::new(replace_start.unwrap_or(position), position),
+        context: &context,
+        options,
+    };
+    items
+        .iter()
+        .filter(|item| options.trigger.is_none_or(|c| triggered_by(item, c)))
+        .filter(|item| {
+            !matches!(item, GrammarItem::Driver(driver)
+                if !driver.available_in(options.version) || !driver.available_on(options.platform))
+        })
+        .map(|item| builder.item(item))
+        .collect()
+}
+
+/// Whether `item` makes sense right after typing the trigger character `c`;
+/// e.g. a `(` typed inside a string should not pop up driver names.
+fn triggered_by(item: &GrammarItem, c: char) -> bool {
+    match c {
+        // The condition of `if (` is a filter expression.
+        '(' => matches!(
+            item,
+            GrammarItem::Option(_)
+                | GrammarItem::Value(_)
+                | GrammarItem::LogFlag(_)
+                | GrammarItem::TimeZone(_)
+                | GrammarItem::ObjectId(..)
+                | GrammarItem::FilterFunction(_)
+        ),
+        '{' => matches!(
+            item,
+            GrammarItem::Driver(_)
+                | GrammarItem::Block { .. }
+                | GrammarItem::GlobalOption(_)
+                | GrammarItem::FilterFunction(_)
+                | GrammarItem::Branch(_)
+                | GrammarItem::Macro { braced: true, .. }
+        ),
+        '@' => matches!(item, GrammarItem::Annotation(_)),
+        '"' => matches!(item, GrammarItem::TimeZone(_)),
+        '`' => matches!(item, GrammarItem::Define { .. }),
+        '$' => matches!(item, GrammarItem::Macro { .. }),
+        _ => false,
+    }
+}
+
+/// The start of the word being typed at `offset`, which a completion
+/// replaces, the context there and the items valid in it. Inside string
+/// literals only time zones are offered. `current` is the parse of `text`
+/// as far as it parses, see [`parse_conf_recovering`]; without a `scope`
+/// every file counts as included.
+fn insertion_point(
+    text: &str,
+    offset: usize,
+    current: &ParsedConfiguration,
+    files: &[(String, ParsedConfiguration)],
+    scope: Option<&IncludeScope>,
+) -> Option<(Option<Position>, Context, Vec<GrammarItem>)> {
+    // Substitutions are also valid inside strings.
+    if let Some(start) = substitution_start(text, offset) {
+        let start = offset_to_position(text, start);
+        return Some((Some(start), Context::None, defines(current, files)));
+    }
+    if let Some((start, braced)) = macro_start(text, offset) {
+        let items = grammar::MACROS
+            .iter()
+            // Names of structured data only work braced.
+            .filter(|info| braced || !info.name.starts_with('.'))
+            .map(|info| GrammarItem::Macro { info, braced })
+            .collect();
+        return Some((Some(offset_to_position(text, start)), Context::None, items));
+    }
+    if let Some((quote_start, quote)) = string_start(text, offset) {
+        let tokens = tokenize(&text[..quote_start]).ok()?;
+        let Context::Call { context, calls } = completion_context(&tokens, quote_start) else {
+            return None;
+        };
+        let items = call_insertions(context, &calls)
+            .into_iter()
+            .filter(|item| matches!(item, GrammarItem::TimeZone(_)))
+            .collect();
+        let closed = text[offset..].starts_with(quote);
+        let start = offset_to_position(text, quote_start + 1);
+        return Some((Some(start), Context::String { quote, closed }, items));
+    }
+    let mut tokens = tokenize(&text[..offset]).ok()?;
+    let replace_start = match tokens.last() {
+        Some(last) if last.end == offset && matches!(last.kind, TokenKind::Word | TokenKind::Pragma) => {
+            if last.kind == TokenKind::Word {
+                tokens.pop().map(|t| t.range.start)
+            } else {
+                Some(last.range.start)
+            }
+        }
+        _ => None,
+    };
+    let context = completion_context(&tokens, offset);
+    let items = match &context {
+        Context::Root => ObjectKind::ALL
+            .iter()
+            .map(|kind| GrammarItem::Keyword(*kind))
+            .chain(ANNOTATIONS.iter().map(|a| GrammarItem::Annotation(a)))
+            .collect(),
+        Context::Annotation => ANNOTATIONS.iter().map(|a| GrammarItem::Annotation(a)).collect(),
+        Context::ObjectBody(ObjectKind::Filter) => grammar::FILTER_FUNCTIONS
+            .iter()
+            .map(GrammarItem::FilterFunction)
+            .collect(),
+        Context::ObjectBody(ObjectKind::Options) => {
+            grammar::GLOBAL_OPTIONS.iter().map(GrammarItem::GlobalOption).collect()
+        }
+        Context::ObjectBody(ObjectKind::Log) => grammar::drivers_for(ObjectKind::Log)
+            .map(GrammarItem::Driver)
+            .chain(
+                ["if", "junction"]
+                    .into_iter()
+                    .filter_map(grammar::find_branch_keyword)
+                    .map(GrammarItem::Branch),
+            )
+            .chain(blocks(ObjectKind::Log, current, files, scope))
+            .collect(),
+        Context::ObjectBody(kind) => grammar::drivers_for(*kind)
+            .map(GrammarItem::Driver)
+            .chain(blocks(*kind, current, files, scope))
+            .collect(),
+        Context::Junction => grammar::find_branch_keyword("channel")
+            .map(GrammarItem::Branch)
+            .into_iter()
+            .collect(),
+        Context::AfterBranch => ["elif", "else"]
+            .into_iter()
+            .filter_map(grammar::find_branch_keyword)
+            .map(GrammarItem::Branch)
+            .collect(),
+        Context::Call {
+            context: ObjectKind::Log,
+            calls,
+        } if calls.len() == 1 && grammar::normalize_name(&calls[0]) == "flags" => {
+            grammar::LOG_FLAGS.iter().map(GrammarItem::LogFlag).collect()
+        }
+        // `source(` and friends in a log path take the identifier of an object.
+        Context::Call {
+            context: ObjectKind::Log,
+            calls,
+        } if calls.len() == 1 => match match_object_kind(&calls[0]).filter(|k| k.has_id()) {
+            Some(kind) => object_ids(kind, current, files, scope)
+                .into_iter()
+                .map(|(name, include)| GrammarItem::ObjectId(kind, name, include))
+                .collect(),
+            None => call_insertions(ObjectKind::Log, calls),
+        },
+        Context::Call { context, calls } => call_insertions(*context, calls),
+        Context::String { .. } | Context::None => Vec::new(),
+    };
+    Some((replace_start, context, items))
+}
+
+const ANNOTATIONS: &[&str] = &["@version", "@include", "@define", "@module", "@requires"];
+
+/// Offset right after the opening backtick of the `` `name` `` being typed
+/// at `offset`, if any. Substitutions do not span lines.
+fn substitution_start(text: &str, offset: usize) -> Option<usize> {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let before = &text[line_start..offset];
+    if before.matches('`').count().is_multiple_of(2) {
+        return None;
+    }
+    let open = before.rfind('`')?;
+    before[open + 1..]
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+        .then_some(line_start + open + 1)
+}
+
+/// Offset right after the `$` or `${` of the macro being typed at
+/// `offset` in a quoted string, and whether it is braced. Like strings in
+/// completion contexts, only the current line is looked at.
+fn macro_start(text: &str, offset: usize) -> Option<(usize, bool)> {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let before = &text[line_start..offset];
+    let dollar = before.rfind('$')?;
+    let (name_start, braced) = match before[dollar + 1..].starts_with('{') {
+        true => (dollar + 2, true),
+        false => (dollar + 1, false),
+    };
+    let name_chars = |c: char| c.is_ascii_alphanumeric() || c == '_' || (braced && matches!(c, '.' | '-'));
+    if !before[name_start..].chars().all(name_chars) || open_quote(&before[..dollar]).is_none() {
+        return None;
+    }
+    Some((line_start + name_start, braced))
+}
+
+/// Offset of the opening quote of the string literal `offset` is inside,
+/// and the quote. Only the current line is looked at.
+fn string_start(text: &str, offset: usize) -> Option<(usize, char)> {
+    let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+    open_quote(&text[line_start..offset]).map(|(start, quote)| (line_start + start, quote))
+}
+
+/// The offset and character of the quote opening the string the end of
+/// `line` is inside, if it is.
+fn open_quote(line: &str) -> Option<(usize, char)> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some((_, '"')), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some((_, open)), c) if c == open && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some((i, c)),
+            _ => {}
+        }
+        escaped = false;
+    }
+    quote
+}
+
+/// The built-in variables and those defined by the document, `current`,
+/// and `files`.
+fn defines(current: &ParsedConfiguration, files: &[(String, ParsedConfiguration)]) -> Vec<GrammarItem> {
+    let mut defines: BTreeMap<String, String> = grammar::BUILTIN_DEFINES
+        .iter()
+        .map(|d| (d.name.to_string(), d.description.to_string()))
+        .collect();
+    for (_, parsed) in files {
+        defines.extend(parsed.defines().map(|d| (d.name.clone(), d.value.clone())));
+    }
+    defines.extend(current.defines().map(|d| (d.name.clone(), d.value.clone())));
+    defines
+        .into_iter()
+        .map(|(name, detail)| GrammarItem::Define { name, detail })
+        .collect()
+}
+
+/// Identifiers of the `kind` objects of `text` and `files`, sorted, with
+/// the `@include` path of the defining file when it is outside `scope`.
+fn object_ids(
+    kind: ObjectKind,
+    current: &ParsedConfiguration,
+    files: &[(String, ParsedConfiguration)],
+    scope: Option<&IncludeScope>,
+) -> Vec<(String, Option<String>)> {
+    let mut ids = BTreeMap::new();
+    let files = files.iter().map(|(uri, parsed)| (Some(uri.as_str()), parsed));
+    for (uri, parsed) in std::iter::once((None, current)).chain(files) {
+        let include = match (uri, scope) {
+            (Some(uri), Some(scope)) if !(scope.visible)(uri) => {
+                let (Some(from), Some(target)) = (uri_to_path(scope.uri), uri_to_path(uri)) else {
+                    continue;
+                };
+                Some(include_path(&from, &target))
+            }
+            _ => None,
+        };
+        for id in parsed.get_objects_by_kind(kind).filter_map(|o| o.id.as_ref()) {
+            // A definition the document can already see wins.
+            let entry = ids.entry(id.name.clone()).or_insert_with(|| include.clone());
+            if include.is_none() {
+                *entry = None;
+            }
+        }
+    }
+    ids.into_iter().collect()
+}
+
+/// The blocks that stand in for a driver of `kind`, defined in `text` or in
+/// a file in `scope`, sorted by name. Unlike object identifiers, blocks of
+/// files not included are not offered: syslog-ng expands a block where it
+/// is used, so it has to be defined before.
+fn blocks(
+    kind: ObjectKind,
+    current: &ParsedConfiguration,
+    files: &[(String, ParsedConfiguration)],
+    scope: Option<&IncludeScope>,
+) -> Vec<GrammarItem> {
+    let visible = files
+        .iter()
+        .filter(|(uri, _)| scope.is_none_or(|scope| (scope.visible)(uri)))
+        .map(|(_, parsed)| parsed);
+    let mut blocks = BTreeMap::new();
+    for parsed in std::iter::once(current).chain(visible) {
+        for block in parsed.blocks_for(kind) {
+            blocks
+                .entry(block.name.name.clone())
+                .or_insert_with(|| GrammarItem::Block {
+                    name: block.name.name.clone(),
+                    context: block.context.name.clone(),
+                    required: block
+                        .parameters
+                        .iter()
+                        .filter(|p| p.default.is_empty())
+                        .map(|p| p.name.name.clone())
+                        .collect(),
+                });
+        }
+    }
+    blocks.into_values().collect()
+}
+
+fn completion_context(tokens: &[Token], offset: usize) -> Context {
+    if let Some(last) = tokens.last() {
+        if last.kind == TokenKind::Pragma && last.end == offset {
+            return Context::Annotation;
+        }
+    }
+
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pending_kind: Option<ObjectKind> = None;
+    // `Some(is_else)` after a branch keyword, until its `{`.
+    let mut pending_branch: Option<bool> = None;
+    let mut pending_junction = false;
+    let mut after_branch = false;
+    for (i, token) in tokens.iter().enumerate() {
+        let mut closes_branch = false;
+        match token.kind {
+            TokenKind::LBrace => match pending_branch.take() {
+                Some(is_else) => stack.push(Frame::Branch { is_else }),
+                None if std::mem::take(&mut pending_junction) => stack.push(Frame::Junction),
+                None => {
+                    let kind = pending_kind.take().unwrap_or(ObjectKind::Log);
+                    stack.push(Frame::Object(kind));
+                }
+            },
+            TokenKind::RBrace => {
+                while let Some(frame) = stack.pop() {
+                    match frame {
+                        Frame::Branch { is_else } => {
+                            closes_branch = !is_else;
+                            break;
+                        }
+                        Frame::Object(_) | Frame::Junction => break,
+                        Frame::Paren(_) => {}
+                    }
+                }
+            }
+            TokenKind::LParen => {
+                let name = i
+                    .checked_sub(1)
+                    .map(|p| &tokens[p])
+                    .filter(|t| t.is(TokenKind::Word))
+                    .map(|t| t.text.clone());
+                stack.push(Frame::Paren(name));
+            }
+            TokenKind::RParen => {
+                if matches!(stack.last(), Some(Frame::Paren(_))) {
+                    stack.pop();
+                }
+            }
+            TokenKind::Semicolon => {
+                pending_kind = None;
+                pending_branch = None;
+                pending_junction = false;
+            }
+            TokenKind::Word if !matches!(stack.last(), Some(Frame::Paren(_))) => {
+                let in_log = stack.last().and_then(Frame::object_kind) == Some(ObjectKind::Log);
+                if in_log && matches!(token.text.as_str(), "if" | "elif" | "else") {
+                    pending_branch = Some(token.text == "else");
+                } else if in_log && token.text == "junction" {
+                    pending_junction = true;
+                } else if let Some(kind) = match_object_kind(&token.text) {
+                    pending_kind = Some(kind);
+                }
+            }
+            _ => {}
+        }
+        after_branch = closes_branch;
+    }
+
+    let previous = tokens.last().map(|t| t.kind);
+    if after_branch {
+        return Context::AfterBranch;
+    }
+    match stack.last().map(|frame| (frame, frame.object_kind())) {
+        Some((Frame::Junction, _)) => match previous {
+            Some(TokenKind::LBrace | TokenKind::Semicolon) => Context::Junction,
+            _ => Context::None,
+        },
+        None => match previous {
+            None | Some(TokenKind::Semicolon | TokenKind::Pragma) => Context::Root,
+            _ => Context::None,
+        },
+        Some((_, Some(kind))) => match previous {
+            Some(TokenKind::LBrace | TokenKind::Semicolon) => Context::ObjectBody(kind),
+            // Filter expressions continue after `and`, `or` and `not`.
+            Some(TokenKind::Word) if kind == ObjectKind::Filter => filter_continuation(tokens),
+            _ => Context::None,
+        },
+        Some((_, None)) => {
+            let mut calls = Vec::new();
+            let mut context = None;
+            for frame in stack.iter().rev() {
+                match frame {
+                    Frame::Paren(Some(name)) => calls.push(name.clone()),
+                    Frame::Paren(None) => {}
+                    _ => {
+                        context = frame.object_kind();
+                        break;
+                    }
+                }
+            }
+            calls.reverse();
+            match context {
+                Some(ObjectKind::Filter) => Context::None,
+                // Directly inside the parentheses of `if (` or `elif (`.
+                Some(ObjectKind::Log) if calls.len() == 1 && matches!(calls[0].as_str(), "if" | "elif") => {
+                    match previous {
+                        Some(TokenKind::LParen) => Context::ObjectBody(ObjectKind::Filter),
+                        Some(TokenKind::Word) => filter_continuation(tokens),
+                        _ => Context::None,
+                    }
+                }
+                Some(context) if !calls.is_empty() => Context::Call { context, calls },
+                _ => Context::None,
+            }
+        }
+    }
+}
+
+/// A filter function can follow the `and`, `or` or `not` ending `tokens`.
+fn filter_continuation(tokens: &[Token]) -> Context {
+    match tokens.last() {
+        Some(t) if matches!(t.text.as_str(), "and" | "or" | "not") => Context::ObjectBody(ObjectKind::Filter),
+        _ => Context::None,
+    }
+}
+
+/// What goes inside the innermost of `calls`: the options of a driver or
+/// block, or the values of an option with a fixed set of them.
+fn call_insertions(context: ObjectKind, calls: &[String]) -> Vec<GrammarItem> {
+    let Some((first, rest)) = calls.split_first() else {
+        return Vec::new();
+    };
+    let mut option = if context == ObjectKind::Options {
+        grammar::find_global_option(first)
+    } else {
+        let Some(driver) = grammar::find_driver(context, first) else {
+            return Vec::new();
+        };
+        match rest.split_first() {
+            None => return driver.all_options().map(GrammarItem::Option).collect(),
+            Some((second, _)) => driver.find_option(second),
+        }
+    };
+    let nested = if context == ObjectKind::Options {
+        rest
+    } else {
+        &rest[1..]
+    };
+    for name in nested {
+        option = option.and_then(|o| o.find_child(name));
+    }
+    match option {
+        Some(option) if option.value == "time zone" => TIME_ZONES.iter().copied().map(GrammarItem::TimeZone).collect(),
+        Some(option) if option.children.is_empty() => option_values(option).map(GrammarItem::Value).collect(),
+        Some(option) => option.children.iter().map(GrammarItem::Option).collect(),
+        None => Vec::new(),
+    }
+}
+
+/// The alternatives of values such as `yes | no`; nothing for descriptions
+/// such as `path` or `number`.
+fn option_values(option: &'static OptionInfo) -> impl Iterator<Item = &'static str> {
+    let values: Vec<&str> = option.value.split(" | ").collect();
+    let enumerated = values.len() > 1
+        && values
+            .iter()
+            .all(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    values.into_iter().filter(move |_| enumerated)
+}
+
+struct ItemBuilder<'a> {
+    range: Range,
+    context: &'a Context,
+    options: &'a CompletionOptions,
+}
+
+impl ItemBuilder<'_> {
+    fn item(&self, item: &GrammarItem) -> CompletionItem {
+        match item {
+            GrammarItem::Keyword(kind) => {
+                let keyword = kind.keyword();
+                let snippet = if kind.has_id() {
+                    format!("{} ${{1:name}} {{\n\t$0\n}};", keyword)
+                } else {
+                    format!("{} {{\n\t$0\n}};", keyword)
+                };
+                let detail = format!("{} object", keyword);
+                self.build(
+                    keyword,
+                    CompletionItemKind::Keyword,
+                    Some(detail),
+                    None,
+                    keyword.to_string(),
+                    snippet,
+                )
+            }
+            GrammarItem::Branch(keyword) => {
+                // `elif` and `else` are offered right after the `}` of a
+                // branch, in front of the `;` ending the block.
+                let snippet = match keyword.name {
+                    "if" => "if (${1}) {\n\t$0\n};".to_string(),
+                    "junction" => "junction {\n\tchannel {\n\t\t$0\n\t};\n};".to_string(),
+                    "channel" => "channel {\n\t$0\n};".to_string(),
+                    "else" => "else {\n\t$0\n}".to_string(),
+                    name => format!("{} (${{1}}) {{\n\t$0\n}}", name),
+                };
+                self.build(
+                    keyword.name,
+                    CompletionItemKind::Keyword,
+                    Some(keyword.description.to_string()),
+                    None,
+                    keyword.name.to_string(),
+                    snippet,
+                )
+            }
+            GrammarItem::Annotation(name) => {
+                let (detail, plain, snippet) = match *name {
+                    "@version" => ("configuration version", "@version: ", "@version: ${1:4.0}"),
+                    "@define" => ("define a variable", "@define ", "@define ${1:name} \"$0\""),
+                    "@module" => ("load a plugin module", "@module ", "@module ${1:name}"),
+                    "@requires" => ("require a plugin module", "@requires ", "@requires ${1:name}"),
+                    _ => ("include another file", "@include ", "@include \"$0\""),
+                };
+                self.build(
+                    name,
+                    CompletionItemKind::Keyword,
+                    Some(detail.to_string()),
+                    None,
+                    plain.to_string(),
+                    snippet.to_string(),
+                )
+            }
+            GrammarItem::Driver(driver) => self.build(
+                driver.name,
+                CompletionItemKind::Function,
+                Some(format!("{} driver", driver.context)),
+                Some(resolve_data(driver.context, &[], driver.name)),
+                driver.name.to_string(),
+                driver_snippet(driver),
+            ),
+            GrammarItem::FilterFunction(function) => self.build(
+                function.name,
+                CompletionItemKind::Function,
+                Some("filter function".to_string()),
+                Some(resolve_data(ObjectKind::Filter, &[], function.name)),
+                function.name.to_string(),
+                format!("{}($0)", function.name),
+            ),
+            GrammarItem::GlobalOption(option) | GrammarItem::Option(option) => {
+                let terminator = if matches!(item, GrammarItem::GlobalOption(_)) {
+                    ";"
+                } else {
+                    ""
+                };
+                let data = match self.context {
+                    Context::Call { context, calls } => resolve_data(*context, calls, option.name),
+                    _ => resolve_data(ObjectKind::Options, &[], option.name),
+                };
+                self.build(
+                    option.name,
+                    CompletionItemKind::Property,
+                    Some(option.value.to_string()),
+                    Some(data),
+                    option.name.to_string(),
+                    format!("{}({}){}", option.name, placeholder(option, 1), terminator),
+                )
+            }
+            GrammarItem::ObjectId(kind, name, include) => self.build(
+                name,
+                CompletionItemKind::Variable,
+                Some(match include {
+                    Some(path) => format!("{} in {}, not included", kind, path),
+                    None => kind.to_string(),
+                }),
+                None,
+                name.clone(),
+                name.clone(),
+            ),
+            GrammarItem::Block {
+                name,
+                context,
+                required,
+            } => self.build(
+                name,
+                CompletionItemKind::Function,
+                Some(format!("{} block", context)),
+                None,
+                name.clone(),
+                block_snippet(name, required),
+            ),
+            GrammarItem::Define { name, detail } => self.build(
+                name,
+                CompletionItemKind::Variable,
+                Some(detail.clone()),
+                None,
+                name.clone(),
+                format!("{}`", name),
+            ),
+            GrammarItem::Value(value) => self.build(
+                value,
+                CompletionItemKind::Value,
+                None,
+                None,
+                value.to_string(),
+                value.to_string(),
+            ),
+            GrammarItem::Macro { info, braced } => self.build(
+                info.name,
+                CompletionItemKind::Variable,
+                Some(info.description.to_string()),
+                None,
+                info.name.to_string(),
+                match braced {
+                    true => format!("{}}}", info.name),
+                    false => info.name.to_string(),
+                },
+            ),
+            GrammarItem::LogFlag(flag) => self.build(
+                flag.name,
+                CompletionItemKind::Value,
+                Some(flag.description.to_string()),
+                None,
+                flag.name.to_string(),
+                flag.name.to_string(),
+            ),
+            GrammarItem::TimeZone(zone) => {
+                let text = match self.context {
+                    Context::String { closed: true, .. } => zone.to_string(),
+                    Context::String { quote, .. } => format!("{}{}", zone, quote),
+                    _ => format!("\"{}\"", zone),
+                };
+                self.build(zone, CompletionItemKind::Value, None, None, text.clone(), text)
+            }
+        }
+    }
+
+    /// `plain` is inserted as is, `snippet` when closing syntax is enabled.
+    fn build(
+        &self,
+        label: &str,
+        kind: CompletionItemKind,
+        detail: Option<String>,
+        data: Option<Value>,
+        plain: String,
+        snippet: String,
+    ) -> CompletionItem {
+        let (new_text, insert_text_format) = match self.options.closing_syntax {
+            true => (snippet, InsertTextFormat::Snippet),
+            false => (plain, InsertTextFormat::PlainText),
+        };
+        let (detail, documentation) = match self.options.verbosity {
+            CompletionVerbosity::Minimal => (None, None),
+            CompletionVerbosity::Normal => (detail, None),
+            CompletionVerbosity::Full => (detail, data.as_ref().and_then(resolve_documentation)),
+        };
+        CompletionItem {
+            label: label.to_string(),
+            kind,
+            detail,
+            documentation,
+            data,
+            text_edit: TextEdit {
+                range: self.range,
+                new_text,
+            },
+            insert_text_format,
+        }
+    }
+}
+
+/// Identifies a grammar item for `completionItem/resolve`: the item `name`
+/// inside the calls `calls` of a `context` object.
+fn resolve_data(context: ObjectKind, calls: &[String], name: &str) -> Value {
+    json_object! {
+        "context" => context.keyword(),
+        "calls" => calls.to_vec(),
+        "name" => name,
+    }
+}
+
+/// Documentation of the item a completion's `data` identifies, looked up
+/// lazily so the completion list itself stays small.
+pub fn resolve_documentation(data: &Value) -> Option<String> {
+    let context = match_object_kind(data["context"].as_str()?)?;
+    let name = data["name"].as_str()?;
+    let calls: Vec<String> = data["calls"]
+        .as_array()?
+        .iter()
+        .filter_map(|c| c.as_str().map(str::to_string))
+        .collect();
+    if !calls.is_empty() {
+        let option = call_insertions(context, &calls)
+            .into_iter()
+            .find_map(|item| match item {
+                GrammarItem::Option(option) if option.name == name => Some(option),
+                _ => None,
+            })?;
+        let names: Vec<&str> = calls.iter().map(String::as_str).chain([name]).collect();
+        let footer = match context {
+            ObjectKind::Options => hover::global_option_footer(&names),
+            _ => hover::option_footer(grammar::find_driver(context, &calls[0])?, &names),
+        };
+        return Some(hover::describe_option(option, &footer));
+    }
+    match context {
+        ObjectKind::Options => Some(hover::describe_option(
+            grammar::find_global_option(name)?,
+            &hover::global_option_footer(&[name]),
+        )),
+        ObjectKind::Filter => Some(hover::describe_option(
+            grammar::find_filter_function(name)?,
+            "Filter function.",
+        )),
+        _ => Some(hover::describe_driver(grammar::find_driver(context, name)?)),
+    }
+}
+
+/// A user-defined block called as `name($0);`, or one line per parameter
+/// without a default, each with a tab stop, like [`driver_snippet`].
+fn block_snippet(name: &str, required: &[String]) -> String {
+    if required.is_empty() {
+        return format!("{}($0);", name);
+    }
+    let mut snippet = format!("{}(", name);
+    for (i, parameter) in required.iter().enumerate() {
+        snippet.push_str(&format!("\n\t{}(${{{}}})", parameter, i + 1));
+    }
+    snippet.push_str("$0\n);");
+    snippet
+}
+
+/// `name($0);`, or one line per option listed in the driver's snippet:
+/// `network(\n\tip(${1:"0.0.0.0"})\n\tport(${2:514})$0\n);`.
+fn driver_snippet(driver: &DriverInfo) -> String {
+    let options: Vec<&OptionInfo> = driver
+        .snippet
+        .iter()
+        .filter_map(|name| driver.find_option(name))
+        .collect();
+    if options.is_empty() {
+        return format!("{}($0);", driver.name);
+    }
+    let mut snippet = format!("{}(", driver.name);
+    for (i, option) in options.iter().enumerate() {
+        snippet.push_str(&format!("\n\t{}({})", option.name, placeholder(option, i + 1)));
+    }
+    snippet.push_str("$0\n);");
+    snippet
+}
+
+/// Tab stop `index` for the value of `option`: its default, a choice of its
+/// values, or an empty (quoted, for strings and paths) stop.
+fn placeholder(option: &'static OptionInfo, index: usize) -> String {
+    if let Some(default) = option.default {
+        return format!("${{{}:{}}}", index, escape_snippet(default));
+    }
+    let values: Vec<&str> = option_values(option).collect();
+    if !values.is_empty() {
+        return format!("${{{}|{}|}}", index, values.join(","));
+    }
+    match option.value {
+        "string" | "path" | "template" => format!("\"${}\"", index),
+        _ => format!("${}", index),
+    }
+}
+
+/// Escapes the characters that are special in snippet text.
+fn escape_snippet(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('$', "\\$").replace('}', "\\}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The context and items at the `|` in `text`.
+    fn at_cursor(text: &str) -> (Option<Position>, Context, Vec<GrammarItem>) {
+        let offset = text.find('|').unwrap();
+        let text = text.replacen('|', "", 1);
+        let (current, _) = parse_conf_recovering(&text);
+        insertion_point(&text, offset, &current, &[], None).unwrap()
+    }
+
+    fn context(text: &str) -> Context {
+        at_cursor(text).1
+    }
+
+    fn labels(text: &str) -> Vec<String> {
+        at_cursor(text)
+            .2
+            .iter()
+            .map(|item| match item {
+                GrammarItem::Keyword(kind) => kind.to_string(),
+                GrammarItem::Annotation(name) | GrammarItem::Value(name) | GrammarItem::TimeZone(name) => {
+                    name.to_string()
+                }
+                GrammarItem::Branch(keyword) => keyword.name.to_string(),
+                GrammarItem::Driver(driver) => driver.name.to_string(),
+                GrammarItem::GlobalOption(option)
+                | GrammarItem::Option(option)
+                | GrammarItem::FilterFunction(option) => option.name.to_string(),
+                GrammarItem::LogFlag(flag) => flag.name.to_string(),
+                GrammarItem::ObjectId(_, name, _)
+                | GrammarItem::Block { name, .. }
+                | GrammarItem::Define { name, .. } => name.clone(),
+                GrammarItem::Macro { info, .. } => info.name.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn offers_keywords_and_annotations_at_the_root() {
+        assert_eq!(context("source s { internal(); };\n|"), Context::Root);
+        let items = labels("|");
+        assert!(items.contains(&"source".to_string()));
+        assert!(items.contains(&"@version".to_string()));
+        assert_eq!(context("@ver|"), Context::Annotation);
+    }
+
+    #[test]
+    fn offers_drivers_in_object_bodies() {
+        assert_eq!(context("source s { |"), Context::ObjectBody(ObjectKind::Source));
+        let items = labels("destination d { file(\"/x\"); |");
+        assert!(items.contains(&"file".to_string()));
+        assert!(!items.contains(&"internal".to_string()));
+        assert!(labels("log { |").contains(&"if".to_string()));
+    }
+
+    #[test]
+    fn replaces_the_word_being_typed() {
+        let (start, context, _) = at_cursor("source s {\n    netw|");
+        assert_eq!(context, Context::ObjectBody(ObjectKind::Source));
+        assert_eq!(start, Some(Position::new(1, 4)));
+    }
+
+    #[test]
+    fn offers_options_inside_calls() {
+        assert_eq!(
+            context("source s { network(ip(\"h\") |"),
+            Context::Call {
+                context: ObjectKind::Source,
+                calls: vec!["network".to_string()],
+            }
+        );
+        assert!(labels("source s { network(|").contains(&"port".to_string()));
+        assert!(labels("log { source(s); flags(|").contains(&"final".to_string()));
+    }
+
+    #[test]
+    fn offers_object_ids_in_log_path_references() {
+        let items = labels("source s_local { internal(); };\nsource s_net { network(); };\nlog { source(|");
+        assert_eq!(items, ["s_local", "s_net"]);
+    }
+
+    #[test]
+    fn offers_time_zones_in_an_open_string() {
+        let (start, open, items) = at_cursor("destination d { file(\"/x\" time-zone(\"Europe/Bu|");
+        assert_eq!(
+            open,
+            Context::String {
+                quote: '"',
+                closed: false
+            }
+        );
+        assert_eq!(start, Some(Position::new(0, 37)));
+        assert!(items.contains(&GrammarItem::TimeZone("Europe/Budapest")));
+        assert_eq!(
+            context("destination d { file(\"/x\" time-zone(\"|\")"),
+            Context::String {
+                quote: '"',
+                closed: true
+            }
+        );
+        assert!(labels("destination d { file(\"/var/l|").is_empty());
+    }
+
+    #[test]
+    fn offers_elif_and_else_after_a_branch() {
+        assert_eq!(
+            context("log { if (level(err)) { destination(d); } |"),
+            Context::AfterBranch
+        );
+        assert_eq!(labels("log { if (level(err)) { destination(d); } |"), ["elif", "else"]);
+        assert_eq!(
+            context("log { if (level(err)) { destination(d); } elif (level(info)) { destination(d); } |"),
+            Context::AfterBranch
+        );
+        assert_ne!(
+            context("log { if (level(err)) { destination(d); } else { destination(d); } |"),
+            Context::AfterBranch
+        );
+    }
+
+    #[test]
+    fn offers_channels_in_junctions() {
+        assert_eq!(context("log { junction { |"), Context::Junction);
+        assert_eq!(labels("log { junction { |"), ["channel"]);
+    }
+}
@@ -0,0 +1,390 @@
+//! Include resolution across configuration files.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::document_store::LoadedText;
+use crate::file_utilities::{path_to_uri, real_path, resolve_include, uri_to_path};
+use crate::language_types::annotations::Annotation;
+use crate::language_types::objects::Object;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Location, Position};
+use crate::parser::{parse_conf, SngSyntaxError};
+
+/// syslog-ng itself refuses to nest includes deeper than this; the default
+/// of [`IncludeOptions::max_depth`].
+pub const MAX_INCLUDE_DEPTH: usize = 15;
+
+/// A file pulled in through an `@include` directive.
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    pub uri: String,
+    pub path: PathBuf,
+    /// The `@include` directive (in the including file) that pulled this file in.
+    pub included_from: Location,
+    pub parsed: Result<ParsedConfiguration, SngSyntaxError>,
+    /// The file lies outside every workspace folder, e.g. a system file
+    /// under `/etc`. Such files are analysed but never edited.
+    pub outside_workspace: bool,
+    /// Where the file stopped being valid UTF-8, see [`LoadedText`].
+    pub invalid_utf8: Option<Position>,
+}
+
+/// How `@include` paths are turned into files, in particular those that
+/// point outside the workspace.
+#[derive(Debug, Clone)]
+pub struct IncludeOptions {
+    pub workspace_folders: Vec<PathBuf>,
+    /// Prefixes replaced before an include is resolved, e.g. `/etc/syslog-ng`
+    /// by a mirror of it inside the workspace. Relative replacements are
+    /// relative to the workspace folder of the including file.
+    pub path_map: Vec<(PathBuf, PathBuf)>,
+    /// Read the files that are still outside the workspace after mapping.
+    pub read_outside_workspace: bool,
+    /// Searched for relative includes not found next to the including file;
+    /// relative to the workspace folder of that file if relative itself.
+    pub include_root: Option<PathBuf>,
+    /// Includes nested deeper than this are not followed.
+    pub max_depth: usize,
+}
+
+impl Default for IncludeOptions {
+    fn default() -> Self {
+        IncludeOptions {
+            workspace_folders: Vec::new(),
+            path_map: Vec::new(),
+            read_outside_workspace: true,
+            include_root: None,
+            max_depth: MAX_INCLUDE_DEPTH,
+        }
+    }
+}
+
+impl IncludeOptions {
+    /// Without workspace folders every file counts as part of the workspace.
+    pub fn is_in_workspace(&self, path: &Path) -> bool {
+        self.workspace_folders.is_empty() || self.workspace_folders.iter().any(|f| path.starts_with(f))
+    }
+
+    /// The innermost workspace folder containing `path`, or the first
+    /// folder for files outside all of them.
+    fn folder_of(&self, path: &Path) -> Option<&Path> {
+        self.workspace_folders
+            .iter()
+            .filter(|f| path.starts_with(f))
+            .max_by_key(|f| f.components().count())
+            .or_else(|| self.workspace_folders.first())
+            .map(PathBuf::as_path)
+    }
+
+    /// `path` with the longest matching prefix of the path map replaced,
+    /// relative replacements taken from `folder`.
+    fn map_path(&self, path: &Path, folder: Option<&Path>) -> PathBuf {
+        self.path_map
+            .iter()
+            .filter_map(|(from, to)| path.strip_prefix(from).ok().map(|rest| (from, to.join(rest))))
+            .max_by_key(|(from, _)| from.components().count())
+            .map_or_else(
+                || path.to_path_buf(),
+                |(_, mapped)| folder.map_or_else(|| mapped.clone(), |f| f.join(&mapped)),
+            )
+    }
+
+    /// The files an `@include` of `include` in `base_dir` pulls in.
+    fn resolve(&self, base_dir: &Path, include: &str) -> Vec<PathBuf> {
+        let folder = self.folder_of(base_dir);
+        let target = self.map_path(&base_dir.join(include), folder);
+        let found = resolve_include(base_dir, &target.to_string_lossy());
+        match &self.include_root {
+            Some(root) if found.is_empty() && Path::new(include).is_relative() => {
+                let root = folder.map_or_else(|| root.clone(), |f| f.join(root));
+                resolve_include(&root, &self.map_path(&root.join(include), folder).to_string_lossy())
+            }
+            _ => found,
+        }
+    }
+}
+
+/// Loads the current text of a file: the editor buffer when the file is
+/// open, its contents on disk otherwise.
+pub type TextLoader<'a> = dyn Fn(&Path) -> Option<LoadedText> + 'a;
+
+/// Parse results of the snippets the open documents include, kept between
+/// analyses so an unchanged snippet is not parsed again on every keystroke
+/// in the file that includes it. Files are only read once an include
+/// reaches them; [`SnippetCache::retain`] drops the ones no longer included.
+#[derive(Debug, Default)]
+pub struct SnippetCache {
+    entries: RefCell<HashMap<String, CachedSnippet>>,
+}
+
+#[derive(Debug)]
+struct CachedSnippet {
+    text_hash: u64,
+    parsed: Result<ParsedConfiguration, SngSyntaxError>,
+}
+
+impl SnippetCache {
+    /// Parses `text` as the contents of `uri`, reusing the previous result
+    /// when the text is unchanged.
+    pub fn parse(&self, uri: &str, text: &str) -> Result<ParsedConfiguration, SngSyntaxError> {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let text_hash = hasher.finish();
+        let mut entries = self.entries.borrow_mut();
+        match entries.get(uri) {
+            Some(entry) if entry.text_hash == text_hash => entry.parsed.clone(),
+            _ => {
+                let parsed = parse_conf(text);
+                entries.insert(
+                    uri.to_string(),
+                    CachedSnippet {
+                        text_hash,
+                        parsed: parsed.clone(),
+                    },
+                );
+                parsed
+            }
+        }
+    }
+
+    /// Drops the entries `keep` returns false for.
+    pub fn retain(&self, keep: impl Fn(&str) -> bool) {
+        self.entries.borrow_mut().retain(|uri, _| keep(uri));
+    }
+}
+
+/// Parse results of workspace files read from disk, valid as long as the
+/// modification time and size of the file are unchanged. A request that
+/// runs out of time hands the files it did not get to to
+/// [`WorkspaceCache::prefetch`], which parses them on a background thread
+/// so that the next request finds them ready.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceCache {
+    entries: Arc<Mutex<HashMap<PathBuf, CachedFile>>>,
+    prefetching: Arc<AtomicBool>,
+}
+
+#[derive(Debug)]
+struct CachedFile {
+    stamp: (SystemTime, u64),
+    parsed: Result<ParsedConfiguration, SngSyntaxError>,
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+impl WorkspaceCache {
+    /// The cached parse of `path`, if the file did not change since.
+    pub fn get(&self, path: &Path) -> Option<Result<ParsedConfiguration, SngSyntaxError>> {
+        let stamp = file_stamp(path)?;
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(path)
+            .filter(|entry| entry.stamp == stamp)
+            .map(|entry| entry.parsed.clone())
+    }
+
+    /// Reads and parses `path`, caching the result.
+    pub fn load(&self, path: &Path) -> Option<Result<ParsedConfiguration, SngSyntaxError>> {
+        let stamp = file_stamp(path)?;
+        let text = LoadedText::decode(fs::read(path).ok()?).text;
+        let parsed = parse_conf(&text);
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(
+            path.to_path_buf(),
+            CachedFile {
+                stamp,
+                parsed: parsed.clone(),
+            },
+        );
+        Some(parsed)
+    }
+
+    /// Forgets `path`, e.g. once the file is deleted or renamed.
+    pub fn remove(&self, path: &Path) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(path);
+    }
+
+    /// Loads `paths` on a background thread, unless one is still busy.
+    pub fn prefetch(&self, paths: Vec<PathBuf>) {
+        if paths.is_empty() || self.prefetching.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let cache = self.clone();
+        thread::spawn(move || {
+            let _busy = Busy(cache.prefetching.clone());
+            for path in &paths {
+                if cache.get(path).is_none() {
+                    cache.load(path);
+                }
+            }
+        });
+    }
+}
+
+/// Clears the prefetching flag when dropped, also when the thread panics.
+struct Busy(Arc<AtomicBool>);
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+/// Resolves every include reachable from `parsed`, depth first, in the order
+/// syslog-ng would process them.
+pub fn resolve_snippets(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    load: &TextLoader,
+    cache: &SnippetCache,
+    options: &IncludeOptions,
+) -> Vec<Snippet> {
+    let mut snippets = Vec::new();
+    // Real paths, so that an include cycle through a symlink is detected too.
+    let mut stack: Vec<PathBuf> = uri_to_path(uri).map(|p| real_path(&p)).into_iter().collect();
+    let resolver = Resolver { load, cache, options };
+    resolver.collect_snippets(uri, parsed, &mut stack, &mut snippets);
+    snippets
+}
+
+struct Resolver<'a, 'b> {
+    load: &'a TextLoader<'b>,
+    cache: &'a SnippetCache,
+    options: &'a IncludeOptions,
+}
+
+impl Resolver<'_, '_> {
+    fn collect_snippets(
+        &self,
+        uri: &str,
+        parsed: &ParsedConfiguration,
+        stack: &mut Vec<PathBuf>,
+        snippets: &mut Vec<Snippet>,
+    ) {
+        if stack.len() > self.options.max_depth {
+            return;
+        }
+        let Some(base_dir) = uri_to_path(uri).and_then(|p| p.parent().map(Path::to_path_buf)) else {
+            return;
+        };
+        for annotation in &parsed.annotations {
+            let Annotation::Include(include) = annotation else {
+                continue;
+            };
+            for path in self.options.resolve(&base_dir, &include.path) {
+                let outside_workspace = !self.options.is_in_workspace(&path);
+                let real = real_path(&path);
+                if stack.contains(&real) || (outside_workspace && !self.options.read_outside_workspace) {
+                    continue;
+                }
+                let Some(loaded) = (self.load)(&path) else {
+                    continue;
+                };
+                let snippet_uri = path_to_uri(&path);
+                let snippet = Snippet {
+                    parsed: self.cache.parse(&snippet_uri, &loaded.text),
+                    uri: snippet_uri.clone(),
+                    path: path.clone(),
+                    included_from: Location {
+                        uri: uri.to_string(),
+                        range: include.range,
+                    },
+                    outside_workspace,
+                    invalid_utf8: loaded.invalid_utf8,
+                };
+                let nested = snippet.parsed.clone();
+                snippets.push(snippet);
+                if let Ok(nested) = nested {
+                    stack.push(real);
+                    self.collect_snippets(&snippet_uri, &nested, stack, snippets);
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// A top-level item of the merged configuration.
+#[derive(Debug, Clone, Copy)]
+pub enum MergedItem<'a> {
+    Annotation(&'a Annotation),
+    Object(&'a Object),
+}
+
+impl MergedItem<'_> {
+    fn start(&self) -> Position {
+        match self {
+            MergedItem::Annotation(annotation) => annotation.range().start,
+            MergedItem::Object(object) => object.location.start,
+        }
+    }
+}
+
+/// Top-level items of the main file and its snippets in merged order, i.e.
+/// with each include expanded at the position of its directive. This is the
+/// order in which syslog-ng reads the configuration.
+pub fn merged_items<'a>(
+    uri: &'a str,
+    parsed: &'a ParsedConfiguration,
+    snippets: &'a [Snippet],
+) -> Vec<(&'a str, MergedItem<'a>)> {
+    let mut merged = Vec::new();
+    push_merged(uri, parsed, snippets, &mut merged);
+    merged
+}
+
+/// The annotations of [`merged_items`].
+pub fn merged_annotations<'a>(
+    uri: &'a str,
+    parsed: &'a ParsedConfiguration,
+    snippets: &'a [Snippet],
+) -> Vec<(&'a str, &'a Annotation)> {
+    merged_items(uri, parsed, snippets)
+        .into_iter()
+        .filter_map(|(uri, item)| match item {
+            MergedItem::Annotation(annotation) => Some((uri, annotation)),
+            MergedItem::Object(_) => None,
+        })
+        .collect()
+}
+
+fn push_merged<'a>(
+    uri: &'a str,
+    parsed: &'a ParsedConfiguration,
+    snippets: &'a [Snippet],
+    merged: &mut Vec<(&'a str, MergedItem<'a>)>,
+) {
+    let mut items: Vec<MergedItem> = parsed
+        .annotations
+        .iter()
+        .map(MergedItem::Annotation)
+        .chain(parsed.objects.iter().map(MergedItem::Object))
+        .collect();
+    items.sort_by_key(MergedItem::start);
+    for item in items {
+        merged.push((uri, item));
+        if let MergedItem::Annotation(Annotation::Include(include)) = item {
+            let included = snippets
+                .iter()
+                .filter(|s| s.included_from.uri == uri && s.included_from.range == include.range);
+            for snippet in included {
+                if let Ok(nested) = &snippet.parsed {
+                    push_merged(&snippet.uri, nested, snippets, merged);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1575 @@
+//! Conversion of parser results into LSP diagnostics.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::configuration::{
+    merged_annotations, merged_items, resolve_snippets, IncludeOptions, MergedItem, Snippet, SnippetCache, TextLoader,
+};
+use crate::grammar::{self, normalize_name, OptionInfo, Platform};
+use crate::language_types::annotations::Annotation;
+use crate::language_types::failover::{Failover, FailoverProblem};
+use crate::language_types::filter_expression::Expression;
+use crate::language_types::log_flags::{LogFlags, LogFlagsProblem};
+use crate::language_types::objects::{
+    Driver, Literal, LiteralKind, Object, ObjectBody, ObjectKind, Parameter, Statement,
+};
+use crate::language_types::rewrite::{RewriteProblem, RewriteRule};
+use crate::language_types::templates::macros;
+use crate::language_types::typed_values::typed_values;
+use crate::language_types::value_pairs::{ValuePairs, ValuePairsProblem};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, Position, Range};
+use crate::parser::references::collect_references;
+use crate::parser::template_parser::{parse_template, verbatim_range};
+use crate::parser::{parse_conf, parse_conf_recovering, SngSyntaxError};
+use crate::time_zones::{closest_time_zone, edit_distance, is_time_zone};
+
+/// Diagnostics produced by analysing one document. Checks that span includes
+/// can report problems located in other files; those are kept in `related`,
+/// keyed by the URI they belong to.
+#[derive(Debug, Default)]
+pub struct DiagnosticReport {
+    pub own: Vec<Diagnostic>,
+    pub related: HashMap<String, Vec<Diagnostic>>,
+    /// URIs of every snippet the analysis read.
+    pub snippets: Vec<String>,
+}
+
+impl DiagnosticReport {
+    fn push(&mut self, main_uri: &str, uri: &str, diagnostic: Diagnostic) {
+        if uri == main_uri {
+            self.own.push(diagnostic);
+        } else {
+            self.related.entry(uri.to_string()).or_default().push(diagnostic);
+        }
+    }
+}
+
+pub fn syntax_error_to_diagnostic(error: &SngSyntaxError) -> Diagnostic {
+    let mut diagnostic = Diagnostic::new(error.range, DiagnosticSeverity::Error, error.kind.to_string());
+    diagnostic.code = Some("syntax-error".to_string());
+    diagnostic
+}
+
+/// Diagnostics for a document and the snippets it includes. An empty
+/// `own` list means the document is valid and any previously published
+/// diagnostics should be cleared.
+pub fn compute_diagnostics(
+    uri: &str,
+    text: &str,
+    load: &TextLoader,
+    cache: &SnippetCache,
+    options: &IncludeOptions,
+) -> DiagnosticReport {
+    match parse_conf(text) {
+        Ok(parsed) => {
+            let snippets = resolve_snippets(uri, &parsed, load, cache, options);
+            analyse_configuration(uri, &parsed, &snippets)
+        }
+        Err(_) => syntax_error_report(&parse_conf_recovering(text).1),
+    }
+}
+
+/// Diagnostics for a document that parses, given the snippets it includes
+/// as returned by [`resolve_snippets`].
+pub fn analyse_configuration(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet]) -> DiagnosticReport {
+    let mut report = DiagnosticReport::default();
+    check_duplicate_versions(uri, parsed, snippets, &mut report);
+    check_builtin_redefinitions(uri, parsed, snippets, &mut report);
+    check_undefined_variables(uri, parsed, snippets, &mut report);
+    check_template_order(uri, parsed, snippets, &mut report);
+    check_undefined_templates(uri, parsed, snippets, &mut report);
+    check_undefined_objects(uri, parsed, snippets, &mut report);
+    check_persist_names(uri, parsed, snippets, &mut report);
+    check_typed_values(uri, parsed, snippets, &mut report);
+    check_conditionals(uri, parsed, snippets, &mut report);
+    check_driver_versions(uri, parsed, snippets, &mut report);
+    check_privileged_binds(uri, parsed, snippets, &mut report);
+    report.own.extend(analyse_file(parsed));
+    check_encoding(snippets, &mut report);
+    mark_outside_workspace(uri, snippets, &mut report);
+    report.snippets = snippets.iter().map(|s| s.uri.clone()).collect();
+    report
+}
+
+/// The part of [`analyse_configuration`] that looks at `parsed` alone,
+/// whatever includes it or it includes: misspelt options, time zones,
+/// templates and the like.
+pub fn analyse_file(parsed: &ParsedConfiguration) -> Vec<Diagnostic> {
+    let mut report = DiagnosticReport::default();
+    check_failover(parsed, &mut report);
+    check_value_pairs(parsed, &mut report);
+    check_rewrite_rules(parsed, &mut report);
+    check_log_flags(parsed, &mut report);
+    check_global_options(parsed, &mut report);
+    check_annotations(parsed, &mut report);
+    check_ignored_options(parsed, &mut report);
+    check_bare_drivers(parsed, &mut report);
+    check_time_zones(parsed, &mut report);
+    check_templates(parsed, &mut report);
+    check_unquoted_templates(parsed, &mut report);
+    check_deprecated_drivers(parsed, &mut report);
+    report.own
+}
+
+fn check_deprecated_drivers(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    for (driver, replacement) in deprecated_drivers(parsed) {
+        report
+            .own
+            .push(deprecated_driver_diagnostic(driver, replacement.as_deref()));
+    }
+}
+
+/// `tcp()`, `udp()` and their IPv6 variants, which syslog-ng deprecated in
+/// favour of `network()`, with the options that keep their behaviour there:
+/// `transport(tcp)` and the like, unless already given. `None` when a
+/// `tls()` block makes the transport to pick unclear.
+pub fn deprecated_drivers(parsed: &ParsedConfiguration) -> Vec<(&Driver, Option<Vec<String>>)> {
+    let mut drivers = Vec::new();
+    for object in &parsed.objects {
+        collect_drivers(object, &mut drivers);
+    }
+    drivers
+        .into_iter()
+        .filter_map(|(_, driver)| {
+            let (transport, ipv6) = grammar::deprecated_network_driver(&driver.name.name)?;
+            let has = |name: &str| driver.options().any(|o| normalize_name(&o.name.name) == name);
+            if has("tls") && !has("transport") {
+                return Some((driver, None));
+            }
+            let mut options = Vec::new();
+            if !has("transport") {
+                options.push(format!("transport({})", transport));
+            }
+            if ipv6 && !has("ip-protocol") {
+                options.push("ip-protocol(6)".to_string());
+            }
+            Some((driver, Some(options)))
+        })
+        .collect()
+}
+
+pub fn deprecated_driver_diagnostic(driver: &Driver, replacement: Option<&[String]>) -> Diagnostic {
+    let message = match replacement {
+        Some(options) if !options.is_empty() => format!(
+            "`{}()` is deprecated, use `network()` with `{}` instead",
+            driver.name.name,
+            options.join(" ")
+        ),
+        _ => format!("`{}()` is deprecated, use `network()` instead", driver.name.name),
+    };
+    let mut diagnostic = Diagnostic::new(driver.name.range, DiagnosticSeverity::Warning, message);
+    diagnostic.code = Some("deprecated-driver".to_string());
+    diagnostic
+}
+
+fn check_unquoted_templates(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    for literal in unquoted_templates(parsed) {
+        report.own.push(unquoted_template_diagnostic(literal));
+    }
+}
+
+/// Bare values of `template()` options that cannot name a template object,
+/// such as `template($HOST)`, which syslog-ng takes as an inline template.
+pub fn unquoted_templates(parsed: &ParsedConfiguration) -> Vec<&Literal> {
+    options_taking(parsed, "template")
+        .into_iter()
+        .filter_map(|option| {
+            let mut literals = option.literals();
+            match (literals.next(), literals.next()) {
+                (Some(literal), None) if literal.kind == LiteralKind::Word && !is_template_name(&literal.value) => {
+                    Some(literal)
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Whether `template(name)` can refer to a template object by `name`.
+fn is_template_name(name: &str) -> bool {
+    name.chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+pub fn unquoted_template_diagnostic(literal: &Literal) -> Diagnostic {
+    let mut diagnostic = Diagnostic::new(
+        literal.range,
+        DiagnosticSeverity::Warning,
+        format!(
+            "`{}` is not a template name; quote it to use it as an inline template",
+            literal.value
+        ),
+    );
+    diagnostic.code = Some("unquoted-template".to_string());
+    diagnostic
+}
+
+/// The report of a document that does not parse: just its syntax errors.
+pub fn syntax_error_report(errors: &[SngSyntaxError]) -> DiagnosticReport {
+    DiagnosticReport {
+        own: errors.iter().map(syntax_error_to_diagnostic).collect(),
+        ..DiagnosticReport::default()
+    }
+}
+
+/// Options syslog-ng accepts but ignores on a driver, see
+/// [`grammar::IGNORED_OPTIONS`].
+fn check_ignored_options(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    let mut drivers = Vec::new();
+    for object in &parsed.objects {
+        collect_drivers(object, &mut drivers);
+    }
+    for (kind, driver) in drivers {
+        let transport = driver
+            .options()
+            .find(|o| o.name.name == "transport")
+            .and_then(|o| o.literals().next())
+            .map(|l| l.value.to_lowercase())
+            .or_else(|| grammar::default_transport(&driver.name.name).map(str::to_string));
+        let driver_name = normalize_name(&driver.name.name);
+        for option in driver.options() {
+            let option_name = normalize_name(&option.name.name);
+            let rule = grammar::IGNORED_OPTIONS.iter().find(|rule| {
+                rule.option == option_name
+                    && rule.contexts.contains(&kind)
+                    && rule.drivers.contains(&driver_name.as_str())
+                    && (rule.transports.is_empty()
+                        || transport.as_deref().is_some_and(|t| rule.transports.contains(&t)))
+            });
+            let Some(rule) = rule else {
+                continue;
+            };
+            let mut diagnostic = Diagnostic::new(
+                option.location,
+                DiagnosticSeverity::Warning,
+                format!("{}() is ignored here: {}", option.name.name, rule.reason),
+            );
+            diagnostic.code = Some("ignored-option".to_string());
+            diagnostic.code_description = Some(grammar::ADMIN_GUIDE_URL.to_string());
+            report.own.push(diagnostic);
+        }
+    }
+}
+
+/// Source or destination drivers written straight into a log path, such
+/// as `file("/var/log/x");`, instead of inside an object.
+fn check_bare_drivers(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    for (driver, kinds) in bare_drivers(parsed) {
+        report.own.push(bare_driver_diagnostic(driver, &kinds));
+    }
+}
+
+pub fn bare_driver_diagnostic(driver: &Driver, kinds: &[ObjectKind]) -> Diagnostic {
+    let mut diagnostic = Diagnostic::new(
+        driver.location,
+        DiagnosticSeverity::Error,
+        format!(
+            "{}() is a {} driver, not a log path element",
+            driver.name.name,
+            kinds.iter().map(ObjectKind::keyword).collect::<Vec<_>>().join(" or ")
+        ),
+    );
+    diagnostic.code = Some("bare-driver".to_string());
+    diagnostic
+}
+
+/// Unterminated macros and template functions in the `template()` of
+/// template objects and in the options taking a template, and macros that
+/// look like misspelt built-in ones.
+fn check_templates(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    let mut templates: Vec<&Driver> = parsed
+        .get_objects_by_kind(ObjectKind::Template)
+        .flat_map(|object| object.drivers())
+        .filter(|driver| driver.name.name == "template")
+        .collect();
+    templates.extend(options_taking(parsed, "template"));
+    let set_names = names_set_by(parsed);
+    for literal in templates.into_iter().flat_map(|t| t.literals()) {
+        let mut problems = Vec::new();
+        let parts = parse_template(&literal.value, &mut problems);
+        for problem in problems {
+            let range = verbatim_range(literal, problem.span).unwrap_or(literal.range);
+            let mut diagnostic = Diagnostic::new(range, DiagnosticSeverity::Error, problem.message);
+            diagnostic.code = Some("invalid-template".to_string());
+            report.own.push(diagnostic);
+        }
+        for reference in macros(&parts) {
+            if set_names.contains(reference.name.as_str()) {
+                continue;
+            }
+            let Some(suggestion) = misspelt_macro(&reference.name) else {
+                continue;
+            };
+            let range = verbatim_range(literal, reference.name_span.clone()).unwrap_or(literal.range);
+            let mut diagnostic = Diagnostic::new(
+                range,
+                DiagnosticSeverity::Warning,
+                format!("unknown macro `{}`, did you mean `{}`?", reference.name, suggestion),
+            );
+            diagnostic.code = Some("unknown-macro".to_string());
+            report.own.push(diagnostic);
+        }
+    }
+}
+
+/// The built-in macro `name` most likely misspells. Any other name is a
+/// name-value pair, which parsers may set without the configuration
+/// naming it, so only names close to a built-in one are suspicious, and
+/// short ones only when they differ from it in case;
+/// `None` for built-in names, match groups such as `$1` and names of
+/// structured data such as `.SDATA.meta.sequenceId`.
+fn misspelt_macro(name: &str) -> Option<&'static str> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.chars().all(|c| c.is_ascii_digit())
+        || grammar::find_macro(name).is_some()
+    {
+        return None;
+    }
+    let allowed = if name.chars().count() < 4 { 0 } else { 2 };
+    let names = grammar::MACROS.iter().map(|m| m.name).filter(|n| !n.starts_with('.'));
+    closest_name(name, names, allowed)
+}
+
+/// The one of `names` closest to `name` within `allowed` edits, a
+/// difference in case counting as none. `None` when several are as close.
+fn closest_name(name: &str, names: impl Iterator<Item = &'static str>, allowed: usize) -> Option<&'static str> {
+    let mut best: Option<(usize, &'static str)> = None;
+    let mut tied = false;
+    for candidate in names {
+        let distance = match candidate.eq_ignore_ascii_case(name) {
+            true => 0,
+            false => edit_distance(name, candidate),
+        };
+        match best {
+            _ if distance > allowed => {}
+            Some((d, _)) if d < distance => {}
+            Some((d, _)) if d == distance => tied = true,
+            _ => {
+                best = Some((distance, candidate));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|_| !tied).map(|(_, name)| name)
+}
+
+/// Names of name-value pairs the configuration sets by name, e.g. with
+/// `set("x" value("NAME"))` or the `columns()` of a `csv-parser()`.
+fn names_set_by(parsed: &ParsedConfiguration) -> HashSet<&str> {
+    fn collect<'a>(driver: &'a Driver, names: &mut HashSet<&'a str>) {
+        for option in driver.options() {
+            if matches!(
+                normalize_name(&option.name.name).as_str(),
+                "value" | "values" | "columns"
+            ) {
+                names.extend(option.literals().map(|l| l.value.as_str()));
+            }
+            collect(option, names);
+        }
+    }
+    fn walk<'a>(object: &'a Object, names: &mut HashSet<&'a str>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) => walk(inline, names),
+                Statement::Driver(driver) => collect(driver, names),
+                Statement::Conditional(_) | Statement::Junction(_) => {}
+            }
+        }
+    }
+    let mut names = HashSet::new();
+    for object in &parsed.objects {
+        walk(object, &mut names);
+    }
+    names
+}
+
+/// Values of `time-zone()` and the like that name no zone of the bundled
+/// time zone database.
+fn check_time_zones(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    for (literal, suggestion) in unknown_time_zones(parsed) {
+        report.own.push(unknown_time_zone_diagnostic(literal, suggestion));
+    }
+}
+
+pub fn unknown_time_zone_diagnostic(literal: &Literal, suggestion: Option<&str>) -> Diagnostic {
+    let message = match suggestion {
+        Some(zone) => format!("unknown time zone `{}`, did you mean `{}`?", literal.value, zone),
+        None => format!("unknown time zone `{}`", literal.value),
+    };
+    let mut diagnostic = Diagnostic::new(literal.range, DiagnosticSeverity::Warning, message);
+    diagnostic.code = Some("unknown-time-zone".to_string());
+    diagnostic
+}
+
+/// The values of options taking a time zone that are neither a zone name
+/// nor an offset, with the zone they most likely misspell. Values that
+/// refer to a define the file does not set are skipped.
+pub fn unknown_time_zones(parsed: &ParsedConfiguration) -> Vec<(&Literal, Option<&'static str>)> {
+    let mut found = Vec::new();
+    for option in options_taking(parsed, "time zone") {
+        let mut literals = option.literals();
+        if let (Some(literal), None) = (literals.next(), literals.next()) {
+            if !literal.value.contains('`') && !is_time_zone(&literal.value) {
+                found.push((literal, closest_time_zone(&literal.value)));
+            }
+        }
+    }
+    found
+}
+
+/// The options, global ones included, whose grammar gives their value as
+/// `value`, e.g. `"time zone"`.
+fn options_taking<'a>(parsed: &'a ParsedConfiguration, value: &str) -> Vec<&'a Driver> {
+    fn check_option<'a>(option: &'a Driver, info: &'static OptionInfo, value: &str, found: &mut Vec<&'a Driver>) {
+        if info.value == value {
+            found.push(option);
+        }
+        for nested in option.options() {
+            if let Some(child) = info.find_child(&nested.name.name) {
+                check_option(nested, child, value, found);
+            }
+        }
+    }
+    fn collect<'a>(object: &'a Object, value: &str, found: &mut Vec<&'a Driver>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) => collect(inline, value, found),
+                Statement::Driver(driver) if object.kind == ObjectKind::Options => {
+                    if let Some(info) = grammar::find_global_option(&driver.name.name) {
+                        check_option(driver, info, value, found);
+                    }
+                }
+                Statement::Driver(driver) if object.kind != ObjectKind::Log => {
+                    let Some(info) = grammar::find_driver(object.kind, &driver.name.name) else {
+                        continue;
+                    };
+                    for option in driver.options() {
+                        if let Some(option_info) = info.find_option(&option.name.name) {
+                            check_option(option, option_info, value, found);
+                        }
+                    }
+                }
+                Statement::Driver(_) | Statement::Conditional(_) | Statement::Junction(_) => {}
+            }
+        }
+    }
+    let mut found = Vec::new();
+    for object in &parsed.objects {
+        collect(object, value, &mut found);
+    }
+    found
+}
+
+/// Drivers in log paths that belong inside an object, with the kinds of
+/// object that accept them.
+pub fn bare_drivers(parsed: &ParsedConfiguration) -> Vec<(&Driver, Vec<ObjectKind>)> {
+    fn collect<'a>(object: &'a Object, found: &mut Vec<(&'a Driver, Vec<ObjectKind>)>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) => collect(inline, found),
+                Statement::Driver(driver) if object.kind == ObjectKind::Log => {
+                    if grammar::find_driver(ObjectKind::Log, &driver.name.name).is_some() {
+                        continue;
+                    }
+                    let kinds: Vec<ObjectKind> = [
+                        ObjectKind::Source,
+                        ObjectKind::Destination,
+                        ObjectKind::Parser,
+                        ObjectKind::Rewrite,
+                    ]
+                    .into_iter()
+                    .filter(|kind| grammar::find_driver(*kind, &driver.name.name).is_some())
+                    .collect();
+                    if !kinds.is_empty() {
+                        found.push((driver, kinds));
+                    }
+                }
+                Statement::Driver(_) | Statement::Conditional(_) | Statement::Junction(_) => {}
+            }
+        }
+    }
+    let mut found = Vec::new();
+    for object in &parsed.objects {
+        collect(object, &mut found);
+    }
+    found
+}
+
+/// The drivers of the sources and destinations of `object`, including the
+/// inline ones of a log path.
+fn collect_drivers<'a>(object: &'a Object, drivers: &mut Vec<(ObjectKind, &'a Driver)>) {
+    for statement in object.flat_statements() {
+        match statement {
+            Statement::Object(inline) => collect_drivers(inline, drivers),
+            Statement::Driver(driver) if matches!(object.kind, ObjectKind::Source | ObjectKind::Destination) => {
+                drivers.push((object.kind, driver))
+            }
+            Statement::Driver(_) | Statement::Conditional(_) | Statement::Junction(_) => {}
+        }
+    }
+}
+
+/// Drivers of `parsed` that do not work on `platform`, such as
+/// `systemd-journal()` on a BSD. Depends on a setting rather than on the
+/// configuration, so it is not part of [`analyse_configuration`].
+pub fn check_platform(parsed: &ParsedConfiguration, platform: Platform) -> Vec<Diagnostic> {
+    let mut drivers = Vec::new();
+    for object in &parsed.objects {
+        collect_drivers(object, &mut drivers);
+    }
+    drivers
+        .into_iter()
+        .filter_map(|(kind, driver)| {
+            let info = grammar::find_driver(kind, &driver.name.name).filter(|i| !i.available_on(Some(platform)))?;
+            let platforms: Vec<&str> = info.platforms().iter().map(Platform::name).collect();
+            let mut diagnostic = Diagnostic::new(
+                driver.name.range,
+                DiagnosticSeverity::Warning,
+                format!(
+                    "`{}()` only works on {}, the target platform is {}",
+                    info.name,
+                    platforms.join(", "),
+                    platform.name()
+                ),
+            );
+            diagnostic.code = Some("wrong-platform".to_string());
+            Some(diagnostic)
+        })
+        .collect()
+}
+
+/// Codes of the deployment checks, which look at how syslog-ng will run
+/// rather than at the configuration itself. They are only published when
+/// asked for, see [`crate::settings::ServerSettings::deployment_lints`].
+pub const DEPLOYMENT_CODES: &[&str] = &["privileged-port", "privileged-socket"];
+
+pub fn is_deployment_lint(diagnostic: &Diagnostic) -> bool {
+    diagnostic
+        .code
+        .as_deref()
+        .is_some_and(|code| DEPLOYMENT_CODES.contains(&code))
+}
+
+/// Keeps the `limit` most severe of `diagnostics`, in their order, and
+/// adds one at the start of the file telling how many were left out.
+pub fn limit_diagnostics(diagnostics: &mut Vec<Diagnostic>, limit: usize) {
+    if diagnostics.len() <= limit {
+        return;
+    }
+    let mut by_severity: Vec<usize> = (0..diagnostics.len()).collect();
+    by_severity.sort_by_key(|&i| diagnostics[i].severity as u8);
+    let mut kept = vec![false; diagnostics.len()];
+    for &i in &by_severity[..limit] {
+        kept[i] = true;
+    }
+    let omitted = diagnostics.len() - limit;
+    let mut index = 0;
+    diagnostics.retain(|_| {
+        index += 1;
+        kept[index - 1]
+    });
+    let mut summary = Diagnostic::new(
+        Range::default(),
+        DiagnosticSeverity::Information,
+        format!(
+            "{} more diagnostics are not shown, see the `maxDiagnosticsPerFile` setting",
+            omitted
+        ),
+    );
+    summary.code = Some("too-many-diagnostics".to_string());
+    diagnostics.insert(0, summary);
+}
+
+/// Directories owned by root on common systems, where an unprivileged
+/// syslog-ng cannot create its sockets.
+const ROOT_OWNED_DIRECTORIES: &[&str] = &["/", "/dev", "/etc", "/run", "/var/run", "/var/lib", "/var/log"];
+
+/// Sources binding a port below 1024 or a socket in a root-owned directory
+/// while the global `user()` option names an unprivileged user, so that
+/// syslog-ng drops root before it gets to bind them.
+fn check_privileged_binds(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    report: &mut DiagnosticReport,
+) {
+    let user = parsed_files(uri, parsed, snippets).find_map(|(file_uri, config)| {
+        let options = config
+            .get_objects_by_kind(ObjectKind::Options)
+            .flat_map(|o| o.drivers());
+        let user = options
+            .filter(|o| o.name.name == "user")
+            .find_map(|o| o.literals().next())?;
+        Some((file_uri, user))
+    });
+    let Some((user_uri, user)) = user.filter(|(_, user)| !matches!(user.value.as_str(), "root" | "0")) else {
+        return;
+    };
+    let related = DiagnosticRelatedInformation {
+        location: Location {
+            uri: user_uri.to_string(),
+            range: user.range,
+        },
+        message: format!("syslog-ng runs as `{}`", user.value),
+    };
+    for (file_uri, config) in parsed_files(uri, parsed, snippets) {
+        let mut drivers = Vec::new();
+        for object in &config.objects {
+            collect_drivers(object, &mut drivers);
+        }
+        for (_, driver) in drivers.into_iter().filter(|(kind, _)| *kind == ObjectKind::Source) {
+            let mut problems = Vec::new();
+            let port = driver
+                .options()
+                .filter(|o| matches!(normalize_name(&o.name.name).as_str(), "port" | "localport"))
+                .find_map(|o| o.literals().next());
+            match port {
+                Some(port) => {
+                    if port.value.parse::<u32>().is_ok_and(|n| n < 1024) {
+                        problems.push((
+                            port.range,
+                            "privileged-port",
+                            format!("port {} is privileged", port.value),
+                        ));
+                    }
+                }
+                None => {
+                    let default = grammar::find_driver(ObjectKind::Source, &driver.name.name)
+                        .and_then(|info| info.find_option("port"))
+                        .and_then(|option| option.default)
+                        .filter(|port| port.parse::<u32>().is_ok_and(|n| n < 1024));
+                    if let Some(default) = default {
+                        problems.push((
+                            driver.name.range,
+                            "privileged-port",
+                            format!(
+                                "`{}()` binds the privileged port {} by default",
+                                driver.name.name, default
+                            ),
+                        ));
+                    }
+                }
+            }
+            if matches!(normalize_name(&driver.name.name).as_str(), "unix-stream" | "unix-dgram") {
+                let path = driver.literals().next().filter(|path| {
+                    let parent = std::path::Path::new(&path.value).parent();
+                    parent.is_some_and(|p| ROOT_OWNED_DIRECTORIES.iter().any(|d| p == std::path::Path::new(d)))
+                });
+                if let Some(path) = path {
+                    problems.push((
+                        path.range,
+                        "privileged-socket",
+                        format!("`{}` is in a directory owned by root", path.value),
+                    ));
+                }
+            }
+            for (range, code, problem) in problems {
+                let mut diagnostic = Diagnostic::new(
+                    range,
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "{}, but syslog-ng runs as `{}` and may not be allowed to bind it",
+                        problem, user.value
+                    ),
+                );
+                diagnostic.code = Some(code.to_string());
+                diagnostic.related_information.push(related.clone());
+                report.push(uri, file_uri, diagnostic);
+            }
+        }
+    }
+}
+
+/// Bytes that are not UTF-8, typically a legacy Latin-1 file. The file
+/// is still analysed with them replaced.
+fn check_encoding(snippets: &[Snippet], report: &mut DiagnosticReport) {
+    for snippet in snippets {
+        let Some(position) = snippet.invalid_utf8 else {
+            continue;
+        };
+        report
+            .related
+            .entry(snippet.uri.clone())
+            .or_default()
+            .push(invalid_utf8_warning(position));
+    }
+}
+
+/// The warning on a file that stops being valid UTF-8 at `position`.
+pub fn invalid_utf8_warning(position: Position) -> Diagnostic {
+    let mut diagnostic = Diagnostic::new(
+        Range::new(position, Position::new(position.line, position.character + 1)),
+        DiagnosticSeverity::Warning,
+        "the file is not valid UTF-8; invalid bytes were replaced while reading it",
+    );
+    diagnostic.code = Some("invalid-utf8".to_string());
+    diagnostic
+}
+
+/// Problems in files outside the workspace are easy to miss, since the
+/// editor rarely has such files open. They are marked as such in their
+/// source, and summarised on the `@include` directive of `uri` that pulls
+/// the file in.
+fn mark_outside_workspace(uri: &str, snippets: &[Snippet], report: &mut DiagnosticReport) {
+    let mut summaries = Vec::new();
+    for snippet in snippets.iter().filter(|s| s.outside_workspace) {
+        let Some(diagnostics) = report.related.get_mut(&snippet.uri) else {
+            continue;
+        };
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.source = "syslog-ng (outside workspace)".to_string();
+        }
+        let Some(directive) = top_level_include(uri, snippet, snippets) else {
+            continue;
+        };
+        let mut summary = Diagnostic::new(
+            directive,
+            DiagnosticSeverity::Information,
+            format!(
+                "{} problem(s) in {}, outside the workspace",
+                diagnostics.len(),
+                snippet.path.display()
+            ),
+        );
+        summary.code = Some("outside-workspace".to_string());
+        summary.related_information = diagnostics
+            .iter()
+            .map(|d| DiagnosticRelatedInformation {
+                location: Location {
+                    uri: snippet.uri.clone(),
+                    range: d.range,
+                },
+                message: d.message.clone(),
+            })
+            .collect();
+        summaries.push(summary);
+    }
+    report.own.extend(summaries);
+}
+
+/// The directive of `uri` through which `snippet` is included, possibly
+/// by way of other snippets.
+fn top_level_include(uri: &str, snippet: &Snippet, snippets: &[Snippet]) -> Option<Range> {
+    let mut from = &snippet.included_from;
+    for _ in 0..=snippets.len() {
+        if from.uri == uri {
+            return Some(from.range);
+        }
+        from = &snippets.iter().find(|s| s.uri == from.uri)?.included_from;
+    }
+    None
+}
+
+/// Only the first `@version` of the merged configuration counts; every
+/// later one is an error pointing back at the first.
+fn check_duplicate_versions(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    report: &mut DiagnosticReport,
+) {
+    let mut first: Option<(&str, Location)> = None;
+    for (annotation_uri, annotation) in merged_annotations(uri, parsed, snippets) {
+        let Annotation::Version(version) = annotation else {
+            continue;
+        };
+        let location = Location {
+            uri: annotation_uri.to_string(),
+            range: version.range,
+        };
+        match &first {
+            None => first = Some((&version.version, location)),
+            Some((first_version, first_location)) => {
+                let mut diagnostic = Diagnostic::new(
+                    version.range,
+                    DiagnosticSeverity::Error,
+                    format!(
+                        "duplicate @version annotation, the configuration version is already set to {}",
+                        first_version
+                    ),
+                );
+                diagnostic.code = Some("duplicate-version".to_string());
+                diagnostic.related_information.push(DiagnosticRelatedInformation {
+                    location: first_location.clone(),
+                    message: "first @version annotation".to_string(),
+                });
+                report.push(uri, annotation_uri, diagnostic);
+            }
+        }
+    }
+}
+
+/// Type hints need a 4.x configuration: 3.x reads `int(42)` as an unknown
+/// option and `$(format-json pid=int($PID))` as a plain string. Nothing is
+/// reported without a known `@version`, as for a snippet on its own.
+fn check_typed_values(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet], report: &mut DiagnosticReport) {
+    let Some(version) = version_before(uri, parsed, snippets, grammar::TYPE_HINTS_SINCE) else {
+        return;
+    };
+    let (major, minor) = grammar::TYPE_HINTS_SINCE;
+    for (file_uri, config) in parsed_files(uri, parsed, snippets) {
+        for typed in typed_values(config) {
+            let mut diagnostic = Diagnostic::new(
+                typed.range,
+                DiagnosticSeverity::Warning,
+                format!(
+                    "the `{}` type hint needs `@version: {}.{}` or later, the configuration version is {}",
+                    typed.hint.name, major, minor, version
+                ),
+            );
+            diagnostic.code = Some("type-hint-version".to_string());
+            report.push(uri, file_uri, diagnostic);
+        }
+    }
+}
+
+/// syslog-ng before 3.23 has no `if` blocks and fails on the keyword. The
+/// `elif` and `else` of a block are not reported again.
+fn check_conditionals(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet], report: &mut DiagnosticReport) {
+    let Some(version) = version_before(uri, parsed, snippets, grammar::CONDITIONALS_SINCE) else {
+        return;
+    };
+    let (major, minor) = grammar::CONDITIONALS_SINCE;
+    for (file_uri, config) in parsed_files(uri, parsed, snippets) {
+        for conditional in config.conditionals() {
+            let mut diagnostic = Diagnostic::new(
+                conditional.branches[0].keyword.range,
+                DiagnosticSeverity::Error,
+                format!(
+                    "`if` blocks in log paths need `@version: {}.{}` or later, the configuration version is {}",
+                    major, minor, version
+                ),
+            );
+            diagnostic.code = Some("conditional-version".to_string());
+            report.push(uri, file_uri, diagnostic);
+        }
+    }
+}
+
+/// Drivers newer than the configuration version, e.g. `opentelemetry()`
+/// in a 4.2 configuration, which syslog-ng rejects as unknown.
+fn check_driver_versions(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet], report: &mut DiagnosticReport) {
+    fn collect<'a>(object: &'a Object, drivers: &mut Vec<(ObjectKind, &'a Driver)>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) => collect(inline, drivers),
+                Statement::Driver(driver) if !matches!(object.kind, ObjectKind::Log | ObjectKind::Options) => {
+                    drivers.push((object.kind, driver))
+                }
+                Statement::Driver(_) | Statement::Conditional(_) | Statement::Junction(_) => {}
+            }
+        }
+    }
+    let Some((version, number)) = merged_version(uri, parsed, snippets) else {
+        return;
+    };
+    for (file_uri, config) in parsed_files(uri, parsed, snippets) {
+        let mut drivers = Vec::new();
+        for object in &config.objects {
+            collect(object, &mut drivers);
+        }
+        for (kind, driver) in drivers {
+            let Some(info) = grammar::find_driver(kind, &driver.name.name) else {
+                continue;
+            };
+            let Some((major, minor)) = info.since.filter(|_| !info.available_in(Some(number))) else {
+                continue;
+            };
+            let mut diagnostic = Diagnostic::new(
+                driver.name.range,
+                DiagnosticSeverity::Error,
+                format!(
+                    "`{}()` is unknown to syslog-ng before {}.{}, the configuration version is {}",
+                    info.name, major, minor, version
+                ),
+            );
+            diagnostic.code = Some("driver-version".to_string());
+            report.push(uri, file_uri, diagnostic);
+        }
+    }
+}
+
+/// The `@version` of the merged configuration if it is older than `since`.
+/// `None` without a known version, as for a snippet on its own.
+fn version_before<'a>(
+    uri: &'a str,
+    parsed: &'a ParsedConfiguration,
+    snippets: &'a [Snippet],
+    since: (u32, u32),
+) -> Option<&'a str> {
+    merged_version(uri, parsed, snippets)
+        .filter(|(_, number)| *number < since)
+        .map(|(version, _)| version)
+}
+
+/// The `@version` of the merged configuration, as written and as a number.
+fn merged_version<'a>(
+    uri: &'a str,
+    parsed: &'a ParsedConfiguration,
+    snippets: &'a [Snippet],
+) -> Option<(&'a str, (u32, u32))> {
+    let version =
+        merged_annotations(uri, parsed, snippets)
+            .into_iter()
+            .find_map(|(_, annotation)| match annotation {
+                Annotation::Version(version) => Some(version),
+                _ => None,
+            })?;
+    Some((version.version.as_str(), version.number()?))
+}
+
+/// `parsed` and the snippets that parse, with their URIs.
+fn parsed_files<'a>(
+    uri: &'a str,
+    parsed: &'a ParsedConfiguration,
+    snippets: &'a [Snippet],
+) -> impl Iterator<Item = (&'a str, &'a ParsedConfiguration)> {
+    let files = snippets
+        .iter()
+        .filter_map(|s| s.parsed.as_ref().ok().map(|p| (s.uri.as_str(), p)));
+    std::iter::once((uri, parsed)).chain(files)
+}
+
+/// `@define` of a variable syslog-ng sets itself, such as `module-path`,
+/// silently overrides it for the rest of the configuration.
+fn check_builtin_redefinitions(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    report: &mut DiagnosticReport,
+) {
+    for (annotation_uri, annotation) in merged_annotations(uri, parsed, snippets) {
+        let Annotation::Define(define) = annotation else {
+            continue;
+        };
+        if grammar::find_builtin_define(&define.name).is_none() {
+            continue;
+        }
+        let mut diagnostic = Diagnostic::new(
+            define.name_range,
+            DiagnosticSeverity::Warning,
+            format!(
+                "`{}` is a built-in variable; this overrides the value syslog-ng sets",
+                define.name
+            ),
+        );
+        diagnostic.code = Some("redefined-builtin".to_string());
+        report.push(uri, annotation_uri, diagnostic);
+    }
+}
+
+/// `` `name` `` references left in values after substituting the
+/// `@define`s above them in the merged configuration, that name neither a
+/// variable syslog-ng sets itself nor one of the environment. syslog-ng
+/// refuses to start with them. Skipped for a snippet on its own, whose
+/// variables may be defined by the file including it.
+fn check_undefined_variables(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    report: &mut DiagnosticReport,
+) {
+    // The defines of a snippet that does not parse are unknown.
+    if merged_version(uri, parsed, snippets).is_none() || snippets.iter().any(|s| s.parsed.is_err()) {
+        return;
+    }
+    let items = merged_items(uri, parsed, snippets);
+    let defined_anywhere: HashSet<&str> = items
+        .iter()
+        .filter_map(|(_, item)| match item {
+            MergedItem::Annotation(Annotation::Define(define)) => Some(define.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut defined = HashSet::new();
+    for (item_uri, item) in items {
+        let object = match item {
+            MergedItem::Annotation(Annotation::Define(define)) => {
+                defined.insert(define.name.as_str());
+                continue;
+            }
+            MergedItem::Annotation(_) => continue,
+            MergedItem::Object(object) => object,
+        };
+        let mut literals = Vec::new();
+        collect_object_literals(object, &mut literals);
+        for literal in literals {
+            let mut undefined: Vec<&str> = Vec::new();
+            for name in variable_references(&literal.value) {
+                if !defined.contains(name)
+                    && grammar::find_builtin_define(name).is_none()
+                    && std::env::var_os(name).is_none()
+                    && !undefined.contains(&name)
+                {
+                    undefined.push(name);
+                }
+            }
+            if undefined.is_empty() {
+                continue;
+            }
+            let names = undefined
+                .iter()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let message = match undefined.as_slice() {
+                [name] if defined_anywhere.contains(name) => {
+                    format!("variable {} is used before its `@define`", names)
+                }
+                [_] => format!("variable {} is not defined by `@define` or in the environment", names),
+                _ => format!("variables {} are not defined by `@define` or in the environment", names),
+            };
+            let mut diagnostic = Diagnostic::new(literal.range, DiagnosticSeverity::Warning, message);
+            diagnostic.code = Some("undefined-variable".to_string());
+            report.push(uri, item_uri, diagnostic);
+        }
+    }
+}
+
+/// The names of the `` `name` `` references in `value`.
+fn variable_references(value: &str) -> impl Iterator<Item = &str> {
+    let parts: Vec<&str> = value.split('`').collect();
+    // A last part after an odd number of backticks is not closed.
+    let closed = parts.len().saturating_sub(1) / 2;
+    (0..closed)
+        .map(move |i| parts[2 * i + 1])
+        .filter(|name| !name.is_empty())
+}
+
+/// Every value in `object`: of drivers and their options, filter
+/// expressions and conditions, and of the inline objects it contains.
+fn collect_object_literals<'a>(object: &'a Object, literals: &mut Vec<&'a Literal>) {
+    fn from_driver<'a>(driver: &'a Driver, literals: &mut Vec<&'a Literal>) {
+        for parameter in &driver.parameters {
+            match parameter {
+                Parameter::Literal(literal) => literals.push(literal),
+                Parameter::Option(option) => from_driver(option, literals),
+            }
+        }
+    }
+    fn from_expression<'a>(expression: &'a Expression, literals: &mut Vec<&'a Literal>) {
+        match expression {
+            Expression::Call(call) => from_driver(call, literals),
+            Expression::Comparison(comparison) => literals.extend([&comparison.left, &comparison.right]),
+            Expression::Not { operand, .. } => from_expression(operand, literals),
+            Expression::Binary { left, right, .. } => {
+                from_expression(left, literals);
+                from_expression(right, literals);
+            }
+            Expression::Group { expression, .. } => from_expression(expression, literals),
+        }
+    }
+    fn from_statements<'a>(statements: &'a [Statement], literals: &mut Vec<&'a Literal>) {
+        for statement in statements {
+            match statement {
+                Statement::Driver(call) => from_driver(call, literals),
+                Statement::Object(inline) => collect_object_literals(inline, literals),
+                Statement::Conditional(conditional) => {
+                    for branch in &conditional.branches {
+                        if let Some(condition) = &branch.condition {
+                            from_expression(&condition.root, literals);
+                        }
+                        from_statements(&branch.statements, literals);
+                    }
+                }
+                Statement::Junction(junction) => {
+                    for channel in &junction.channels {
+                        from_statements(&channel.statements, literals);
+                    }
+                }
+            }
+        }
+    }
+    match &object.body {
+        ObjectBody::Expression(filter) => from_expression(&filter.root, literals),
+        ObjectBody::Statements(body) => from_statements(body, literals),
+    }
+}
+
+/// syslog-ng looks a template up when it reads the `template(t_name)`
+/// option, and takes a name that is not defined yet as an inline template
+/// string. References to templates defined further down the merged
+/// configuration therefore silently produce the wrong output.
+fn check_template_order(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet], report: &mut DiagnosticReport) {
+    let items = merged_items(uri, parsed, snippets);
+    let mut definitions: HashMap<&str, (usize, Location)> = HashMap::new();
+    for (index, (item_uri, item)) in items.iter().enumerate() {
+        let MergedItem::Object(object) = item else {
+            continue;
+        };
+        if let (ObjectKind::Template, Some(id)) = (object.kind, &object.id) {
+            definitions.entry(&id.name).or_insert_with(|| {
+                let location = Location {
+                    uri: item_uri.to_string(),
+                    range: id.range,
+                };
+                (index, location)
+            });
+        }
+    }
+
+    for (index, (item_uri, item)) in items.iter().enumerate() {
+        let MergedItem::Object(object) = item else {
+            continue;
+        };
+        for reference in collect_references(std::slice::from_ref(*object)) {
+            if reference.kind != ObjectKind::Template {
+                continue;
+            }
+            let Some((defined_at, location)) = definitions.get(reference.name.as_str()) else {
+                continue;
+            };
+            if *defined_at <= index {
+                continue;
+            }
+            let mut diagnostic = Diagnostic::new(
+                reference.range,
+                DiagnosticSeverity::Warning,
+                format!(
+                    "template `{}` is defined later in the configuration, syslog-ng will use `{}` as a literal template here",
+                    reference.name, reference.name
+                ),
+            );
+            diagnostic.code = Some("template-defined-later".to_string());
+            diagnostic.related_information.push(DiagnosticRelatedInformation {
+                location: location.clone(),
+                message: "template defined here".to_string(),
+            });
+            report.push(uri, item_uri, diagnostic);
+        }
+    }
+}
+
+/// References to templates the merged configuration does not define. A
+/// bare `template(t_name)` then becomes the literal template `t_name`, and
+/// `$(template t_name)` fails to compile. Skipped without a known
+/// `@version`, like [`check_undefined_objects`].
+fn check_undefined_templates(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    report: &mut DiagnosticReport,
+) {
+    // Templates of a snippet that does not parse are unknown.
+    if merged_version(uri, parsed, snippets).is_none() || snippets.iter().any(|s| s.parsed.is_err()) {
+        return;
+    }
+    let items = merged_items(uri, parsed, snippets);
+    let objects: Vec<(&str, &Object)> = items
+        .iter()
+        .filter_map(|(item_uri, item)| match item {
+            MergedItem::Object(object) => Some((*item_uri, *object)),
+            MergedItem::Annotation(_) => None,
+        })
+        .collect();
+    let defined: Vec<&str> = objects
+        .iter()
+        .filter(|(_, object)| object.kind == ObjectKind::Template)
+        .filter_map(|(_, object)| object.id.as_ref().map(|id| id.name.as_str()))
+        .collect();
+
+    for (item_uri, object) in objects {
+        for reference in collect_references(std::slice::from_ref(object)) {
+            if reference.kind != ObjectKind::Template || defined.contains(&reference.name.as_str()) {
+                continue;
+            }
+            // Reported as an unquoted inline template instead.
+            if !reference.in_string && !is_template_name(&reference.name) {
+                continue;
+            }
+            let mut diagnostic = if reference.in_string {
+                Diagnostic::new(
+                    reference.range,
+                    DiagnosticSeverity::Error,
+                    format!("template `{}` is not defined", reference.name),
+                )
+            } else {
+                Diagnostic::new(
+                    reference.range,
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "template `{}` is not defined, syslog-ng will use `{}` as a literal template here",
+                        reference.name, reference.name
+                    ),
+                )
+            };
+            diagnostic.code = Some("undefined-reference".to_string());
+            report.push(uri, item_uri, diagnostic);
+        }
+    }
+}
+
+/// Sources, destinations, filters, parsers and rewrite rules referred to
+/// by identifier, in log paths, their branches and junction channels
+/// included, that the merged configuration does not define. syslog-ng
+/// refuses to start with them. Skipped without a known `@version`, as for
+/// a snippet on its own, whose objects may be defined by the file
+/// including it.
+fn check_undefined_objects(
+    uri: &str,
+    parsed: &ParsedConfiguration,
+    snippets: &[Snippet],
+    report: &mut DiagnosticReport,
+) {
+    // Objects of a snippet that does not parse are unknown.
+    if merged_version(uri, parsed, snippets).is_none() || snippets.iter().any(|s| s.parsed.is_err()) {
+        return;
+    }
+    let items = merged_items(uri, parsed, snippets);
+    let objects: Vec<(&str, &Object)> = items
+        .iter()
+        .filter_map(|(item_uri, item)| match item {
+            MergedItem::Object(object) => Some((*item_uri, *object)),
+            MergedItem::Annotation(_) => None,
+        })
+        .collect();
+    let defined: HashSet<(ObjectKind, &str)> = objects
+        .iter()
+        .filter_map(|(_, object)| object.id.as_ref().map(|id| (object.kind, id.name.as_str())))
+        .collect();
+
+    for (item_uri, object) in objects {
+        for reference in collect_references(std::slice::from_ref(object)) {
+            // Undefined templates are reported by `check_undefined_templates`.
+            if reference.kind == ObjectKind::Template
+                || reference.name.contains('`')
+                || defined.contains(&(reference.kind, reference.name.as_str()))
+            {
+                continue;
+            }
+            let mut diagnostic = Diagnostic::new(
+                reference.range,
+                DiagnosticSeverity::Error,
+                format!("{} `{}` is not defined", reference.kind, reference.name),
+            );
+            diagnostic.code = Some("undefined-reference".to_string());
+            report.push(uri, item_uri, diagnostic);
+        }
+    }
+}
+
+/// syslog-ng keys the saved state of a driver (file positions, queue
+/// contents) by its persist name, so two drivers sharing one overwrite
+/// each other's state.
+fn check_persist_names(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet], report: &mut DiagnosticReport) {
+    let mut by_name: BTreeMap<&str, Vec<(&str, &Driver, &Literal)>> = BTreeMap::new();
+    for (item_uri, item) in merged_items(uri, parsed, snippets) {
+        if let MergedItem::Object(object) = item {
+            for (driver, literal) in object.persist_names() {
+                by_name
+                    .entry(&literal.value)
+                    .or_default()
+                    .push((item_uri, driver, literal));
+            }
+        }
+    }
+
+    for (name, users) in by_name.iter().filter(|(_, users)| users.len() > 1) {
+        for (i, (user_uri, _, literal)) in users.iter().enumerate() {
+            let mut diagnostic = Diagnostic::new(
+                literal.range,
+                DiagnosticSeverity::Warning,
+                format!(
+                    "persist-name `{}` is used by {} drivers, they will overwrite each other's saved state",
+                    name,
+                    users.len()
+                ),
+            );
+            diagnostic.code = Some("duplicate-persist-name".to_string());
+            for (j, (other_uri, other_driver, other_literal)) in users.iter().enumerate() {
+                if i != j {
+                    diagnostic.related_information.push(DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: other_uri.to_string(),
+                            range: other_literal.range,
+                        },
+                        message: format!("also used by {}()", other_driver.name.name),
+                    });
+                }
+            }
+            report.push(uri, user_uri, diagnostic);
+        }
+    }
+}
+
+fn check_failover(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    let mut problems = Vec::new();
+    for object in parsed.get_objects_by_kind(ObjectKind::Destination) {
+        for driver in object.drivers() {
+            let Some(failover) = Failover::from_driver(driver, &mut problems) else {
+                continue;
+            };
+            if !matches!(
+                driver.name.name.as_str(),
+                "network" | "syslog" | "tcp" | "tcp6" | "udp" | "udp6"
+            ) {
+                problems.push(FailoverProblem {
+                    range: failover.location,
+                    message: format!("{}() does not support failover servers", driver.name.name),
+                    is_error: false,
+                });
+            }
+        }
+    }
+    for problem in problems {
+        let severity = match problem.is_error {
+            true => DiagnosticSeverity::Error,
+            false => DiagnosticSeverity::Warning,
+        };
+        let mut diagnostic = Diagnostic::new(problem.range, severity, problem.message);
+        diagnostic.code = Some("invalid-failover".to_string());
+        report.own.push(diagnostic);
+    }
+}
+
+/// Annotation keywords syslog-ng knows besides those parsed into their own
+/// [`Annotation`] variant; `@line` is written by its preprocessor.
+const OTHER_ANNOTATIONS: &[&str] = &["line"];
+
+/// `@keyword` lines syslog-ng does not know.
+fn check_annotations(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    for annotation in &parsed.annotations {
+        let Annotation::Other(other) = annotation else {
+            continue;
+        };
+        if OTHER_ANNOTATIONS.contains(&other.name.as_str()) {
+            continue;
+        }
+        let keywords = ["version", "include", "define", "module", "requires"].into_iter();
+        let mut message = format!("unknown annotation `@{}`", other.name);
+        if let Some(suggestion) = closest_name(&other.name, keywords, 2) {
+            message.push_str(&format!(", did you mean `@{}`?", suggestion));
+        }
+        let mut diagnostic = Diagnostic::new(other.range, DiagnosticSeverity::Warning, message);
+        diagnostic.code = Some("unknown-annotation".to_string());
+        report.own.push(diagnostic);
+    }
+}
+
+/// Statements of `options { ... };` and options of its blocks, such as
+/// `stats(...)`, that the grammar database does not know. A warning only,
+/// since the database may lag behind syslog-ng.
+fn check_global_options(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    /// `block` is the option whose child `option` is, if any.
+    fn unknown(option: &Driver, names: &[OptionInfo], block: Option<&str>) -> Diagnostic {
+        let mut message = match block {
+            Some(block) => format!("unknown option `{}` of {}()", option.name.name, block),
+            None => format!("unknown global option `{}`", option.name.name),
+        };
+        let name = normalize_name(&option.name.name);
+        if let Some(suggestion) = closest_name(&name, names.iter().map(|o| o.name), 2) {
+            message.push_str(&format!(", did you mean `{}`?", suggestion));
+        }
+        let mut diagnostic = Diagnostic::new(option.name.range, DiagnosticSeverity::Warning, message);
+        diagnostic.code = Some("unknown-global-option".to_string());
+        diagnostic
+    }
+    fn check_children(option: &Driver, info: &'static OptionInfo, report: &mut DiagnosticReport) {
+        if info.children.is_empty() {
+            return;
+        }
+        for child in option.options() {
+            match info.find_child(&child.name.name) {
+                Some(child_info) => check_children(child, child_info, report),
+                None => report.own.push(unknown(child, info.children, Some(info.name))),
+            }
+        }
+    }
+    for object in parsed.get_objects_by_kind(ObjectKind::Options) {
+        for driver in object.drivers() {
+            match grammar::find_global_option(&driver.name.name) {
+                Some(info) => check_children(driver, info, report),
+                None => report.own.push(unknown(driver, grammar::GLOBAL_OPTIONS, None)),
+            }
+        }
+    }
+}
+
+/// The `flags()` of log paths, embedded ones and channels included.
+fn check_log_flags(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    fn check_path(object: &Object, problems: &mut Vec<LogFlagsProblem>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) if inline.kind == ObjectKind::Log => check_path(inline, problems),
+                statement => {
+                    LogFlags::from_statement(statement, problems);
+                }
+            }
+        }
+    }
+    let mut problems = Vec::new();
+    for object in parsed.get_objects_by_kind(ObjectKind::Log) {
+        check_path(object, &mut problems);
+    }
+    for problem in problems {
+        let mut diagnostic = Diagnostic::new(problem.range, DiagnosticSeverity::Error, problem.message);
+        diagnostic.code = Some("invalid-log-flags".to_string());
+        report.own.push(diagnostic);
+    }
+}
+
+/// `map-value-pairs()` parsers and `value-pairs()` options anywhere in a
+/// driver.
+fn check_value_pairs(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    fn check_options(driver: &Driver, problems: &mut Vec<ValuePairsProblem>) {
+        for option in driver.options() {
+            if normalize_name(&option.name.name) == "value-pairs" {
+                ValuePairs::from_block(option, problems);
+            } else {
+                check_options(option, problems);
+            }
+        }
+    }
+    fn collect(object: &Object, problems: &mut Vec<ValuePairsProblem>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) => collect(inline, problems),
+                Statement::Driver(driver)
+                    if object.kind == ObjectKind::Parser && normalize_name(&driver.name.name) == "map-value-pairs" =>
+                {
+                    ValuePairs::from_block(driver, problems);
+                }
+                Statement::Driver(driver) => check_options(driver, problems),
+                Statement::Conditional(_) | Statement::Junction(_) => {}
+            }
+        }
+    }
+    let mut problems = Vec::new();
+    for object in &parsed.objects {
+        collect(object, &mut problems);
+    }
+    for problem in problems {
+        let mut diagnostic = Diagnostic::new(problem.range, DiagnosticSeverity::Error, problem.message);
+        diagnostic.code = Some("invalid-value-pairs".to_string());
+        report.own.push(diagnostic);
+    }
+}
+
+/// The rules of rewrite objects, inline ones in log paths included.
+fn check_rewrite_rules(parsed: &ParsedConfiguration, report: &mut DiagnosticReport) {
+    fn collect(object: &Object, problems: &mut Vec<RewriteProblem>) {
+        for statement in object.flat_statements() {
+            match statement {
+                Statement::Object(inline) => collect(inline, problems),
+                Statement::Driver(driver) if object.kind == ObjectKind::Rewrite => {
+                    RewriteRule::from_driver(driver, problems);
+                }
+                _ => {}
+            }
+        }
+    }
+    let mut problems = Vec::new();
+    for object in &parsed.objects {
+        collect(object, &mut problems);
+    }
+    for problem in problems {
+        let mut diagnostic = Diagnostic::new(problem.range, DiagnosticSeverity::Error, problem.message);
+        diagnostic.code = Some("invalid-rewrite".to_string());
+        report.own.push(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(line: u32, severity: DiagnosticSeverity) -> Diagnostic {
+        let position = Position::new(line, 0);
+        Diagnostic::new(Range::new(position, position), severity, format!("line {}", line))
+    }
+
+    fn lines(diagnostics: &[Diagnostic]) -> Vec<u32> {
+        diagnostics.iter().map(|d| d.range.start.line).collect()
+    }
+
+    #[test]
+    fn keeps_diagnostics_within_the_limit() {
+        let mut diagnostics = vec![
+            diagnostic(1, DiagnosticSeverity::Warning),
+            diagnostic(2, DiagnosticSeverity::Error),
+        ];
+        let before = diagnostics.clone();
+        limit_diagnostics(&mut diagnostics, 2);
+        assert_eq!(diagnostics, before);
+    }
+
+    #[test]
+    fn keeps_the_most_severe_in_order() {
+        let mut diagnostics = vec![
+            diagnostic(1, DiagnosticSeverity::Hint),
+            diagnostic(2, DiagnosticSeverity::Warning),
+            diagnostic(3, DiagnosticSeverity::Error),
+            diagnostic(4, DiagnosticSeverity::Information),
+            diagnostic(5, DiagnosticSeverity::Error),
+            diagnostic(6, DiagnosticSeverity::Warning),
+        ];
+        limit_diagnostics(&mut diagnostics, 3);
+        assert_eq!(lines(&diagnostics[1..]), [2, 3, 5]);
+    }
+
+    #[test]
+    fn puts_the_summary_first() {
+        let mut diagnostics: Vec<_> = (1..=5)
+            .map(|line| diagnostic(line, DiagnosticSeverity::Error))
+            .collect();
+        limit_diagnostics(&mut diagnostics, 2);
+        assert_eq!(diagnostics.len(), 3);
+        let summary = &diagnostics[0];
+        assert_eq!(summary.code.as_deref(), Some("too-many-diagnostics"));
+        assert_eq!(summary.severity, DiagnosticSeverity::Information);
+        assert_eq!(summary.range, Range::default());
+        assert!(summary.message.starts_with("3 more diagnostics"));
+        assert_eq!(lines(&diagnostics[1..]), [1, 2]);
+    }
+
+    #[test]
+    fn knows_the_user_and_group_global_options() {
+        let parsed = parse_conf("options { user(\"syslog\"); group(\"adm\"); frobnicate(yes); };\n").unwrap();
+        let mut report = DiagnosticReport::default();
+        check_global_options(&parsed, &mut report);
+        let messages: Vec<_> = report.own.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["unknown global option `frobnicate`"]);
+    }
+
+    #[test]
+    fn leaves_the_templates_of_a_snippet_to_its_includer() {
+        let text = "destination d { file(\"/x\" template(t_fmt)); };\n";
+        let mut report = DiagnosticReport::default();
+        check_undefined_templates(
+            "file:///etc/conf.d/d.conf",
+            &parse_conf(text).unwrap(),
+            &[],
+            &mut report,
+        );
+        assert!(report.own.is_empty(), "{:?}", report.own);
+
+        let main = parse_conf(&format!("@version: 4.8\n{}", text)).unwrap();
+        check_undefined_templates("file:///etc/syslog-ng.conf", &main, &[], &mut report);
+        let messages: Vec<_> = report.own.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            ["template `t_fmt` is not defined, syslog-ng will use `t_fmt` as a literal template here"]
+        );
+    }
+
+    #[test]
+    fn reports_drivers_of_other_platforms() {
+        let parsed = parse_conf(
+            "source s { systemd-journal(); network(); };\nlog { source { sun-streams(\"/dev/log\"); }; };\n",
+        )
+        .unwrap();
+        let diagnostics = check_platform(&parsed, Platform::Bsd);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "`systemd-journal()` only works on linux, the target platform is bsd",
+                "`sun-streams()` only works on solaris, the target platform is bsd",
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.code.as_deref() == Some("wrong-platform")));
+        assert!(check_platform(&parsed, Platform::Linux).len() == 1);
+    }
+}
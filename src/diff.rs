@@ -0,0 +1,146 @@
+//! Line based unified diffs (Myers' algorithm).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m) as usize;
+    let offset = max as isize + 1;
+    let mut v = vec![0isize; 2 * max + 3];
+    let mut trace = Vec::new();
+
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        let mut k = -d;
+        while k <= d {
+            let index = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+                v[index + 1]
+            } else {
+                v[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+            k += 2;
+        }
+    }
+
+    // Walk the trace backwards to recover the edit script.
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let index = (k + offset) as usize;
+        let previous_k = if k == -d || (k != d && v[index - 1] < v[index + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == previous_x {
+                edits.push(Edit::Insert(previous_y as usize));
+            } else {
+                edits.push(Edit::Delete(previous_x as usize));
+            }
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Renders the difference between `old` and `new` as a unified diff with
+/// `context` lines around every change. Returns an empty string when the
+/// texts are equal.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = diff_lines(&old_lines, &new_lines);
+    if edits.iter().all(|e| matches!(e, Edit::Equal(..))) {
+        return String::new();
+    }
+
+    // Old and new lines consumed before each edit.
+    let mut consumed = Vec::with_capacity(edits.len());
+    let (mut old_seen, mut new_seen) = (0, 0);
+    for edit in &edits {
+        consumed.push((old_seen, new_seen));
+        match edit {
+            Edit::Equal(..) => {
+                old_seen += 1;
+                new_seen += 1;
+            }
+            Edit::Delete(_) => old_seen += 1,
+            Edit::Insert(_) => new_seen += 1,
+        }
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let changes: Vec<usize> = (0..edits.len())
+        .filter(|&i| !matches!(edits[i], Edit::Equal(..)))
+        .collect();
+    let mut i = 0;
+    while i < changes.len() {
+        // Extend the hunk while the next change is within 2 * context lines.
+        let start = changes[i].saturating_sub(context);
+        let mut end = changes[i];
+        while i + 1 < changes.len() && changes[i + 1] <= end + 2 * context + 1 {
+            i += 1;
+            end = changes[i];
+        }
+        let end = (end + context + 1).min(edits.len());
+        i += 1;
+
+        let (mut old_count, mut new_count) = (0, 0);
+        let mut body = String::new();
+        for edit in &edits[start..end] {
+            match *edit {
+                Edit::Equal(o, _) => {
+                    old_count += 1;
+                    new_count += 1;
+                    body.push_str(&format!(" {}\n", old_lines[o]));
+                }
+                Edit::Delete(o) => {
+                    old_count += 1;
+                    body.push_str(&format!("-{}\n", old_lines[o]));
+                }
+                Edit::Insert(n) => {
+                    new_count += 1;
+                    body.push_str(&format!("+{}\n", new_lines[n]));
+                }
+            }
+        }
+        // An empty side is reported at the line before the hunk, as `diff` does.
+        let (old_before, new_before) = consumed[start];
+        output.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n{}",
+            old_before + usize::from(old_count > 0),
+            old_count,
+            new_before + usize::from(new_count > 0),
+            new_count,
+            body
+        ));
+    }
+    output
+}
@@ -0,0 +1,198 @@
+//! In-memory copies of the documents the editor has open.
+//!
+//! Every feature reads file contents through the store so that unsaved
+//! buffers win over whatever is on disk.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::file_utilities::{normalize_uri, path_to_uri};
+use crate::lsp_types::{Position, TextDocumentContentChangeEvent};
+
+#[derive(Debug, Clone)]
+pub struct TextDocument {
+    /// As the client spells it.
+    pub uri: String,
+    pub text: String,
+    pub version: Option<i64>,
+}
+
+/// Applies an LSP content change to `text`: a replacement of its range,
+/// or of the whole text when it has none.
+pub fn apply_change(text: &mut String, change: &TextDocumentContentChangeEvent) {
+    match change.range {
+        Some(range) => {
+            let start = position_to_offset(text, range.start);
+            let end = position_to_offset(text, range.end).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+        None => *text = change.text.clone(),
+    }
+}
+
+/// Byte offset of an LSP position (UTF-16 based columns). Positions past the
+/// end of a line or of the document are clamped.
+pub fn position_to_offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let mut column = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if c == '\n' || column >= position.character {
+            return line_start + i;
+        }
+        column += c.len_utf16() as u32;
+    }
+    text.len()
+}
+
+/// Position of the byte at `offset`.
+pub fn offset_to_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].chars().map(|c| c.len_utf16() as u32).sum(),
+    )
+}
+
+/// Start offsets of the lines of a text, so that positions are converted
+/// without scanning the text from its beginning.
+#[derive(Debug, Clone, Default)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// [`position_to_offset`] of the text the index was built from.
+    pub fn offset(&self, text: &str, position: Position) -> usize {
+        let Some(&line_start) = self.line_starts.get(position.line as usize) else {
+            return text.len();
+        };
+        let mut column = 0;
+        for (i, c) in text[line_start..].char_indices() {
+            if c == '\n' || column >= position.character {
+                return line_start + i;
+            }
+            column += c.len_utf16() as u32;
+        }
+        text.len()
+    }
+
+    /// [`offset_to_position`] of the text the index was built from.
+    pub fn position(&self, text: &str, offset: usize) -> Position {
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let line_start = self.line_starts[line];
+        Position::new(
+            line as u32,
+            text[line_start..offset].chars().map(|c| c.len_utf16() as u32).sum(),
+        )
+    }
+}
+
+/// The text of a file as the features see it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedText {
+    pub text: String,
+    /// Where the file stopped being valid UTF-8. Invalid sequences are
+    /// replaced by U+FFFD, one per sequence, as editors display them, so
+    /// positions stay in line with the editor.
+    pub invalid_utf8: Option<Position>,
+}
+
+impl LoadedText {
+    pub fn decode(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => LoadedText {
+                text,
+                invalid_utf8: None,
+            },
+            Err(error) => {
+                let valid_up_to = error.utf8_error().valid_up_to();
+                let text = String::from_utf8_lossy(error.as_bytes()).into_owned();
+                LoadedText {
+                    invalid_utf8: Some(offset_to_position(&text, valid_up_to)),
+                    text,
+                }
+            }
+        }
+    }
+}
+
+/// Documents by [`normalize_uri`], so that a file is found however its
+/// URI is spelt.
+#[derive(Debug, Default)]
+pub struct DocumentStore {
+    documents: HashMap<String, TextDocument>,
+}
+
+impl DocumentStore {
+    pub fn open(&mut self, uri: &str, text: String, version: Option<i64>) {
+        let document = TextDocument {
+            uri: uri.to_string(),
+            text,
+            version,
+        };
+        self.documents.insert(normalize_uri(uri), document);
+    }
+
+    /// Applies `changes` in order; returns `false` if `uri` is not open.
+    pub fn change(&mut self, uri: &str, changes: &[TextDocumentContentChangeEvent], version: Option<i64>) -> bool {
+        let Some(document) = self.documents.get_mut(&normalize_uri(uri)) else {
+            return false;
+        };
+        for change in changes {
+            apply_change(&mut document.text, change);
+        }
+        document.version = version;
+        true
+    }
+
+    pub fn close(&mut self, uri: &str) {
+        self.documents.remove(&normalize_uri(uri));
+    }
+
+    pub fn get(&self, uri: &str) -> Option<&TextDocument> {
+        self.documents.get(&normalize_uri(uri))
+    }
+
+    pub fn is_open(&self, uri: &str) -> bool {
+        self.documents.contains_key(&normalize_uri(uri))
+    }
+
+    /// The URIs of the open documents as the client spells them.
+    pub fn uris(&self) -> impl Iterator<Item = &str> {
+        self.documents.values().map(|d| d.uri.as_str())
+    }
+
+    pub fn version(&self, uri: &str) -> Option<i64> {
+        self.get(uri).and_then(|d| d.version)
+    }
+
+    /// Editor buffer of `path` if it is open, otherwise its contents on disk.
+    pub fn load_text(&self, path: &Path) -> Option<LoadedText> {
+        match self.get(&path_to_uri(path)) {
+            Some(document) => Some(LoadedText {
+                text: document.text.clone(),
+                invalid_utf8: None,
+            }),
+            None => fs::read(path).ok().map(LoadedText::decode),
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Helpers for mapping URIs to paths and resolving include patterns.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // Skip an optional authority (`file://localhost/etc`).
+    let path = &rest[rest.find('/')?..];
+    Some(PathBuf::from(percent_decode(path)))
+}
+
+pub fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// `uri` as [`path_to_uri`] spells it, so that the URI of an open document
+/// and the one derived from its path on disk compare equal however the
+/// client encodes it: without `.` and `..` components, and with a lower
+/// case Windows drive letter. Other URIs are returned unchanged.
+pub fn normalize_uri(uri: &str) -> String {
+    let Some(path) = uri_to_path(uri) else {
+        return uri.to_string();
+    };
+    let mut normalized = path_to_uri(&normalize_path(&path));
+    let drive = "file:///".len();
+    if normalized.get(drive + 1..drive + 4) == Some("%3A") {
+        normalized[drive..drive + 1].make_ascii_lowercase();
+    }
+    normalized
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = text.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn has_wildcard(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Matches `name` against a shell-like pattern supporting `*` and `?`.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// How `target` is written in an `@include` of the file `from`: relative
+/// to the directory of `from` when below it, absolute otherwise.
+pub fn include_path(from: &Path, target: &Path) -> String {
+    let relative = from.parent().and_then(|dir| target.strip_prefix(dir).ok());
+    relative.unwrap_or(target).to_string_lossy().into_owned()
+}
+
+/// The canonical path of `path`, with symlinks resolved, or `path` itself
+/// if it cannot be resolved.
+pub fn real_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// `path` without `.` components, and with each `..` applied to the real
+/// path of what precedes it. Through a symlinked `conf.d`,
+/// `conf.d/../x.conf` is next to the target of the link, not `x.conf`;
+/// only the part of `path` that does not exist is taken lexically.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => {
+                normalized = real_path(&normalized);
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+fn sorted_entries(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|rd| rd.filter_map(|e| e.ok().map(|e| e.path())).collect())
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+/// Expands a pattern such as `/etc/syslog-ng/conf.d/*.conf` into the matching
+/// files in lexical order. Hidden files are only matched by patterns that
+/// start with a dot, like in a shell. Files reachable through several paths
+/// (symlinks) are only returned once, under the first path found.
+pub fn get_files_from_wildcard(pattern: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let part = component.as_os_str().to_string_lossy();
+        let is_pattern = matches!(component, Component::Normal(_)) && has_wildcard(&part);
+        candidates = if is_pattern {
+            candidates
+                .iter()
+                .flat_map(|dir| sorted_entries(dir))
+                .filter(|entry| {
+                    let name = entry
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    (part.starts_with('.') || !name.starts_with('.')) && wildcard_match(&part, &name)
+                })
+                .collect()
+        } else {
+            candidates.into_iter().map(|dir| dir.join(component)).collect()
+        };
+    }
+    let mut seen = HashSet::new();
+    candidates.retain(|path| path.is_file() && seen.insert(real_path(path)));
+    candidates
+}
+
+/// The files directly inside `dir` in lexical order, skipping hidden ones,
+/// which is what syslog-ng includes for a directory.
+pub fn get_files_from_directory(dir: &Path) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    sorted_entries(dir)
+        .into_iter()
+        .filter(|entry| {
+            let hidden = entry.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.'));
+            !hidden && entry.is_file() && seen.insert(real_path(entry))
+        })
+        .collect()
+}
+
+/// Resolves the path of an `@include` relative to the including file's
+/// directory. A directory, with or without a trailing slash, includes every
+/// file in it.
+pub fn resolve_include(base_dir: &Path, include: &str) -> Vec<PathBuf> {
+    let path = base_dir.join(include);
+    if has_wildcard(include) {
+        get_files_from_wildcard(&path)
+    } else if include.ends_with('/') || path.is_dir() {
+        get_files_from_directory(&path)
+    } else if path.is_file() {
+        vec![path]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Maximum directory depth scanned below a workspace folder.
+const MAX_SCAN_DEPTH: usize = 8;
+
+/// Every `*.conf` file below `folder`, skipping hidden directories.
+/// Directories are followed through symlinks, but each real directory and
+/// file is visited once, so symlink loops terminate.
+pub fn get_workspace_config_files(folder: &Path) -> Vec<PathBuf> {
+    let mut scan = Scan::default();
+    scan.directory(folder, 0);
+    scan.files
+}
+
+#[derive(Default)]
+struct Scan {
+    files: Vec<PathBuf>,
+    visited: HashSet<PathBuf>,
+}
+
+impl Scan {
+    fn directory(&mut self, dir: &Path, depth: usize) {
+        if depth > MAX_SCAN_DEPTH || !self.visited.insert(real_path(dir)) {
+            return;
+        }
+        for entry in sorted_entries(dir) {
+            let name = entry
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if name.starts_with('.') {
+                continue;
+            }
+            if entry.is_dir() {
+                self.directory(&entry, depth + 1);
+            } else if name.ends_with(".conf") && self.visited.insert(real_path(&entry)) {
+                self.files.push(entry);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for one test, removed first if a previous run
+    /// left it behind.
+    fn scratch_directory(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sng-lsp-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        real_path(&dir)
+    }
+
+    #[test]
+    fn removes_dot_components() {
+        assert_eq!(
+            normalize_path(Path::new("/nonexistent/./a/./b.conf")),
+            PathBuf::from("/nonexistent/a/b.conf")
+        );
+    }
+
+    #[test]
+    fn applies_parent_components_lexically_where_nothing_exists() {
+        assert_eq!(
+            normalize_path(Path::new("/nonexistent/conf.d/../x.conf")),
+            PathBuf::from("/nonexistent/x.conf")
+        );
+        assert_eq!(normalize_path(Path::new("/..")), PathBuf::from("/.."));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn applies_parent_components_to_the_target_of_a_symlink() {
+        let dir = scratch_directory("normalize-path");
+        fs::create_dir_all(dir.join("shared/snippets")).unwrap();
+        fs::create_dir_all(dir.join("etc")).unwrap();
+        std::os::unix::fs::symlink(dir.join("shared/snippets"), dir.join("etc/conf.d")).unwrap();
+        assert_eq!(
+            normalize_path(&dir.join("etc/conf.d/../x.conf")),
+            dir.join("shared/x.conf")
+        );
+        assert_eq!(normalize_path(&dir.join("etc/./y.conf")), dir.join("etc/y.conf"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,539 @@
+//! Pretty-printer for configuration files.
+//!
+//! Only documents that parse are formatted. The output is produced from the
+//! token stream including comments, so no comment is ever dropped.
+
+use crate::document_store::position_to_offset;
+use crate::json::Value;
+use crate::lsp_types::{Position, Range, TextEdit};
+use crate::parser::lexer::{tokenize_with_comments, Token, TokenKind};
+use crate::parser::parse_conf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FormattingOptions {
+    pub tab_size: u32,
+    pub insert_spaces: bool,
+}
+
+impl Default for FormattingOptions {
+    fn default() -> Self {
+        FormattingOptions {
+            tab_size: 4,
+            insert_spaces: true,
+        }
+    }
+}
+
+impl FormattingOptions {
+    /// Reads the `FormattingOptions` of an LSP request; missing fields keep
+    /// their defaults.
+    pub fn from_lsp(value: &Value) -> Self {
+        let defaults = FormattingOptions::default();
+        FormattingOptions {
+            tab_size: value["tabSize"].as_u64().map_or(defaults.tab_size, |t| t as u32),
+            insert_spaces: value["insertSpaces"].as_bool().unwrap_or(defaults.insert_spaces),
+        }
+    }
+}
+
+/// Drivers longer than this are split into one option per line.
+const MAX_INLINE_WIDTH: usize = 100;
+
+/// Returns the formatted text, or `None` if the document has syntax errors.
+pub fn format_document(text: &str, options: &FormattingOptions) -> Option<String> {
+    parse_conf(text).ok()?;
+    let tokens = tokenize_with_comments(text).ok()?;
+    let indent_unit = if options.insert_spaces {
+        " ".repeat(options.tab_size as usize)
+    } else {
+        "\t".to_string()
+    };
+    let mut formatter = Formatter {
+        source: text,
+        tokens,
+        pos: 0,
+        lines: Vec::new(),
+        depth: 0,
+        indent_unit,
+        last_token_line: None,
+        ends_with_comment: false,
+    };
+    formatter.format_top_level();
+    let mut output = formatter.lines.join("\n");
+    output.push('\n');
+    Some(output)
+}
+
+/// Edits formatting `text`, or only the top-level items overlapping `range`
+/// if given. `None` if the document has syntax errors.
+pub fn formatting_edits(text: &str, range: Option<Range>, options: &FormattingOptions) -> Option<Vec<TextEdit>> {
+    let parsed = parse_conf(text).ok()?;
+    let (start, end) = match range {
+        None => (Position::default(), end_position(text)),
+        Some(range) => {
+            let mut items: Vec<Range> = parsed
+                .annotations
+                .iter()
+                .map(|a| a.range())
+                .chain(parsed.objects.iter().map(|o| o.location))
+                .filter(|item| item.start <= range.end && item.end >= range.start)
+                .collect();
+            items.sort_by_key(|item| item.start);
+            let (Some(first), Some(last)) = (items.first(), items.last()) else {
+                return Some(Vec::new());
+            };
+            // Top-level items are formatted from the first column.
+            let line_start = Position::new(first.start.line, 0);
+            let indent = &text[position_to_offset(text, line_start)..position_to_offset(text, first.start)];
+            let start = if indent.trim().is_empty() {
+                line_start
+            } else {
+                first.start
+            };
+            (start, last.end)
+        }
+    };
+    let original = &text[position_to_offset(text, start)..position_to_offset(text, end)];
+    let mut formatted = format_document(original, options)?;
+    if range.is_some() {
+        formatted.truncate(formatted.trim_end().len());
+    }
+    if formatted == original {
+        return Some(Vec::new());
+    }
+    Some(vec![TextEdit {
+        range: Range::new(start, end),
+        new_text: formatted,
+    }])
+}
+
+fn end_position(text: &str) -> Position {
+    let line = text.matches('\n').count() as u32;
+    let last_line = text.rsplit('\n').next().unwrap_or_default();
+    Position::new(line, last_line.chars().map(|c| c.len_utf16() as u32).sum())
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    lines: Vec<String>,
+    depth: usize,
+    indent_unit: String,
+    /// Source line of the last written token and the output line holding it.
+    last_token_line: Option<(u32, usize)>,
+    /// The current output line ends in a comment, so nothing may follow it.
+    ends_with_comment: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_kind(&self) -> Option<TokenKind> {
+        self.peek().map(|t| t.kind)
+    }
+
+    /// Kind of the next token that is not a comment.
+    fn peek_significant_kind(&self, skip: usize) -> Option<TokenKind> {
+        self.tokens[self.pos..]
+            .iter()
+            .filter(|t| t.kind != TokenKind::Comment)
+            .nth(skip)
+            .map(|t| t.kind)
+    }
+
+    /// Index of the next token that is not a comment.
+    fn significant_index(&self, from: usize) -> usize {
+        (from..self.tokens.len())
+            .find(|&i| self.tokens[i].kind != TokenKind::Comment)
+            .unwrap_or(self.tokens.len() - 1)
+    }
+
+    fn raw(&self, token: &Token) -> &'a str {
+        self.source[token.start..token.end].trim_end()
+    }
+
+    fn start_line(&mut self) {
+        let indent = self.indent_unit.repeat(self.depth);
+        self.lines.push(indent);
+        self.ends_with_comment = false;
+    }
+
+    fn write(&mut self, text: &str) {
+        if self.lines.is_empty() {
+            self.start_line();
+        } else if self.ends_with_comment {
+            // Continuation of a construct interrupted by a comment.
+            self.start_line();
+            let unit = self.indent_unit.clone();
+            self.lines.last_mut().unwrap().push_str(&unit);
+        }
+        self.lines.last_mut().unwrap().push_str(text);
+    }
+
+    /// A separating space, unless the line ended in a comment and the next
+    /// token starts a continuation line anyway.
+    fn space(&mut self) {
+        if !self.ends_with_comment {
+            self.write(" ");
+        }
+    }
+
+    /// Writes the next token and remembers where it went.
+    fn emit(&mut self) {
+        let token = self.tokens[self.pos].clone();
+        self.pos += 1;
+        let raw = self.raw(&token);
+        self.write(raw);
+        self.last_token_line = Some((token.range.end.line, self.lines.len() - 1));
+    }
+
+    /// Writes the `;` or `,` that comes next apart from comments in front
+    /// of them, so `} # comment` followed by `;` becomes `}; # comment`.
+    fn emit_punctuation(&mut self) {
+        let index = self.significant_index(self.pos);
+        if index > self.pos {
+            let token = self.tokens.remove(index);
+            self.tokens.insert(self.pos, token);
+            let previous = self.last_token_line;
+            self.emit();
+            // The comments still trail the token they followed in the source.
+            if let Some((line, _)) = previous {
+                self.last_token_line = Some((line, self.lines.len() - 1));
+            }
+        } else {
+            self.emit();
+        }
+    }
+
+    /// Starts a new line for the next item, keeping one blank line if the
+    /// source separated it from the previous item with blank lines.
+    fn start_item(&mut self) {
+        if let (Some(next), Some((last_line, _))) = (self.peek(), self.last_token_line) {
+            if next.range.start.line > last_line + 1 && self.lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                self.lines.push(String::new());
+            }
+        }
+        self.start_line();
+    }
+
+    /// Writes pending comments: on the line of the previous token when they
+    /// trailed it in the source, on their own lines otherwise.
+    fn flush_comments(&mut self) {
+        while self.peek_kind() == Some(TokenKind::Comment) {
+            let token = self.tokens[self.pos].clone();
+            match self.last_token_line {
+                Some((line, index)) if line == token.range.start.line => {
+                    self.pos += 1;
+                    let raw = self.raw(&token);
+                    self.lines[index].push(' ');
+                    self.lines[index].push_str(raw);
+                    if index == self.lines.len() - 1 {
+                        self.ends_with_comment = true;
+                    }
+                }
+                _ => {
+                    self.start_item();
+                    self.emit();
+                    self.ends_with_comment = true;
+                }
+            }
+        }
+    }
+
+    fn format_top_level(&mut self) {
+        loop {
+            self.flush_comments();
+            match self.peek_kind() {
+                None => break,
+                Some(TokenKind::Pragma) => {
+                    self.start_item();
+                    self.emit();
+                }
+                Some(_) if self.peek().is_some_and(|t| t.is_word("block")) => {
+                    self.start_item();
+                    self.format_block();
+                }
+                Some(_) => {
+                    self.start_item();
+                    self.format_object();
+                }
+            }
+        }
+    }
+
+    /// `kind [id] { ... };` starting at the kind keyword.
+    fn format_object(&mut self) {
+        let is_filter = self.peek().is_some_and(|t| t.is_word("filter"));
+        self.emit();
+        self.flush_comments();
+        if self.peek_kind() == Some(TokenKind::Word) {
+            self.space();
+            self.emit();
+            self.flush_comments();
+        }
+        self.space();
+        self.emit(); // {
+        self.depth += 1;
+        if is_filter {
+            self.format_expression_body();
+        } else {
+            self.format_statements();
+        }
+        self.depth -= 1;
+        self.start_line();
+        self.emit(); // }
+        self.emit_punctuation(); // ;
+    }
+
+    /// `block context name(parameters...) { ... };`. The body is copied as
+    /// written, since its substitutions can stand for any part of the syntax.
+    fn format_block(&mut self) {
+        self.emit(); // block
+        self.flush_comments();
+        self.space();
+        self.emit(); // context
+        self.flush_comments();
+        self.space();
+        self.format_call();
+        self.flush_comments();
+        self.space();
+        let open = self.pos;
+        let mut depth = 0usize;
+        let close = (open..self.tokens.len())
+            .find(|&i| {
+                match self.tokens[i].kind {
+                    TokenKind::LBrace => depth += 1,
+                    TokenKind::RBrace => depth -= 1,
+                    _ => {}
+                }
+                depth == 0
+            })
+            .unwrap_or(self.tokens.len() - 1);
+        let body = &self.source[self.tokens[open].start..self.tokens[close].end];
+        self.write(body);
+        self.pos = close + 1;
+        self.last_token_line = Some((self.tokens[close].range.end.line, self.lines.len() - 1));
+        self.emit_punctuation(); // ;
+    }
+
+    fn format_statements(&mut self) {
+        loop {
+            self.flush_comments();
+            if matches!(self.peek_kind(), Some(TokenKind::RBrace) | None) {
+                return;
+            }
+            self.start_item();
+            if self.at_conditional() {
+                self.format_conditional();
+            } else if self.peek_significant_kind(1) == Some(TokenKind::LBrace) {
+                self.format_object();
+            } else {
+                self.format_call();
+                self.emit_punctuation(); // ;
+            }
+        }
+    }
+
+    /// Whether an `if` block starts at `self.pos`, rather than a call named `if`.
+    fn at_conditional(&self) -> bool {
+        if !self.peek().is_some_and(|t| t.is_word("if")) {
+            return false;
+        }
+        match self.peek_significant_kind(1) {
+            Some(TokenKind::LBrace) => return true,
+            Some(TokenKind::LParen) => {}
+            _ => return false,
+        }
+        let close = self.matching_paren(self.significant_index(self.pos + 1));
+        self.tokens[self.significant_index(close + 1)].kind == TokenKind::LBrace
+    }
+
+    /// `if (filter) { ... } elif (filter) { ... } else { ... };`, starting
+    /// at `if`. The conditions stay on the line of their keyword.
+    fn format_conditional(&mut self) {
+        loop {
+            let is_else = self.peek().is_some_and(|t| t.is_word("else"));
+            self.emit(); // if, elif or else
+            self.flush_comments();
+            if !is_else && self.peek_kind() == Some(TokenKind::LParen) {
+                self.space();
+                self.format_condition();
+                self.flush_comments();
+            }
+            self.space();
+            self.emit(); // {
+            self.depth += 1;
+            self.format_statements();
+            self.depth -= 1;
+            self.start_line();
+            self.emit(); // }
+            let next = &self.tokens[self.significant_index(self.pos)];
+            if is_else || !(next.is_word("elif") || next.is_word("else")) {
+                break;
+            }
+            self.flush_comments();
+            self.space();
+        }
+        self.emit_punctuation(); // ;
+    }
+
+    /// The parenthesised filter expression of `if` or `elif`.
+    fn format_condition(&mut self) {
+        let close = self.matching_paren(self.pos);
+        let mut previous: Option<Token> = None;
+        while self.pos <= close {
+            let token = self.tokens[self.pos].clone();
+            if token.kind == TokenKind::Comment {
+                self.flush_comments();
+                previous = None;
+                continue;
+            }
+            if previous.as_ref().is_some_and(|p| needs_space(p, &token)) {
+                self.write(" ");
+            }
+            self.emit();
+            previous = Some(token);
+        }
+    }
+
+    /// Filter expressions: one `;`-terminated expression per line.
+    fn format_expression_body(&mut self) {
+        loop {
+            self.flush_comments();
+            if matches!(self.peek_kind(), Some(TokenKind::RBrace) | None) {
+                return;
+            }
+            self.start_item();
+            let mut depth = 0usize;
+            let mut previous: Option<Token> = None;
+            while let Some(token) = self.peek().cloned() {
+                match token.kind {
+                    TokenKind::Comment => {
+                        self.flush_comments();
+                        previous = None;
+                        continue;
+                    }
+                    TokenKind::RBrace if depth == 0 => break,
+                    TokenKind::LParen | TokenKind::LBrace => depth += 1,
+                    TokenKind::RParen | TokenKind::RBrace => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                if previous.as_ref().is_some_and(|p| needs_space(p, &token)) {
+                    self.write(" ");
+                }
+                self.emit();
+                if token.kind == TokenKind::Semicolon && depth == 0 {
+                    break;
+                }
+                previous = Some(token);
+            }
+        }
+    }
+
+    /// Index of the `)` matching the `(` at `open`.
+    fn matching_paren(&self, open: usize) -> usize {
+        let mut depth = 0usize;
+        for (i, token) in self.tokens.iter().enumerate().skip(open) {
+            match token.kind {
+                TokenKind::LParen => depth += 1,
+                TokenKind::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.tokens.len() - 1
+    }
+
+    /// Whether the call starting at `self.pos` is written over several lines.
+    fn is_multiline_call(&self, open: usize, close: usize) -> bool {
+        let inner = &self.tokens[open + 1..close];
+        if self.tokens[self.pos..close]
+            .iter()
+            .any(|t| t.kind == TokenKind::Comment)
+        {
+            return true;
+        }
+        let mut depth = 0usize;
+        let mut nested_options = 0;
+        for (i, token) in inner.iter().enumerate() {
+            match token.kind {
+                TokenKind::LParen => depth += 1,
+                TokenKind::RParen => depth -= 1,
+                TokenKind::Word if inner.get(i + 1).is_some_and(|t| t.kind == TokenKind::LParen) => {
+                    if depth == 0 {
+                        nested_options += 1;
+                    } else {
+                        // An inner block such as `tls(key-file(...))`.
+                        return true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let width: usize = self.tokens[self.pos..=close]
+            .iter()
+            .map(|t| self.raw(t).len() + 1)
+            .sum();
+        nested_options >= 2 || width + self.depth * self.indent_unit.len() > MAX_INLINE_WIDTH
+    }
+
+    /// `name(parameters...)`, starting at the name.
+    fn format_call(&mut self) {
+        let open = self.significant_index(self.pos + 1);
+        let close = self.matching_paren(open);
+        if !self.is_multiline_call(open, close) {
+            let mut previous: Option<Token> = None;
+            while self.pos <= close {
+                let token = self.tokens[self.pos].clone();
+                if previous.as_ref().is_some_and(|p| needs_space(p, &token)) {
+                    self.write(" ");
+                }
+                self.emit();
+                previous = Some(token);
+            }
+            return;
+        }
+
+        self.emit(); // name
+        self.flush_comments();
+        self.emit(); // (
+        self.depth += 1;
+        loop {
+            if self.peek_significant_kind(0) == Some(TokenKind::Comma) {
+                self.emit_punctuation();
+            }
+            self.flush_comments();
+            match self.peek_kind() {
+                Some(TokenKind::RParen) | None => break,
+                Some(TokenKind::Comma) => self.emit(),
+                Some(TokenKind::Word) if self.peek_significant_kind(1) == Some(TokenKind::LParen) => {
+                    self.start_item();
+                    self.format_call();
+                }
+                Some(_) => {
+                    self.start_item();
+                    self.emit();
+                }
+            }
+        }
+        self.depth -= 1;
+        self.start_line();
+        self.emit(); // )
+    }
+}
+
+fn needs_space(previous: &Token, next: &Token) -> bool {
+    match (previous.kind, next.kind) {
+        (_, TokenKind::RParen | TokenKind::Comma | TokenKind::Semicolon) => false,
+        (TokenKind::LParen, _) => false,
+        (TokenKind::Word, TokenKind::LParen) => matches!(previous.text.as_str(), "and" | "or" | "not"),
+        _ => true,
+    }
+}
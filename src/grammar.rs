@@ -0,0 +1,2123 @@
+//! Database of the drivers and options syslog-ng understands.
+//!
+//! The tables only cover the commonly used parts of the configuration
+//! language; a name missing from here is not necessarily invalid.
+
+use crate::language_types::objects::ObjectKind;
+
+/// An option accepted inside a driver, e.g. `port(514)`.
+#[derive(Debug, PartialEq)]
+pub struct OptionInfo {
+    pub name: &'static str,
+    /// Human readable description of the accepted value, e.g. `yes | no`.
+    pub value: &'static str,
+    pub description: &'static str,
+    /// Options of an inner block such as `tls(...)`.
+    pub children: &'static [OptionInfo],
+    /// Value suggested when the option is completed, e.g. `514`.
+    pub default: Option<&'static str>,
+    /// What a number passed to the option counts, when `value` does not
+    /// already say, see [`OptionInfo::unit`].
+    pub unit: Option<Unit>,
+}
+
+/// What the number passed to an option counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Seconds,
+    Milliseconds,
+    Bytes,
+    Messages,
+    /// A rate; 0 means no limit.
+    MessagesPerSecond,
+    Lines,
+}
+
+impl Unit {
+    /// How syslog-ng reads `value`, e.g. "60 seconds (1 minute)". Sizes
+    /// may carry a suffix such as `10MiB`.
+    pub fn interpret(&self, value: &str) -> Option<String> {
+        let number = match self {
+            Unit::Bytes => parse_size(value)?,
+            _ => value.parse::<u64>().ok()?,
+        };
+        let counted = |singular: &str, plural: &str| match number {
+            1 => format!("1 {}", singular),
+            _ => format!("{} {}", number, plural),
+        };
+        Some(match self {
+            Unit::Seconds => match human_duration(number) {
+                Some(human) => format!("{} ({})", counted("second", "seconds"), human),
+                None => counted("second", "seconds"),
+            },
+            Unit::Milliseconds if number >= 1000 => format!(
+                "{} ({} seconds)",
+                counted("millisecond", "milliseconds"),
+                number as f64 / 1000.0
+            ),
+            Unit::Milliseconds => counted("millisecond", "milliseconds"),
+            Unit::Bytes if number >= 1024 => format!("{} ({})", counted("byte", "bytes"), human_size(number)),
+            Unit::Bytes => counted("byte", "bytes"),
+            Unit::Messages => counted("message", "messages"),
+            Unit::MessagesPerSecond if number == 0 => "no limit".to_string(),
+            Unit::MessagesPerSecond => format!("{} per second", counted("message", "messages")),
+            Unit::Lines => counted("line", "lines"),
+        })
+    }
+}
+
+/// A number with an optional `K`, `M` or `G` suffix, decimal unless
+/// followed by `i`, and an optional trailing `B`: `1KB` is 1000 bytes,
+/// `1KiB` 1024.
+fn parse_size(value: &str) -> Option<u64> {
+    let digits = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let number: u64 = value[..digits].parse().ok()?;
+    let suffix = value[digits..].to_ascii_lowercase();
+    let suffix = suffix.strip_suffix('b').unwrap_or(&suffix);
+    let (exponent, base) = match suffix {
+        "" => return Some(number),
+        "k" => (1, 1000),
+        "ki" => (1, 1024),
+        "m" => (2, 1000),
+        "mi" => (2, 1024),
+        "g" => (3, 1000),
+        "gi" => (3, 1024),
+        _ => return None,
+    };
+    number.checked_mul(u64::pow(base, exponent))
+}
+
+/// `number` seconds in the largest unit that divides it, if over a minute.
+fn human_duration(seconds: u64) -> Option<String> {
+    let units = [(86400, "day"), (3600, "hour"), (60, "minute")];
+    let (size, name) = units
+        .into_iter()
+        .find(|(size, _)| seconds >= *size && seconds.is_multiple_of(*size))?;
+    let count = seconds / size;
+    Some(match count {
+        1 => format!("1 {}", name),
+        _ => format!("{} {}s", count, name),
+    })
+}
+
+fn human_size(bytes: u64) -> String {
+    let units = [(1 << 30, "GiB"), (1 << 20, "MiB"), (1 << 10, "KiB")];
+    let (size, name) = units.into_iter().find(|(size, _)| bytes >= *size).unwrap_or((1, "B"));
+    let value = bytes as f64 / size as f64;
+    if value.fract() == 0.0 {
+        format!("{} {}", value, name)
+    } else {
+        format!("{:.1} {}", value, name)
+    }
+}
+
+/// A driver such as `network()`, or a log path element such as `source()`.
+#[derive(Debug, PartialEq)]
+pub struct DriverInfo {
+    pub name: &'static str,
+    pub context: ObjectKind,
+    pub description: &'static str,
+    pub options: &'static [&'static [OptionInfo]],
+    /// Options filled in when the driver is completed as a snippet.
+    pub snippet: &'static [&'static str],
+    /// Names of the values the driver takes without an option name, in
+    /// order, e.g. the path of `file("/var/log/messages")`.
+    pub positional: &'static [&'static str],
+    /// The configuration version that introduced the driver; `None` for
+    /// the drivers every supported version has.
+    pub since: Option<(u32, u32)>,
+}
+
+impl DriverInfo {
+    pub fn all_options(&self) -> impl Iterator<Item = &'static OptionInfo> {
+        self.options.iter().flat_map(|group| group.iter())
+    }
+
+    pub fn find_option(&self, name: &str) -> Option<&'static OptionInfo> {
+        let name = normalize_name(name);
+        self.all_options().find(|o| o.name == name)
+    }
+
+    /// Whether a configuration of `version` can use the driver; every
+    /// driver counts as available when the version is unknown.
+    pub fn available_in(&self, version: Option<(u32, u32)>) -> bool {
+        match (self.since, version) {
+            (Some(since), Some(version)) => version >= since,
+            _ => true,
+        }
+    }
+
+    /// The platforms the driver works on; empty for all of them.
+    pub fn platforms(&self) -> &'static [Platform] {
+        match (self.context, self.name) {
+            (ObjectKind::Source, "systemd-journal" | "systemd-syslog") => &[Platform::Linux],
+            (ObjectKind::Source, "openbsd") => &[Platform::Bsd],
+            (ObjectKind::Source, "sun-streams") => &[Platform::Solaris],
+            _ => &[],
+        }
+    }
+
+    /// Whether the driver works on `platform`; every driver does when the
+    /// platform is unknown.
+    pub fn available_on(&self, platform: Option<Platform>) -> bool {
+        let platforms = self.platforms();
+        platform.is_none_or(|platform| platforms.is_empty() || platforms.contains(&platform))
+    }
+}
+
+/// An operating system syslog-ng runs on, as far as its drivers differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Linux,
+    Bsd,
+    Solaris,
+}
+
+impl Platform {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linux" => Some(Platform::Linux),
+            "bsd" => Some(Platform::Bsd),
+            "solaris" => Some(Platform::Solaris),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::Linux => "linux",
+            Platform::Bsd => "bsd",
+            Platform::Solaris => "solaris",
+        }
+    }
+}
+
+impl OptionInfo {
+    const fn with_default(self, default: &'static str) -> Self {
+        OptionInfo {
+            default: Some(default),
+            ..self
+        }
+    }
+
+    const fn with_unit(self, unit: Unit) -> Self {
+        OptionInfo {
+            unit: Some(unit),
+            ..self
+        }
+    }
+
+    /// The unit of the option's value: the explicit one, or the one
+    /// `value` names.
+    pub fn unit(&self) -> Option<Unit> {
+        self.unit.or(match self.value {
+            "seconds" => Some(Unit::Seconds),
+            "milliseconds" => Some(Unit::Milliseconds),
+            "bytes" => Some(Unit::Bytes),
+            _ => None,
+        })
+    }
+
+    pub fn find_child(&self, name: &str) -> Option<&'static OptionInfo> {
+        let name = normalize_name(name);
+        self.children.iter().find(|o| o.name == name)
+    }
+}
+
+/// syslog-ng treats `-` and `_` in driver and option names as the same character.
+pub fn normalize_name(name: &str) -> String {
+    name.replace('_', "-")
+}
+
+pub fn find_driver(context: ObjectKind, name: &str) -> Option<&'static DriverInfo> {
+    let name = normalize_name(name);
+    DRIVERS.iter().find(|d| d.context == context && d.name == name)
+}
+
+pub fn drivers_for(context: ObjectKind) -> impl Iterator<Item = &'static DriverInfo> {
+    DRIVERS.iter().filter(move |d| d.context == context)
+}
+
+/// The drivers taking the inner block `name`, such as `tls`, directly.
+pub fn drivers_with_block(name: &str) -> impl Iterator<Item = &'static DriverInfo> {
+    let name = normalize_name(name);
+    DRIVERS
+        .iter()
+        .filter(move |d| d.find_option(&name).is_some_and(|o| !o.children.is_empty()))
+}
+
+pub fn find_global_option(name: &str) -> Option<&'static OptionInfo> {
+    let name = normalize_name(name);
+    GLOBAL_OPTIONS.iter().find(|o| o.name == name)
+}
+
+pub fn find_filter_function(name: &str) -> Option<&'static OptionInfo> {
+    let name = normalize_name(name);
+    FILTER_FUNCTIONS.iter().find(|o| o.name == name)
+}
+
+const fn opt(name: &'static str, value: &'static str, description: &'static str) -> OptionInfo {
+    OptionInfo {
+        name,
+        value,
+        description,
+        children: &[],
+        default: None,
+        unit: None,
+    }
+}
+
+const fn block(name: &'static str, description: &'static str, children: &'static [OptionInfo]) -> OptionInfo {
+    OptionInfo {
+        name,
+        value: "block",
+        description,
+        children,
+        default: None,
+        unit: None,
+    }
+}
+
+const YES_NO: &str = "yes | no";
+
+const TLS_OPTIONS: &[OptionInfo] = &[
+    opt("key-file", "path", "Private key of the host, in PEM format."),
+    opt("cert-file", "path", "Certificate of the host, in PEM format."),
+    opt("ca-dir", "path", "Directory holding the trusted CA certificates."),
+    opt("ca-file", "path", "File holding the trusted CA certificates."),
+    opt("crl-dir", "path", "Directory holding certificate revocation lists."),
+    opt(
+        "peer-verify",
+        "optional-trusted | optional-untrusted | required-trusted | required-untrusted",
+        "How the certificate of the remote peer is verified.",
+    ),
+    opt("cipher-suite", "string", "OpenSSL cipher list to allow."),
+    opt(
+        "ssl-options",
+        "no-sslv2, no-tlsv1, ...",
+        "Protocol versions to disable.",
+    ),
+    opt(
+        "sni",
+        YES_NO,
+        "Send the destination host name in the Server Name Indication extension.",
+    ),
+];
+
+const TLS: OptionInfo = block("tls", "TLS settings of the connection.", TLS_OPTIONS);
+
+const DISK_BUFFER_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "reliable",
+        YES_NO,
+        "Reliable buffers write every message to disk, at the cost of performance.",
+    ),
+    opt("disk-buf-size", "bytes", "Maximum size of the disk buffer file."),
+    opt("mem-buf-size", "bytes", "Memory buffer size of a reliable disk buffer."),
+    opt(
+        "mem-buf-length",
+        "number",
+        "Memory buffer length, in messages, of a non-reliable disk buffer.",
+    )
+    .with_unit(Unit::Messages),
+    opt(
+        "qout-size",
+        "number",
+        "Messages kept in memory in front of the disk buffer.",
+    )
+    .with_unit(Unit::Messages),
+    opt("dir", "path", "Directory to store the disk buffer files in."),
+];
+
+const SOURCE_COMMON: &[OptionInfo] = &[
+    opt(
+        "flags",
+        "no-parse, no-multi-line, kernel, store-raw-message, ...",
+        "Flags changing how incoming messages are handled.",
+    ),
+    opt(
+        "log-iw-size",
+        "number",
+        "Size of the initial window, used for flow control.",
+    )
+    .with_unit(Unit::Messages),
+    opt(
+        "log-fetch-limit",
+        "number",
+        "Maximum number of messages fetched in a single poll loop.",
+    )
+    .with_unit(Unit::Messages),
+    opt("log-msg-size", "bytes", "Maximum length of an incoming message."),
+    opt(
+        "host-override",
+        "string",
+        "Replace the HOST part of the message with this value.",
+    ),
+    opt(
+        "program-override",
+        "string",
+        "Replace the PROGRAM part of the message with this value.",
+    ),
+    opt("keep-hostname", YES_NO, "Keep the host name found in the message."),
+    opt(
+        "keep-timestamp",
+        YES_NO,
+        "Use the timestamp found in the message instead of the receive time.",
+    ),
+    opt(
+        "default-facility",
+        "facility",
+        "Facility of messages that do not specify one.",
+    ),
+    opt(
+        "default-priority",
+        "priority",
+        "Priority of messages that do not specify one.",
+    ),
+    opt("tags", "string", "Tags to add to every message of this source."),
+    opt("encoding", "string", "Character set of the incoming messages."),
+    opt(
+        "format",
+        "string",
+        "Name of the parser plugin used to parse the messages.",
+    ),
+    opt(
+        "time-zone",
+        "time zone",
+        "Time zone of timestamps that do not carry one.",
+    ),
+    opt(
+        "use-fqdn",
+        YES_NO,
+        "Use the fully qualified domain name of the sending host.",
+    ),
+    opt(
+        "use-dns",
+        "yes | no | persist_only",
+        "Resolve the address of the sender to a host name.",
+    ),
+    opt(
+        "persist-name",
+        "string",
+        "Name under which the state of the driver is persisted.",
+    ),
+];
+
+const FILE_SOURCE_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "follow-freq",
+        "seconds",
+        "How often the file is checked for new messages.",
+    ),
+    opt(
+        "multi-line-mode",
+        "indented | regexp | prefix-garbage | prefix-suffix",
+        "How multi-line messages are detected.",
+    ),
+    opt(
+        "multi-line-prefix",
+        "regexp",
+        "Regular expression matching the start of a message.",
+    ),
+    opt(
+        "multi-line-garbage",
+        "regexp",
+        "Regular expression matching the end of a message.",
+    ),
+    opt("pad-size", "bytes", "Size of the fixed length records of the file."),
+];
+
+const WILDCARD_FILE_OPTIONS: &[OptionInfo] = &[
+    opt("base-dir", "path", "Directory holding the monitored files."),
+    opt("filename-pattern", "glob", "Pattern of the file names to monitor."),
+    opt("recursive", YES_NO, "Also monitor the subdirectories of base-dir."),
+    opt(
+        "max-files",
+        "number",
+        "Maximum number of files monitored at the same time.",
+    ),
+    opt(
+        "monitor-method",
+        "auto | inotify | poll",
+        "How new files are discovered.",
+    ),
+];
+
+const NETWORK_COMMON: &[OptionInfo] = &[
+    opt("port", "number", "Port number to use.").with_default("514"),
+    opt(
+        "transport",
+        "tcp | udp | tls | proxied-tcp | ...",
+        "Transport protocol to use.",
+    ),
+    opt("ip-protocol", "4 | 6", "Use IPv4 or IPv6."),
+    opt("keep-alive", YES_NO, "Keep connections open across reloads."),
+    opt("so-keepalive", YES_NO, "Enable TCP keep-alive messages on the socket."),
+    TLS,
+];
+
+const NETWORK_SOURCE_OPTIONS: &[OptionInfo] = &[
+    opt("ip", "ip address", "Address to listen on.").with_default("\"0.0.0.0\""),
+    opt(
+        "max-connections",
+        "number",
+        "Maximum number of simultaneous connections.",
+    ),
+    opt("so-rcvbuf", "bytes", "Size of the socket receive buffer."),
+];
+
+const SYSTEMD_JOURNAL_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "prefix",
+        "string",
+        "Prefix of the names the journal fields are stored under.",
+    )
+    .with_default("\".journald.\""),
+    opt("namespace", "string", "Journal namespace to read."),
+    opt("max-field-size", "bytes", "Longest journal field value read."),
+];
+
+const SUN_STREAMS_OPTIONS: &[OptionInfo] = &[opt("door", "path", "Door file used to signal syslogd clients.")];
+
+const DESTINATION_COMMON: &[OptionInfo] = &[
+    opt("template", "template", "Template used to format the outgoing messages."),
+    opt("template-escape", YES_NO, "Escape quote characters in macro values."),
+    opt(
+        "log-fifo-size",
+        "number",
+        "Number of messages held in the output queue.",
+    )
+    .with_unit(Unit::Messages),
+    opt("throttle", "number", "Maximum number of messages sent per second.").with_unit(Unit::MessagesPerSecond),
+    opt(
+        "time-reopen",
+        "seconds",
+        "Time to wait before reconnecting after an error, overriding the global option.",
+    ),
+    opt(
+        "frac-digits",
+        "number",
+        "Number of digits of fractional seconds in timestamps.",
+    ),
+    opt("time-zone", "time zone", "Time zone to convert timestamps to."),
+    opt(
+        "ts-format",
+        "rfc3164 | bsd | rfc3339 | iso | unix",
+        "Format of the timestamps.",
+    ),
+    opt(
+        "persist-name",
+        "string",
+        "Name under which the state of the driver is persisted.",
+    ),
+    block(
+        "disk-buffer",
+        "Buffer messages on disk while the destination is unavailable.",
+        DISK_BUFFER_OPTIONS,
+    ),
+];
+
+const FILE_DESTINATION_OPTIONS: &[OptionInfo] = &[
+    opt("create-dirs", YES_NO, "Create missing directories of the file name."),
+    opt("owner", "user", "Owner of the created files."),
+    opt("group", "group", "Group of the created files."),
+    opt("perm", "octal", "Permissions of the created files."),
+    opt("dir-owner", "user", "Owner of the created directories."),
+    opt("dir-group", "group", "Group of the created directories."),
+    opt("dir-perm", "octal", "Permissions of the created directories."),
+    opt("fsync", YES_NO, "Call fsync() after every write."),
+    opt("flush-lines", "number", "Number of lines written in a single batch.").with_unit(Unit::Lines),
+    opt(
+        "overwrite-if-older",
+        "seconds",
+        "Overwrite files older than this instead of appending to them.",
+    ),
+];
+
+const FAILBACK_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "tcp-probe-interval",
+        "seconds",
+        "How often the primary server is probed while a failover server is in use.",
+    ),
+    opt(
+        "successful-probes-required",
+        "number",
+        "Successful probes needed before switching back to the primary server.",
+    ),
+];
+
+const FAILOVER_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "servers",
+        "string list",
+        "Failover servers, tried in order when the primary server is down.",
+    ),
+    block(
+        "failback",
+        "Switch back to the primary server once it is reachable again.",
+        FAILBACK_OPTIONS,
+    ),
+];
+
+const NETWORK_DESTINATION_OPTIONS: &[OptionInfo] = &[
+    block(
+        "failover",
+        "Servers to send messages to when the primary server is unavailable.",
+        FAILOVER_OPTIONS,
+    ),
+    opt(
+        "failover-servers",
+        "string list",
+        "Failover servers, tried in order when the primary server is down.",
+    ),
+    opt("so-sndbuf", "bytes", "Size of the socket send buffer."),
+    opt("localip", "ip address", "Local address to send the messages from."),
+    opt("flush-lines", "number", "Number of lines written in a single batch.").with_unit(Unit::Lines),
+];
+
+const HTTP_OPTIONS: &[OptionInfo] = &[
+    opt("url", "string", "URL of the HTTP server."),
+    opt("method", "POST | PUT", "HTTP method of the requests."),
+    opt("headers", "string list", "Extra HTTP headers of the requests."),
+    opt("user", "string", "User name for HTTP basic authentication."),
+    opt("password", "string", "Password for HTTP basic authentication."),
+    opt("body", "template", "Template of the request body."),
+    opt("batch-lines", "number", "Number of messages sent in a single request.").with_unit(Unit::Messages),
+    opt("batch-timeout", "milliseconds", "Time to wait for a batch to fill up."),
+    opt("workers", "number", "Number of worker threads sending requests."),
+    opt("timeout", "seconds", "Timeout of a single request."),
+    TLS,
+];
+
+const OTLP_SOURCE_OPTIONS: &[OptionInfo] = &[
+    opt("port", "number", "Port to accept OTLP/gRPC connections on.").with_default("4317"),
+    opt("workers", "number", "Number of threads processing incoming requests."),
+];
+
+const OTLP_DESTINATION_OPTIONS: &[OptionInfo] = &[
+    opt("url", "string", "Address of the OTLP/gRPC endpoint, as `host:port`."),
+    opt("workers", "number", "Number of worker threads sending requests."),
+    opt("batch-lines", "number", "Number of messages sent in a single request.").with_unit(Unit::Messages),
+    opt("batch-timeout", "milliseconds", "Time to wait for a batch to fill up."),
+    opt("compression", YES_NO, "Compress the requests."),
+];
+
+const LOKI_OPTIONS: &[OptionInfo] = &[
+    opt("url", "string", "Address of the Loki gRPC endpoint, as `host:port`."),
+    opt(
+        "timestamp",
+        "current | received | msg",
+        "Which timestamp of the message Loki receives.",
+    ),
+    opt("tenant-id", "string", "Tenant of the messages in a multi-tenant Loki."),
+    opt("workers", "number", "Number of worker threads sending requests."),
+    opt("batch-lines", "number", "Number of messages sent in a single request.").with_unit(Unit::Messages),
+    opt("batch-timeout", "milliseconds", "Time to wait for a batch to fill up."),
+];
+
+const PROGRAM_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "inherit-environment",
+        YES_NO,
+        "Pass the environment of syslog-ng to the program.",
+    ),
+    opt("keep-alive", YES_NO, "Keep the program running across reloads."),
+];
+
+const PARSER_COMMON: &[OptionInfo] = &[
+    opt("template", "template", "Template producing the text that is parsed."),
+    opt("prefix", "string", "Prefix of the names of the extracted fields."),
+];
+
+const CSV_PARSER_OPTIONS: &[OptionInfo] = &[
+    opt("columns", "string list", "Names of the columns."),
+    opt("delimiters", "string", "Characters separating the columns."),
+    opt("quote-pairs", "string", "Pairs of characters quoting a column."),
+    opt(
+        "dialect",
+        "escape-none | escape-backslash | escape-double-char",
+        "How quotes are escaped.",
+    ),
+    opt(
+        "flags",
+        "escape-none, greedy, strip-whitespace, drop-invalid, ...",
+        "Flags changing how columns are split.",
+    ),
+    opt("null", "string", "Value that is stored as an empty column."),
+];
+
+const KV_PARSER_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "value-separator",
+        "character",
+        "Character between the key and the value.",
+    ),
+    opt("pair-separator", "string", "String between the key-value pairs."),
+    opt(
+        "extract-stray-words-into",
+        "string",
+        "Field to store words that are not key-value pairs in.",
+    ),
+];
+
+const JSON_PARSER_OPTIONS: &[OptionInfo] = &[
+    opt("marker", "string", "Text that precedes the JSON object in the message."),
+    opt(
+        "extract-prefix",
+        "string",
+        "Only extract the members below this JSON path.",
+    ),
+];
+
+const REGEXP_PARSER_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "patterns",
+        "regexp list",
+        "Regular expressions with named capture groups.",
+    ),
+    opt("flags", "string list", "Regular expression flags."),
+];
+
+const DATE_PARSER_OPTIONS: &[OptionInfo] = &[
+    opt("format", "strptime format", "Format of the date."),
+    opt("time-zone", "time zone", "Time zone of dates that do not carry one."),
+];
+
+const SYSLOG_PARSER_OPTIONS: &[OptionInfo] = &[opt(
+    "flags",
+    "no-hostname, syslog-protocol, ...",
+    "Flags changing how the message is parsed.",
+)];
+
+/// Scopes of name-value pairs `scope()` accepts.
+pub const VALUE_PAIRS_SCOPES: &[&str] = &[
+    "nv-pairs",
+    "dot-nv-pairs",
+    "all-nv-pairs",
+    "rfc3164",
+    "core",
+    "base",
+    "rfc5424",
+    "syslog-proto",
+    "all-macros",
+    "selected-macros",
+    "everything",
+    "none",
+];
+
+/// Renaming of keys by `rekey(pattern ...)`, applied in order.
+pub const REKEY_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "shift",
+        "number",
+        "Removes this many characters from the start of the key.",
+    ),
+    opt(
+        "shift-levels",
+        "number",
+        "Removes this many dot-separated levels from the start of the key.",
+    ),
+    opt("add-prefix", "string", "Adds a prefix to the key."),
+    opt(
+        "replace-prefix",
+        "old new",
+        "Replaces the prefix `old` of the key by `new`.",
+    ),
+];
+
+/// Selection of the name-value pairs a driver sends or a parser maps.
+const VALUE_PAIRS_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "scope",
+        "nv-pairs | dot-nv-pairs | all-nv-pairs | rfc3164 | core | base | rfc5424 | syslog-proto | all-macros | selected-macros | everything | none",
+        "Groups of name-value pairs to include.",
+    ),
+    opt("key", "glob pattern", "Includes the pairs whose names match."),
+    opt("exclude", "glob pattern", "Excludes the pairs whose names match."),
+    opt("pair", "name template", "Adds a pair with the value of a template."),
+    block(
+        "rekey",
+        "Renames the keys matching the glob patterns given first.",
+        REKEY_OPTIONS,
+    ),
+    opt("omit-empty-values", YES_NO, "Leaves out the pairs with empty values."),
+];
+
+const REWRITE_CONDITION: OptionInfo = opt(
+    "condition",
+    "filter expression",
+    "Only rewrite messages matching this filter.",
+);
+
+const REWRITE_COMMON: &[OptionInfo] = &[
+    opt("value", "field name", "Name of the field to rewrite."),
+    REWRITE_CONDITION,
+];
+
+const GROUPSET_OPTIONS: &[OptionInfo] = &[
+    opt("values", "glob pattern...", "Fields to set, all matching names."),
+    REWRITE_CONDITION,
+];
+
+const SUBST_OPTIONS: &[OptionInfo] = &[
+    opt("type", "string | pcre | glob", "Type of the search pattern."),
+    opt(
+        "flags",
+        "global | ignore-case | store-matches | substring | prefix | utf8 | newline | dupnames | nobackslash | disable-jit",
+        "Flags of the search pattern.",
+    ),
+];
+
+/// The `flags()` of `subst()`, in the order of the grammar.
+pub const SUBST_FLAGS: &[&str] = &[
+    "global",
+    "ignore-case",
+    "store-matches",
+    "substring",
+    "prefix",
+    "utf8",
+    "newline",
+    "dupnames",
+    "nobackslash",
+    "disable-jit",
+];
+
+pub static DRIVERS: &[DriverInfo] = &[
+    DriverInfo {
+        name: "file",
+        context: ObjectKind::Source,
+        description: "Reads messages from a file.",
+        options: &[FILE_SOURCE_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "wildcard-file",
+        context: ObjectKind::Source,
+        description: "Reads messages from every file matching a pattern.",
+        options: &[WILDCARD_FILE_OPTIONS, FILE_SOURCE_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "network",
+        context: ObjectKind::Source,
+        description: "Receives messages over the network using the legacy (RFC3164) or IETF syslog protocol.",
+        options: &[NETWORK_SOURCE_OPTIONS, NETWORK_COMMON, SOURCE_COMMON],
+        snippet: &["ip", "port"],
+        positional: &["ip", "port"],
+        since: None,
+    },
+    DriverInfo {
+        name: "syslog",
+        context: ObjectKind::Source,
+        description: "Receives messages using the IETF syslog protocol (RFC5424).",
+        options: &[NETWORK_SOURCE_OPTIONS, NETWORK_COMMON, SOURCE_COMMON],
+        snippet: &[],
+        positional: &["ip", "port"],
+        since: None,
+    },
+    DriverInfo {
+        name: "tcp",
+        context: ObjectKind::Source,
+        description: "Receives messages over TCP. Deprecated in favour of `network()`.",
+        options: &[NETWORK_SOURCE_OPTIONS, NETWORK_COMMON, SOURCE_COMMON],
+        snippet: &["ip", "port"],
+        positional: &["ip", "port"],
+        since: None,
+    },
+    DriverInfo {
+        name: "udp",
+        context: ObjectKind::Source,
+        description: "Receives messages over UDP. Deprecated in favour of `network()`.",
+        options: &[NETWORK_SOURCE_OPTIONS, NETWORK_COMMON, SOURCE_COMMON],
+        snippet: &["ip", "port"],
+        positional: &["ip", "port"],
+        since: None,
+    },
+    DriverInfo {
+        name: "unix-stream",
+        context: ObjectKind::Source,
+        description: "Receives messages from a stream-oriented UNIX domain socket.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "unix-dgram",
+        context: ObjectKind::Source,
+        description: "Receives messages from a datagram-oriented UNIX domain socket.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "system",
+        context: ObjectKind::Source,
+        description: "Collects the platform specific local log messages.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "systemd-journal",
+        context: ObjectKind::Source,
+        description: "Reads the systemd journal directly. Linux only.",
+        options: &[SYSTEMD_JOURNAL_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "systemd-syslog",
+        context: ObjectKind::Source,
+        description: "Receives the messages systemd forwards over its syslog socket. Linux only.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "openbsd",
+        context: ObjectKind::Source,
+        description:
+            "Reads the local log messages of OpenBSD from /dev/klog and the sendsyslog(2) interface. BSD only.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "sun-streams",
+        context: ObjectKind::Source,
+        description: "Reads the STREAMS log device of Solaris. Solaris only.",
+        options: &[SUN_STREAMS_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "internal",
+        context: ObjectKind::Source,
+        description: "Messages generated by syslog-ng itself.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "opentelemetry",
+        context: ObjectKind::Source,
+        description: "Receives logs, metrics and traces from OpenTelemetry senders over OTLP/gRPC.",
+        options: &[OTLP_SOURCE_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: Some((4, 3)),
+    },
+    DriverInfo {
+        name: "syslog-ng-otlp",
+        context: ObjectKind::Source,
+        description: "Receives messages forwarded by another syslog-ng with `syslog-ng-otlp()`.",
+        options: &[OTLP_SOURCE_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: Some((4, 4)),
+    },
+    DriverInfo {
+        name: "program",
+        context: ObjectKind::Source,
+        description: "Starts a program and reads messages from its standard output.",
+        options: &[PROGRAM_OPTIONS, SOURCE_COMMON],
+        snippet: &[],
+        positional: &["command"],
+        since: None,
+    },
+    DriverInfo {
+        name: "pipe",
+        context: ObjectKind::Source,
+        description: "Reads messages from a named pipe.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "stdin",
+        context: ObjectKind::Source,
+        description: "Reads messages from the standard input.",
+        options: &[SOURCE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "file",
+        context: ObjectKind::Destination,
+        description: "Writes messages to a file. The file name may contain macros.",
+        options: &[FILE_DESTINATION_OPTIONS, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "network",
+        context: ObjectKind::Destination,
+        description: "Sends messages over the network using the legacy (RFC3164) syslog protocol.",
+        options: &[NETWORK_DESTINATION_OPTIONS, NETWORK_COMMON, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["host"],
+        since: None,
+    },
+    DriverInfo {
+        name: "syslog",
+        context: ObjectKind::Destination,
+        description: "Sends messages using the IETF syslog protocol (RFC5424).",
+        options: &[NETWORK_DESTINATION_OPTIONS, NETWORK_COMMON, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["host"],
+        since: None,
+    },
+    DriverInfo {
+        name: "tcp",
+        context: ObjectKind::Destination,
+        description: "Sends messages over TCP. Deprecated in favour of `network()`.",
+        options: &[NETWORK_DESTINATION_OPTIONS, NETWORK_COMMON, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["host"],
+        since: None,
+    },
+    DriverInfo {
+        name: "udp",
+        context: ObjectKind::Destination,
+        description: "Sends messages over UDP. Deprecated in favour of `network()`.",
+        options: &[NETWORK_DESTINATION_OPTIONS, NETWORK_COMMON, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["host"],
+        since: None,
+    },
+    DriverInfo {
+        name: "unix-stream",
+        context: ObjectKind::Destination,
+        description: "Sends messages to a stream-oriented UNIX domain socket.",
+        options: &[DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "unix-dgram",
+        context: ObjectKind::Destination,
+        description: "Sends messages to a datagram-oriented UNIX domain socket.",
+        options: &[DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "program",
+        context: ObjectKind::Destination,
+        description: "Starts a program and writes messages to its standard input.",
+        options: &[PROGRAM_OPTIONS, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["command"],
+        since: None,
+    },
+    DriverInfo {
+        name: "pipe",
+        context: ObjectKind::Destination,
+        description: "Writes messages to a named pipe.",
+        options: &[DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["path"],
+        since: None,
+    },
+    DriverInfo {
+        name: "usertty",
+        context: ObjectKind::Destination,
+        description: "Writes messages to the terminal of a logged-in user.",
+        options: &[DESTINATION_COMMON],
+        snippet: &[],
+        positional: &["user"],
+        since: None,
+    },
+    DriverInfo {
+        name: "http",
+        context: ObjectKind::Destination,
+        description: "Sends messages to an HTTP server.",
+        options: &[HTTP_OPTIONS, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "elasticsearch-http",
+        context: ObjectKind::Destination,
+        description: "Sends messages to Elasticsearch through its HTTP bulk API.",
+        options: &[HTTP_OPTIONS, DESTINATION_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "opentelemetry",
+        context: ObjectKind::Destination,
+        description: "Sends logs to an OpenTelemetry collector over OTLP/gRPC.",
+        options: &[OTLP_DESTINATION_OPTIONS, DESTINATION_COMMON],
+        snippet: &["url"],
+        positional: &[],
+        since: Some((4, 3)),
+    },
+    DriverInfo {
+        name: "syslog-ng-otlp",
+        context: ObjectKind::Destination,
+        description: "Forwards messages to another syslog-ng over OTLP/gRPC, keeping all their name-value pairs.",
+        options: &[OTLP_DESTINATION_OPTIONS, DESTINATION_COMMON],
+        snippet: &["url"],
+        positional: &[],
+        since: Some((4, 4)),
+    },
+    DriverInfo {
+        name: "loki",
+        context: ObjectKind::Destination,
+        description: "Sends messages to Grafana Loki over gRPC.",
+        options: &[LOKI_OPTIONS, DESTINATION_COMMON],
+        snippet: &["url"],
+        positional: &[],
+        since: Some((4, 4)),
+    },
+    DriverInfo {
+        name: "csv-parser",
+        context: ObjectKind::Parser,
+        description: "Splits the message into columns.",
+        options: &[CSV_PARSER_OPTIONS, PARSER_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "kv-parser",
+        context: ObjectKind::Parser,
+        description: "Extracts `key=value` pairs from the message.",
+        options: &[KV_PARSER_OPTIONS, PARSER_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "json-parser",
+        context: ObjectKind::Parser,
+        description: "Extracts the members of a JSON object into fields.",
+        options: &[JSON_PARSER_OPTIONS, PARSER_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "regexp-parser",
+        context: ObjectKind::Parser,
+        description: "Extracts fields using named capture groups of regular expressions.",
+        options: &[REGEXP_PARSER_OPTIONS, PARSER_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "date-parser",
+        context: ObjectKind::Parser,
+        description: "Parses a date and uses it as the timestamp of the message.",
+        options: &[DATE_PARSER_OPTIONS, PARSER_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "syslog-parser",
+        context: ObjectKind::Parser,
+        description: "Parses the message as a syslog message.",
+        options: &[SYSLOG_PARSER_OPTIONS, PARSER_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "map-value-pairs",
+        context: ObjectKind::Parser,
+        description: "Copies the selected name-value pairs of the message to new names.",
+        options: &[VALUE_PAIRS_OPTIONS],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "set",
+        context: ObjectKind::Rewrite,
+        description: "Sets a field of the message to a template.",
+        options: &[REWRITE_COMMON],
+        snippet: &[],
+        positional: &["value"],
+        since: None,
+    },
+    DriverInfo {
+        name: "subst",
+        context: ObjectKind::Rewrite,
+        description: "Replaces text matching a pattern in a field of the message.",
+        options: &[SUBST_OPTIONS, REWRITE_COMMON],
+        snippet: &[],
+        positional: &["pattern", "replacement"],
+        since: None,
+    },
+    DriverInfo {
+        name: "unset",
+        context: ObjectKind::Rewrite,
+        description: "Removes a field from the message.",
+        options: &[REWRITE_COMMON],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "rename",
+        context: ObjectKind::Rewrite,
+        description: "Renames a field of the message.",
+        options: &[&[REWRITE_CONDITION]],
+        snippet: &[],
+        positional: &["old name", "new name"],
+        since: Some((3, 21)),
+    },
+    DriverInfo {
+        name: "groupset",
+        context: ObjectKind::Rewrite,
+        description: "Sets every field matching `values()` to a template, in which `$_` is the current value.",
+        options: &[GROUPSET_OPTIONS],
+        snippet: &[],
+        positional: &["value"],
+        since: None,
+    },
+    DriverInfo {
+        name: "set-tag",
+        context: ObjectKind::Rewrite,
+        description: "Adds a tag to the message.",
+        options: &[],
+        snippet: &[],
+        positional: &["tag"],
+        since: None,
+    },
+    DriverInfo {
+        name: "clear-tag",
+        context: ObjectKind::Rewrite,
+        description: "Removes a tag from the message.",
+        options: &[],
+        snippet: &[],
+        positional: &["tag"],
+        since: None,
+    },
+    DriverInfo {
+        name: "template",
+        context: ObjectKind::Template,
+        description: "The text of the template, with macros such as `$MSG`.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "template-escape",
+        context: ObjectKind::Template,
+        description: "Escape quote characters in macro values.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "source",
+        context: ObjectKind::Log,
+        description: "Messages of a source object enter the log path.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "filter",
+        context: ObjectKind::Log,
+        description: "Only messages matching the filter continue along the log path.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "parser",
+        context: ObjectKind::Log,
+        description: "Messages are run through a parser object.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "rewrite",
+        context: ObjectKind::Log,
+        description: "Messages are modified by a rewrite rule.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "destination",
+        context: ObjectKind::Log,
+        description: "Messages are sent to a destination object.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+    DriverInfo {
+        name: "flags",
+        context: ObjectKind::Log,
+        description: "Flags of the log path: `final`, `fallback`, `catchall`, `flow-control`, `drop-unmatched`.",
+        options: &[],
+        snippet: &[],
+        positional: &[],
+        since: None,
+    },
+];
+
+/// Options accepted in the global `options { ... };` block.
+pub static GLOBAL_OPTIONS: &[OptionInfo] = &[
+    opt(
+        "chain-hostnames",
+        YES_NO,
+        "Append the names of the relaying hosts to the host name.",
+    ),
+    opt("keep-hostname", YES_NO, "Keep the host name found in the message."),
+    opt(
+        "keep-timestamp",
+        YES_NO,
+        "Use the timestamp found in the message instead of the receive time.",
+    ),
+    opt(
+        "use-dns",
+        "yes | no | persist_only",
+        "Resolve the address of the sender to a host name.",
+    ),
+    opt("use-fqdn", YES_NO, "Use fully qualified domain names."),
+    opt("dns-cache", YES_NO, "Cache the results of DNS lookups."),
+    opt("normalize-hostnames", YES_NO, "Convert host names to lowercase."),
+    opt(
+        "create-dirs",
+        YES_NO,
+        "Create missing directories of destination files.",
+    ),
+    opt("user", "user", "User syslog-ng switches to after start-up."),
+    opt("owner", "user", "Owner of the created files."),
+    opt("group", "group", "Group of the created files."),
+    opt("perm", "octal", "Permissions of the created files."),
+    opt("dir-perm", "octal", "Permissions of the created directories."),
+    opt(
+        "flush-lines",
+        "number",
+        "Number of lines written to destinations in a single batch.",
+    )
+    .with_unit(Unit::Lines),
+    opt(
+        "log-fifo-size",
+        "number",
+        "Default size of the output queue of destinations.",
+    )
+    .with_unit(Unit::Messages),
+    opt("log-msg-size", "bytes", "Maximum length of a message."),
+    opt("mark-freq", "seconds", "Interval of the MARK messages."),
+    opt(
+        "stats-freq",
+        "seconds",
+        "Interval of the statistics messages, 0 disables them.",
+    ),
+    opt("stats-level", "0 | 1 | 2 | 3", "Detail of the collected statistics."),
+    opt(
+        "time-reopen",
+        "seconds",
+        "Time to wait before reconnecting to a failed destination.",
+    ),
+    opt("time-reap", "seconds", "Close idle destination files after this time."),
+    opt(
+        "ts-format",
+        "rfc3164 | bsd | rfc3339 | iso | unix",
+        "Format of the timestamps.",
+    ),
+    opt(
+        "frac-digits",
+        "number",
+        "Number of digits of fractional seconds in timestamps.",
+    ),
+    opt(
+        "recv-time-zone",
+        "time zone",
+        "Time zone of received timestamps that do not carry one.",
+    ),
+    opt(
+        "send-time-zone",
+        "time zone",
+        "Time zone to convert sent timestamps to.",
+    ),
+    opt("threaded", YES_NO, "Use multiple threads for processing."),
+    opt("bad-hostname", "regexp", "Host names matching this are not accepted."),
+    opt("check-hostname", YES_NO, "Reject messages whose host name contains invalid characters."),
+    opt("custom-domain", "string", "Domain appended to short host names with `use-fqdn(yes)`."),
+    opt("dns-cache-size", "number", "Number of host names kept in the DNS cache."),
+    opt("dns-cache-expire", "seconds", "Time a successful DNS lookup is cached."),
+    opt("dns-cache-expire-failed", "seconds", "Time a failed DNS lookup is cached."),
+    opt("dns-cache-hosts", "path", "File of static host name entries for the DNS cache."),
+    opt("dir-owner", "user", "Owner of the created directories."),
+    opt("dir-group", "group", "Group of the created directories."),
+    opt("file-template", "template", "Default template of file destinations."),
+    opt("proto-template", "template", "Default template of network destinations."),
+    opt(
+        "mark-mode",
+        "internal | dst-idle | host-idle | periodical | none | global",
+        "When destinations send MARK messages.",
+    ),
+    opt(
+        "log-fetch-limit",
+        "number",
+        "Messages fetched from a source in a single poll.",
+    )
+    .with_unit(Unit::Messages),
+    opt(
+        "log-iw-size",
+        "number",
+        "Initial window size of sources, for flow control.",
+    )
+    .with_unit(Unit::Messages),
+    opt("use-uniqid", YES_NO, "Give every message a unique ID, the `UNIQID` macro."),
+    opt("use-rcptid", YES_NO, "Number received messages, the `RCPTID` macro."),
+    opt("suppress", "seconds", "Replace repeated messages by a count after this time."),
+    opt("time-sleep", "milliseconds", "Time to wait between polls of the sources."),
+    opt("pass-unix-credentials", YES_NO, "Read the credentials of processes sending to UNIX sockets."),
+    opt("trim-large-messages", YES_NO, "Truncate messages longer than `log-msg-size()` instead of splitting them."),
+    opt(
+        "on-error",
+        "drop-message | drop-property | fallback-to-string | silently-drop-message | silently-drop-property | silently-fallback-to-string",
+        "What to do when a value cannot be converted to its type.",
+    ),
+    block(
+        "stats",
+        "Settings of the statistics counters.",
+        &[
+            opt("freq", "seconds", "Interval of the statistics messages, 0 disables them."),
+            opt("level", "0 | 1 | 2 | 3", "Detail of the collected statistics."),
+            opt("lifetime", "seconds", "Time dynamic counters are kept without updates."),
+            opt("max-dynamics", "number", "Maximum number of dynamic counters."),
+        ],
+    ),
+];
+
+/// Functions usable in filter expressions.
+pub static FILTER_FUNCTIONS: &[OptionInfo] = &[
+    opt("facility", "facility list", "Matches messages of the given facilities."),
+    opt(
+        "level",
+        "priority range",
+        "Matches messages of the given priorities, e.g. `warning..emerg`.",
+    ),
+    opt("priority", "priority range", "Same as `level()`."),
+    opt("program", "regexp", "Matches the PROGRAM part of the message."),
+    opt("host", "regexp", "Matches the HOST part of the message."),
+    opt("message", "regexp", "Matches the text of the message."),
+    opt(
+        "match",
+        "regexp",
+        "Matches the message, or the field given in `value()`.",
+    ),
+    opt("netmask", "ip/mask", "Matches messages sent from an IPv4 network."),
+    opt("netmask6", "ip/prefix", "Matches messages sent from an IPv6 network."),
+    opt("tags", "string", "Matches messages carrying a tag."),
+    opt("filter", "filter id", "Includes another filter object."),
+    opt("source", "source id", "Matches messages of a source object."),
+    opt(
+        "in-list",
+        "path",
+        "Matches if the value of a field is listed in a file.",
+    ),
+];
+
+/// An option syslog-ng accepts on a driver but then ignores, because of the
+/// driver or of its transport.
+#[derive(Debug)]
+pub struct IgnoredOption {
+    pub contexts: &'static [ObjectKind],
+    pub drivers: &'static [&'static str],
+    /// The rule only applies with one of these transports; empty for any.
+    pub transports: &'static [&'static str],
+    pub option: &'static str,
+    pub reason: &'static str,
+}
+
+/// Where the options are documented.
+pub const ADMIN_GUIDE_URL: &str = "https://syslog-ng.github.io/admin-guide/";
+
+const SOURCE_AND_DESTINATION: &[ObjectKind] = &[ObjectKind::Source, ObjectKind::Destination];
+const NETWORK_DRIVERS: &[&str] = &["network", "syslog", "tcp", "tcp6", "udp", "udp6"];
+
+pub static IGNORED_OPTIONS: &[IgnoredOption] = &[
+    IgnoredOption {
+        contexts: &[ObjectKind::Source],
+        drivers: NETWORK_DRIVERS,
+        transports: &["udp"],
+        option: "max-connections",
+        reason: "UDP has no connections, so there is nothing to limit",
+    },
+    IgnoredOption {
+        contexts: &[ObjectKind::Source],
+        drivers: &["unix-dgram"],
+        transports: &[],
+        option: "max-connections",
+        reason: "datagram sockets have no connections, so there is nothing to limit",
+    },
+    IgnoredOption {
+        contexts: SOURCE_AND_DESTINATION,
+        drivers: NETWORK_DRIVERS,
+        transports: &["udp"],
+        option: "so-keepalive",
+        reason: "TCP keep-alive messages are not sent over UDP",
+    },
+    IgnoredOption {
+        contexts: SOURCE_AND_DESTINATION,
+        drivers: NETWORK_DRIVERS,
+        transports: &["tcp", "udp"],
+        option: "tls",
+        reason: "TLS settings only take effect with `transport(tls)`; this connection is not encrypted",
+    },
+    IgnoredOption {
+        contexts: &[ObjectKind::Destination],
+        drivers: &["http"],
+        transports: &[],
+        option: "flush-lines",
+        reason: "http() batches messages with `batch-lines()` instead",
+    },
+];
+
+/// The transport a network driver uses when `transport()` is not set.
+pub fn default_transport(driver: &str) -> Option<&'static str> {
+    match normalize_name(driver).as_str() {
+        "network" | "syslog" | "tcp" | "tcp6" => Some("tcp"),
+        "udp" | "udp6" => Some("udp"),
+        _ => None,
+    }
+}
+
+/// The `transport()` of `network()` standing in for the deprecated network
+/// driver `driver`, and whether that is the IPv6 variant.
+pub fn deprecated_network_driver(driver: &str) -> Option<(&'static str, bool)> {
+    match normalize_name(driver).as_str() {
+        "tcp" => Some(("tcp", false)),
+        "udp" => Some(("udp", false)),
+        "tcp6" => Some(("tcp", true)),
+        "udp6" => Some(("udp", true)),
+        _ => None,
+    }
+}
+
+/// A variable syslog-ng defines itself, usable as `` `name` `` without a
+/// matching `@define`.
+#[derive(Debug, PartialEq)]
+pub struct BuiltinDefine {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+pub static BUILTIN_DEFINES: &[BuiltinDefine] = &[
+    BuiltinDefine {
+        name: "syslog-ng-root",
+        description: "Installation prefix of syslog-ng.",
+    },
+    BuiltinDefine {
+        name: "syslog-ng-data",
+        description: "Directory of the data files shipped with syslog-ng.",
+    },
+    BuiltinDefine {
+        name: "syslog-ng-include",
+        description: "Directory of the configuration snippets shipped with syslog-ng.",
+    },
+    BuiltinDefine {
+        name: "syslog-ng-sysconfdir",
+        description: "Directory holding the main configuration file.",
+    },
+    BuiltinDefine {
+        name: "scl-root",
+        description: "Directory of the syslog-ng configuration library (SCL).",
+    },
+    BuiltinDefine {
+        name: "module-path",
+        description: "Directories searched for plugin modules.",
+    },
+    BuiltinDefine {
+        name: "module-install-dir",
+        description: "Directory the plugin modules were installed to.",
+    },
+    BuiltinDefine {
+        name: "include-path",
+        description: "Directories searched for relative `@include` paths.",
+    },
+    BuiltinDefine {
+        name: "autoload-compiled-modules",
+        description: "Whether plugin modules are loaded automatically when used.",
+    },
+];
+
+pub fn find_builtin_define(name: &str) -> Option<&'static BuiltinDefine> {
+    BUILTIN_DEFINES.iter().find(|d| d.name == name)
+}
+
+/// A syslog-ng 4.x type hint, such as `int` in `set(int("$PID") value("pid"))`
+/// or in `$(format-json pid=int($PID))`.
+#[derive(Debug, PartialEq)]
+pub struct TypeHint {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// The configuration version type hints need.
+pub const TYPE_HINTS_SINCE: (u32, u32) = (4, 0);
+
+pub static TYPE_HINTS: &[TypeHint] = &[
+    TypeHint {
+        name: "string",
+        description: "A string; the type of untyped values.",
+    },
+    TypeHint {
+        name: "literal",
+        description: "A string embedded verbatim, e.g. a JSON fragment formatted elsewhere.",
+    },
+    TypeHint {
+        name: "boolean",
+        description: "`true` or `false`.",
+    },
+    TypeHint {
+        name: "int",
+        description: "A 64-bit signed integer.",
+    },
+    TypeHint {
+        name: "int32",
+        description: "A 32-bit signed integer.",
+    },
+    TypeHint {
+        name: "int64",
+        description: "A 64-bit signed integer.",
+    },
+    TypeHint {
+        name: "double",
+        description: "A floating point number.",
+    },
+    TypeHint {
+        name: "float",
+        description: "A floating point number; the same as `double`.",
+    },
+    TypeHint {
+        name: "datetime",
+        description: "A timestamp, in seconds since the epoch with an optional fraction.",
+    },
+    TypeHint {
+        name: "list",
+        description: "A list, written as comma separated values.",
+    },
+    TypeHint {
+        name: "json",
+        description: "A JSON value.",
+    },
+    TypeHint {
+        name: "null",
+        description: "No value.",
+    },
+    TypeHint {
+        name: "bytes",
+        description: "Binary data.",
+    },
+    TypeHint {
+        name: "protobuf",
+        description: "A serialized protobuf message.",
+    },
+];
+
+pub fn find_type_hint(name: &str) -> Option<&'static TypeHint> {
+    TYPE_HINTS.iter().find(|h| h.name == name)
+}
+
+/// A built-in macro of templates, such as `HOST` in `"$HOST $MSG"`.
+#[derive(Debug, PartialEq)]
+pub struct MacroInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// A part of the date of the message, which also comes prefixed: `R_`
+    /// for the time of receipt, `S_` for the time in the message, `C_` for
+    /// the current time and `P_` for the time the message was processed.
+    pub date: bool,
+}
+
+pub const DATE_MACRO_PREFIXES: &[&str] = &["R_", "S_", "C_", "P_"];
+
+pub static MACROS: &[MacroInfo] = &[
+    MacroInfo {
+        name: "HOST",
+        description: "Name of the host that sent the message, or of the last hop with `keep-hostname(no)`.",
+        date: false,
+    },
+    MacroInfo {
+        name: "HOST_FROM",
+        description: "Name of the host the message was received from.",
+        date: false,
+    },
+    MacroInfo {
+        name: "FULLHOST",
+        description: "Fully qualified name of the host that sent the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "FULLHOST_FROM",
+        description: "Fully qualified name of the host the message was received from.",
+        date: false,
+    },
+    MacroInfo {
+        name: "SOURCEIP",
+        description: "IP address of the host the message was received from.",
+        date: false,
+    },
+    MacroInfo {
+        name: "DESTIP",
+        description: "Local IP address the message was received on.",
+        date: false,
+    },
+    MacroInfo {
+        name: "DESTPORT",
+        description: "Local port the message was received on.",
+        date: false,
+    },
+    MacroInfo {
+        name: "LOGHOST",
+        description: "Name of the host running syslog-ng.",
+        date: false,
+    },
+    MacroInfo {
+        name: "PROGRAM",
+        description: "Name of the program that sent the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "PID",
+        description: "Process ID of the program that sent the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "MSGID",
+        description: "The MSGID field of RFC5424 messages.",
+        date: false,
+    },
+    MacroInfo {
+        name: "MSG",
+        description: "Text of the message, without the header.",
+        date: false,
+    },
+    MacroInfo {
+        name: "MESSAGE",
+        description: "Text of the message; the same as `MSG`.",
+        date: false,
+    },
+    MacroInfo {
+        name: "MSGHDR",
+        description: "The `PROGRAM[PID]: ` header of the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "LEGACY_MSGHDR",
+        description: "The header of the message as received.",
+        date: false,
+    },
+    MacroInfo {
+        name: "RAWMSG",
+        description: "The message as received, when the source has `store-raw-message` in its flags.",
+        date: false,
+    },
+    MacroInfo {
+        name: "FACILITY",
+        description: "Facility of the message by name, e.g. `mail`.",
+        date: false,
+    },
+    MacroInfo {
+        name: "FACILITY_NUM",
+        description: "Facility of the message as a number.",
+        date: false,
+    },
+    MacroInfo {
+        name: "LEVEL",
+        description: "Severity of the message by name, e.g. `err`.",
+        date: false,
+    },
+    MacroInfo {
+        name: "LEVEL_NUM",
+        description: "Severity of the message as a number.",
+        date: false,
+    },
+    MacroInfo {
+        name: "PRIORITY",
+        description: "Severity of the message by name; the same as `LEVEL`.",
+        date: false,
+    },
+    MacroInfo {
+        name: "PRI",
+        description: "Priority of the message: facility times 8 plus severity.",
+        date: false,
+    },
+    MacroInfo {
+        name: "TAG",
+        description: "Priority and facility as a hexadecimal number.",
+        date: false,
+    },
+    MacroInfo {
+        name: "BSDTAG",
+        description: "Priority and facility as a number and a letter.",
+        date: false,
+    },
+    MacroInfo {
+        name: "TAGS",
+        description: "Comma separated tags of the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "SDATA",
+        description: "Structured data of RFC5424 messages, formatted as in the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "SEQNUM",
+        description: "Sequence number of the message on the local host.",
+        date: false,
+    },
+    MacroInfo {
+        name: "RCPTID",
+        description: "Unique ID of the message, when `use-uniqid(yes)` is set.",
+        date: false,
+    },
+    MacroInfo {
+        name: "UNIQID",
+        description: "Unique ID of the message: the host ID and the receipt ID.",
+        date: false,
+    },
+    MacroInfo {
+        name: "HOSTID",
+        description: "ID of the host running syslog-ng, set at its first start.",
+        date: false,
+    },
+    MacroInfo {
+        name: "SYSUPTIME",
+        description: "Time since syslog-ng started, in hundredths of a second.",
+        date: false,
+    },
+    MacroInfo {
+        name: "TRANSPORT",
+        description: "Transport and protocol the message was received over.",
+        date: false,
+    },
+    MacroInfo {
+        name: "CONTEXT_ID",
+        description: "ID of the correlation context of the message.",
+        date: false,
+    },
+    MacroInfo {
+        name: "DATE",
+        description: "Date of the message, `Jun 13 15:58:00`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "FULLDATE",
+        description: "Date of the message with the year, `2006 Jun 13 15:58:00`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "ISODATE",
+        description: "Date of the message in ISO 8601 format, `2006-06-13T15:58:00.123+01:00`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "STAMP",
+        description: "Date of the message in the format of `ts-format()`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "UNIXTIME",
+        description: "Date of the message in seconds since the epoch.",
+        date: true,
+    },
+    MacroInfo {
+        name: "YEAR",
+        description: "Four digit year.",
+        date: true,
+    },
+    MacroInfo {
+        name: "MONTH",
+        description: "Two digit month.",
+        date: true,
+    },
+    MacroInfo {
+        name: "MONTH_ABBREV",
+        description: "Abbreviated name of the month, `Jun`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "MONTH_NAME",
+        description: "Name of the month, `June`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "MONTH_WEEK",
+        description: "Week of the month, from 0.",
+        date: true,
+    },
+    MacroInfo {
+        name: "DAY",
+        description: "Two digit day of the month.",
+        date: true,
+    },
+    MacroInfo {
+        name: "YEAR_DAY",
+        description: "Day of the year, from 001.",
+        date: true,
+    },
+    MacroInfo {
+        name: "WEEK",
+        description: "Week of the year, from 00.",
+        date: true,
+    },
+    MacroInfo {
+        name: "WEEK_DAY",
+        description: "Day of the week as a number, from 1 to 7.",
+        date: true,
+    },
+    MacroInfo {
+        name: "WEEK_DAY_ABBREV",
+        description: "Abbreviated name of the day of the week, `Tue`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "WEEK_DAY_NAME",
+        description: "Name of the day of the week, `Tuesday`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "WEEKDAY",
+        description: "Abbreviated name of the day of the week; the same as `WEEK_DAY_ABBREV`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "HOUR",
+        description: "Two digit hour in 24 hour format.",
+        date: true,
+    },
+    MacroInfo {
+        name: "HOUR12",
+        description: "Two digit hour in 12 hour format.",
+        date: true,
+    },
+    MacroInfo {
+        name: "AMPM",
+        description: "`AM` or `PM`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "MIN",
+        description: "Two digit minute.",
+        date: true,
+    },
+    MacroInfo {
+        name: "SEC",
+        description: "Two digit second.",
+        date: true,
+    },
+    MacroInfo {
+        name: "MSEC",
+        description: "Milliseconds.",
+        date: true,
+    },
+    MacroInfo {
+        name: "USEC",
+        description: "Microseconds.",
+        date: true,
+    },
+    MacroInfo {
+        name: "TZ",
+        description: "Time zone offset of the date, `+01:00`.",
+        date: true,
+    },
+    MacroInfo {
+        name: "TZOFFSET",
+        description: "Time zone offset of the date; the same as `TZ`.",
+        date: true,
+    },
+    MacroInfo {
+        name: ".SDATA.meta.sequenceId",
+        description: "Sequence number from the `meta` structured data of RFC5424 messages.",
+        date: false,
+    },
+    MacroInfo {
+        name: ".SDATA.meta.sysUpTime",
+        description: "Uptime of the sender from the `meta` structured data.",
+        date: false,
+    },
+    MacroInfo {
+        name: ".SDATA.meta.language",
+        description: "Language of the message from the `meta` structured data.",
+        date: false,
+    },
+    MacroInfo {
+        name: ".SDATA.origin.ip",
+        description: "IP address of the originator from the `origin` structured data.",
+        date: false,
+    },
+    MacroInfo {
+        name: ".SDATA.origin.software",
+        description: "Software of the originator from the `origin` structured data.",
+        date: false,
+    },
+    MacroInfo {
+        name: ".SDATA.timeQuality.tzKnown",
+        description: "Whether the sender knows its time zone, from the `timeQuality` structured data.",
+        date: false,
+    },
+    MacroInfo {
+        name: ".SDATA.timeQuality.isSynced",
+        description: "Whether the clock of the sender is synchronized, from the `timeQuality` structured data.",
+        date: false,
+    },
+];
+
+/// The built-in macro `name`, with or without the prefix of a date macro.
+pub fn find_macro(name: &str) -> Option<&'static MacroInfo> {
+    MACROS.iter().find(|m| m.name == name).or_else(|| {
+        let unprefixed = DATE_MACRO_PREFIXES.iter().find_map(|p| name.strip_prefix(p))?;
+        MACROS.iter().find(|m| m.date && m.name == unprefixed)
+    })
+}
+
+/// A keyword of the `if (filter) { ... } elif (filter) { ... } else { ... };`
+/// and `junction { channel { ... }; ... };` blocks of log paths, which
+/// split them into branches.
+#[derive(Debug, PartialEq)]
+pub struct BranchKeyword {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// The configuration version `if` blocks in log paths need.
+pub const CONDITIONALS_SINCE: (u32, u32) = (3, 23);
+
+pub static BRANCH_KEYWORDS: &[BranchKeyword] = &[
+    BranchKeyword {
+        name: "if",
+        description: "Messages matching the filter expression continue in this branch.",
+    },
+    BranchKeyword {
+        name: "elif",
+        description: "Messages no earlier branch took that match the filter expression continue in this branch.",
+    },
+    BranchKeyword {
+        name: "else",
+        description: "Messages no earlier branch took continue in this branch.",
+    },
+    BranchKeyword {
+        name: "junction",
+        description: "Every message continues in each of the channels of the junction.",
+    },
+    BranchKeyword {
+        name: "channel",
+        description: "A branch of a junction, a log path of its own that can set `flags()`.",
+    },
+];
+
+pub fn find_branch_keyword(name: &str) -> Option<&'static BranchKeyword> {
+    BRANCH_KEYWORDS.iter().find(|k| k.name == name)
+}
+
+/// A flag of a log path, `flags(flow-control final)`, also given to the
+/// channels of junctions. `since` is the configuration version a flag
+/// needs, `None` for those every supported version has.
+#[derive(Debug, PartialEq)]
+pub struct LogFlag {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub since: Option<(u32, u32)>,
+}
+
+pub static LOG_FLAGS: &[LogFlag] = &[
+    LogFlag {
+        name: "final",
+        description: "Messages this log path processed are not passed to the log paths after it. \
+            In a channel of a junction, the later channels of the junction do not get them either.",
+        since: None,
+    },
+    LogFlag {
+        name: "fallback",
+        description: "The log path only processes messages no log path without this flag processed, \
+            whatever their order in the configuration.",
+        since: None,
+    },
+    LogFlag {
+        name: "catchall",
+        description: "The log path processes messages of every source, its own `source()` statements \
+            are ignored. Only meaningful for top-level log paths.",
+        since: None,
+    },
+    LogFlag {
+        name: "flow-control",
+        description: "Sources stop reading when a destination of the log path is full instead of \
+            dropping messages; the destinations then need `log-fifo-size()` to be large enough \
+            for the window of every source feeding them.",
+        since: None,
+    },
+    LogFlag {
+        name: "drop-unmatched",
+        description: "Messages the filters of the log path do not match are dropped instead of \
+            being passed on to the log paths after it.",
+        since: None,
+    },
+];
+
+/// The flag `name`, also written `catch-all` for `catchall`.
+pub fn find_log_flag(name: &str) -> Option<&'static LogFlag> {
+    let name = match normalize_name(name).as_str() {
+        "catch-all" => "catchall".to_string(),
+        name => name.to_string(),
+    };
+    LOG_FLAGS.iter().find(|f| f.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knows_the_platforms_of_platform_specific_drivers() {
+        let journal = find_driver(ObjectKind::Source, "systemd-journal").unwrap();
+        assert_eq!(journal.platforms(), [Platform::Linux]);
+        assert!(journal.available_on(None));
+        assert!(journal.available_on(Some(Platform::Linux)));
+        assert!(!journal.available_on(Some(Platform::Bsd)));
+        let network = find_driver(ObjectKind::Source, "network").unwrap();
+        assert!(network.platforms().is_empty());
+        assert!(network.available_on(Some(Platform::Solaris)));
+    }
+
+    #[test]
+    fn reads_platform_names() {
+        for platform in [Platform::Linux, Platform::Bsd, Platform::Solaris] {
+            assert_eq!(Platform::from_name(platform.name()), Some(platform));
+        }
+        assert_eq!(Platform::from_name("windows"), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! `textDocument/hover` contents built from the grammar database.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::configuration::Snippet;
+use crate::grammar::{self, DriverInfo, OptionInfo, TypeHint};
+use crate::language_types::annotations::{Annotation, IncludeAnnotation};
+use crate::language_types::objects::{Driver, Literal, ObjectKind};
+use crate::language_types::{Element, ParsedConfiguration};
+use crate::lsp_types::{Hover, Position, Range};
+use crate::symbols::display_name;
+
+pub fn hover(parsed: &ParsedConfiguration, position: Position) -> Option<Hover> {
+    let Some(element) = parsed.element_at(position) else {
+        return flag_hover(parsed, position).or_else(|| unit_hover(parsed, position));
+    };
+    match element {
+        Element::FilterFunction(token) => {
+            let function = grammar::find_filter_function(&token.text)?;
+            Some(Hover {
+                contents: describe_option(function, "Filter function."),
+                range: Some(token.range),
+            })
+        }
+        Element::Call { context, calls } => {
+            let last = calls.last()?;
+            let contents = describe_call(context, &calls).or_else(|| {
+                // `int` in `set(int("$PID") value("pid"))`
+                let hint = grammar::find_type_hint(&last.name.name).filter(|_| calls.len() > 1)?;
+                Some(describe_type_hint(hint))
+            })?;
+            Some(Hover {
+                contents,
+                range: Some(last.name.range),
+            })
+        }
+    }
+}
+
+fn describe_call(context: ObjectKind, calls: &[&Driver]) -> Option<String> {
+    if context == ObjectKind::Options {
+        let option = resolve_nested(grammar::find_global_option(&calls[0].name.name)?, &calls[1..])?;
+        return Some(describe_option(option, &global_option_footer(&names(calls))));
+    }
+    let driver = grammar::find_driver(context, &calls[0].name.name)?;
+    Some(match &calls[1..] {
+        [] => describe_driver(driver),
+        [first, rest @ ..] => {
+            let option = resolve_nested(driver.find_option(&first.name.name)?, rest)?;
+            describe_option(option, &option_footer(driver, &names(calls)))
+        }
+    })
+}
+
+fn describe_type_hint(hint: &TypeHint) -> String {
+    let (major, minor) = grammar::TYPE_HINTS_SINCE;
+    format!(
+        "```\n{}(value)\n```\n{}\n\n*Type hint, syslog-ng {}.{} and later.*",
+        hint.name, hint.description, major, minor
+    )
+}
+
+/// How syslog-ng reads a number passed to a time or size option, e.g.
+/// "60 seconds (1 minute)" for `time-reopen(60)`.
+fn unit_hover(parsed: &ParsedConfiguration, position: Position) -> Option<Hover> {
+    let (context, calls, literal) = parsed.value_at(position)?;
+    let option = if context == ObjectKind::Options {
+        resolve_nested(grammar::find_global_option(&calls[0].name.name)?, &calls[1..])?
+    } else {
+        let driver = grammar::find_driver(context, &calls[0].name.name)?;
+        let (first, rest) = calls[1..].split_first()?;
+        resolve_nested(driver.find_option(&first.name.name)?, rest)?
+    };
+    let meaning = option.unit()?.interpret(&literal.value)?;
+    Some(Hover {
+        contents: format!("`{}({})`: {}", option.name, literal.value, meaning),
+        range: Some(literal.range),
+    })
+}
+
+/// What a flag in `flags()` of a log path does.
+fn flag_hover(parsed: &ParsedConfiguration, position: Position) -> Option<Hover> {
+    let (context, calls, literal) = parsed.value_at(position)?;
+    if context != ObjectKind::Log || calls.len() != 1 || grammar::normalize_name(&calls[0].name.name) != "flags" {
+        return None;
+    }
+    let flag = grammar::find_log_flag(&literal.value)?;
+    let since = match flag.since {
+        Some((major, minor)) => format!("\n\nNeeds `@version: {}.{}` or later.", major, minor),
+        None => String::new(),
+    };
+    Some(Hover {
+        contents: format!(
+            "```\nflags({})\n```\n{}{}\n\n*Log path flag.*",
+            flag.name, flag.description, since
+        ),
+        range: Some(literal.range),
+    })
+}
+
+/// Follows `calls` through the inner blocks of `option`.
+fn resolve_nested(option: &'static OptionInfo, calls: &[&Driver]) -> Option<&'static OptionInfo> {
+    calls
+        .iter()
+        .try_fold(option, |option, call| option.find_child(&call.name.name))
+}
+
+fn names<'a>(calls: &[&'a Driver]) -> Vec<&'a str> {
+    calls.iter().map(|c| c.name.name.as_str()).collect()
+}
+
+pub(crate) fn describe_driver(driver: &DriverInfo) -> String {
+    let role = if driver.context == ObjectKind::Log {
+        "log path element".to_string()
+    } else {
+        format!("{} driver", driver.context)
+    };
+    let since = match driver.since {
+        Some((major, minor)) => format!("\n\nNeeds `@version: {}.{}` or later.", major, minor),
+        None => String::new(),
+    };
+    format!(
+        "```\n{}()\n```\n{}{}\n\n*{}*",
+        driver.name, driver.description, since, role
+    )
+}
+
+pub(crate) fn describe_option(option: &OptionInfo, footer: &str) -> String {
+    let mut contents = format!("```\n{}({})\n```\n{}", option.name, option.value, option.description);
+    if let Some(default) = option.default {
+        contents.push_str(&format!("\n\nSuggested value: `{}`", default));
+    }
+    if !option.children.is_empty() {
+        let names: Vec<&str> = option.children.iter().map(|c| c.name).collect();
+        contents.push_str(&format!("\n\nAccepts: `{}`", names.join("`, `")));
+        contents.push_str(&block_drivers(option.name));
+    }
+    contents.push_str("\n\n*");
+    contents.push_str(footer);
+    contents.push('*');
+    contents
+}
+
+/// The drivers taking the inner block `name`, by context: "Supported by
+/// the source drivers `network()`, `syslog()`; ...".
+fn block_drivers(name: &str) -> String {
+    let mut groups: Vec<(ObjectKind, Vec<String>)> = Vec::new();
+    for driver in grammar::drivers_with_block(name) {
+        let call = format!("`{}()`", driver.name);
+        match groups.iter_mut().find(|(context, _)| *context == driver.context) {
+            Some((_, calls)) => calls.push(call),
+            None => groups.push((driver.context, vec![call])),
+        }
+    }
+    if groups.is_empty() {
+        return String::new();
+    }
+    let groups: Vec<String> = groups
+        .iter()
+        .map(|(context, calls)| match context {
+            ObjectKind::Log => format!("the log path elements {}", calls.join(", ")),
+            _ => format!("the {} drivers {}", context, calls.join(", ")),
+        })
+        .collect();
+    format!("\n\nSupported by {}.", groups.join("; "))
+}
+
+/// Names the enclosing blocks of the last of `calls`, innermost first.
+fn enclosing_blocks(calls: &[&str]) -> String {
+    calls[..calls.len() - 1]
+        .iter()
+        .rev()
+        .map(|c| format!("`{}()`", c))
+        .collect::<Vec<_>>()
+        .join(" in ")
+}
+
+/// `calls` runs from the driver to the described option.
+pub(crate) fn option_footer(driver: &DriverInfo, calls: &[&str]) -> String {
+    match calls.len() {
+        2 => format!("Option of the `{}()` {} driver.", driver.name, driver.context),
+        _ => {
+            let blocks = enclosing_blocks(&calls[1..]);
+            format!(
+                "Option of {} in the `{}()` {} driver.",
+                blocks, driver.name, driver.context
+            )
+        }
+    }
+}
+
+/// `calls` runs from the global option to the described option.
+pub(crate) fn global_option_footer(calls: &[&str]) -> String {
+    match calls.len() {
+        1 => "Global option.".to_string(),
+        _ => format!("Option of {} in the global options.", enclosing_blocks(calls)),
+    }
+}
+
+/// The `persist-name()` under `position`, either its option name or its value.
+pub fn persist_name_at(parsed: &ParsedConfiguration, position: Position) -> Option<&Literal> {
+    parsed
+        .objects
+        .iter()
+        .flat_map(|o| o.persist_names())
+        .find(|(driver, literal)| {
+            literal.range.contains(position)
+                || driver
+                    .options()
+                    .any(|o| o.name.range.contains(position) && o.literals().any(|l| l == *literal))
+        })
+        .map(|(_, literal)| literal)
+}
+
+/// Lists the other drivers of `files` that use the persist name `literal`
+/// found at `uri`.
+pub fn persist_name_users(literal: &Literal, uri: &str, files: &[(String, ParsedConfiguration)]) -> String {
+    let mut users = Vec::new();
+    for (file_uri, parsed) in files {
+        for (driver, other) in parsed.objects.iter().flat_map(|o| o.persist_names()) {
+            if other.value == literal.value && !(file_uri == uri && other.range == literal.range) {
+                users.push(format!(
+                    "- `{}()` in {}",
+                    driver.name.name,
+                    describe_location(file_uri, other.range)
+                ));
+            }
+        }
+    }
+    if users.is_empty() {
+        format!("No other driver uses the persist name `{}`.", literal.value)
+    } else {
+        format!("Also used by:\n{}", users.join("\n"))
+    }
+}
+
+/// The `@include` directive under `position`.
+pub fn include_at(parsed: &ParsedConfiguration, position: Position) -> Option<&IncludeAnnotation> {
+    parsed.annotations.iter().find_map(|annotation| match annotation {
+        Annotation::Include(include) if include.range.contains(position) => Some(include),
+        _ => None,
+    })
+}
+
+/// Lists the files `include` (in `uri`) resolves to with their size and
+/// parse status, and below each the files it includes in turn.
+pub fn include_report(
+    uri: &str,
+    include: &IncludeAnnotation,
+    snippets: &[Snippet],
+    workspace_folders: &[PathBuf],
+) -> String {
+    let mut lines = Vec::new();
+    push_included(uri, include.range, snippets, workspace_folders, 0, &mut lines);
+    if lines.is_empty() {
+        format!("`{}` matches no files.", include.path)
+    } else {
+        format!("`{}` includes:\n{}", include.path, lines.join("\n"))
+    }
+}
+
+fn push_included(
+    uri: &str,
+    range: Range,
+    snippets: &[Snippet],
+    workspace_folders: &[PathBuf],
+    depth: usize,
+    lines: &mut Vec<String>,
+) {
+    let included = snippets
+        .iter()
+        .filter(|s| s.included_from.uri == uri && s.included_from.range == range);
+    for snippet in included {
+        let size = fs::metadata(&snippet.path).map_or_else(|_| "unreadable".to_string(), |m| format_size(m.len()));
+        let status = match &snippet.parsed {
+            Ok(_) => "parses".to_string(),
+            Err(error) => format!("syntax error on line {}: {}", error.range.start.line + 1, error.kind),
+        };
+        lines.push(format!(
+            "{}- {} ({}, {})",
+            "  ".repeat(depth),
+            display_name(&snippet.uri, workspace_folders),
+            size,
+            status
+        ));
+        let Ok(parsed) = &snippet.parsed else {
+            continue;
+        };
+        for annotation in &parsed.annotations {
+            if let Annotation::Include(nested) = annotation {
+                push_included(
+                    &snippet.uri,
+                    nested.range,
+                    snippets,
+                    workspace_folders,
+                    depth + 1,
+                    lines,
+                );
+            }
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        _ => format!("{:.1} KiB", bytes as f64 / 1024.0),
+    }
+}
+
+fn describe_location(uri: &str, range: Range) -> String {
+    let file = uri.rsplit('/').next().unwrap_or(uri);
+    format!("{}:{}", file, range.start.line + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network_option(context: ObjectKind, name: &str) -> &'static OptionInfo {
+        grammar::find_driver(context, "network")
+            .unwrap()
+            .find_option(name)
+            .unwrap()
+    }
+
+    #[test]
+    fn lists_the_drivers_taking_an_inner_block() {
+        let contents = describe_option(network_option(ObjectKind::Source, "tls"), "");
+        let supported = &contents[contents.find("\n\nSupported by ").expect(&contents)..];
+        assert!(
+            supported.starts_with("\n\nSupported by the source drivers `"),
+            "{}",
+            supported
+        );
+        assert!(supported.contains("; the destination drivers `"), "{}", supported);
+        assert_eq!(supported.matches("`network()`").count(), 2, "{}", supported);
+        assert!(contents.find("\n\nAccepts: `") < contents.find("\n\nSupported by "));
+    }
+
+    #[test]
+    fn lists_only_the_contexts_with_the_block() {
+        let contents = describe_option(network_option(ObjectKind::Destination, "disk-buffer"), "");
+        assert!(
+            contents.contains("Supported by the destination drivers `"),
+            "{}",
+            contents
+        );
+        assert!(contents.contains("`network()`"), "{}", contents);
+        assert!(!contents.contains("source drivers"), "{}", contents);
+    }
+
+    #[test]
+    fn leaves_plain_options_alone() {
+        assert!(!describe_option(network_option(ObjectKind::Source, "port"), "").contains("Supported by"));
+        assert_eq!(block_drivers("port"), "");
+    }
+}
@@ -0,0 +1,47 @@
+//! `textDocument/inlayHint`: names of positional driver parameters.
+
+use crate::grammar;
+use crate::language_types::objects::{Object, ObjectKind, Parameter, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{InlayHint, Range};
+
+/// A `name:` hint before every value passed to a driver without an option
+/// name, e.g. `path:` in `file("/var/log/messages")`, limited to `range`.
+pub fn inlay_hints(parsed: &ParsedConfiguration, range: Range) -> Vec<InlayHint> {
+    let mut hints = Vec::new();
+    for object in &parsed.objects {
+        collect_object(object, range, &mut hints);
+    }
+    hints
+}
+
+fn collect_object(object: &Object, range: Range, hints: &mut Vec<InlayHint>) {
+    for statement in object.flat_statements() {
+        let driver = match statement {
+            Statement::Object(inline) => {
+                collect_object(inline, range, hints);
+                continue;
+            }
+            Statement::Driver(driver) => driver,
+            Statement::Conditional(_) | Statement::Junction(_) => continue,
+        };
+        if object.kind == ObjectKind::Log || !driver.location.overlaps(&range) {
+            continue;
+        }
+        let Some(info) = grammar::find_driver(object.kind, &driver.name.name) else {
+            continue;
+        };
+        let literals = driver.parameters.iter().filter_map(|p| match p {
+            Parameter::Literal(literal) => Some(literal),
+            Parameter::Option(_) => None,
+        });
+        for (name, literal) in info.positional.iter().zip(literals) {
+            if range.contains(literal.range.start) {
+                hints.push(InlayHint {
+                    position: literal.range.start,
+                    label: format!("{}:", name),
+                });
+            }
+        }
+    }
+}
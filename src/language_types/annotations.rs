@@ -0,0 +1,91 @@
+use crate::lsp_types::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Version(VersionAnnotation),
+    Include(IncludeAnnotation),
+    Define(DefineAnnotation),
+    Module(ModuleAnnotation),
+    Requires(RequiresAnnotation),
+    /// Any other `@keyword`; kept so that its line is accounted for.
+    Other(OtherAnnotation),
+}
+
+impl Annotation {
+    pub fn range(&self) -> Range {
+        match self {
+            Annotation::Version(a) => a.range,
+            Annotation::Include(a) => a.range,
+            Annotation::Define(a) => a.range,
+            Annotation::Module(a) => a.range,
+            Annotation::Requires(a) => a.range,
+            Annotation::Other(a) => a.range,
+        }
+    }
+}
+
+/// `@version: 3.35`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionAnnotation {
+    pub version: String,
+    pub range: Range,
+    pub value_range: Range,
+}
+
+impl VersionAnnotation {
+    /// The version as `(major, minor)`.
+    pub fn number(&self) -> Option<(u32, u32)> {
+        parse_version(&self.version)
+    }
+}
+
+/// `"4.2"` as `(4, 2)`.
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.trim().split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// `@include "scl.conf"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncludeAnnotation {
+    pub path: String,
+    pub range: Range,
+    pub path_range: Range,
+}
+
+/// `@define name "value"`, substituted for `` `name` `` in the rest of
+/// the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefineAnnotation {
+    pub name: String,
+    pub value: String,
+    pub range: Range,
+    pub name_range: Range,
+}
+
+/// `@module mod-java`, loading a plugin module; the arguments, if any,
+/// are kept as written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleAnnotation {
+    pub name: String,
+    pub arguments: String,
+    pub range: Range,
+    pub name_range: Range,
+}
+
+/// `@requires json-plugin "message"`: syslog-ng refuses the configuration,
+/// with the optional message, when the module is not available.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequiresAnnotation {
+    pub module: String,
+    pub message: Option<String>,
+    pub range: Range,
+    pub module_range: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtherAnnotation {
+    pub name: String,
+    pub arguments: String,
+    pub range: Range,
+}
@@ -0,0 +1,217 @@
+//! Typed view of the failover settings of network destinations:
+//!
+//! ```text
+//! network("primary" failover(servers("backup1", "backup2")
+//!                            failback(tcp-probe-interval(10) successful-probes-required(3))));
+//! network("primary" failover-servers("backup1", "backup2"));
+//! ```
+
+use crate::language_types::objects::{Driver, Literal, LiteralKind, Parameter};
+use crate::lsp_types::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failover<'a> {
+    pub servers: Vec<&'a Literal>,
+    pub failback: Option<Failback<'a>>,
+    /// The `failover(...)` or `failover-servers(...)` option.
+    pub location: Range,
+}
+
+/// Switching back to the primary server once it is reachable again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failback<'a> {
+    pub tcp_probe_interval: Option<&'a Literal>,
+    pub successful_probes_required: Option<&'a Literal>,
+    pub location: Range,
+}
+
+/// Something wrong with a failover block, found while reading it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailoverProblem {
+    pub range: Range,
+    pub message: String,
+    /// `false` for problems syslog-ng accepts but that are likely mistakes.
+    pub is_error: bool,
+}
+
+impl FailoverProblem {
+    fn error(range: Range, message: String) -> Self {
+        FailoverProblem {
+            range,
+            message,
+            is_error: true,
+        }
+    }
+
+    fn warning(range: Range, message: String) -> Self {
+        FailoverProblem {
+            range,
+            message,
+            is_error: false,
+        }
+    }
+}
+
+fn is_named(driver: &Driver, name: &str) -> bool {
+    driver.name.name.replace('_', "-") == name
+}
+
+impl<'a> Failover<'a> {
+    /// Reads the failover settings of a destination driver, if it has any.
+    pub fn from_driver(driver: &'a Driver, problems: &mut Vec<FailoverProblem>) -> Option<Self> {
+        let blocks: Vec<&Driver> = driver
+            .options()
+            .filter(|o| is_named(o, "failover") || is_named(o, "failover-servers"))
+            .collect();
+        let (first, rest) = blocks.split_first()?;
+        for duplicate in rest {
+            problems.push(FailoverProblem::error(
+                duplicate.name.range,
+                format!("{}() is already set by {}()", duplicate.name.name, first.name.name),
+            ));
+        }
+        let failover = if is_named(first, "failover") {
+            Failover::from_block(first, problems)
+        } else {
+            Failover {
+                servers: server_list(first, problems),
+                failback: None,
+                location: first.location,
+            }
+        };
+        failover.check_servers(driver, problems);
+        Some(failover)
+    }
+
+    fn from_block(block: &'a Driver, problems: &mut Vec<FailoverProblem>) -> Self {
+        let mut servers = None;
+        let mut failback = None;
+        for parameter in &block.parameters {
+            match parameter {
+                Parameter::Option(option) if is_named(option, "servers") => {
+                    servers = Some(server_list(option, problems));
+                }
+                Parameter::Option(option) if is_named(option, "failback") => {
+                    failback = Some(Failback::from_block(option, problems));
+                }
+                Parameter::Option(option) => problems.push(FailoverProblem::error(
+                    option.name.range,
+                    format!(
+                        "unknown failover() option `{}`, expected servers() or failback()",
+                        option.name.name
+                    ),
+                )),
+                Parameter::Literal(literal) => problems.push(FailoverProblem::error(
+                    literal.range,
+                    "failover() only takes servers() and failback()".to_string(),
+                )),
+            }
+        }
+        if servers.is_none() {
+            problems.push(FailoverProblem::error(
+                block.name.range,
+                "failover() requires a servers() list".to_string(),
+            ));
+        }
+        Failover {
+            servers: servers.unwrap_or_default(),
+            failback,
+            location: block.location,
+        }
+    }
+
+    /// Duplicates, and the primary server listed as its own failover.
+    fn check_servers(&self, driver: &Driver, problems: &mut Vec<FailoverProblem>) {
+        let primary = driver.literals().next();
+        for (i, server) in self.servers.iter().enumerate() {
+            if self.servers[..i].iter().any(|s| s.value == server.value) {
+                problems.push(FailoverProblem::warning(
+                    server.range,
+                    format!("failover server `{}` is listed more than once", server.value),
+                ));
+            } else if primary.is_some_and(|p| p.value == server.value) {
+                problems.push(FailoverProblem::warning(
+                    server.range,
+                    format!("`{}` is the primary server of this destination", server.value),
+                ));
+            }
+        }
+    }
+}
+
+impl<'a> Failback<'a> {
+    fn from_block(block: &'a Driver, problems: &mut Vec<FailoverProblem>) -> Self {
+        let mut failback = Failback {
+            tcp_probe_interval: None,
+            successful_probes_required: None,
+            location: block.location,
+        };
+        for parameter in &block.parameters {
+            let option = match parameter {
+                Parameter::Option(option) => option,
+                Parameter::Literal(literal) => {
+                    problems.push(FailoverProblem::error(
+                        literal.range,
+                        "failback() only takes tcp-probe-interval() and successful-probes-required()".to_string(),
+                    ));
+                    continue;
+                }
+            };
+            let slot = if is_named(option, "tcp-probe-interval") {
+                &mut failback.tcp_probe_interval
+            } else if is_named(option, "successful-probes-required") {
+                &mut failback.successful_probes_required
+            } else {
+                problems.push(FailoverProblem::error(
+                    option.name.range,
+                    format!(
+                        "unknown failback() option `{}`, expected tcp-probe-interval() or successful-probes-required()",
+                        option.name.name
+                    ),
+                ));
+                continue;
+            };
+            *slot = positive_number(option, problems);
+        }
+        failback
+    }
+}
+
+/// The server names of `servers(...)` or `failover-servers(...)`.
+fn server_list<'a>(option: &'a Driver, problems: &mut Vec<FailoverProblem>) -> Vec<&'a Literal> {
+    let mut servers = Vec::new();
+    for parameter in &option.parameters {
+        match parameter {
+            Parameter::Literal(literal) => servers.push(literal),
+            Parameter::Option(nested) => problems.push(FailoverProblem::error(
+                nested.name.range,
+                format!("{}() takes a list of server names", option.name.name),
+            )),
+        }
+    }
+    if servers.is_empty() {
+        problems.push(FailoverProblem::error(
+            option.location,
+            format!("{}() needs at least one server", option.name.name),
+        ));
+    }
+    servers
+}
+
+fn positive_number<'a>(option: &'a Driver, problems: &mut Vec<FailoverProblem>) -> Option<&'a Literal> {
+    let mut literals = option.literals();
+    match (literals.next(), literals.next(), option.options().next()) {
+        (Some(literal), None, None)
+            if literal.kind == LiteralKind::Number && literal.value.parse::<u64>().is_ok_and(|n| n > 0) =>
+        {
+            Some(literal)
+        }
+        _ => {
+            problems.push(FailoverProblem::error(
+                option.location,
+                format!("{}() expects a positive whole number", option.name.name),
+            ));
+            None
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Filter expressions: the body of `filter f_name { ... };` and the
+//! condition of `if (...)` in a log path.
+//!
+//! ```text
+//! level(info..err) and not facility(mail) or match("foo" value("MESSAGE"))
+//! ```
+//!
+//! `not` binds tighter than `and`, which binds tighter than `or`.
+
+use crate::language_types::objects::{Driver, Identifier, Literal, LiteralKind, Parameter};
+use crate::lsp_types::Range;
+use crate::parser::lexer::Token;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterExpression {
+    pub root: Expression,
+    /// The tokens the expression was read from, for the features that work
+    /// on the words of an expression rather than on its structure.
+    pub tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// A filter function such as `level(info..err)`,
+    /// `match("foo" value("MESSAGE"))` or `filter(f_other)`.
+    Call(Driver),
+    /// `"${PID}" == 1` or `"${HOST}" eq "server"`.
+    Comparison(Comparison),
+    Not {
+        keyword: Identifier,
+        operand: Box<Expression>,
+    },
+    /// `and` or `or`.
+    Binary {
+        operator: Identifier,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    /// An expression in parentheses, which cover `range`.
+    Group { expression: Box<Expression>, range: Range },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub left: Literal,
+    /// `==`, `!=`, `<`, `<=`, `>` and `>=` compare numbers; `eq`, `ne`,
+    /// `lt`, `le`, `gt` and `ge` compare strings.
+    pub operator: Identifier,
+    pub right: Literal,
+}
+
+/// The operators comparing strings.
+pub const STRING_COMPARISONS: &[&str] = &["eq", "ne", "lt", "le", "gt", "ge"];
+
+/// The operators comparing numbers, and the same comparing both type and
+/// value (`===`, `!==`).
+pub const NUMERIC_COMPARISONS: &[&str] = &["==", "!=", "<", "<=", ">", ">=", "===", "!=="];
+
+impl Expression {
+    pub fn range(&self) -> Range {
+        match self {
+            Expression::Call(call) => call.location,
+            Expression::Comparison(comparison) => Range::new(comparison.left.range.start, comparison.right.range.end),
+            Expression::Not { keyword, operand } => Range::new(keyword.range.start, operand.range().end),
+            Expression::Binary { left, right, .. } => Range::new(left.range().start, right.range().end),
+            Expression::Group { range, .. } => *range,
+        }
+    }
+
+    /// The filter functions called, in document order.
+    pub fn calls(&self) -> Vec<&Driver> {
+        fn collect<'a>(expression: &'a Expression, calls: &mut Vec<&'a Driver>) {
+            match expression {
+                Expression::Call(call) => calls.push(call),
+                Expression::Comparison(_) => {}
+                Expression::Not { operand, .. } => collect(operand, calls),
+                Expression::Binary { left, right, .. } => {
+                    collect(left, calls);
+                    collect(right, calls);
+                }
+                Expression::Group { expression, .. } => collect(expression, calls),
+            }
+        }
+        let mut calls = Vec::new();
+        collect(self, &mut calls);
+        calls
+    }
+}
+
+impl FilterExpression {
+    /// The identifiers `filter(f_other)` calls to include other filters.
+    pub fn filter_references(&self) -> impl Iterator<Item = &Literal> {
+        self.root
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call.parameters.as_slice() {
+                [Parameter::Literal(name)] if call.name.name == "filter" && name.kind == LiteralKind::Word => {
+                    Some(name)
+                }
+                _ => None,
+            })
+    }
+}
@@ -0,0 +1,80 @@
+//! Typed view of the flags of log paths and of the channels of junctions:
+//!
+//! ```text
+//! log { source(s_net); destination(d_file); flags(flow-control, final); };
+//! ```
+
+use crate::grammar::{self, LogFlag};
+use crate::language_types::objects::{Driver, Literal, Parameter, Statement};
+use crate::lsp_types::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFlags<'a> {
+    pub flags: Vec<LogFlagValue<'a>>,
+    /// The `flags(...)` statement.
+    pub location: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFlagValue<'a> {
+    pub literal: &'a Literal,
+    /// `None` for flags syslog-ng does not know, which are reported.
+    pub info: Option<&'static LogFlag>,
+}
+
+/// Something wrong with a `flags()` statement, found while reading it.
+/// syslog-ng rejects all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFlagsProblem {
+    pub range: Range,
+    pub message: String,
+}
+
+impl<'a> LogFlags<'a> {
+    /// Reads a statement of a log path; `None` for statements other than
+    /// `flags()`.
+    pub fn from_statement(statement: &'a Statement, problems: &mut Vec<LogFlagsProblem>) -> Option<Self> {
+        match statement {
+            Statement::Driver(driver) if grammar::normalize_name(&driver.name.name) == "flags" => {
+                Some(LogFlags::from_driver(driver, problems))
+            }
+            _ => None,
+        }
+    }
+
+    fn from_driver(driver: &'a Driver, problems: &mut Vec<LogFlagsProblem>) -> Self {
+        let mut flags = Vec::new();
+        for parameter in &driver.parameters {
+            let literal = match parameter {
+                Parameter::Literal(literal) => literal,
+                Parameter::Option(option) => {
+                    problems.push(LogFlagsProblem {
+                        range: option.location,
+                        message: format!("flags() takes flag names, not {}()", option.name.name),
+                    });
+                    continue;
+                }
+            };
+            let info = grammar::find_log_flag(&literal.value);
+            if info.is_none() {
+                problems.push(LogFlagsProblem {
+                    range: literal.range,
+                    message: format!(
+                        "unknown log path flag `{}`, expected {}",
+                        literal.value,
+                        grammar::LOG_FLAGS.iter().map(|f| f.name).collect::<Vec<_>>().join(", ")
+                    ),
+                });
+            }
+            flags.push(LogFlagValue { literal, info });
+        }
+        LogFlags {
+            flags,
+            location: driver.location,
+        }
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f.info.is_some_and(|info| info.name == name))
+    }
+}
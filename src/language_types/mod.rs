@@ -0,0 +1,211 @@
+//! AST types produced by the configuration parser.
+
+pub mod annotations;
+pub mod failover;
+pub mod filter_expression;
+pub mod log_flags;
+pub mod objects;
+pub mod rewrite;
+pub mod source;
+pub mod templates;
+pub mod typed_values;
+pub mod value_pairs;
+
+use annotations::{Annotation, DefineAnnotation};
+use objects::{Block, Conditional, Driver, Identifier, Literal, Object, ObjectBody, ObjectKind, Reference, Statement};
+
+use crate::lsp_types::{Position, Range};
+use crate::parser::lexer::{Token, TokenKind};
+
+/// The configuration element under a position, see [`ParsedConfiguration::element_at`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element<'a> {
+    /// The name of a driver or option. `calls` runs from the statement of the
+    /// object body (a driver, a global option, a log path element) down to the
+    /// call whose name is under the position; `context` is the kind of the
+    /// innermost enclosing object.
+    Call {
+        context: ObjectKind,
+        calls: Vec<&'a Driver>,
+    },
+    /// A function name in a filter expression, e.g. `facility` in `facility(mail)`.
+    FilterFunction(&'a Token),
+}
+
+/// The parsed contents of a single configuration file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedConfiguration {
+    pub annotations: Vec<Annotation>,
+    pub objects: Vec<Object>,
+    /// `block` definitions, in document order.
+    pub blocks: Vec<Block>,
+    /// Every place an object is referred to by its identifier.
+    pub references: Vec<Reference>,
+}
+
+impl ParsedConfiguration {
+    pub fn get_objects_by_kind(&self, kind: ObjectKind) -> impl Iterator<Item = &Object> {
+        self.objects.iter().filter(move |o| o.kind == kind)
+    }
+
+    /// The blocks that can be used in the body of a `kind` object.
+    pub fn blocks_for(&self, kind: ObjectKind) -> impl Iterator<Item = &Block> {
+        self.blocks.iter().filter(move |b| b.object_kind() == Some(kind))
+    }
+
+    /// The `@define` annotations, in order.
+    pub fn defines(&self) -> impl Iterator<Item = &DefineAnnotation> {
+        self.annotations.iter().filter_map(|a| match a {
+            Annotation::Define(define) => Some(define),
+            _ => None,
+        })
+    }
+
+    /// Every `if` block, nested ones included, in document order; those in
+    /// the channels of junctions too.
+    pub fn conditionals(&self) -> Vec<&Conditional> {
+        fn collect<'a>(statements: &'a [Statement], found: &mut Vec<&'a Conditional>) {
+            for statement in statements {
+                match statement {
+                    Statement::Object(inline) => collect(inline.statements(), found),
+                    Statement::Conditional(conditional) => {
+                        found.push(conditional);
+                        for branch in &conditional.branches {
+                            collect(&branch.statements, found);
+                        }
+                    }
+                    Statement::Junction(junction) => {
+                        for channel in &junction.channels {
+                            collect(&channel.statements, found);
+                        }
+                    }
+                    Statement::Driver(_) => {}
+                }
+            }
+        }
+        let mut found = Vec::new();
+        for object in &self.objects {
+            collect(object.statements(), &mut found);
+        }
+        found
+    }
+
+    /// Identifiers of the named top-level objects.
+    pub fn definitions(&self) -> impl Iterator<Item = (ObjectKind, &Identifier)> {
+        self.objects.iter().filter_map(|o| o.id.as_ref().map(|id| (o.kind, id)))
+    }
+
+    /// The object identifier (definition or reference) under `position`,
+    /// with the range it occupies.
+    pub fn symbol_at(&self, position: Position) -> Option<(ObjectKind, &str, Range)> {
+        self.definitions()
+            .find(|(_, id)| id.range.contains(position))
+            .map(|(kind, id)| (kind, id.name.as_str(), id.range))
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|r| r.range.contains(position))
+                    .map(|r| (r.kind, r.name.as_str(), r.range))
+            })
+    }
+
+    /// The driver, option or filter function name under `position`.
+    pub fn element_at(&self, position: Position) -> Option<Element<'_>> {
+        self.objects
+            .iter()
+            .find(|o| o.location.contains(position))
+            .and_then(|o| element_in_object(o, position))
+    }
+
+    /// The value under `position`, with the kind of the innermost enclosing
+    /// object and the calls leading to it as in [`Element::Call`]; the
+    /// value is passed to the last of them.
+    pub fn value_at(&self, position: Position) -> Option<(ObjectKind, Vec<&Driver>, &Literal)> {
+        self.objects
+            .iter()
+            .find(|o| o.location.contains(position))
+            .and_then(|o| value_in_object(o, position))
+    }
+}
+
+fn value_in_object(object: &Object, position: Position) -> Option<(ObjectKind, Vec<&Driver>, &Literal)> {
+    value_in_statements(object.kind, object.statements(), position)
+}
+
+fn value_in_statements(
+    kind: ObjectKind,
+    statements: &[Statement],
+    position: Position,
+) -> Option<(ObjectKind, Vec<&Driver>, &Literal)> {
+    statements.iter().find_map(|statement| match statement {
+        Statement::Object(inline) if inline.location.contains(position) => value_in_object(inline, position),
+        Statement::Driver(driver) if driver.location.contains(position) => {
+            let mut calls = Vec::new();
+            value_chain(driver, position, &mut calls).map(|literal| (kind, calls, literal))
+        }
+        Statement::Conditional(conditional) if conditional.location.contains(position) => {
+            let branch = conditional.branches.iter().find(|b| b.location.contains(position))?;
+            value_in_statements(ObjectKind::Log, &branch.statements, position)
+        }
+        Statement::Junction(junction) if junction.location.contains(position) => {
+            let channel = junction.channels.iter().find(|c| c.location.contains(position))?;
+            value_in_statements(ObjectKind::Log, &channel.statements, position)
+        }
+        _ => None,
+    })
+}
+
+fn value_chain<'a>(driver: &'a Driver, position: Position, calls: &mut Vec<&'a Driver>) -> Option<&'a Literal> {
+    calls.push(driver);
+    if let Some(literal) = driver.literals().find(|l| l.range.contains(position)) {
+        return Some(literal);
+    }
+    let option = driver.options().find(|o| o.location.contains(position))?;
+    value_chain(option, position, calls)
+}
+
+fn element_in_object(object: &Object, position: Position) -> Option<Element<'_>> {
+    match &object.body {
+        ObjectBody::Expression(expression) => filter_function_at(&expression.tokens, position),
+        ObjectBody::Statements(statements) => element_in_statements(object.kind, statements, position),
+    }
+}
+
+fn element_in_statements(kind: ObjectKind, statements: &[Statement], position: Position) -> Option<Element<'_>> {
+    statements.iter().find_map(|statement| match statement {
+        Statement::Object(inline) if inline.location.contains(position) => element_in_object(inline, position),
+        Statement::Driver(driver) if driver.location.contains(position) => {
+            let mut calls = Vec::new();
+            call_chain(driver, position, &mut calls).then_some(Element::Call { context: kind, calls })
+        }
+        Statement::Conditional(conditional) if conditional.location.contains(position) => {
+            let branch = conditional.branches.iter().find(|b| b.location.contains(position))?;
+            filter_function_at(branch.condition_tokens(), position)
+                .or_else(|| element_in_statements(ObjectKind::Log, &branch.statements, position))
+        }
+        Statement::Junction(junction) if junction.location.contains(position) => {
+            let channel = junction.channels.iter().find(|c| c.location.contains(position))?;
+            element_in_statements(ObjectKind::Log, &channel.statements, position)
+        }
+        _ => None,
+    })
+}
+
+fn filter_function_at(tokens: &[Token], position: Position) -> Option<Element<'_>> {
+    tokens
+        .windows(2)
+        .find(|w| w[0].is(TokenKind::Word) && w[1].is(TokenKind::LParen) && w[0].range.contains(position))
+        .map(|w| Element::FilterFunction(&w[0]))
+}
+
+/// Pushes the calls leading to the name under `position` and reports whether one was found.
+fn call_chain<'a>(driver: &'a Driver, position: Position, calls: &mut Vec<&'a Driver>) -> bool {
+    calls.push(driver);
+    if driver.name.range.contains(position) {
+        return true;
+    }
+    if let Some(option) = driver.options().find(|o| o.location.contains(position)) {
+        return call_chain(option, position, calls);
+    }
+    false
+}
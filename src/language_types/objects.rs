@@ -0,0 +1,312 @@
+use std::fmt;
+
+use crate::language_types::filter_expression::FilterExpression;
+use crate::lsp_types::Range;
+use crate::parser::lexer::Token;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ObjectKind {
+    Source,
+    Destination,
+    Filter,
+    Parser,
+    Rewrite,
+    Template,
+    Log,
+    Options,
+}
+
+impl ObjectKind {
+    pub const ALL: [ObjectKind; 8] = [
+        ObjectKind::Source,
+        ObjectKind::Destination,
+        ObjectKind::Filter,
+        ObjectKind::Parser,
+        ObjectKind::Rewrite,
+        ObjectKind::Template,
+        ObjectKind::Log,
+        ObjectKind::Options,
+    ];
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ObjectKind::Source => "source",
+            ObjectKind::Destination => "destination",
+            ObjectKind::Filter => "filter",
+            ObjectKind::Parser => "parser",
+            ObjectKind::Rewrite => "rewrite",
+            ObjectKind::Template => "template",
+            ObjectKind::Log => "log",
+            ObjectKind::Options => "options",
+        }
+    }
+
+    /// Whether objects of this kind carry an identifier (`source s_net { ... }`).
+    pub fn has_id(&self) -> bool {
+        !matches!(self, ObjectKind::Log | ObjectKind::Options)
+    }
+}
+
+impl fmt::Display for ObjectKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.keyword())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identifier {
+    pub name: String,
+    pub range: Range,
+}
+
+/// A top-level (or inline) object such as `source s_local { ... };`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    pub kind: ObjectKind,
+    pub keyword_range: Range,
+    pub id: Option<Identifier>,
+    pub body: ObjectBody,
+    /// From the kind keyword up to and including the terminating `;`.
+    pub location: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectBody {
+    Statements(Vec<Statement>),
+    /// The body of a filter object.
+    Expression(Box<FilterExpression>),
+}
+
+impl Object {
+    pub fn statements(&self) -> &[Statement] {
+        match &self.body {
+            ObjectBody::Statements(statements) => statements,
+            ObjectBody::Expression(_) => &[],
+        }
+    }
+
+    /// [`Self::statements`] with the statements of the branches of `if`
+    /// blocks and of the channels of junctions in place of those, for
+    /// checks that do not care which branch a log path element is in.
+    pub fn flat_statements(&self) -> Vec<&Statement> {
+        fn flatten<'a>(statements: &'a [Statement], flat: &mut Vec<&'a Statement>) {
+            for statement in statements {
+                match statement {
+                    Statement::Conditional(conditional) => {
+                        for branch in &conditional.branches {
+                            flatten(&branch.statements, flat);
+                        }
+                    }
+                    Statement::Junction(junction) => {
+                        for channel in &junction.channels {
+                            flatten(&channel.statements, flat);
+                        }
+                    }
+                    _ => flat.push(statement),
+                }
+            }
+        }
+        let mut flat = Vec::new();
+        flatten(self.statements(), &mut flat);
+        flat
+    }
+
+    pub fn drivers(&self) -> impl Iterator<Item = &Driver> {
+        self.statements().iter().filter_map(|s| match s {
+            Statement::Driver(d) => Some(d),
+            Statement::Object(_) | Statement::Conditional(_) | Statement::Junction(_) => None,
+        })
+    }
+
+    /// `persist-name()` values of the drivers of this object and of the
+    /// inline objects it contains, with the driver setting each.
+    pub fn persist_names(&self) -> Vec<(&Driver, &Literal)> {
+        let mut names = Vec::new();
+        for statement in self.flat_statements() {
+            match statement {
+                Statement::Object(inline) => names.extend(inline.persist_names()),
+                Statement::Driver(driver) => {
+                    let options = driver
+                        .options()
+                        .filter(|o| matches!(o.name.name.as_str(), "persist-name" | "persist_name"));
+                    names.extend(options.flat_map(|o| o.literals()).map(|l| (driver, l)));
+                }
+                Statement::Conditional(_) | Statement::Junction(_) => {}
+            }
+        }
+        names
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Driver(Driver),
+    /// Inline object inside a log path, e.g. `source { file("x"); };`.
+    Object(Object),
+    /// `if (...) { ... } else { ... };` inside a log path.
+    Conditional(Conditional),
+    /// `junction { channel { ... }; ... };` inside a log path.
+    Junction(Junction),
+}
+
+/// The `if`, `elif` and `else` branches of a log path, up to and including
+/// the terminating `;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conditional {
+    pub branches: Vec<Branch>,
+    pub location: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    /// `if`, `elif` or `else`.
+    pub keyword: Identifier,
+    /// The filter expression between the parentheses; `None` for `else`
+    /// and for a branch whose filters are among its statements,
+    /// `if { filter(f); ... }`.
+    pub condition: Option<FilterExpression>,
+    /// Log path elements, the same as in the body of `log { ... };`.
+    pub statements: Vec<Statement>,
+    /// From the keyword up to and including the closing brace.
+    pub location: Range,
+}
+
+impl Branch {
+    /// The tokens of the condition, none without one.
+    pub fn condition_tokens(&self) -> &[Token] {
+        self.condition.as_ref().map_or(&[], |condition| &condition.tokens)
+    }
+}
+
+/// A junction of a log path, up to and including the terminating `;`:
+/// every message goes through each of its channels, like through parallel
+/// embedded log paths.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Junction {
+    pub keyword: Identifier,
+    pub channels: Vec<Channel>,
+    pub location: Range,
+}
+
+/// `channel { ... };` in a junction, up to and including its `;`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub keyword: Identifier,
+    /// Log path elements, the same as in the body of `log { ... };`.
+    pub statements: Vec<Statement>,
+    pub location: Range,
+}
+
+impl Channel {
+    /// The values of the `flags()` of the channel, e.g. `final`.
+    pub fn flags(&self) -> impl Iterator<Item = &Literal> {
+        self.statements
+            .iter()
+            .filter_map(|s| match s {
+                Statement::Driver(driver) if driver.name.name == "flags" => Some(driver.literals()),
+                _ => None,
+            })
+            .flatten()
+    }
+}
+
+/// A `name(parameters...)` call. At the top of an object body this is a
+/// driver (`file(...)`, `source(s_local)` in a log path); nested inside
+/// another call it is an option or an inner block (`tls(...)`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Driver {
+    pub name: Identifier,
+    pub parameters: Vec<Parameter>,
+    /// From the name up to and including the closing parenthesis.
+    pub location: Range,
+}
+
+impl Driver {
+    pub fn options(&self) -> impl Iterator<Item = &Driver> {
+        self.parameters.iter().filter_map(|p| match p {
+            Parameter::Option(o) => Some(o),
+            Parameter::Literal(_) => None,
+        })
+    }
+
+    pub fn literals(&self) -> impl Iterator<Item = &Literal> {
+        self.parameters.iter().filter_map(|p| match p {
+            Parameter::Literal(l) => Some(l),
+            Parameter::Option(_) => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parameter {
+    Literal(Literal),
+    Option(Driver),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiteralKind {
+    String,
+    Number,
+    Word,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Literal {
+    pub kind: LiteralKind,
+    /// The value syslog-ng sees, with the `` `name` `` references to
+    /// `@define`s above it in the file substituted.
+    pub value: String,
+    /// The value as written, when it differs from `value`.
+    pub raw: Option<String>,
+    pub range: Range,
+}
+
+/// `block source s_custom(port(514) ...) { ... };`: a reusable piece of
+/// configuration, used like a driver of its context, `s_custom(port(601))`.
+/// The body is kept as raw tokens since its `` `parameter` `` substitutions
+/// can stand for any part of the syntax.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub keyword_range: Range,
+    /// `source`, `destination` and the like, or `root` for a block of
+    /// top-level objects.
+    pub context: Identifier,
+    pub name: Identifier,
+    pub parameters: Vec<BlockParameter>,
+    /// The block takes arguments beyond its parameters, `...` in the
+    /// parameter list.
+    pub variadic: bool,
+    /// Tokens between the braces.
+    pub body: Vec<Token>,
+    /// From the `block` keyword up to and including the terminating `;`.
+    pub location: Range,
+}
+
+impl Block {
+    /// The kind of object the block stands in for; `None` for `root` and
+    /// contexts without objects.
+    pub fn object_kind(&self) -> Option<ObjectKind> {
+        ObjectKind::ALL
+            .into_iter()
+            .find(|kind| kind.keyword() == self.context.name)
+    }
+}
+
+/// `port(514)` in the parameter list of a block, with `514` as the default
+/// used when a call leaves the parameter out; `port()` has none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockParameter {
+    pub name: Identifier,
+    pub default: Vec<Parameter>,
+    pub location: Range,
+}
+
+/// A use of an object identifier, e.g. `s_local` in `source(s_local);`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub kind: ObjectKind,
+    pub name: String,
+    pub range: Range,
+    /// Inside a string literal, like `t_name` in `"$(template t_name)"`.
+    pub in_string: bool,
+}
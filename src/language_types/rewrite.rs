@@ -0,0 +1,200 @@
+//! Typed view of the rules of rewrite objects:
+//!
+//! ```text
+//! rewrite r_x { set("value" value("HOST")); subst("foo", "bar" value("MSG") flags(global)); };
+//! ```
+
+use crate::grammar::{normalize_name, SUBST_FLAGS};
+use crate::language_types::objects::{Driver, Literal};
+use crate::lsp_types::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewriteRule<'a> {
+    pub operation: RewriteOperation<'a>,
+    /// `condition(...)`, a filter expression kept as the option.
+    pub condition: Option<&'a Driver>,
+    /// The driver of the rule.
+    pub location: Range,
+}
+
+/// The fields are `None` where syslog-ng uses a default, e.g. `MESSAGE`
+/// for a missing `value()`, or where the rule is missing an argument,
+/// which is reported as a problem.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RewriteOperation<'a> {
+    Set {
+        template: Option<&'a Literal>,
+        field: Option<&'a Literal>,
+    },
+    Subst {
+        pattern: Option<&'a Literal>,
+        replacement: Option<&'a Literal>,
+        field: Option<&'a Literal>,
+        pattern_type: Option<&'a Literal>,
+        flags: Vec<&'a Literal>,
+    },
+    Unset {
+        field: Option<&'a Literal>,
+    },
+    Rename {
+        old: Option<&'a Literal>,
+        new: Option<&'a Literal>,
+    },
+    SetTag(Option<&'a Literal>),
+    ClearTag(Option<&'a Literal>),
+    Groupset {
+        template: Option<&'a Literal>,
+        fields: Vec<&'a Literal>,
+    },
+}
+
+/// Something wrong with a rewrite rule, found while reading it. syslog-ng
+/// rejects all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewriteProblem {
+    pub range: Range,
+    pub message: String,
+}
+
+impl RewriteProblem {
+    fn new(range: Range, message: String) -> Self {
+        RewriteProblem { range, message }
+    }
+}
+
+fn option<'a>(driver: &'a Driver, name: &str) -> Option<&'a Driver> {
+    driver.options().find(|o| normalize_name(&o.name.name) == name)
+}
+
+/// The single value of the option `name` of `driver`.
+fn option_value<'a>(driver: &'a Driver, name: &str, problems: &mut Vec<RewriteProblem>) -> Option<&'a Literal> {
+    let option = option(driver, name)?;
+    let mut literals = option.literals();
+    match (literals.next(), literals.next()) {
+        (Some(value), None) => Some(value),
+        _ => {
+            problems.push(RewriteProblem::new(
+                option.location,
+                format!("{}() expects one value", option.name.name),
+            ));
+            None
+        }
+    }
+}
+
+/// The values given to `driver` itself, which must be `names.len()`.
+fn arguments<'a, const N: usize>(
+    driver: &'a Driver,
+    names: [&str; N],
+    problems: &mut Vec<RewriteProblem>,
+) -> [Option<&'a Literal>; N] {
+    let literals: Vec<&Literal> = driver.literals().collect();
+    if literals.len() != names.len() {
+        let expected = match names.as_slice() {
+            [] => "no values".to_string(),
+            [name] => format!("the {}", name),
+            [first, second] => format!("the {} and the {}", first, second),
+            _ => format!("{} values", names.len()),
+        };
+        problems.push(RewriteProblem::new(
+            driver.name.range,
+            format!(
+                "{}() expects {}, found {}",
+                driver.name.name,
+                expected,
+                count(literals.len())
+            ),
+        ));
+    }
+    std::array::from_fn(|i| literals.get(i).copied())
+}
+
+fn count(values: usize) -> String {
+    match values {
+        0 => "none".to_string(),
+        1 => "one value".to_string(),
+        n => format!("{} values", n),
+    }
+}
+
+impl<'a> RewriteRule<'a> {
+    /// Reads a rule of a rewrite object; `None` for drivers that are not
+    /// rewrite rules, like the calls of user-defined blocks.
+    pub fn from_driver(driver: &'a Driver, problems: &mut Vec<RewriteProblem>) -> Option<Self> {
+        let operation = match normalize_name(&driver.name.name).as_str() {
+            "set" => {
+                let [template] = arguments(driver, ["template"], problems);
+                RewriteOperation::Set {
+                    template,
+                    field: option_value(driver, "value", problems),
+                }
+            }
+            "subst" => {
+                let [pattern, replacement] = arguments(driver, ["pattern", "replacement"], problems);
+                let pattern_type = option_value(driver, "type", problems);
+                if let Some(pattern_type) = pattern_type {
+                    if !matches!(pattern_type.value.as_str(), "string" | "pcre" | "glob") {
+                        problems.push(RewriteProblem::new(
+                            pattern_type.range,
+                            format!(
+                                "unknown pattern type `{}`, expected string, pcre or glob",
+                                pattern_type.value
+                            ),
+                        ));
+                    }
+                }
+                let flags: Vec<&Literal> = option(driver, "flags").into_iter().flat_map(|o| o.literals()).collect();
+                for flag in &flags {
+                    if !SUBST_FLAGS.contains(&normalize_name(&flag.value).as_str()) {
+                        problems.push(RewriteProblem::new(
+                            flag.range,
+                            format!("unknown flag `{}`", flag.value),
+                        ));
+                    }
+                }
+                RewriteOperation::Subst {
+                    pattern,
+                    replacement,
+                    field: option_value(driver, "value", problems),
+                    pattern_type,
+                    flags,
+                }
+            }
+            "unset" => {
+                arguments(driver, [], problems);
+                RewriteOperation::Unset {
+                    field: option_value(driver, "value", problems),
+                }
+            }
+            "rename" => {
+                let [old, new] = arguments(driver, ["old name", "new name"], problems);
+                RewriteOperation::Rename { old, new }
+            }
+            "set-tag" | "clear-tag" => {
+                let [tag] = arguments(driver, ["tag"], problems);
+                match driver.name.name.starts_with("set") {
+                    true => RewriteOperation::SetTag(tag),
+                    false => RewriteOperation::ClearTag(tag),
+                }
+            }
+            "groupset" => {
+                let [template] = arguments(driver, ["template"], problems);
+                let values = option(driver, "values");
+                let fields: Vec<&Literal> = values.into_iter().flat_map(|o| o.literals()).collect();
+                if fields.is_empty() {
+                    problems.push(RewriteProblem::new(
+                        values.map_or(driver.name.range, |v| v.location),
+                        "groupset() needs the fields to set in values()".to_string(),
+                    ));
+                }
+                RewriteOperation::Groupset { template, fields }
+            }
+            _ => return None,
+        };
+        Some(RewriteRule {
+            operation,
+            condition: option(driver, "condition"),
+            location: driver.location,
+        })
+    }
+}
@@ -0,0 +1,678 @@
+//! syslog-ng syntax for AST nodes, the inverse of parsing: parsing the
+//! text [`ToSource::to_source`] returns gives back the same nodes, ranges
+//! aside. Values are written as they were in the source when they came
+//! from it, so `@define` references are kept, and comments are lost. The
+//! text has one top-level item per line; the formatter lays it out
+//! further.
+
+use crate::language_types::annotations::Annotation;
+use crate::language_types::filter_expression::{Expression, FilterExpression};
+use crate::language_types::objects::{Block, Driver, Literal, LiteralKind, Object, ObjectBody, Parameter, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::Range;
+use crate::parser::lexer::{is_word_char, Token, TokenKind};
+
+pub trait ToSource {
+    fn to_source(&self) -> String;
+}
+
+/// Implements [`ToSource`] through a function appending to a string.
+macro_rules! to_source_with {
+    ($($type:ty => $write:ident),* $(,)?) => {
+        $(impl ToSource for $type {
+            fn to_source(&self) -> String {
+                let mut out = String::new();
+                $write(self, &mut out);
+                out
+            }
+        })*
+    };
+}
+
+to_source_with! {
+    Annotation => write_annotation,
+    Object => write_object,
+    Block => write_block,
+    Statement => write_statement,
+    Driver => write_driver,
+    Literal => write_literal,
+    FilterExpression => write_filter_expression,
+    Expression => write_root_expression,
+}
+
+/// The directives, objects and blocks in document order.
+impl ToSource for ParsedConfiguration {
+    fn to_source(&self) -> String {
+        let mut items: Vec<(Range, String)> = Vec::new();
+        items.extend(self.annotations.iter().map(|a| (a.range(), a.to_source())));
+        items.extend(self.objects.iter().map(|o| (o.location, o.to_source())));
+        items.extend(self.blocks.iter().map(|b| (b.location, b.to_source())));
+        // Stable, so built items without positions keep their order.
+        items.sort_by_key(|(range, _)| range.start);
+        let mut text = String::new();
+        for (_, line) in items {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+fn write_annotation(annotation: &Annotation, out: &mut String) {
+    let line = match annotation {
+        Annotation::Version(a) => format!("@version: {}", a.version),
+        Annotation::Include(a) => format!("@include {}", quote(&a.path)),
+        Annotation::Define(a) => format!("@define {} {}", a.name, quote(&a.value)),
+        Annotation::Module(a) => format!("@module {} {}", a.name, a.arguments).trim_end().to_string(),
+        Annotation::Requires(a) => match &a.message {
+            Some(message) => format!("@requires {} {}", a.module, quote(message)),
+            None => format!("@requires {}", a.module),
+        },
+        Annotation::Other(a) => format!("@{} {}", a.name, a.arguments).trim_end().to_string(),
+    };
+    out.push_str(&line);
+}
+
+fn write_object(object: &Object, out: &mut String) {
+    out.push_str(object.kind.keyword());
+    if let Some(id) = &object.id {
+        out.push(' ');
+        out.push_str(&id.name);
+    }
+    out.push_str(" { ");
+    match &object.body {
+        ObjectBody::Expression(expression) => {
+            write_filter_expression(expression, out);
+            out.push(' ');
+        }
+        ObjectBody::Statements(statements) => write_statements(statements, out),
+    }
+    out.push_str("};");
+}
+
+fn write_statements(statements: &[Statement], out: &mut String) {
+    for statement in statements {
+        write_statement(statement, out);
+        out.push(' ');
+    }
+}
+
+fn write_statement(statement: &Statement, out: &mut String) {
+    match statement {
+        Statement::Driver(driver) => {
+            write_driver(driver, out);
+            out.push(';');
+        }
+        Statement::Object(inline) => write_object(inline, out),
+        Statement::Conditional(conditional) => {
+            for (i, branch) in conditional.branches.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                out.push_str(&branch.keyword.name);
+                if let Some(condition) = &branch.condition {
+                    out.push_str(" (");
+                    write_expression(&condition.root, 0, out);
+                    out.push(')');
+                }
+                out.push_str(" { ");
+                write_statements(&branch.statements, out);
+                out.push('}');
+            }
+            out.push(';');
+        }
+        Statement::Junction(junction) => {
+            out.push_str("junction { ");
+            for channel in &junction.channels {
+                out.push_str("channel { ");
+                write_statements(&channel.statements, out);
+                out.push_str("}; ");
+            }
+            out.push_str("};");
+        }
+    }
+}
+
+fn write_driver(driver: &Driver, out: &mut String) {
+    out.push_str(&driver.name.name);
+    out.push('(');
+    write_parameters(&driver.parameters, out);
+    out.push(')');
+}
+
+fn write_parameters(parameters: &[Parameter], out: &mut String) {
+    for (i, parameter) in parameters.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        match parameter {
+            Parameter::Literal(literal) => write_literal(literal, out),
+            Parameter::Option(option) => write_driver(option, out),
+        }
+    }
+}
+
+fn write_literal(literal: &Literal, out: &mut String) {
+    let text = literal.raw.as_deref().unwrap_or(&literal.value);
+    let bare = !text.is_empty() && text.chars().all(is_word_char);
+    match literal.kind {
+        LiteralKind::Word | LiteralKind::Number if bare => out.push_str(text),
+        _ => out.push_str(&quote(text)),
+    }
+}
+
+fn write_filter_expression(expression: &FilterExpression, out: &mut String) {
+    write_expression(&expression.root, 0, out);
+}
+
+fn write_root_expression(expression: &Expression, out: &mut String) {
+    write_expression(expression, 0, out);
+}
+
+/// `and` binds tighter than `or` and `not` tighter than both, so operands
+/// of a lower `precedence` than their place needs are parenthesized.
+fn write_expression(expression: &Expression, precedence: u8, out: &mut String) {
+    let own = match expression {
+        Expression::Binary { operator, .. } if operator.name == "or" => 0,
+        Expression::Binary { .. } => 1,
+        _ => 2,
+    };
+    if own < precedence {
+        out.push('(');
+        write_expression(expression, 0, out);
+        out.push(')');
+        return;
+    }
+    match expression {
+        Expression::Call(call) => write_driver(call, out),
+        Expression::Comparison(comparison) => {
+            write_literal(&comparison.left, out);
+            out.push(' ');
+            out.push_str(&comparison.operator.name);
+            out.push(' ');
+            write_literal(&comparison.right, out);
+        }
+        Expression::Not { operand, .. } => {
+            out.push_str("not ");
+            write_expression(operand, 2, out);
+        }
+        Expression::Binary { operator, left, right } => {
+            write_expression(left, own, out);
+            out.push(' ');
+            out.push_str(&operator.name);
+            out.push(' ');
+            // Left associative: `a or (b or c)` keeps its parentheses.
+            write_expression(right, own + 1, out);
+        }
+        Expression::Group { expression, .. } => {
+            out.push('(');
+            write_expression(expression, 0, out);
+            out.push(')');
+        }
+    }
+}
+
+fn write_block(block: &Block, out: &mut String) {
+    out.push_str(&format!("block {} {}(", block.context.name, block.name.name));
+    let mut parameters: Vec<String> = block
+        .parameters
+        .iter()
+        .map(|parameter| {
+            let mut text = format!("{}(", parameter.name.name);
+            write_parameters(&parameter.default, &mut text);
+            text.push(')');
+            text
+        })
+        .collect();
+    if block.variadic {
+        parameters.push("...".to_string());
+    }
+    out.push_str(&parameters.join(" "));
+    out.push_str(") { ");
+    let mut previous: Option<&Token> = None;
+    for token in &block.body {
+        let attached = token.is(TokenKind::LParen)
+            || token.is(TokenKind::RParen)
+            || token.is(TokenKind::Semicolon)
+            || token.is(TokenKind::Comma)
+            || previous.is_some_and(|p| p.is(TokenKind::LParen));
+        if previous.is_some() && !attached {
+            out.push(' ');
+        }
+        write_token(token, out);
+        previous = Some(token);
+    }
+    out.push_str(" };");
+}
+
+fn write_token(token: &Token, out: &mut String) {
+    match token.kind {
+        TokenKind::String => out.push_str(&quote(&token.text)),
+        _ => out.push_str(&token.text),
+    }
+}
+
+/// `value` as a string literal, escaped so the lexer reads back `value`.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{self, OptionInfo};
+    use crate::parser::parse_conf;
+
+    /// The `Debug` text of a node without what the round trip may change:
+    /// positions, byte offsets and the tokens filter expressions keep of
+    /// their source, which include optional commas.
+    fn without_positions(debug: &str) -> String {
+        let mut out = String::with_capacity(debug.len());
+        let mut rest = debug;
+        while let Some(c) = rest.chars().next() {
+            if c == '[' && out.ends_with("tokens: ") {
+                let end = closing_bracket(rest);
+                out.push_str("[..]");
+                rest = &rest[end + 1..];
+                continue;
+            }
+            let after_field = ["line: ", "character: ", "start: ", "end: "]
+                .iter()
+                .any(|field| out.ends_with(field));
+            if after_field && c.is_ascii_digit() {
+                let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                out.push('_');
+                rest = &rest[digits..];
+                continue;
+            }
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        out
+    }
+
+    /// Offset of the `]` closing the `[` `debug` starts with, skipping
+    /// brackets in quoted strings.
+    fn closing_bracket(debug: &str) -> usize {
+        let (mut depth, mut in_string, mut escaped) = (0, false, false);
+        for (i, c) in debug.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '[' if !in_string => depth += 1,
+                ']' if !in_string => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return i;
+                    }
+                }
+                _ => {}
+            }
+        }
+        debug.len() - 1
+    }
+
+    /// Parsing `text`, writing it and parsing the result gives the same
+    /// nodes, and writing those again the same text.
+    fn assert_round_trip(text: &str) {
+        let first = parse_conf(text).unwrap_or_else(|e| panic!("{}\nin\n{}", e, text));
+        let written = first.to_source();
+        let second = parse_conf(&written).unwrap_or_else(|e| panic!("{}\nin the written\n{}", e, written));
+        assert_eq!(
+            without_positions(&format!("{:?}", first)),
+            without_positions(&format!("{:?}", second)),
+            "\n{}\nwritten as\n{}",
+            text,
+            written
+        );
+        assert_eq!(second.to_source(), written, "\n{}", text);
+    }
+
+    #[test]
+    fn round_trips_objects() {
+        assert_round_trip(
+            r#"@version: 4.8
+# comments are dropped
+options { chain-hostnames(no); stats(level(1) freq(600)); };
+source s_net { network(ip("0.0.0.0") port(514) transport(tcp) tls(peer-verify(yes) key-file("/k"))); };
+destination d_file { file("/var/log/$HOST/messages" create-dirs(yes) owner(root)); };
+parser p_kv { kv-parser(prefix(".kv.")); };
+rewrite r_set { set("x" value("HOST")); subst("a", "b", value("MSG") flags(global)); };
+"#,
+        );
+    }
+
+    #[test]
+    fn round_trips_inline_log_paths() {
+        assert_round_trip(
+            r#"log {
+    source(s_net);
+    source { internal(); };
+    filter { level(err..emerg) and not program("cron") };
+    parser { json-parser(); };
+    destination { file("/var/log/errors"); };
+    log { destination(d_file); flags(final); };
+    flags(flow-control);
+};
+"#,
+        );
+    }
+
+    #[test]
+    fn round_trips_conditionals_and_junctions() {
+        assert_round_trip(
+            r#"log {
+    source(s_net);
+    if (level(err)) { destination(d_err); } elif ("${PID}" == 1) { destination(d_init); } else { destination(d_rest); };
+    if { filter(f_a); destination(d_a); };
+    junction {
+        channel { filter(f_a); destination(d_a); flags(final); };
+        channel { destination(d_b); };
+    };
+};
+"#,
+        );
+    }
+
+    #[test]
+    fn round_trips_templates() {
+        assert_round_trip(
+            r#"template t_line { template("${ISODATE} ${HOST} $(format-json --scope rfc5424)\n"); template-escape(no); };
+template t_tab { template("$MSG\t$PROGRAM\\x"); };
+destination d { file("/x" template(t_line)); file("/y" template("$(if ('$HOST' eq 'a') yes no)\n")); };
+"#,
+        );
+    }
+
+    #[test]
+    fn round_trips_filters() {
+        assert_round_trip(
+            r#"filter f_a { facility(mail, auth) or (level(debug) and not host("h1")) };
+filter f_b { not (filter(f_a) or message("x" type(glob))) and "$PROGRAM" ne "sshd" };
+filter f_c { (((program(a)))) or program(b) or program(c) and program(d) };
+filter f_d { not not level(err) };
+"#,
+        );
+    }
+
+    #[test]
+    fn round_trips_defines() {
+        assert_round_trip(
+            r#"@define root '/var/log # not a comment'
+@define backslash 'C:\logs\new'
+@define quoted 'say "hi"'
+@define plain "a\tb"
+@define port 514
+destination d { file("`root`/messages"); network("h" port(`port`)); };
+@include "conf.d/*.conf"
+@module python
+"#,
+        );
+    }
+
+    #[test]
+    fn round_trips_blocks() {
+        assert_round_trip(
+            r#"block source s_custom(port(514) host("localhost") ...) {
+    network(ip(`host`) port(`port`) `__VARARGS__`);
+};
+block destination d_custom() { file("/x" template("$MSG\n")); };
+source s { s_custom(port(601)); };
+"#,
+        );
+    }
+
+    /// A xorshift generator, so the generated configurations are the same
+    /// on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.below(items.len())]
+        }
+    }
+
+    const VALUES: &[&str] = &[
+        r#""/var/log/messages""#,
+        r#""${HOST} $MSG\n""#,
+        r#""tab\there""#,
+        r#""quote \" inside""#,
+        r#"'single \ quoted'"#,
+        r#""`root`/x""#,
+        "514",
+        "yes",
+        "tcp",
+        "`port`",
+    ];
+
+    fn driver(rng: &mut Rng, depth: usize) -> String {
+        let name = rng.pick(&["file", "network", "program", "kv-parser", "set", "tls", "disk-buffer"]);
+        let mut parameters = Vec::new();
+        for _ in 0..rng.below(4) {
+            if depth < 3 && rng.below(2) == 0 {
+                parameters.push(driver(rng, depth + 1));
+            } else {
+                parameters.push(rng.pick(VALUES).to_string());
+            }
+        }
+        format!("{}({})", name, parameters.join(" "))
+    }
+
+    fn expression(rng: &mut Rng, depth: usize) -> String {
+        match rng.below(if depth < 3 { 6 } else { 2 }) {
+            0 => format!(
+                "{}({})",
+                rng.pick(&["facility", "level", "program", "host"]),
+                rng.pick(VALUES)
+            ),
+            1 => format!(r#""$PID" {} {}"#, rng.pick(&["==", "!=", "<", ">="]), rng.below(100)),
+            2 => format!("not {}", expression(rng, depth + 1)),
+            3 => format!("({})", expression(rng, depth + 1)),
+            _ => format!(
+                "{} {} {}",
+                expression(rng, depth + 1),
+                rng.pick(&["and", "or"]),
+                expression(rng, depth + 1)
+            ),
+        }
+    }
+
+    fn log_statements(rng: &mut Rng, depth: usize) -> String {
+        let mut statements = String::new();
+        for _ in 0..1 + rng.below(4) {
+            let statement = match rng.below(if depth < 2 { 7 } else { 3 }) {
+                0 => format!(
+                    "{}(x_{});",
+                    rng.pick(&["source", "destination", "filter"]),
+                    rng.below(5)
+                ),
+                1 => format!("filter {{ {} }};", expression(rng, 0)),
+                2 => format!("destination {{ {}; }};", driver(rng, 0)),
+                3 => format!(
+                    "if ({}) {{ {} }} elif ({}) {{ {} }} else {{ {} }};",
+                    expression(rng, 0),
+                    log_statements(rng, depth + 1),
+                    expression(rng, 0),
+                    log_statements(rng, depth + 1),
+                    log_statements(rng, depth + 1)
+                ),
+                4 => format!(
+                    "junction {{ channel {{ {} }}; channel {{ {} }}; }};",
+                    log_statements(rng, depth + 1),
+                    log_statements(rng, depth + 1)
+                ),
+                5 => format!("log {{ {} }};", log_statements(rng, depth + 1)),
+                _ => "flags(final);".to_string(),
+            };
+            statements.push_str(&statement);
+            statements.push(' ');
+        }
+        statements
+    }
+
+    fn configuration(rng: &mut Rng) -> String {
+        let mut text = String::from("@version: 4.8\n@define root '/var # log'\n@define port \"514\"\n");
+        for i in 0..1 + rng.below(6) {
+            let item = match rng.below(5) {
+                0 => format!("source s_{} {{ {}; {}; }};", i, driver(rng, 0), driver(rng, 0)),
+                1 => format!("destination d_{} {{ {}; }};", i, driver(rng, 0)),
+                2 => format!("filter f_{} {{ {} }};", i, expression(rng, 0)),
+                3 => format!("template t_{} {{ template({}); }};", i, rng.pick(VALUES)),
+                _ => format!("log {{ {} }};", log_statements(rng, 0)),
+            };
+            text.push_str(&item);
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn round_trips_generated_configurations() {
+        for seed in 1..200 {
+            let mut rng = Rng(seed);
+            assert_round_trip(&configuration(&mut rng));
+        }
+    }
+
+    /// The string literals of `source`, unescaped.
+    fn string_literals(source: &str) -> Vec<String> {
+        let chars: Vec<char> = source.chars().collect();
+        let starts = |i: usize, prefix: &str| prefix.chars().enumerate().all(|(n, c)| chars.get(i + n) == Some(&c));
+        let mut literals = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if starts(i, "//") {
+                i += chars[i..].iter().position(|&c| c == '\n').unwrap_or(chars.len() - i);
+            } else if chars[i] == '\'' {
+                // A character literal such as '"' or '\'', or a lifetime.
+                let close = if chars.get(i + 1) == Some(&'\\') { 3 } else { 2 };
+                i += if chars.get(i + close) == Some(&'\'') {
+                    close + 1
+                } else {
+                    1
+                };
+            } else if chars[i] == 'r' && (starts(i + 1, "\"") || starts(i + 1, "#")) && !is_word_char(chars[i - 1]) {
+                let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+                let end = format!("\"{}", "#".repeat(hashes));
+                let start = i + 2 + hashes;
+                let length = (start..chars.len())
+                    .position(|j| starts(j, &end))
+                    .unwrap_or(chars.len() - start);
+                literals.push(chars[start..start + length].iter().collect());
+                i = start + length + end.len();
+            } else if chars[i] == '"' {
+                let mut literal = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] != '\\' {
+                        literal.push(chars[i]);
+                        i += 1;
+                        continue;
+                    }
+                    i += 1;
+                    match chars.get(i) {
+                        Some('n') => literal.push('\n'),
+                        Some('t') => literal.push('\t'),
+                        Some('r') => literal.push('\r'),
+                        Some('0') => literal.push('\0'),
+                        Some('\n') => {
+                            while chars.get(i + 1).is_some_and(|c| c.is_whitespace()) {
+                                i += 1;
+                            }
+                        }
+                        Some(&c) => literal.push(c),
+                        None => {}
+                    }
+                    i += 1;
+                }
+                literals.push(literal);
+                i += 1;
+            } else {
+                i += 1;
+            }
+        }
+        literals
+    }
+
+    fn rust_files(directory: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                rust_files(&path, files);
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_the_configurations_of_every_test() {
+        let mut files = Vec::new();
+        rust_files(
+            &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut files,
+        );
+        let mut checked = 0;
+        for file in files {
+            let source = std::fs::read_to_string(&file).unwrap();
+            for literal in string_literals(&source) {
+                if parse_conf(&literal).is_ok_and(|parsed| !parsed.objects.is_empty()) {
+                    assert_round_trip(&literal);
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked >= 20, "only {} configurations found", checked);
+    }
+
+    /// `name(...)` with every option of the grammar entry `option`, nested
+    /// blocks included, set to its default or to a string.
+    fn option_call(option: &OptionInfo) -> String {
+        if option.children.is_empty() {
+            return format!("{}({})", option.name, option.default.unwrap_or("\"x\""));
+        }
+        let children: Vec<String> = option.children.iter().map(option_call).collect();
+        format!("{}({})", option.name, children.join(" "))
+    }
+
+    #[test]
+    fn round_trips_the_grammar() {
+        for driver in grammar::DRIVERS {
+            let mut parameters: Vec<String> = driver.positional.iter().map(|_| "\"x\"".to_string()).collect();
+            parameters.extend(driver.all_options().map(option_call));
+            let id = if driver.context.has_id() { " x" } else { "" };
+            assert_round_trip(&format!(
+                "{}{} {{ {}({}); }};\n",
+                driver.context.keyword(),
+                id,
+                driver.name,
+                parameters.join(" ")
+            ));
+        }
+        let options: Vec<String> = grammar::GLOBAL_OPTIONS.iter().map(option_call).collect();
+        assert_round_trip(&format!("options {{ {}; }};\n", options.join("; ")));
+        let functions: Vec<String> = grammar::FILTER_FUNCTIONS.iter().map(option_call).collect();
+        assert_round_trip(&format!("filter f {{ {} }};\n", functions.join(" or ")));
+    }
+}
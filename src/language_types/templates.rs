@@ -0,0 +1,114 @@
+//! The contents of template strings, such as the value of
+//! `template("${ISODATE} $HOST $(format-json --scope rfc5424)\n")`: text
+//! with macros (`$HOST`, `${.SDATA.meta.id}`) and template functions
+//! (`$(name arguments...)`) in it.
+//!
+//! Positions are byte offsets into the string value; see
+//! [`crate::parser::template_parser::verbatim_range`] for turning them
+//! into document ranges.
+
+use std::ops::Range as Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    /// Text copied as is; `$$` stands for a single `$` in it.
+    Text {
+        text: String,
+        span: Span<usize>,
+    },
+    Macro(MacroReference),
+    Function(TemplateFunction),
+}
+
+impl TemplatePart {
+    pub fn span(&self) -> Span<usize> {
+        match self {
+            TemplatePart::Text { span, .. } => span.clone(),
+            TemplatePart::Macro(reference) => reference.span.clone(),
+            TemplatePart::Function(function) => function.span.clone(),
+        }
+    }
+}
+
+/// `$NAME`, `${NAME}` or a match group such as `$1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MacroReference {
+    pub name: String,
+    pub braced: bool,
+    /// The name alone.
+    pub name_span: Span<usize>,
+    /// From the `$` up to and including a closing brace.
+    pub span: Span<usize>,
+}
+
+/// `$(name arguments...)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateFunction {
+    pub name: String,
+    pub name_span: Span<usize>,
+    pub arguments: Vec<TemplateArgument>,
+    /// From the `$` up to and including the closing parenthesis.
+    pub span: Span<usize>,
+}
+
+/// A whitespace separated argument of a template function, itself a
+/// template; quotes around it are not part of `parts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateArgument {
+    pub parts: Vec<TemplatePart>,
+    pub span: Span<usize>,
+}
+
+impl TemplateArgument {
+    /// The argument without macros or functions in it, e.g. `--scope`.
+    pub fn text(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [TemplatePart::Text { text, .. }] => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// An unterminated `${` or `$(`, which syslog-ng rejects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateProblem {
+    pub span: Span<usize>,
+    pub message: String,
+}
+
+/// The macros of `parts`, those in function arguments included, in order.
+pub fn macros(parts: &[TemplatePart]) -> Vec<&MacroReference> {
+    fn collect<'a>(parts: &'a [TemplatePart], found: &mut Vec<&'a MacroReference>) {
+        for part in parts {
+            match part {
+                TemplatePart::Text { .. } => {}
+                TemplatePart::Macro(reference) => found.push(reference),
+                TemplatePart::Function(function) => {
+                    for argument in &function.arguments {
+                        collect(&argument.parts, found);
+                    }
+                }
+            }
+        }
+    }
+    let mut found = Vec::new();
+    collect(parts, &mut found);
+    found
+}
+
+/// The template functions of `parts`, nested ones included, in order.
+pub fn functions(parts: &[TemplatePart]) -> Vec<&TemplateFunction> {
+    fn collect<'a>(parts: &'a [TemplatePart], found: &mut Vec<&'a TemplateFunction>) {
+        for part in parts {
+            if let TemplatePart::Function(function) = part {
+                found.push(function);
+                for argument in &function.arguments {
+                    collect(&argument.parts, found);
+                }
+            }
+        }
+    }
+    let mut found = Vec::new();
+    collect(parts, &mut found);
+    found
+}
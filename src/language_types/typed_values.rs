@@ -0,0 +1,96 @@
+//! syslog-ng 4.x type hints on values:
+//!
+//! ```text
+//! rewrite { set(int("$PID") value("pid")); };
+//! destination { file("/var/log/x.json" template("$(format-json pid=int($PID))\n")); };
+//! ```
+
+use crate::grammar::{self, TypeHint};
+use crate::language_types::objects::{Driver, Literal, LiteralKind, Object, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Position, Range};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedValue {
+    pub hint: &'static TypeHint,
+    /// The name of the hint, or the whole string literal holding it if
+    /// positions inside that string are not known.
+    pub range: Range,
+    /// Inside a template string, like `int` in `"$(format-json pid=int($PID))"`.
+    pub in_string: bool,
+}
+
+/// Every type hint of `parsed`, in document order.
+pub fn typed_values(parsed: &ParsedConfiguration) -> Vec<TypedValue> {
+    let mut values = Vec::new();
+    for object in &parsed.objects {
+        collect_object(object, &mut values);
+    }
+    values
+}
+
+fn collect_object(object: &Object, values: &mut Vec<TypedValue>) {
+    for statement in object.flat_statements() {
+        match statement {
+            Statement::Object(inline) => collect_object(inline, values),
+            Statement::Driver(driver) => collect_driver(driver, false, values),
+            Statement::Conditional(_) | Statement::Junction(_) => {}
+        }
+    }
+}
+
+/// A call wrapping exactly one value is a type hint rather than an option
+/// when it is named like one; drivers at the top of a body never are.
+fn collect_driver(driver: &Driver, nested: bool, values: &mut Vec<TypedValue>) {
+    if nested && driver.parameters.len() == 1 && driver.literals().count() == 1 {
+        if let Some(hint) = grammar::find_type_hint(&driver.name.name) {
+            values.push(TypedValue {
+                hint,
+                range: driver.name.range,
+                in_string: false,
+            });
+        }
+    }
+    for literal in driver.literals().filter(|l| l.kind == LiteralKind::String) {
+        collect_string(literal, values);
+    }
+    for option in driver.options() {
+        collect_driver(option, true, values);
+    }
+}
+
+/// `name=hint(...)` pairs inside a template string.
+fn collect_string(literal: &Literal, values: &mut Vec<TypedValue>) {
+    let range = literal.range;
+    // Positions inside the string are only known when it is the value
+    // verbatim: on one line, without escapes or defines.
+    let verbatim = literal.raw.is_none()
+        && range.start.line == range.end.line
+        && range.end.character - range.start.character == utf16_len(&literal.value) + 2;
+    for (offset, _) in literal.value.match_indices('=') {
+        let after = &literal.value[offset + 1..];
+        let name_len = after.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(after.len());
+        let Some(hint) = grammar::find_type_hint(&after[..name_len]).filter(|_| after[name_len..].starts_with('('))
+        else {
+            continue;
+        };
+        let range = if verbatim {
+            let start = range.start.character + 1 + utf16_len(&literal.value[..offset + 1]);
+            Range::new(
+                Position::new(range.start.line, start),
+                Position::new(range.start.line, start + name_len as u32),
+            )
+        } else {
+            range
+        };
+        values.push(TypedValue {
+            hint,
+            range,
+            in_string: true,
+        });
+    }
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|c| c.len_utf16() as u32).sum()
+}
@@ -0,0 +1,175 @@
+//! Typed view of value-pairs, the selection of name-value pairs taken by
+//! `map-value-pairs()` and by the `value-pairs()` option of drivers:
+//!
+//! ```text
+//! parser { map-value-pairs(scope(nv-pairs) key("MESSAGE") rekey(".cee.*" shift(5) add-prefix("cee."))); };
+//! ```
+
+use crate::grammar::{normalize_name, VALUE_PAIRS_SCOPES};
+use crate::language_types::objects::{Driver, Literal, LiteralKind, Parameter};
+use crate::lsp_types::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValuePairs<'a> {
+    pub scopes: Vec<&'a Literal>,
+    pub rekeys: Vec<Rekey<'a>>,
+    /// The `map-value-pairs(...)` driver or `value-pairs(...)` option.
+    pub location: Range,
+}
+
+/// `rekey(pattern... transformation...)`: the keys matching one of the
+/// glob patterns are renamed by each transformation in turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rekey<'a> {
+    pub patterns: Vec<&'a Literal>,
+    pub transformations: Vec<Transformation<'a>>,
+    pub location: Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transformation<'a> {
+    Shift(usize),
+    ShiftLevels(usize),
+    AddPrefix(&'a Literal),
+    ReplacePrefix { old: &'a Literal, new: &'a Literal },
+}
+
+/// Something wrong with a value-pairs block, found while reading it.
+/// syslog-ng rejects all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValuePairsProblem {
+    pub range: Range,
+    pub message: String,
+}
+
+impl ValuePairsProblem {
+    fn new(range: Range, message: String) -> Self {
+        ValuePairsProblem { range, message }
+    }
+}
+
+fn is_named(driver: &Driver, name: &str) -> bool {
+    normalize_name(&driver.name.name) == name
+}
+
+impl<'a> ValuePairs<'a> {
+    /// Reads `map-value-pairs(...)` or `value-pairs(...)`. Options other
+    /// than `scope()` and `rekey()` are kept to the grammar.
+    pub fn from_block(block: &'a Driver, problems: &mut Vec<ValuePairsProblem>) -> Self {
+        let mut scopes = Vec::new();
+        let mut rekeys = Vec::new();
+        for option in block.options() {
+            if is_named(option, "scope") {
+                for parameter in &option.parameters {
+                    match parameter {
+                        Parameter::Literal(scope)
+                            if VALUE_PAIRS_SCOPES.contains(&normalize_name(&scope.value).as_str()) =>
+                        {
+                            scopes.push(scope)
+                        }
+                        Parameter::Literal(scope) => problems.push(ValuePairsProblem::new(
+                            scope.range,
+                            format!("unknown scope `{}`", scope.value),
+                        )),
+                        Parameter::Option(nested) => problems.push(ValuePairsProblem::new(
+                            nested.name.range,
+                            "scope() takes a list of scope names".to_string(),
+                        )),
+                    }
+                }
+            } else if is_named(option, "rekey") {
+                rekeys.push(Rekey::from_block(option, problems));
+            }
+        }
+        ValuePairs {
+            scopes,
+            rekeys,
+            location: block.location,
+        }
+    }
+}
+
+impl<'a> Rekey<'a> {
+    fn from_block(block: &'a Driver, problems: &mut Vec<ValuePairsProblem>) -> Self {
+        let mut patterns = Vec::new();
+        let mut transformations = Vec::new();
+        for parameter in &block.parameters {
+            let option = match parameter {
+                Parameter::Literal(pattern) => {
+                    patterns.push(pattern);
+                    continue;
+                }
+                Parameter::Option(option) => option,
+            };
+            let transformation = if is_named(option, "shift") {
+                count(option, problems).map(Transformation::Shift)
+            } else if is_named(option, "shift-levels") {
+                count(option, problems).map(Transformation::ShiftLevels)
+            } else if is_named(option, "add-prefix") {
+                match strings(option, 1, problems)[..] {
+                    [prefix] => Some(Transformation::AddPrefix(prefix)),
+                    _ => None,
+                }
+            } else if is_named(option, "replace-prefix") || is_named(option, "replace") {
+                match strings(option, 2, problems)[..] {
+                    [old, new] => Some(Transformation::ReplacePrefix { old, new }),
+                    _ => None,
+                }
+            } else {
+                problems.push(ValuePairsProblem::new(
+                    option.name.range,
+                    format!(
+                        "unknown rekey() transformation `{}`, expected shift(), shift-levels(), add-prefix() or replace-prefix()",
+                        option.name.name
+                    ),
+                ));
+                None
+            };
+            transformations.extend(transformation);
+        }
+        if patterns.is_empty() {
+            problems.push(ValuePairsProblem::new(
+                block.name.range,
+                "rekey() needs a glob pattern of the keys to rename".to_string(),
+            ));
+        }
+        Rekey {
+            patterns,
+            transformations,
+            location: block.location,
+        }
+    }
+}
+
+/// The argument of `shift()` and `shift-levels()`.
+fn count(option: &Driver, problems: &mut Vec<ValuePairsProblem>) -> Option<usize> {
+    let mut literals = option.literals();
+    match (literals.next(), literals.next(), option.options().next()) {
+        (Some(literal), None, None) if literal.kind == LiteralKind::Number => {
+            if let Ok(count) = literal.value.parse() {
+                return Some(count);
+            }
+        }
+        _ => {}
+    }
+    problems.push(ValuePairsProblem::new(
+        option.location,
+        format!("{}() expects a whole number", option.name.name),
+    ));
+    None
+}
+
+/// The `expected` values of `option`, or nothing when it has a different
+/// number of them.
+fn strings<'a>(option: &'a Driver, expected: usize, problems: &mut Vec<ValuePairsProblem>) -> Vec<&'a Literal> {
+    let literals: Vec<&Literal> = option.literals().collect();
+    if literals.len() == expected && option.options().next().is_none() {
+        return literals;
+    }
+    let message = match expected {
+        1 => format!("{}() expects one value", option.name.name),
+        n => format!("{}() expects {} values", option.name.name, n),
+    };
+    problems.push(ValuePairsProblem::new(option.location, message));
+    Vec::new()
+}
@@ -0,0 +1,35 @@
+//! A language server for syslog-ng configuration files.
+
+pub mod backend;
+pub mod builder;
+pub mod call_hierarchy;
+pub mod cli;
+pub mod code_actions;
+pub mod commands;
+pub mod completion;
+pub mod configuration;
+pub mod diagnostics;
+pub mod diff;
+pub mod document_store;
+pub mod file_utilities;
+pub mod formatter;
+pub mod grammar;
+pub mod hover;
+pub mod inlay_hints;
+pub mod json;
+pub mod language_types;
+pub mod linked_editing;
+pub mod logging;
+pub mod lsp_types;
+pub mod parser;
+pub mod regexp;
+pub mod selection_ranges;
+pub mod semantic_tokens;
+pub mod settings;
+pub mod snapshot;
+pub mod structure;
+pub mod symbols;
+pub mod syslog_ng;
+pub mod time_zones;
+pub mod trace;
+pub mod transport;
@@ -0,0 +1,56 @@
+//! `textDocument/linkedEditingRange`: the name of a `block` definition and
+//! its invocations in the same file are edited together.
+//!
+//! Block definitions are not part of the AST, so they are found in the
+//! token stream: `block <context> <name>(` defines `<name>`, and every other
+//! `<name>(` invokes it.
+
+use crate::lsp_types::{LinkedEditingRanges, Position, Range};
+use crate::parser::lexer::{tokenize, Token, TokenKind};
+
+/// What the client accepts as a block name while editing; the ASCII
+/// subset of `is_valid_identifier`, as clients compile it without the
+/// Unicode flag.
+const WORD_PATTERN: &str = r"[A-Za-z_][\w\-.]*";
+
+/// The definition and invocations of the block whose name is under
+/// `position`, or `None` if there is no block name there.
+pub fn linked_editing_ranges(text: &str, position: Position) -> Option<LinkedEditingRanges> {
+    let tokens = tokenize(text).ok()?;
+    block_definitions(&tokens)
+        .into_iter()
+        .map(|definition| {
+            let name = &tokens[definition].text;
+            tokens
+                .iter()
+                .enumerate()
+                .filter(|&(i, t)| {
+                    t.is(TokenKind::Word)
+                        && t.text == *name
+                        && (i == definition || tokens.get(i + 1).is_some_and(|next| next.is(TokenKind::LParen)))
+                })
+                .map(|(_, t)| t.range)
+                .collect::<Vec<Range>>()
+        })
+        .find(|ranges| ranges.iter().any(|r| r.contains(position)))
+        .map(|ranges| LinkedEditingRanges {
+            ranges,
+            word_pattern: Some(WORD_PATTERN.to_string()),
+        })
+}
+
+/// Indices of the name tokens of `block <context> <name>(` definitions.
+fn block_definitions(tokens: &[Token]) -> Vec<usize> {
+    tokens
+        .windows(4)
+        .enumerate()
+        .filter(|(_, w)| {
+            w[0].is(TokenKind::Word)
+                && w[0].text == "block"
+                && w[1].is(TokenKind::Word)
+                && w[2].is(TokenKind::Word)
+                && w[3].is(TokenKind::LParen)
+        })
+        .map(|(i, _)| i + 2)
+        .collect()
+}
@@ -0,0 +1,206 @@
+//! Log output of the server for debugging it, separate from the protocol on
+//! stdout: `--log-file <path>` writes events to a file that is rotated once
+//! it grows past [`MAX_FILE_SIZE`], and `--log-level` picks how much is
+//! written (`info` unless given). Without a file, a given level writes to
+//! stderr instead. Messages for the user still go to the client through
+//! [`crate::transport::Client::log_message`], which records them here too.
+//!
+//! Events are lines of `time level target: message key=value...`, written
+//! with [`log_event!`](crate::log_event).
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::lsp_types::MessageType;
+
+/// Size after which the log file is renamed to `<path>.1`, replacing an
+/// older one, and a new file started.
+pub const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn parse(name: &str) -> Option<Level> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl From<MessageType> for Level {
+    fn from(typ: MessageType) -> Self {
+        match typ {
+            MessageType::Error => Level::Error,
+            MessageType::Warning => Level::Warn,
+            MessageType::Info => Level::Info,
+            MessageType::Log => Level::Debug,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// What the command line asked for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LogOptions {
+    pub file: Option<PathBuf>,
+    pub level: Option<Level>,
+}
+
+enum Output {
+    Stderr,
+    File { path: PathBuf, file: File, size: u64 },
+}
+
+struct Logger {
+    level: Level,
+    output: Mutex<Output>,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Starts writing events as `options` say; nothing is written when they
+/// name neither a file nor a level. Only the first call has an effect.
+pub fn init(options: &LogOptions) -> io::Result<()> {
+    let level = options.level.unwrap_or(Level::Info);
+    let output = match &options.file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let size = file.metadata()?.len();
+            Output::File {
+                path: path.clone(),
+                file,
+                size,
+            }
+        }
+        None if options.level.is_some() => Output::Stderr,
+        None => return Ok(()),
+    };
+    let _ = LOGGER.set(Logger {
+        level,
+        output: Mutex::new(output),
+    });
+    Ok(())
+}
+
+/// Whether events of `level` are written anywhere, to skip formatting
+/// those that are not.
+pub fn enabled(level: Level) -> bool {
+    LOGGER.get().is_some_and(|logger| level <= logger.level)
+}
+
+/// Writes one event. Use [`log_event!`](crate::log_event), which checks
+/// [`enabled`] first.
+pub fn write(level: Level, target: &str, message: &str) {
+    let Some(logger) = LOGGER.get().filter(|logger| level <= logger.level) else {
+        return;
+    };
+    // Continuation lines are indented so every event starts a line.
+    let line = format!(
+        "{} {:5} {}: {}\n",
+        timestamp(SystemTime::now()),
+        level,
+        target,
+        message.replace('\n', "\n    ")
+    );
+    let mut output = logger.output.lock().unwrap_or_else(|e| e.into_inner());
+    match &mut *output {
+        Output::Stderr => {
+            let _ = io::stderr().write_all(line.as_bytes());
+        }
+        Output::File { path, file, size } => {
+            if *size > 0 && *size + line.len() as u64 > MAX_FILE_SIZE {
+                let mut rotated = path.clone().into_os_string();
+                rotated.push(".1");
+                let reopened = fs::rename(&*path, &rotated)
+                    .and_then(|_| OpenOptions::new().create(true).append(true).open(&*path));
+                if let Ok(new_file) = reopened {
+                    *file = new_file;
+                    *size = 0;
+                }
+            }
+            if file.write_all(line.as_bytes()).is_ok() {
+                *size += line.len() as u64;
+            }
+        }
+    }
+}
+
+/// Writes an event of the given [`Level`] from the current module if that
+/// level is enabled, formatting the message like `format!`:
+/// `log_event!(Level::Debug, "parsed {} file={}", what, uri)`.
+#[macro_export]
+macro_rules! log_event {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::logging::enabled($level) {
+            $crate::logging::write($level, module_path!(), &format!($($arg)*));
+        }
+    };
+}
+
+/// `2026-01-31T12:34:56.789Z`, in UTC.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_date((seconds / 86_400) as i64);
+    let time_of_day = seconds % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Year, month and day of the day `days` after 1970-01-01, by Howard
+/// Hinnant's `civil_from_days`.
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
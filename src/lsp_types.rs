@@ -0,0 +1,655 @@
+//! The subset of Language Server Protocol structures used by the server.
+
+use std::collections::BTreeMap;
+
+use crate::json::Value;
+use crate::json_object;
+
+pub trait ToJson {
+    fn to_json(&self) -> Value;
+}
+
+pub trait FromJson: Sized {
+    fn from_json(value: &Value) -> Option<Self>;
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Value {
+        Value::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Position {
+    pub fn new(line: u32, character: u32) -> Self {
+        Position { line, character }
+    }
+}
+
+impl ToJson for Position {
+    fn to_json(&self) -> Value {
+        json_object! { "line" => self.line, "character" => self.character }
+    }
+}
+
+impl FromJson for Position {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Position {
+            line: value["line"].as_u64()? as u32,
+            character: value["character"].as_u64()? as u32,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    pub fn new(start: Position, end: Position) -> Self {
+        Range { start, end }
+    }
+
+    pub fn contains(&self, position: Position) -> bool {
+        self.start <= position && position <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Range) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+impl ToJson for Range {
+    fn to_json(&self) -> Value {
+        json_object! { "start" => self.start.to_json(), "end" => self.end.to_json() }
+    }
+}
+
+impl FromJson for Range {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(Range {
+            start: Position::from_json(&value["start"])?,
+            end: Position::from_json(&value["end"])?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+impl ToJson for Location {
+    fn to_json(&self) -> Value {
+        json_object! { "uri" => &self.uri, "range" => self.range.to_json() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticRelatedInformation {
+    pub location: Location,
+    pub message: String,
+}
+
+impl ToJson for DiagnosticRelatedInformation {
+    fn to_json(&self) -> Value {
+        json_object! { "location" => self.location.to_json(), "message" => &self.message }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: DiagnosticSeverity,
+    pub code: Option<String>,
+    pub source: String,
+    pub message: String,
+    pub related_information: Vec<DiagnosticRelatedInformation>,
+    /// Link to documentation explaining the diagnostic.
+    pub code_description: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(range: Range, severity: DiagnosticSeverity, message: impl Into<String>) -> Self {
+        Diagnostic {
+            range,
+            severity,
+            code: None,
+            source: "syslog-ng".to_string(),
+            message: message.into(),
+            related_information: Vec::new(),
+            code_description: None,
+        }
+    }
+}
+
+impl ToJson for Diagnostic {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "range" => self.range.to_json(),
+            "severity" => self.severity as u32,
+            "source" => &self.source,
+            "message" => &self.message,
+        };
+        if let Some(code) = &self.code {
+            value.insert("code", code);
+        }
+        if !self.related_information.is_empty() {
+            value.insert("relatedInformation", self.related_information.to_json());
+        }
+        if let Some(href) = &self.code_description {
+            value.insert("codeDescription", json_object! { "href" => href });
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Error = 1,
+    Warning = 2,
+    Info = 3,
+    Log = 4,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDocumentSyncKind {
+    None = 0,
+    Full = 1,
+    Incremental = 2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range,
+    pub new_text: String,
+}
+
+impl ToJson for TextEdit {
+    fn to_json(&self) -> Value {
+        json_object! { "range" => self.range.to_json(), "newText" => &self.new_text }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    File = 1,
+    Module = 2,
+    Namespace = 3,
+    Package = 4,
+    Class = 5,
+    Method = 6,
+    Property = 7,
+    Field = 8,
+    Constructor = 9,
+    Enum = 10,
+    Interface = 11,
+    Function = 12,
+    Variable = 13,
+    Constant = 14,
+    String = 15,
+    Number = 16,
+    Boolean = 17,
+    Array = 18,
+    Object = 19,
+    Key = 20,
+    Null = 21,
+    EnumMember = 22,
+    Struct = 23,
+    Event = 24,
+    Operator = 25,
+    TypeParameter = 26,
+}
+
+/// An entry of the document outline. `selection_range` is the part that is
+/// highlighted when the symbol is picked, e.g. the identifier of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub detail: Option<String>,
+    pub kind: SymbolKind,
+    pub range: Range,
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+impl ToJson for DocumentSymbol {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "name" => &self.name,
+            "kind" => self.kind as u32,
+            "range" => self.range.to_json(),
+            "selectionRange" => self.selection_range.to_json(),
+        };
+        if let Some(detail) = &self.detail {
+            value.insert("detail", detail);
+        }
+        if !self.children.is_empty() {
+            value.insert("children", self.children.to_json());
+        }
+        value
+    }
+}
+
+/// A `workspace/symbol` result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolInformation {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub location: Location,
+    pub container_name: Option<String>,
+}
+
+impl ToJson for SymbolInformation {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "name" => &self.name,
+            "kind" => self.kind as u32,
+            "location" => self.location.to_json(),
+        };
+        if let Some(container_name) = &self.container_name {
+            value.insert("containerName", container_name);
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionItemKind {
+    Text = 1,
+    Method = 2,
+    Function = 3,
+    Constructor = 4,
+    Field = 5,
+    Variable = 6,
+    Class = 7,
+    Interface = 8,
+    Module = 9,
+    Property = 10,
+    Unit = 11,
+    Value = 12,
+    Enum = 13,
+    Keyword = 14,
+    Snippet = 15,
+    Color = 16,
+    File = 17,
+    Reference = 18,
+    Folder = 19,
+    EnumMember = 20,
+    Constant = 21,
+    Struct = 22,
+    Event = 23,
+    Operator = 24,
+    TypeParameter = 25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertTextFormat {
+    PlainText = 1,
+    /// `$1`, `${1:default}` and `$0` placeholders, see the LSP snippet syntax.
+    Snippet = 2,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub kind: CompletionItemKind,
+    pub detail: Option<String>,
+    /// Markdown.
+    pub documentation: Option<String>,
+    /// Sent back by the client in `completionItem/resolve`.
+    pub data: Option<Value>,
+    pub text_edit: TextEdit,
+    pub insert_text_format: InsertTextFormat,
+}
+
+impl ToJson for CompletionItem {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "label" => &self.label,
+            "kind" => self.kind as u32,
+            "textEdit" => self.text_edit.to_json(),
+            "insertTextFormat" => self.insert_text_format as u32,
+        };
+        if let Some(detail) = &self.detail {
+            value.insert("detail", detail);
+        }
+        if let Some(documentation) = &self.documentation {
+            value.insert(
+                "documentation",
+                json_object! { "kind" => "markdown", "value" => documentation },
+            );
+        }
+        if let Some(data) = &self.data {
+            value.insert("data", data.clone());
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub title: String,
+    pub command: String,
+    pub arguments: Vec<Value>,
+}
+
+impl ToJson for Command {
+    fn to_json(&self) -> Value {
+        json_object! {
+            "title" => &self.title,
+            "command" => &self.command,
+            "arguments" => self.arguments.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeLens {
+    pub range: Range,
+    pub command: Command,
+}
+
+impl ToJson for CodeLens {
+    fn to_json(&self) -> Value {
+        json_object! { "range" => self.range.to_json(), "command" => self.command.to_json() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CodeActionKind {
+    #[default]
+    QuickFix,
+    RefactorExtract,
+    SourceFixAll,
+}
+
+impl CodeActionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CodeActionKind::QuickFix => "quickfix",
+            CodeActionKind::RefactorExtract => "refactor.extract",
+            CodeActionKind::SourceFixAll => "source.fixAll",
+        }
+    }
+}
+
+/// A quick fix or refactoring offered by `textDocument/codeAction`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeAction {
+    pub title: String,
+    pub kind: CodeActionKind,
+    pub diagnostics: Vec<Diagnostic>,
+    pub edit: WorkspaceEdit,
+    pub is_preferred: bool,
+}
+
+impl ToJson for CodeAction {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "title" => &self.title,
+            "kind" => self.kind.as_str(),
+            "edit" => self.edit.to_json(),
+        };
+        if !self.diagnostics.is_empty() {
+            value.insert("diagnostics", self.diagnostics.to_json());
+        }
+        if self.is_preferred {
+            value.insert("isPreferred", true);
+        }
+        value
+    }
+}
+
+/// A parameter name shown in front of a value by `textDocument/inlayHint`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InlayHint {
+    pub position: Position,
+    pub label: String,
+}
+
+impl ToJson for InlayHint {
+    fn to_json(&self) -> Value {
+        json_object! {
+            "position" => self.position.to_json(),
+            "label" => &self.label,
+            // InlayHintKind.Parameter
+            "kind" => 2,
+            "paddingRight" => true,
+        }
+    }
+}
+
+/// One step of expand-selection; `parent` contains `range`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectionRange {
+    pub range: Range,
+    pub parent: Option<Box<SelectionRange>>,
+}
+
+impl ToJson for SelectionRange {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! { "range" => self.range.to_json() };
+        if let Some(parent) = &self.parent {
+            value.insert("parent", parent.to_json());
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentHighlightKind {
+    Text = 1,
+    Read = 2,
+    Write = 3,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentHighlight {
+    pub range: Range,
+    pub kind: DocumentHighlightKind,
+}
+
+impl ToJson for DocumentHighlight {
+    fn to_json(&self) -> Value {
+        json_object! { "range" => self.range.to_json(), "kind" => self.kind as u32 }
+    }
+}
+
+/// An entry of the call hierarchy. `data` is sent back by the client when
+/// the calls of the item are requested.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallHierarchyItem {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub detail: Option<String>,
+    pub uri: String,
+    pub range: Range,
+    pub selection_range: Range,
+    pub data: Value,
+}
+
+impl ToJson for CallHierarchyItem {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "name" => &self.name,
+            "kind" => self.kind as u32,
+            "uri" => &self.uri,
+            "range" => self.range.to_json(),
+            "selectionRange" => self.selection_range.to_json(),
+            "data" => self.data.clone(),
+        };
+        if let Some(detail) = &self.detail {
+            value.insert("detail", detail);
+        }
+        value
+    }
+}
+
+/// An incoming or outgoing call: `item` is the caller of an incoming call
+/// and the callee of an outgoing one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallHierarchyCall {
+    pub item: CallHierarchyItem,
+    pub from_ranges: Vec<Range>,
+}
+
+impl CallHierarchyCall {
+    pub fn to_json(&self, incoming: bool) -> Value {
+        json_object! {
+            if incoming { "from" } else { "to" } => self.item.to_json(),
+            "fromRanges" => self.from_ranges.to_json(),
+        }
+    }
+}
+
+/// Ranges that `textDocument/linkedEditingRange` edits together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkedEditingRanges {
+    pub ranges: Vec<Range>,
+    pub word_pattern: Option<String>,
+}
+
+impl ToJson for LinkedEditingRanges {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! { "ranges" => self.ranges.to_json() };
+        if let Some(pattern) = &self.word_pattern {
+            value.insert("wordPattern", pattern.as_str());
+        }
+        value
+    }
+}
+
+/// Result of `textDocument/hover`; the contents are Markdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hover {
+    pub contents: String,
+    pub range: Option<Range>,
+}
+
+impl ToJson for Hover {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "contents" => json_object! { "kind" => "markdown", "value" => &self.contents },
+        };
+        if let Some(range) = &self.range {
+            value.insert("range", range.to_json());
+        }
+        value
+    }
+}
+
+/// Edits grouped by document URI.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceEdit {
+    pub changes: BTreeMap<String, Vec<TextEdit>>,
+    /// URIs of files to create before the edits are applied. Needs a client
+    /// supporting the `create` resource operation.
+    pub create_files: Vec<String>,
+}
+
+impl WorkspaceEdit {
+    pub fn push(&mut self, uri: &str, edit: TextEdit) {
+        self.changes.entry(uri.to_string()).or_default().push(edit);
+    }
+
+    pub fn edit_count(&self) -> usize {
+        self.changes.values().map(Vec::len).sum()
+    }
+}
+
+impl ToJson for WorkspaceEdit {
+    fn to_json(&self) -> Value {
+        if !self.create_files.is_empty() {
+            // Resource operations only exist in `documentChanges`, which
+            // applies them in order: the files are created first.
+            let mut document_changes: Vec<Value> = self
+                .create_files
+                .iter()
+                .map(|uri| json_object! { "kind" => "create", "uri" => uri })
+                .collect();
+            document_changes.extend(self.changes.iter().map(|(uri, edits)| {
+                json_object! {
+                    "textDocument" => json_object! { "uri" => uri, "version" => Value::Null },
+                    "edits" => edits.to_json(),
+                }
+            }));
+            return json_object! { "documentChanges" => document_changes };
+        }
+        let mut changes = Value::Object(Default::default());
+        for (uri, edits) in &self.changes {
+            changes.insert(uri, edits.to_json());
+        }
+        json_object! { "changes" => changes }
+    }
+}
+
+/// One entry of `didChange`'s `contentChanges`. Without a range the text
+/// replaces the whole document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextDocumentContentChangeEvent {
+    pub range: Option<Range>,
+    pub text: String,
+}
+
+impl FromJson for TextDocumentContentChangeEvent {
+    fn from_json(value: &Value) -> Option<Self> {
+        Some(TextDocumentContentChangeEvent {
+            range: Range::from_json(&value["range"]),
+            text: value["text"].as_str()?.to_string(),
+        })
+    }
+}
+
+/// A JSON-RPC error returned in place of a request result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ResponseError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// The request was valid but could not be carried out, e.g. formatting a
+    /// document with syntax errors.
+    pub const REQUEST_FAILED: i64 = -32803;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        ResponseError {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        ResponseError::new(Self::INVALID_PARAMS, message)
+    }
+}
+
+impl ToJson for ResponseError {
+    fn to_json(&self) -> Value {
+        json_object! { "code" => self.code, "message" => &self.message }
+    }
+}
+
+pub type LspResult<T> = Result<T, ResponseError>;
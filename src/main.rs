@@ -1,3 +1,49 @@
+use std::io;
+
+use lsp_syslog_ng::logging::{self, Level, LogOptions};
+
+const LOG_USAGE: &str = "usage: lsp-syslog-ng [--log-file <path>] [--log-level error|warn|info|debug|trace]";
+
+/// The value of `--name <value>` or `--name=<value>`; `Some(None)` when the
+/// flag is given without one.
+fn flag_value<'a>(args: &'a [String], name: &str) -> Option<Option<&'a str>> {
+    let prefix = format!("{}=", name);
+    let i = args.iter().position(|a| a == name || a.starts_with(&prefix))?;
+    Some(match args[i].strip_prefix(&prefix) {
+        Some(value) => Some(value),
+        None => args.get(i + 1).map(String::as_str),
+    })
+}
+
 fn main() {
-    println!("Hello, world!");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "check") {
+        std::process::exit(lsp_syslog_ng::cli::check(&args[1..]));
+    }
+    let mut log_options = LogOptions::default();
+    if let Some(path) = flag_value(&args, "--log-file") {
+        let Some(path) = path else {
+            eprintln!("{}", LOG_USAGE);
+            std::process::exit(2);
+        };
+        log_options.file = Some(path.into());
+    }
+    if let Some(level) = flag_value(&args, "--log-level") {
+        let Some(level) = level.and_then(Level::parse) else {
+            eprintln!("{}", LOG_USAGE);
+            std::process::exit(2);
+        };
+        log_options.level = Some(level);
+    }
+    if let Err(error) = logging::init(&log_options) {
+        eprintln!("cannot open log file: {}", error);
+        std::process::exit(2);
+    }
+    // Other arguments, such as the `--stdio` some clients pass, are ignored.
+    if let Some(address) = flag_value(&args, "--listen") {
+        std::process::exit(lsp_syslog_ng::cli::listen(address));
+    }
+    let stdin = io::stdin();
+    let code = lsp_syslog_ng::backend::run(&mut stdin.lock(), Box::new(io::stdout()));
+    std::process::exit(code);
 }
@@ -0,0 +1,233 @@
+//! Parsing of `@keyword ...` pragma lines.
+
+use crate::language_types::annotations::{
+    Annotation, DefineAnnotation, IncludeAnnotation, ModuleAnnotation, OtherAnnotation, RequiresAnnotation,
+    VersionAnnotation,
+};
+use crate::lsp_types::{Position, Range};
+use crate::parser::lexer::Token;
+use crate::parser::{SngSyntaxError, SngSyntaxErrorKind};
+
+/// Range of `text[from..to]` inside a single-line pragma token.
+fn sub_range(token: &Token, from: usize, to: usize) -> Range {
+    let column = |offset: usize| token.range.start.character + token.text[..offset].encode_utf16().count() as u32;
+    let line = token.range.start.line;
+    Range::new(Position::new(line, column(from)), Position::new(line, column(to)))
+}
+
+/// Where what should follow `text[..from]` is missing: the text there
+/// instead, or the part of the line before it when nothing follows.
+fn missing_range(token: &Token, from: usize) -> Range {
+    let text = token.text.as_str();
+    let start = from + (text[from..].len() - text[from..].trim_start().len());
+    if start < text.len() {
+        sub_range(token, start, text.len())
+    } else {
+        sub_range(token, 0, from)
+    }
+}
+
+pub fn parse_annotation(token: &Token) -> Result<Annotation, SngSyntaxError> {
+    let text = token.text.as_str();
+    let name_end = text[1..]
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .map_or(text.len(), |i| i + 1);
+    let name = &text[1..name_end];
+
+    match name {
+        "version" => parse_version(token, name_end),
+        "include" => parse_include(token, name_end),
+        "define" => parse_define(token, name_end),
+        "module" => parse_module(token, name_end),
+        "requires" => parse_requires(token, name_end),
+        "" => Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation("missing annotation keyword after `@`".to_string()),
+            sub_range(token, 0, 1),
+        )),
+        _ => Ok(Annotation::Other(OtherAnnotation {
+            name: name.to_string(),
+            arguments: text[name_end..].trim().to_string(),
+            range: token.range,
+        })),
+    }
+}
+
+fn parse_version(token: &Token, name_end: usize) -> Result<Annotation, SngSyntaxError> {
+    let text = token.text.as_str();
+    let rest = &text[name_end..];
+    let Some(colon) = rest.find(':').filter(|&i| rest[..i].trim().is_empty()) else {
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation("expected `:` after `@version`".to_string()),
+            missing_range(token, name_end),
+        ));
+    };
+    let value_start = name_end + colon + 1;
+    let value = text[value_start..].trim();
+    let value_start = value_start + (text[value_start..].len() - text[value_start..].trim_start().len());
+    let value_end = value_start + value.len();
+
+    let is_valid = {
+        let mut parts = value.split('.');
+        let major = parts.next().unwrap_or_default();
+        let minor = parts.next().unwrap_or_default();
+        let numeric = |p: &str| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit());
+        numeric(major) && numeric(minor) && parts.next().is_none()
+    };
+    if !is_valid {
+        let range = if value.is_empty() {
+            token.range
+        } else {
+            sub_range(token, value_start, value_end)
+        };
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation(format!(
+                "`{}` is not a valid version, expected `<major>.<minor>`",
+                value
+            )),
+            range,
+        ));
+    }
+
+    Ok(Annotation::Version(VersionAnnotation {
+        version: value.to_string(),
+        range: token.range,
+        value_range: sub_range(token, value_start, value_end),
+    }))
+}
+
+fn parse_define(token: &Token, name_end: usize) -> Result<Annotation, SngSyntaxError> {
+    let text = token.text.as_str();
+    let rest = &text[name_end..];
+    let name_start = name_end + (rest.len() - rest.trim_start().len());
+    let name_len = text[name_start..]
+        .find(char::is_whitespace)
+        .unwrap_or(text.len() - name_start);
+    let name = &text[name_start..name_start + name_len];
+    if name.is_empty() {
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation("expected a name after `@define`".to_string()),
+            missing_range(token, name_end),
+        ));
+    }
+    let value = text[name_start + name_len..].trim();
+    let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+        // Single quotes are taken verbatim, as in strings.
+        None => value
+            .strip_prefix('\'')
+            .and_then(|v| v.strip_suffix('\''))
+            .unwrap_or(value)
+            .to_string(),
+    };
+
+    Ok(Annotation::Define(DefineAnnotation {
+        name: name.to_string(),
+        value,
+        range: token.range,
+        name_range: sub_range(token, name_start, name_start + name_len),
+    }))
+}
+
+/// The word after the keyword, e.g. the module of `@module mod-java`, as
+/// the offsets of its start and end.
+fn module_name(token: &Token, name_end: usize, keyword: &str) -> Result<(usize, usize), SngSyntaxError> {
+    let text = token.text.as_str();
+    let rest = &text[name_end..];
+    let start = name_end + (rest.len() - rest.trim_start().len());
+    let end = text[start..]
+        .find(char::is_whitespace)
+        .map_or(text.len(), |i| start + i);
+    if start == end {
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation(format!("expected a module name after `@{}`", keyword)),
+            missing_range(token, name_end),
+        ));
+    }
+    Ok((start, end))
+}
+
+fn parse_module(token: &Token, name_end: usize) -> Result<Annotation, SngSyntaxError> {
+    let (start, end) = module_name(token, name_end, "module")?;
+    let text = token.text.as_str();
+    Ok(Annotation::Module(ModuleAnnotation {
+        name: text[start..end].to_string(),
+        arguments: text[end..].trim().to_string(),
+        range: token.range,
+        name_range: sub_range(token, start, end),
+    }))
+}
+
+fn parse_requires(token: &Token, name_end: usize) -> Result<Annotation, SngSyntaxError> {
+    let (start, end) = module_name(token, name_end, "requires")?;
+    let text = token.text.as_str();
+    let rest = text[end..].trim();
+    let message = match rest.strip_prefix('"').and_then(|m| m.strip_suffix('"')) {
+        Some(quoted) => Some(quoted.replace("\\\"", "\"").replace("\\\\", "\\")),
+        None if rest.is_empty() => None,
+        None => {
+            return Err(SngSyntaxError::new(
+                SngSyntaxErrorKind::InvalidAnnotation("expected a quoted message after the module name".to_string()),
+                sub_range(
+                    token,
+                    end + (text[end..].len() - text[end..].trim_start().len()),
+                    text.len(),
+                ),
+            ))
+        }
+    };
+    Ok(Annotation::Requires(RequiresAnnotation {
+        module: text[start..end].to_string(),
+        message,
+        range: token.range,
+        module_range: sub_range(token, start, end),
+    }))
+}
+
+fn parse_include(token: &Token, name_end: usize) -> Result<Annotation, SngSyntaxError> {
+    let text = token.text.as_str();
+    let rest = &text[name_end..];
+    let quote = rest.find('"').filter(|&i| rest[..i].trim().is_empty());
+    let Some(quote) = quote else {
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation("expected a quoted path after `@include`".to_string()),
+            missing_range(token, name_end),
+        ));
+    };
+    let path_start = name_end + quote;
+
+    let mut path = String::new();
+    let mut chars = text[path_start + 1..].char_indices();
+    let mut path_end = None;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                path_end = Some(path_start + 1 + i + 1);
+                break;
+            }
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    path.push(escaped);
+                }
+            }
+            c => path.push(c),
+        }
+    }
+    let Some(path_end) = path_end else {
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::UnterminatedString,
+            sub_range(token, path_start, text.len()),
+        ));
+    };
+    if !text[path_end..].trim().is_empty() {
+        return Err(SngSyntaxError::new(
+            SngSyntaxErrorKind::InvalidAnnotation("unexpected text after the included path".to_string()),
+            sub_range(token, path_end, text.len()),
+        ));
+    }
+
+    Ok(Annotation::Include(IncludeAnnotation {
+        path,
+        range: token.range,
+        path_range: sub_range(token, path_start, path_end),
+    }))
+}
@@ -0,0 +1,105 @@
+//! Recursive-descent parser for filter expressions, see
+//! [`crate::language_types::filter_expression`].
+
+use crate::language_types::filter_expression::{
+    Comparison, Expression, FilterExpression, NUMERIC_COMPARISONS, STRING_COMPARISONS,
+};
+use crate::language_types::objects::Identifier;
+use crate::lsp_types::Range;
+use crate::parser::lexer::{Token, TokenKind};
+use crate::parser::object_parser::{identifier, literal, parse_driver, TokenStream};
+use crate::parser::SngSyntaxError;
+
+/// Parses a filter expression up to the first token that cannot continue
+/// it, which the caller expects: the `}` of a filter object or the `)` of
+/// a condition.
+pub(super) fn parse_filter_expression(stream: &mut TokenStream) -> Result<FilterExpression, SngSyntaxError> {
+    let start = stream.pos;
+    let root = parse_or(stream)?;
+    Ok(FilterExpression {
+        root,
+        tokens: stream.tokens[start..stream.pos].to_vec(),
+    })
+}
+
+fn parse_or(stream: &mut TokenStream) -> Result<Expression, SngSyntaxError> {
+    let mut expression = parse_and(stream)?;
+    while stream.peek().is_some_and(|t| t.is_word("or")) {
+        let operator = identifier(&stream.next().unwrap());
+        let right = parse_and(stream)?;
+        expression = Expression::Binary {
+            operator,
+            left: Box::new(expression),
+            right: Box::new(right),
+        };
+    }
+    Ok(expression)
+}
+
+fn parse_and(stream: &mut TokenStream) -> Result<Expression, SngSyntaxError> {
+    let mut expression = parse_not(stream)?;
+    while stream.peek().is_some_and(|t| t.is_word("and")) {
+        let operator = identifier(&stream.next().unwrap());
+        let right = parse_not(stream)?;
+        expression = Expression::Binary {
+            operator,
+            left: Box::new(expression),
+            right: Box::new(right),
+        };
+    }
+    Ok(expression)
+}
+
+fn parse_not(stream: &mut TokenStream) -> Result<Expression, SngSyntaxError> {
+    if stream.peek().is_some_and(|t| t.is_word("not")) {
+        let keyword = identifier(&stream.next().unwrap());
+        let operand = parse_not(stream)?;
+        return Ok(Expression::Not {
+            keyword,
+            operand: Box::new(operand),
+        });
+    }
+    parse_primary(stream)
+}
+
+fn parse_primary(stream: &mut TokenStream) -> Result<Expression, SngSyntaxError> {
+    const EXPECTED: &str = "a filter function such as `level(...)`, a comparison, `not` or `(`";
+    match stream.peek_kind() {
+        Some(TokenKind::LParen) => {
+            let open = stream.next().unwrap();
+            let expression = parse_or(stream)?;
+            let close = stream.expect(TokenKind::RParen, "`and`, `or` or `)`")?;
+            Ok(Expression::Group {
+                expression: Box::new(expression),
+                range: Range::new(open.range.start, close.range.end),
+            })
+        }
+        Some(TokenKind::Word) if stream.peek_nth_kind(1) == Some(TokenKind::LParen) => {
+            let name = stream.next().unwrap();
+            Ok(Expression::Call(parse_driver(stream, &name)?))
+        }
+        Some(TokenKind::Word | TokenKind::Number | TokenKind::String) => {
+            let left = stream.next().unwrap();
+            let left = literal(stream, left);
+            let operator = parse_comparison_operator(stream)?;
+            let right = match stream.peek_kind() {
+                Some(TokenKind::Word | TokenKind::Number | TokenKind::String) => stream.next().unwrap(),
+                _ => return Err(stream.unexpected("a value to compare with")),
+            };
+            let right = literal(stream, right);
+            Ok(Expression::Comparison(Comparison { left, operator, right }))
+        }
+        _ => Err(stream.unexpected(EXPECTED)),
+    }
+}
+
+fn parse_comparison_operator(stream: &mut TokenStream) -> Result<Identifier, SngSyntaxError> {
+    let is_operator = |t: &Token| {
+        (t.is(TokenKind::Operator) && NUMERIC_COMPARISONS.contains(&t.text.as_str()))
+            || (t.is(TokenKind::Word) && STRING_COMPARISONS.contains(&t.text.as_str()))
+    };
+    if !stream.peek().is_some_and(is_operator) {
+        return Err(stream.unexpected("a comparison operator such as `==` or `eq`"));
+    }
+    Ok(identifier(&stream.next().unwrap()))
+}
@@ -0,0 +1,427 @@
+//! Tokenizer for syslog-ng configuration files.
+//!
+//! Comments are skipped here, so the parser never sees them; only the
+//! formatter asks for them through [`tokenize_with_comments`]. Pragma lines
+//! (`@version: 3.35`, `@include "..."`) are returned as a single token and
+//! handed over to the annotation parser.
+
+use crate::lsp_types::{Position, Range};
+use crate::parser::{SngSyntaxError, SngSyntaxErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Bare word: keywords, identifiers, option names, unquoted values.
+    Word,
+    Number,
+    /// Quoted string; `Token::text` holds the unescaped contents.
+    String,
+    Pragma,
+    LBrace,
+    RBrace,
+    LParen,
+    RParen,
+    Semicolon,
+    Comma,
+    /// Comparison and assignment operators such as `==`, `!=` or `<`.
+    Operator,
+    /// `# ...` up to the end of the line, only produced by [`tokenize_with_comments`].
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    pub range: Range,
+    /// Byte offsets of the token in the source text.
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    pub fn is(&self, kind: TokenKind) -> bool {
+        self.kind == kind
+    }
+
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text == word
+    }
+}
+
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+        || matches!(
+            c,
+            '_' | '-' | '.' | '/' | ':' | '$' | '*' | '`' | '+' | '~' | '?' | '%' | '^' | '&' | '[' | ']'
+        )
+}
+
+fn is_number(word: &str) -> bool {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && digits.starts_with(|c: char| c.is_ascii_digit())
+        && digits.matches('.').count() <= 1
+        && !digits.ends_with('.')
+}
+
+struct Cursor<'a> {
+    text: &'a str,
+    offset: usize,
+    position: Position,
+    at_line_start: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.offset..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.text[self.offset..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.character = 0;
+            self.at_line_start = true;
+        } else {
+            self.position.character += c.len_utf16() as u32;
+            if !c.is_whitespace() {
+                self.at_line_start = false;
+            }
+        }
+        Some(c)
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            self.bump();
+        }
+    }
+}
+
+pub fn tokenize(text: &str) -> Result<Vec<Token>, SngSyntaxError> {
+    first_error(lex(text, false))
+}
+
+pub fn tokenize_with_comments(text: &str) -> Result<Vec<Token>, SngSyntaxError> {
+    first_error(lex(text, true))
+}
+
+/// The tokens of `text` with the errors met on the way. Unexpected
+/// characters are left out; an unterminated string ends the tokens.
+pub fn tokenize_recovering(text: &str) -> (Vec<Token>, Vec<SngSyntaxError>) {
+    lex(text, false)
+}
+
+fn first_error((tokens, errors): (Vec<Token>, Vec<SngSyntaxError>)) -> Result<Vec<Token>, SngSyntaxError> {
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(tokens),
+    }
+}
+
+fn lex(text: &str, keep_comments: bool) -> (Vec<Token>, Vec<SngSyntaxError>) {
+    let mut cursor = Cursor {
+        text,
+        offset: 0,
+        position: Position::default(),
+        at_line_start: true,
+    };
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    while let Some(c) = cursor.peek() {
+        if c.is_whitespace() {
+            cursor.bump();
+            continue;
+        }
+        let start = cursor.offset;
+        let start_position = cursor.position;
+        if c == '#' {
+            cursor.skip_line();
+            if keep_comments {
+                tokens.push(Token {
+                    kind: TokenKind::Comment,
+                    text: text[start..cursor.offset].trim_end().to_string(),
+                    range: Range::new(start_position, cursor.position),
+                    start,
+                    end: cursor.offset,
+                });
+            }
+            continue;
+        }
+
+        let at_line_start = cursor.at_line_start;
+        let kind = match c {
+            '@' if at_line_start => {
+                lex_pragma(&mut cursor);
+                TokenKind::Pragma
+            }
+            '"' | '\'' => {
+                let value = match lex_string(&mut cursor) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        errors.push(error);
+                        break;
+                    }
+                };
+                tokens.push(Token {
+                    kind: TokenKind::String,
+                    text: value,
+                    range: Range::new(start_position, cursor.position),
+                    start,
+                    end: cursor.offset,
+                });
+                continue;
+            }
+            '{' | '}' | '(' | ')' | ';' | ',' => {
+                cursor.bump();
+                match c {
+                    '{' => TokenKind::LBrace,
+                    '}' => TokenKind::RBrace,
+                    '(' => TokenKind::LParen,
+                    ')' => TokenKind::RParen,
+                    ';' => TokenKind::Semicolon,
+                    _ => TokenKind::Comma,
+                }
+            }
+            '=' | '!' | '<' | '>' => {
+                cursor.bump();
+                if c == '=' && cursor.peek() == Some('~') {
+                    cursor.bump();
+                } else if cursor.peek() == Some('=') {
+                    cursor.bump();
+                    // `===` and `!==`
+                    if matches!(c, '=' | '!') && cursor.peek() == Some('=') {
+                        cursor.bump();
+                    }
+                }
+                TokenKind::Operator
+            }
+            c if is_word_char(c) => {
+                while cursor.peek().is_some_and(is_word_char) {
+                    cursor.bump();
+                }
+                if is_number(&text[start..cursor.offset]) {
+                    TokenKind::Number
+                } else {
+                    TokenKind::Word
+                }
+            }
+            c => {
+                cursor.bump();
+                errors.push(SngSyntaxError::new(
+                    SngSyntaxErrorKind::UnexpectedCharacter(c),
+                    Range::new(start_position, cursor.position),
+                ));
+                continue;
+            }
+        };
+        tokens.push(Token {
+            kind,
+            text: text[start..cursor.offset].to_string(),
+            range: Range::new(start_position, cursor.position),
+            start,
+            end: cursor.offset,
+        });
+    }
+    (tokens, errors)
+}
+
+/// Consumes an annotation line, stopping before a trailing comment.
+fn lex_pragma(cursor: &mut Cursor) {
+    // The quote of the string the cursor is in.
+    let mut quote = None;
+    while let Some(c) = cursor.peek() {
+        match (c, quote) {
+            ('\n', _) => break,
+            ('#', None) => break,
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('\\', Some('"')) if cursor.peek_second().is_some() => {
+                cursor.bump();
+            }
+            _ => {}
+        }
+        cursor.bump();
+    }
+    // Leave trailing whitespace, and the `\r` of a CRLF line end, out of
+    // the token range.
+    while cursor.text[..cursor.offset].ends_with([' ', '\t', '\r']) {
+        cursor.offset -= 1;
+        cursor.position.character -= 1;
+    }
+}
+
+/// A string at the opening quote. Single-quoted strings are taken
+/// verbatim, as syslog-ng does; double-quoted ones have backslash escapes.
+fn lex_string(cursor: &mut Cursor) -> Result<String, SngSyntaxError> {
+    let start = cursor.position;
+    let quote = cursor.bump();
+    let mut value = String::new();
+    loop {
+        match cursor.bump() {
+            None => {
+                return Err(SngSyntaxError::new(
+                    SngSyntaxErrorKind::UnterminatedString,
+                    Range::new(start, cursor.position),
+                ))
+            }
+            Some(c) if Some(c) == quote => return Ok(value),
+            Some('\\') if quote == Some('"') => match cursor.bump() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('r') => value.push('\r'),
+                Some('a') => value.push('\u{7}'),
+                Some('v') => value.push('\u{b}'),
+                Some('x') => value.push(lex_hex_escape(cursor).unwrap_or('x')),
+                Some(c) => value.push(c),
+                None => continue,
+            },
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+/// The character of the two hex digits after `\x`, moving past them; a
+/// `\x` without them stands for `x` like any other unknown escape.
+fn lex_hex_escape(cursor: &mut Cursor) -> Option<char> {
+    let digits = cursor.text.get(cursor.offset..cursor.offset + 2)?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let code = u8::from_str_radix(digits, 16).ok()?;
+    cursor.bump();
+    cursor.bump();
+    Some(char::from(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(text: &str) -> Vec<(TokenKind, String)> {
+        tokenize(text)
+            .unwrap()
+            .into_iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    fn string(text: &str) -> String {
+        match &texts(text)[..] {
+            [(TokenKind::String, value)] => value.clone(),
+            tokens => panic!("{:?} is not a single string", tokens),
+        }
+    }
+
+    #[test]
+    fn unescapes_double_quoted_strings() {
+        assert_eq!(string(r#""a\nb\tc\rd""#), "a\nb\tc\rd");
+        assert_eq!(string(r#""\x41\x7a""#), "Az");
+        assert_eq!(string(r#""\xZZ""#), "xZZ");
+        assert_eq!(string(r#""\x4""#), "x4");
+        assert_eq!(string(r#""say \"hi\" \\ \q""#), r#"say "hi" \ q"#);
+    }
+
+    #[test]
+    fn takes_single_quoted_strings_verbatim() {
+        assert_eq!(string(r"'C:\logs\new'"), r"C:\logs\new");
+        assert_eq!(string(r#"'say "hi" # here'"#), r#"say "hi" # here"#);
+    }
+
+    #[test]
+    fn reports_unterminated_strings() {
+        let (tokens, errors) = tokenize_recovering("file(\"/var/log\n");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, SngSyntaxErrorKind::UnterminatedString);
+        assert_eq!(errors[0].range.start, Position::new(0, 5));
+    }
+
+    #[test]
+    fn keeps_pragma_lines_in_one_token() {
+        assert_eq!(
+            texts("@version: 4.8\n@include \"a.conf\"  # trailing\nlog{};"),
+            [
+                (TokenKind::Pragma, "@version: 4.8".to_string()),
+                (TokenKind::Pragma, "@include \"a.conf\"".to_string()),
+                (TokenKind::Word, "log".to_string()),
+                (TokenKind::LBrace, "{".to_string()),
+                (TokenKind::RBrace, "}".to_string()),
+                (TokenKind::Semicolon, ";".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ends_pragmas_at_comments_outside_strings() {
+        assert_eq!(
+            texts("@define root '/var/log # not a comment' # a comment"),
+            [(TokenKind::Pragma, "@define root '/var/log # not a comment'".to_string())]
+        );
+        assert_eq!(
+            texts(r#"@define q "a \" # still a string" # a comment"#),
+            [(TokenKind::Pragma, r#"@define q "a \" # still a string""#.to_string())]
+        );
+    }
+
+    #[test]
+    fn lexes_at_signs_inside_lines_as_unexpected() {
+        let (_, errors) = tokenize_recovering("log { @version };");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, SngSyntaxErrorKind::UnexpectedCharacter('@'));
+    }
+
+    #[test]
+    fn handles_crlf_line_endings() {
+        let tokens = tokenize_with_comments("@version: 4.8\r\n# comment\r\nlog {\r\n};\r\n").unwrap();
+        let summary: Vec<_> = tokens
+            .iter()
+            .map(|token| (token.kind, token.text.as_str(), token.range))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (
+                    TokenKind::Pragma,
+                    "@version: 4.8",
+                    Range::new(Position::new(0, 0), Position::new(0, 13))
+                ),
+                (
+                    TokenKind::Comment,
+                    "# comment",
+                    Range::new(Position::new(1, 0), Position::new(1, 10))
+                ),
+                (
+                    TokenKind::Word,
+                    "log",
+                    Range::new(Position::new(2, 0), Position::new(2, 3))
+                ),
+                (
+                    TokenKind::LBrace,
+                    "{",
+                    Range::new(Position::new(2, 4), Position::new(2, 5))
+                ),
+                (
+                    TokenKind::RBrace,
+                    "}",
+                    Range::new(Position::new(3, 0), Position::new(3, 1))
+                ),
+                (
+                    TokenKind::Semicolon,
+                    ";",
+                    Range::new(Position::new(3, 1), Position::new(3, 2))
+                ),
+            ]
+        );
+    }
+}
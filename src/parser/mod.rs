@@ -0,0 +1,212 @@
+//! Parser for syslog-ng configuration files.
+
+pub mod annotation_parser;
+pub mod filter_parser;
+pub mod lexer;
+pub mod object_parser;
+pub mod references;
+pub mod template_parser;
+
+use std::fmt;
+
+use crate::language_types::annotations::Annotation;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SngSyntaxErrorKind {
+    UnterminatedString,
+    UnexpectedCharacter(char),
+    UnexpectedToken {
+        found: String,
+        expected: String,
+    },
+    UnexpectedEndOfFile {
+        expected: String,
+    },
+    UnknownObjectKind(String),
+    InvalidAnnotation(String),
+    /// An identifier on an object inside a log path; only top-level
+    /// objects can be referred to.
+    NamedInlineObject {
+        kind: String,
+        name: String,
+    },
+    /// `elif` or `else` that does not follow the block of an `if`.
+    DanglingBranch(String),
+}
+
+impl fmt::Display for SngSyntaxErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SngSyntaxErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            SngSyntaxErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character `{}`", c),
+            SngSyntaxErrorKind::UnexpectedToken { found, expected } => {
+                write!(f, "expected {}, found `{}`", expected, found)
+            }
+            SngSyntaxErrorKind::UnexpectedEndOfFile { expected } => {
+                write!(f, "expected {}, found end of file", expected)
+            }
+            SngSyntaxErrorKind::UnknownObjectKind(kind) => write!(f, "unknown object kind `{}`", kind),
+            SngSyntaxErrorKind::InvalidAnnotation(message) => write!(f, "invalid annotation: {}", message),
+            SngSyntaxErrorKind::NamedInlineObject { kind, name } => write!(
+                f,
+                "objects inside a log path cannot be named; define `{}` at the top level and use `{}({})`",
+                name, kind, name
+            ),
+            SngSyntaxErrorKind::DanglingBranch(keyword) => write!(f, "`{}` without a preceding `if` block", keyword),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SngSyntaxError {
+    pub kind: SngSyntaxErrorKind,
+    pub range: Range,
+}
+
+impl SngSyntaxError {
+    pub fn new(kind: SngSyntaxErrorKind, range: Range) -> Self {
+        SngSyntaxError { kind, range }
+    }
+}
+
+impl fmt::Display for SngSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.range.start.line + 1,
+            self.range.start.character + 1,
+            self.kind
+        )
+    }
+}
+
+impl std::error::Error for SngSyntaxError {}
+
+pub fn parse_conf(text: &str) -> Result<ParsedConfiguration, SngSyntaxError> {
+    let tokens = lexer::tokenize(text)?;
+    object_parser::parse_tokens(tokens)
+}
+
+/// The parts of `text` that parse, with every syntax error of the rest in
+/// document order. For the features that should go on working while an
+/// object is being typed.
+pub fn parse_conf_recovering(text: &str) -> (ParsedConfiguration, Vec<SngSyntaxError>) {
+    let (tokens, mut errors) = lexer::tokenize_recovering(text);
+    let (config, mut parse_errors) = object_parser::parse_tokens_recovering(tokens);
+    // The tokens end at an unterminated string, and what the parser has to
+    // say about that end adds nothing.
+    if let Some(end) = errors.iter().find(|e| e.kind == SngSyntaxErrorKind::UnterminatedString) {
+        let end = end.range.start;
+        parse_errors.retain(|error| error.range.start < end);
+    }
+    errors.extend(parse_errors);
+    errors.sort_by_key(|error| error.range.start);
+    (config, errors)
+}
+
+/// `text` with every `` `name` `` of `defines` replaced by its value.
+pub fn substitute_defines(text: &str, defines: &[(String, String)]) -> String {
+    let mut substituted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('`') {
+        substituted.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let value = after.find('`').and_then(|close| {
+            defines
+                .iter()
+                .find(|(name, _)| *name == after[..close])
+                .map(|d| (close, d))
+        });
+        match value {
+            Some((close, (_, value))) => {
+                substituted.push_str(value);
+                rest = &after[close + 1..];
+            }
+            None => {
+                substituted.push('`');
+                rest = after;
+            }
+        }
+    }
+    substituted.push_str(rest);
+    substituted
+}
+
+/// The valid annotations of `text`, found even when its objects do not
+/// parse. Nothing is found if `text` cannot be tokenized.
+pub fn parse_annotations(text: &str) -> Vec<Annotation> {
+    let Ok(tokens) = lexer::tokenize(text) else {
+        return Vec::new();
+    };
+    tokens
+        .iter()
+        .filter(|token| token.is(lexer::TokenKind::Pragma))
+        .filter_map(|token| annotation_parser::parse_annotation(token).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp_types::Position;
+
+    fn names(config: &ParsedConfiguration) -> Vec<&str> {
+        config
+            .objects
+            .iter()
+            .filter_map(|object| object.id.as_ref())
+            .map(|id| id.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn recovers_after_a_broken_object() {
+        let (config, errors) = parse_conf_recovering(
+            "source s_a { internal(); };\ndestination d_b { file(\"/x\" };\nfilter f_c { level(err) };\n",
+        );
+        assert_eq!(names(&config), ["s_a", "f_c"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].range.start, Position::new(1, 28));
+    }
+
+    #[test]
+    fn recovers_after_an_unclosed_body() {
+        let (config, errors) = parse_conf_recovering("source s_a { internal();\nfilter f_b { level(err) };\n");
+        assert_eq!(names(&config), ["f_b"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].range.start.line, 1);
+    }
+
+    #[test]
+    fn reports_every_broken_item_in_order() {
+        let (config, errors) =
+            parse_conf_recovering("frobnicate x { };\nsource s_a { internal(); };\n@version\nlog { source(s_a) };\n");
+        assert_eq!(names(&config), ["s_a"]);
+        let lines: Vec<_> = errors.iter().map(|error| error.range.start.line).collect();
+        assert_eq!(lines, [0, 2, 3]);
+        assert_eq!(
+            errors[0].kind,
+            SngSyntaxErrorKind::UnknownObjectKind("frobnicate".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_an_unterminated_string_once() {
+        let (config, errors) = parse_conf_recovering("source s_a { internal(); };\ndestination d_b { file(\"/x); };\n");
+        assert_eq!(names(&config), ["s_a"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, SngSyntaxErrorKind::UnterminatedString);
+        assert_eq!(errors[0].range.start, Position::new(1, 23));
+    }
+
+    #[test]
+    fn agrees_with_parse_conf_on_valid_text() {
+        let text = "@version: 4.8\nsource s { internal(); };\nlog { source(s); };\n";
+        let (config, errors) = parse_conf_recovering(text);
+        assert!(errors.is_empty());
+        assert_eq!(config, parse_conf(text).unwrap());
+    }
+}
@@ -0,0 +1,499 @@
+//! Recursive-descent parser turning tokens into objects and drivers.
+
+use crate::language_types::annotations::Annotation;
+use crate::language_types::filter_expression::FilterExpression;
+use crate::language_types::objects::{
+    Block, BlockParameter, Branch, Channel, Conditional, Driver, Identifier, Junction, Literal, LiteralKind, Object,
+    ObjectBody, ObjectKind, Parameter, Statement,
+};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::Range;
+use crate::parser::annotation_parser::parse_annotation;
+use crate::parser::filter_parser::parse_filter_expression;
+use crate::parser::lexer::{Token, TokenKind};
+use crate::parser::references::collect_references;
+use crate::parser::{substitute_defines, SngSyntaxError, SngSyntaxErrorKind};
+
+pub fn match_object_kind(word: &str) -> Option<ObjectKind> {
+    ObjectKind::ALL.into_iter().find(|kind| kind.keyword() == word)
+}
+
+/// Whether `name` can be used as an object identifier: a letter or `_`
+/// followed by letters, digits, `_`, `-` and `.`, and not an object keyword.
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+        && match_object_kind(name).is_none()
+}
+
+pub(super) struct TokenStream {
+    pub(super) tokens: Vec<Token>,
+    pub(super) pos: usize,
+    /// The `@define`s read so far, substituted into later values.
+    defines: Vec<(String, String)>,
+}
+
+impl TokenStream {
+    pub(super) fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    pub(super) fn peek_kind(&self) -> Option<TokenKind> {
+        self.peek().map(|t| t.kind)
+    }
+
+    pub(super) fn peek_nth_kind(&self, n: usize) -> Option<TokenKind> {
+        self.tokens.get(self.pos + n).map(|t| t.kind)
+    }
+
+    pub(super) fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn end_of_file_range(&self) -> Range {
+        self.tokens
+            .last()
+            .map(|t| Range::new(t.range.end, t.range.end))
+            .unwrap_or_default()
+    }
+
+    pub(super) fn unexpected(&self, expected: &str) -> SngSyntaxError {
+        match self.peek() {
+            Some(token) => SngSyntaxError::new(
+                SngSyntaxErrorKind::UnexpectedToken {
+                    found: describe(token),
+                    expected: expected.to_string(),
+                },
+                token.range,
+            ),
+            None => SngSyntaxError::new(
+                SngSyntaxErrorKind::UnexpectedEndOfFile {
+                    expected: expected.to_string(),
+                },
+                self.end_of_file_range(),
+            ),
+        }
+    }
+
+    pub(super) fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<Token, SngSyntaxError> {
+        if self.peek_kind() == Some(kind) {
+            Ok(self.next().unwrap())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token.kind {
+        TokenKind::String => format!("\"{}\"", token.text),
+        TokenKind::Pragma => token.text.split_whitespace().next().unwrap_or("@").to_string(),
+        _ => token.text.clone(),
+    }
+}
+
+pub(super) fn identifier(token: &Token) -> Identifier {
+    Identifier {
+        name: token.text.clone(),
+        range: token.range,
+    }
+}
+
+pub fn parse_tokens(tokens: Vec<Token>) -> Result<ParsedConfiguration, SngSyntaxError> {
+    let (config, errors) = parse_tokens_recovering(tokens);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(config),
+    }
+}
+
+/// Parses `tokens` like [`parse_tokens`], but carries on after a top-level
+/// item that does not parse, see [`skip_broken_item`]. Returns the items
+/// that parse and the error of each one that does not, in order.
+pub fn parse_tokens_recovering(tokens: Vec<Token>) -> (ParsedConfiguration, Vec<SngSyntaxError>) {
+    let mut stream = TokenStream {
+        tokens,
+        pos: 0,
+        defines: Vec::new(),
+    };
+    let mut config = ParsedConfiguration::default();
+    let mut errors = Vec::new();
+    while stream.peek().is_some() {
+        let start = stream.pos;
+        if let Err(error) = parse_item(&mut stream, &mut config) {
+            errors.push(error);
+            skip_broken_item(&mut stream, start);
+        }
+    }
+    config.references = collect_references(&config.objects);
+    (config, errors)
+}
+
+/// Parses the annotation, block or object at the start of `stream` into
+/// `config`.
+fn parse_item(stream: &mut TokenStream, config: &mut ParsedConfiguration) -> Result<(), SngSyntaxError> {
+    let Some(token) = stream.peek() else {
+        return Ok(());
+    };
+    match token.kind {
+        TokenKind::Pragma => {
+            let token = stream.next().unwrap();
+            let annotation = parse_annotation(&token)?;
+            if let Annotation::Define(define) = &annotation {
+                stream.defines.retain(|(name, _)| *name != define.name);
+                stream.defines.push((define.name.clone(), define.value.clone()));
+            }
+            config.annotations.push(annotation);
+        }
+        TokenKind::Word if token.text == "block" => {
+            let keyword = stream.next().unwrap();
+            config.blocks.push(parse_block(stream, &keyword)?);
+        }
+        TokenKind::Word => {
+            let Some(kind) = match_object_kind(&token.text) else {
+                return Err(SngSyntaxError::new(
+                    SngSyntaxErrorKind::UnknownObjectKind(token.text.clone()),
+                    token.range,
+                ));
+            };
+            let keyword = stream.next().unwrap();
+            config.objects.push(parse_object_block(stream, kind, &keyword, true)?);
+        }
+        _ => return Err(stream.unexpected("an object definition or annotation")),
+    }
+    Ok(())
+}
+
+/// Moves `stream` past the top-level item at `start` that did not parse:
+/// up to and including the `}` closing its body and the `;` after it, or
+/// up to the next annotation or object starting a line if that comes
+/// first, as when the body is not closed. An annotation or a stray token
+/// is an item by itself.
+fn skip_broken_item(stream: &mut TokenStream, start: usize) {
+    stream.pos = start + 1;
+    if !stream.tokens[start].is(TokenKind::Word) {
+        return;
+    }
+    let mut depth = 0usize;
+    while let Some(token) = stream.peek() {
+        match token.kind {
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace if depth <= 1 => {
+                stream.next();
+                if stream.peek_kind() == Some(TokenKind::Semicolon) {
+                    stream.next();
+                }
+                return;
+            }
+            TokenKind::RBrace => depth -= 1,
+            TokenKind::Pragma => return,
+            // `source(s_local);` can start a line of a log path too.
+            TokenKind::Word
+                if token.range.start.character == 0
+                    && (token.text == "block" || match_object_kind(&token.text).is_some())
+                    && stream.peek_nth_kind(1) != Some(TokenKind::LParen) =>
+            {
+                return
+            }
+            _ => {}
+        }
+        stream.next();
+    }
+}
+
+/// Parses an object after its kind keyword: `[id] { body };`.
+///
+/// Inline objects inside log paths have no identifier.
+fn parse_object_block(
+    stream: &mut TokenStream,
+    kind: ObjectKind,
+    keyword: &Token,
+    top_level: bool,
+) -> Result<Object, SngSyntaxError> {
+    let id = if top_level && kind.has_id() {
+        Some(identifier(
+            &stream.expect(TokenKind::Word, &format!("{} identifier", kind))?,
+        ))
+    } else {
+        if kind.has_id()
+            && stream.peek_kind() == Some(TokenKind::Word)
+            && stream.peek_nth_kind(1) == Some(TokenKind::LBrace)
+        {
+            let name = stream.peek().unwrap();
+            return Err(SngSyntaxError::new(
+                SngSyntaxErrorKind::NamedInlineObject {
+                    kind: kind.to_string(),
+                    name: name.text.clone(),
+                },
+                name.range,
+            ));
+        }
+        None
+    };
+    stream.expect(TokenKind::LBrace, "`{`")?;
+
+    let body = if kind == ObjectKind::Filter {
+        let expression = parse_filter_expression(stream)?;
+        // `filter f_mail { facility(mail); };` is common and accepted.
+        if stream.peek_kind() == Some(TokenKind::Semicolon) {
+            stream.next();
+        }
+        if stream.peek_kind() != Some(TokenKind::RBrace) {
+            return Err(stream.unexpected("`and`, `or` or `}`"));
+        }
+        ObjectBody::Expression(Box::new(expression))
+    } else {
+        let mut statements = Vec::new();
+        while stream.peek_kind() != Some(TokenKind::RBrace) {
+            statements.push(parse_statement(stream, kind)?);
+        }
+        ObjectBody::Statements(statements)
+    };
+
+    stream.expect(TokenKind::RBrace, "`}`")?;
+    let semicolon = stream.expect(TokenKind::Semicolon, "`;` after `}`")?;
+
+    Ok(Object {
+        kind,
+        keyword_range: keyword.range,
+        id,
+        body,
+        location: Range::new(keyword.range.start, semicolon.range.end),
+    })
+}
+
+/// Parses a block definition after the `block` keyword:
+/// `context name(parameter(default)... [...]) { body };`.
+fn parse_block(stream: &mut TokenStream, keyword: &Token) -> Result<Block, SngSyntaxError> {
+    let context = identifier(&stream.expect(TokenKind::Word, "a block context such as `source` or `root`")?);
+    let name = stream.expect(TokenKind::Word, "block name")?;
+    let header = parse_driver(stream, &name)?;
+    let mut parameters = Vec::new();
+    let mut variadic = false;
+    for parameter in header.parameters {
+        match parameter {
+            Parameter::Option(option) => parameters.push(BlockParameter {
+                name: option.name,
+                default: option.parameters,
+                location: option.location,
+            }),
+            Parameter::Literal(literal) if literal.value == "..." => variadic = true,
+            Parameter::Literal(literal) => {
+                return Err(SngSyntaxError::new(
+                    SngSyntaxErrorKind::UnexpectedToken {
+                        found: literal.value,
+                        expected: "a parameter such as `name()` or `name(default)`".to_string(),
+                    },
+                    literal.range,
+                ))
+            }
+        }
+    }
+    stream.expect(TokenKind::LBrace, "`{`")?;
+    let body = parse_raw_until_brace(stream)?;
+    stream.expect(TokenKind::RBrace, "`}`")?;
+    let semicolon = stream.expect(TokenKind::Semicolon, "`;` after `}`")?;
+    Ok(Block {
+        keyword_range: keyword.range,
+        context,
+        name: header.name,
+        parameters,
+        variadic,
+        body,
+        location: Range::new(keyword.range.start, semicolon.range.end),
+    })
+}
+
+/// Collects the tokens of a block body, stopping before the `}` that
+/// closes it.
+fn parse_raw_until_brace(stream: &mut TokenStream) -> Result<Vec<Token>, SngSyntaxError> {
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    loop {
+        match stream.peek_kind() {
+            None => return Err(stream.unexpected("`}`")),
+            Some(TokenKind::RBrace) if depth == 0 => return Ok(tokens),
+            Some(TokenKind::RParen) if depth == 0 => return Err(stream.unexpected("`}`")),
+            Some(TokenKind::LBrace | TokenKind::LParen) => depth += 1,
+            Some(TokenKind::RBrace | TokenKind::RParen) => depth -= 1,
+            Some(_) => {}
+        }
+        tokens.push(stream.next().unwrap());
+    }
+}
+
+fn parse_statement(stream: &mut TokenStream, parent: ObjectKind) -> Result<Statement, SngSyntaxError> {
+    let name = stream.expect(TokenKind::Word, "a driver name or `}`")?;
+
+    if parent == ObjectKind::Log {
+        match name.text.as_str() {
+            "if" if matches!(stream.peek_kind(), Some(TokenKind::LParen | TokenKind::LBrace)) => {
+                return Ok(Statement::Conditional(parse_conditional(stream, name)?));
+            }
+            "junction" if stream.peek_kind() == Some(TokenKind::LBrace) => {
+                return Ok(Statement::Junction(parse_junction(stream, name)?));
+            }
+            "elif" | "else" => {
+                return Err(SngSyntaxError::new(
+                    SngSyntaxErrorKind::DanglingBranch(name.text.clone()),
+                    name.range,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    // `source s_name { ... }` is caught as a named inline object by `parse_object_block`.
+    let inline = stream.peek_kind() == Some(TokenKind::LBrace)
+        || (stream.peek_kind() == Some(TokenKind::Word) && stream.peek_nth_kind(1) == Some(TokenKind::LBrace));
+    if parent == ObjectKind::Log && inline {
+        let Some(kind) = match_object_kind(&name.text) else {
+            return Err(SngSyntaxError::new(
+                SngSyntaxErrorKind::UnknownObjectKind(name.text.clone()),
+                name.range,
+            ));
+        };
+        return Ok(Statement::Object(parse_object_block(stream, kind, &name, false)?));
+    }
+
+    let driver = parse_driver(stream, &name)?;
+    stream.expect(TokenKind::Semicolon, "`;`")?;
+    Ok(Statement::Driver(driver))
+}
+
+/// Parses the branches of an `if` block after the `if` keyword:
+/// `[(filter)] { ... } [elif [(filter)] { ... }]... [else { ... }];`.
+/// A branch without a condition is taken when the filters among its
+/// statements match.
+fn parse_conditional(stream: &mut TokenStream, keyword: Token) -> Result<Conditional, SngSyntaxError> {
+    let start = keyword.range.start;
+    let mut branches = Vec::new();
+    let mut keyword = Some(keyword);
+    while let Some(current) = keyword.take() {
+        let is_else = current.is_word("else");
+        let condition = match stream.peek_kind() {
+            Some(TokenKind::LParen) if !is_else => Some(parse_condition(stream)?),
+            _ => None,
+        };
+        stream.expect(TokenKind::LBrace, "`{`")?;
+        let mut statements = Vec::new();
+        while stream.peek_kind() != Some(TokenKind::RBrace) {
+            statements.push(parse_statement(stream, ObjectKind::Log)?);
+        }
+        let close = stream.expect(TokenKind::RBrace, "`}`")?;
+        if !is_else && stream.peek().is_some_and(|t| t.is_word("elif") || t.is_word("else")) {
+            keyword = stream.next();
+        }
+        branches.push(Branch {
+            keyword: identifier(&current),
+            condition,
+            statements,
+            location: Range::new(current.range.start, close.range.end),
+        });
+    }
+    let semicolon = stream.expect(TokenKind::Semicolon, "`;` after `}`")?;
+    Ok(Conditional {
+        branches,
+        location: Range::new(start, semicolon.range.end),
+    })
+}
+
+/// Parses the channels of a junction after the `junction` keyword:
+/// `{ channel { ... }; ... };`.
+fn parse_junction(stream: &mut TokenStream, keyword: Token) -> Result<Junction, SngSyntaxError> {
+    stream.expect(TokenKind::LBrace, "`{`")?;
+    let mut channels = Vec::new();
+    while stream.peek_kind() != Some(TokenKind::RBrace) {
+        if !stream.peek().is_some_and(|t| t.is_word("channel")) {
+            return Err(stream.unexpected("`channel` or `}`"));
+        }
+        let channel_keyword = stream.next().unwrap();
+        stream.expect(TokenKind::LBrace, "`{`")?;
+        let mut statements = Vec::new();
+        while stream.peek_kind() != Some(TokenKind::RBrace) {
+            statements.push(parse_statement(stream, ObjectKind::Log)?);
+        }
+        stream.expect(TokenKind::RBrace, "`}`")?;
+        let semicolon = stream.expect(TokenKind::Semicolon, "`;` after `}`")?;
+        channels.push(Channel {
+            keyword: identifier(&channel_keyword),
+            statements,
+            location: Range::new(channel_keyword.range.start, semicolon.range.end),
+        });
+    }
+    stream.expect(TokenKind::RBrace, "`}`")?;
+    let semicolon = stream.expect(TokenKind::Semicolon, "`;` after `}`")?;
+    Ok(Junction {
+        keyword: identifier(&keyword),
+        channels,
+        location: Range::new(keyword.range.start, semicolon.range.end),
+    })
+}
+
+/// Parses the filter expression of an `if` or `elif` between its
+/// parentheses, consuming both.
+fn parse_condition(stream: &mut TokenStream) -> Result<FilterExpression, SngSyntaxError> {
+    stream.expect(TokenKind::LParen, "`(`")?;
+    let expression = parse_filter_expression(stream)?;
+    stream.expect(TokenKind::RParen, "`and`, `or` or `)`")?;
+    Ok(expression)
+}
+
+/// Parses `(parameters...)` following an already consumed name.
+pub(super) fn parse_driver(stream: &mut TokenStream, name: &Token) -> Result<Driver, SngSyntaxError> {
+    stream.expect(TokenKind::LParen, "`(`")?;
+    let mut parameters = Vec::new();
+    loop {
+        let Some(token) = stream.peek() else {
+            return Err(stream.unexpected("`)`"));
+        };
+        match token.kind {
+            TokenKind::RParen => break,
+            TokenKind::Comma => {
+                stream.next();
+            }
+            TokenKind::Word if stream.peek_nth_kind(1) == Some(TokenKind::LParen) => {
+                let option_name = stream.next().unwrap();
+                parameters.push(Parameter::Option(parse_driver(stream, &option_name)?));
+            }
+            TokenKind::Word | TokenKind::Number | TokenKind::String => {
+                let token = stream.next().unwrap();
+                parameters.push(Parameter::Literal(literal(stream, token)));
+            }
+            _ => return Err(stream.unexpected("an option, a value or `)`")),
+        }
+    }
+    let close = stream.next().unwrap();
+
+    Ok(Driver {
+        name: identifier(name),
+        parameters,
+        location: Range::new(name.range.start, close.range.end),
+    })
+}
+
+/// The value of a word, number or string token, with the `@define`s read
+/// so far substituted.
+pub(super) fn literal(stream: &TokenStream, token: Token) -> Literal {
+    let kind = match token.kind {
+        TokenKind::String => LiteralKind::String,
+        TokenKind::Number => LiteralKind::Number,
+        _ => LiteralKind::Word,
+    };
+    let substituted = Some(substitute_defines(&token.text, &stream.defines)).filter(|v| *v != token.text);
+    let (value, raw) = match substituted {
+        Some(value) => (value, Some(token.text)),
+        None => (token.text, None),
+    };
+    Literal {
+        kind,
+        value,
+        raw,
+        range: token.range,
+    }
+}
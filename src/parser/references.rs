@@ -0,0 +1,129 @@
+//! Collection of the places where objects are referred to by identifier.
+
+use crate::language_types::filter_expression::FilterExpression;
+use crate::language_types::objects::{
+    Driver, LiteralKind, Object, ObjectBody, ObjectKind, Parameter, Reference, Statement,
+};
+use crate::language_types::templates::{functions, TemplatePart};
+use crate::parser::object_parser::match_object_kind;
+use crate::parser::template_parser::{parse_template, verbatim_range};
+
+pub fn collect_references(objects: &[Object]) -> Vec<Reference> {
+    let mut references = Vec::new();
+    for object in objects {
+        collect_object(object, &mut references);
+    }
+    references
+}
+
+fn collect_object(object: &Object, references: &mut Vec<Reference>) {
+    match &object.body {
+        ObjectBody::Expression(expression) => collect_filter_expression(expression, references),
+        ObjectBody::Statements(statements) => collect_statements(object.kind, statements, references),
+    }
+}
+
+fn collect_statements(kind: ObjectKind, statements: &[Statement], references: &mut Vec<Reference>) {
+    for statement in statements {
+        match statement {
+            Statement::Object(inline) => collect_object(inline, references),
+            Statement::Conditional(conditional) => {
+                for branch in &conditional.branches {
+                    if let Some(condition) = &branch.condition {
+                        collect_filter_expression(condition, references);
+                    }
+                    collect_statements(ObjectKind::Log, &branch.statements, references);
+                }
+            }
+            Statement::Junction(junction) => {
+                for channel in &junction.channels {
+                    collect_statements(ObjectKind::Log, &channel.statements, references);
+                }
+            }
+            Statement::Driver(driver) if kind == ObjectKind::Log => collect_log_element(driver, references),
+            Statement::Driver(driver) => {
+                collect_template_references(driver, references);
+                collect_template_functions(driver, references);
+            }
+        }
+    }
+}
+
+/// `source(s_local);` and friends inside a log path.
+fn collect_log_element(driver: &Driver, references: &mut Vec<Reference>) {
+    let Some(kind) = match_object_kind(&driver.name.name).filter(|k| k.has_id()) else {
+        return;
+    };
+    for literal in driver.literals().filter(|l| l.kind == LiteralKind::Word) {
+        references.push(Reference {
+            kind,
+            name: literal.value.clone(),
+            range: literal.range,
+            in_string: false,
+        });
+    }
+}
+
+/// `template(t_name)` with a bare identifier refers to a template object;
+/// a quoted string is an inline template instead.
+fn collect_template_references(driver: &Driver, references: &mut Vec<Reference>) {
+    for parameter in &driver.parameters {
+        let Parameter::Option(option) = parameter else {
+            continue;
+        };
+        if option.name.name == "template" {
+            let mut literals = option.literals();
+            if let (Some(literal), None) = (literals.next(), literals.next()) {
+                if literal.kind == LiteralKind::Word {
+                    references.push(Reference {
+                        kind: ObjectKind::Template,
+                        name: literal.value.clone(),
+                        range: literal.range,
+                        in_string: false,
+                    });
+                }
+            }
+        }
+        collect_template_references(option, references);
+    }
+}
+
+/// `$(template t_name)` in the string literals of `driver` and its options.
+fn collect_template_functions(driver: &Driver, references: &mut Vec<Reference>) {
+    for parameter in &driver.parameters {
+        match parameter {
+            Parameter::Option(option) => collect_template_functions(option, references),
+            Parameter::Literal(literal) if literal.kind == LiteralKind::String => {
+                let parts = parse_template(&literal.value, &mut Vec::new());
+                for function in functions(&parts).into_iter().filter(|f| f.name == "template") {
+                    let Some([TemplatePart::Text { text, span }]) = function.arguments.first().map(|a| &a.parts[..])
+                    else {
+                        continue;
+                    };
+                    // Positions inside the string are only known when it is
+                    // the value verbatim.
+                    let Some(range) = verbatim_range(literal, span.clone()) else {
+                        continue;
+                    };
+                    references.push(Reference {
+                        kind: ObjectKind::Template,
+                        name: text.clone(),
+                        range,
+                        in_string: true,
+                    });
+                }
+            }
+            Parameter::Literal(_) => {}
+        }
+    }
+}
+
+/// `filter(f_other)` inside a filter expression.
+fn collect_filter_expression(expression: &FilterExpression, references: &mut Vec<Reference>) {
+    references.extend(expression.filter_references().map(|name| Reference {
+        kind: ObjectKind::Filter,
+        name: name.value.clone(),
+        range: name.range,
+        in_string: false,
+    }));
+}
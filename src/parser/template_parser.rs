@@ -0,0 +1,197 @@
+//! Parser for the contents of template strings, see
+//! [`crate::language_types::templates`].
+
+use crate::language_types::objects::{Literal, LiteralKind};
+use crate::language_types::templates::{
+    MacroReference, TemplateArgument, TemplateFunction, TemplatePart, TemplateProblem,
+};
+use crate::lsp_types::{Position, Range};
+
+/// Where a sequence of parts ends besides the end of the string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Until {
+    End,
+    /// The closing quote of a quoted function argument.
+    Quote(char),
+    /// Whitespace or the `)` closing the function, outside parentheses
+    /// within the argument such as those of `$(if ("$HOST" eq "x") a b)`.
+    Argument,
+}
+
+/// The parts of the template `value`. Unterminated macros and functions
+/// are reported in `problems` and taken up to the end of the string.
+pub fn parse_template(value: &str, problems: &mut Vec<TemplateProblem>) -> Vec<TemplatePart> {
+    let mut pos = 0;
+    parse_parts(value, &mut pos, Until::End, problems)
+}
+
+fn parse_parts(value: &str, pos: &mut usize, until: Until, problems: &mut Vec<TemplateProblem>) -> Vec<TemplatePart> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut text_start = *pos;
+    let mut depth = 0usize;
+    while let Some(c) = value[*pos..].chars().next() {
+        match (c, until) {
+            (c, Until::Quote(quote)) if c == quote => break,
+            (')', Until::Argument) if depth == 0 => break,
+            (c, Until::Argument) if c.is_whitespace() && depth == 0 => break,
+            ('(', Until::Argument) => depth += 1,
+            (')', Until::Argument) => depth -= 1,
+            ('$', _) => {
+                let start = *pos;
+                if let Some(part) = parse_dollar(value, pos, problems) {
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text {
+                            text: std::mem::take(&mut text),
+                            span: text_start..start,
+                        });
+                    }
+                    parts.push(part);
+                    text_start = *pos;
+                    continue;
+                }
+                if value[*pos..].starts_with("$$") {
+                    text.push('$');
+                    *pos += 2;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        text.push(c);
+        *pos += c.len_utf8();
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text {
+            text,
+            span: text_start..*pos,
+        });
+    }
+    parts
+}
+
+/// The macro or function at the `$` at `pos`, moving past it; `None` for
+/// a `$` that starts neither.
+fn parse_dollar(value: &str, pos: &mut usize, problems: &mut Vec<TemplateProblem>) -> Option<TemplatePart> {
+    let start = *pos;
+    let rest = &value[start + 1..];
+    if let Some(braced) = rest.strip_prefix('{') {
+        let name_start = start + 2;
+        let (name_end, end) = match braced.find('}') {
+            Some(close) => (name_start + close, name_start + close + 1),
+            None => {
+                problems.push(TemplateProblem {
+                    span: start..value.len(),
+                    message: "unterminated macro, expected `}`".to_string(),
+                });
+                (value.len(), value.len())
+            }
+        };
+        *pos = end;
+        return Some(TemplatePart::Macro(MacroReference {
+            name: value[name_start..name_end].to_string(),
+            braced: true,
+            name_span: name_start..name_end,
+            span: start..end,
+        }));
+    }
+    if rest.starts_with('(') {
+        return Some(TemplatePart::Function(parse_function(value, pos, problems)));
+    }
+    let len = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    if len == 0 {
+        return None;
+    }
+    *pos = start + 1 + len;
+    Some(TemplatePart::Macro(MacroReference {
+        name: rest[..len].to_string(),
+        braced: false,
+        name_span: start + 1..*pos,
+        span: start..*pos,
+    }))
+}
+
+fn skip_whitespace(value: &str, pos: &mut usize) {
+    let rest = &value[*pos..];
+    *pos += rest.len() - rest.trim_start().len();
+}
+
+/// `$(name arguments...)` at `pos`.
+fn parse_function(value: &str, pos: &mut usize, problems: &mut Vec<TemplateProblem>) -> TemplateFunction {
+    let start = *pos;
+    *pos += 2;
+    skip_whitespace(value, pos);
+    let name_start = *pos;
+    let rest = &value[name_start..];
+    *pos += rest.find(|c: char| c.is_whitespace() || c == ')').unwrap_or(rest.len());
+    let name_span = name_start..*pos;
+    let mut arguments = Vec::new();
+    loop {
+        skip_whitespace(value, pos);
+        match value[*pos..].chars().next() {
+            None => {
+                problems.push(TemplateProblem {
+                    span: start..value.len(),
+                    message: "unterminated template function, expected `)`".to_string(),
+                });
+                break;
+            }
+            Some(')') => {
+                *pos += 1;
+                break;
+            }
+            Some(quote @ ('"' | '\'')) => {
+                let argument_start = *pos;
+                *pos += 1;
+                let parts = parse_parts(value, pos, Until::Quote(quote), problems);
+                if *pos < value.len() {
+                    *pos += 1;
+                }
+                arguments.push(TemplateArgument {
+                    parts,
+                    span: argument_start..*pos,
+                });
+            }
+            Some(_) => {
+                let argument_start = *pos;
+                let parts = parse_parts(value, pos, Until::Argument, problems);
+                arguments.push(TemplateArgument {
+                    parts,
+                    span: argument_start..*pos,
+                });
+            }
+        }
+    }
+    TemplateFunction {
+        name: value[name_span.clone()].to_string(),
+        name_span,
+        arguments,
+        span: start..*pos,
+    }
+}
+
+/// The document range of the bytes `span` of the value of the string
+/// `literal`. Only known when the literal is its value verbatim: on one
+/// line, without escapes or `@define` references.
+pub fn verbatim_range(literal: &Literal, span: std::ops::Range<usize>) -> Option<Range> {
+    let range = literal.range;
+    let quoted = utf16_len(&literal.value) + 2;
+    if literal.kind != LiteralKind::String
+        || literal.raw.is_some()
+        || range.start.line != range.end.line
+        || range.end.character - range.start.character != quoted
+    {
+        return None;
+    }
+    let character = |offset: usize| range.start.character + 1 + utf16_len(&literal.value[..offset]);
+    Some(Range::new(
+        Position::new(range.start.line, character(span.start)),
+        Position::new(range.start.line, character(span.end)),
+    ))
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|c| c.len_utf16() as u32).sum()
+}
@@ -0,0 +1,482 @@
+//! A small backtracking matcher for the regular expressions of filters and
+//! `subst()` rules, used to evaluate them on a sample message.
+//!
+//! It covers what PCRE and POSIX extended expressions have in common:
+//! literals, `.`, `^`, `$`, bracket expressions with ranges and `[:name:]`
+//! classes, `\d`, `\w`, `\s` and their negations, `\b`, groups, `|` and
+//! the `*`, `+`, `?` and `{n,m}` quantifiers, greedy or lazy.
+//! Back-references, lookaround and other extensions are rejected rather
+//! than matched differently from syslog-ng.
+
+use std::ops::Range as Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    /// The whole pattern as a group of its alternatives.
+    root: Node,
+    ignore_case: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char(char),
+    /// `.`, anything but a newline.
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    /// `\b`, or `\B` when `false`.
+    WordBoundary(bool),
+    Group(Vec<Vec<Node>>),
+    Repeat(Box<Repetition>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Repetition {
+    node: Node,
+    min: usize,
+    max: Option<usize>,
+    lazy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ClassItem {
+    Range(char, char),
+    /// `[:digit:]`, `\d` and the like; `\D` is a negated one.
+    Named {
+        name: &'static str,
+        negated: bool,
+    },
+}
+
+impl Regex {
+    /// Compiles `pattern`; the error says what part of it is not supported.
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.alternatives(0)?;
+        if parser.pos < parser.chars.len() {
+            return Err("unmatched `)`".to_string());
+        }
+        Ok(Regex {
+            root: Node::Group(alternatives),
+            ignore_case,
+        })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.find(text).is_some()
+    }
+
+    /// The byte range of the leftmost match in `text`.
+    pub fn find(&self, text: &str) -> Option<Span<usize>> {
+        self.find_from(text, 0)
+    }
+
+    /// [`Self::find`] for matches starting at byte `from` or later.
+    pub fn find_from(&self, text: &str, from: usize) -> Option<Span<usize>> {
+        let offsets: Vec<usize> = text.char_indices().map(|(i, _)| i).chain([text.len()]).collect();
+        let chars: Vec<char> = text.chars().collect();
+        let matcher = Matcher {
+            text: &chars,
+            ignore_case: self.ignore_case,
+        };
+        let first = offsets.iter().position(|&offset| offset >= from)?;
+        (first..offsets.len()).find_map(|start| {
+            let mut end = None;
+            matcher.sequence(std::slice::from_ref(&self.root), start, &mut |pos| {
+                end = Some(pos);
+                true
+            });
+            end.map(|end| offsets[start]..offsets[end])
+        })
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn alternatives(&mut self, depth: usize) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence(depth)?];
+        while self.eat('|') {
+            alternatives.push(self.sequence(depth)?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self, depth: usize) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || (c == ')' && depth > 0) {
+                break;
+            }
+            if c == ')' {
+                return Err("unmatched `)`".to_string());
+            }
+            let atom = self.atom(depth)?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn quantified(&mut self, node: Node) -> Result<Node, String> {
+        let start = self.pos;
+        let (min, max) = match self.next() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => match self.bounds() {
+                Some(bounds) => bounds,
+                // Not a quantifier, a literal `{` as in PCRE.
+                None => {
+                    self.pos = start;
+                    return Ok(node);
+                }
+            },
+            _ => {
+                self.pos = start;
+                return Ok(node);
+            }
+        };
+        if matches!(self.peek(), Some('*' | '+' | '{')) {
+            return Err("nested quantifiers are not supported".to_string());
+        }
+        let lazy = self.eat('?');
+        Ok(Node::Repeat(Box::new(Repetition { node, min, max, lazy })))
+    }
+
+    /// `n}`, `n,}` or `n,m}` after a `{`.
+    fn bounds(&mut self) -> Option<(usize, Option<usize>)> {
+        let number = |parser: &mut Parser| {
+            let start = parser.pos;
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.pos += 1;
+            }
+            parser.chars[start..parser.pos]
+                .iter()
+                .collect::<String>()
+                .parse::<usize>()
+                .ok()
+        };
+        let min = number(self)?;
+        let max = match self.eat(',') {
+            true => number(self),
+            false => Some(min),
+        };
+        (self.eat('}') && max.is_none_or(|max| max >= min)).then_some((min, max))
+    }
+
+    fn atom(&mut self, depth: usize) -> Result<Node, String> {
+        match self.next() {
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('(') => {
+                if self.eat('?') && !self.eat(':') {
+                    return Err("only `(?:` groups are supported".to_string());
+                }
+                let alternatives = self.alternatives(depth + 1)?;
+                if !self.eat(')') {
+                    return Err("missing `)`".to_string());
+                }
+                Ok(Node::Group(alternatives))
+            }
+            Some('[') => self.class(),
+            Some('\\') => self.escape(),
+            Some('*' | '+' | '?') => Err("a quantifier needs something to repeat".to_string()),
+            Some(c) => Ok(Node::Char(c)),
+            None => Err("unexpected end of the pattern".to_string()),
+        }
+    }
+
+    fn escape(&mut self) -> Result<Node, String> {
+        let c = self.next().ok_or("trailing `\\`")?;
+        if let Some(item) = named_escape(c) {
+            return Ok(Node::Class {
+                items: vec![item],
+                negated: false,
+            });
+        }
+        match c {
+            'b' => Ok(Node::WordBoundary(true)),
+            'B' => Ok(Node::WordBoundary(false)),
+            c => Ok(Node::Char(literal_escape(c)?)),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or("missing `]`")?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let low = match c {
+                '[' if self.eat(':') => {
+                    let start = self.pos;
+                    while self.peek().is_some_and(|c| c != ':') {
+                        self.pos += 1;
+                    }
+                    let name: String = self.chars[start..self.pos].iter().collect();
+                    if !(self.eat(':') && self.eat(']')) {
+                        return Err("missing `:]`".to_string());
+                    }
+                    let name = NAMED_CLASSES
+                        .iter()
+                        .find(|n| **n == name)
+                        .ok_or_else(|| format!("unknown class `[:{}:]`", name))?;
+                    items.push(ClassItem::Named { name, negated: false });
+                    continue;
+                }
+                '\\' => {
+                    let escaped = self.next().ok_or("trailing `\\`")?;
+                    if let Some(item) = named_escape(escaped) {
+                        items.push(item);
+                        continue;
+                    }
+                    literal_escape(escaped)?
+                }
+                c => c,
+            };
+            let high = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(&high)) if high != ']' => {
+                    self.pos += 2;
+                    match high {
+                        '\\' => literal_escape(self.next().ok_or("trailing `\\`")?)?,
+                        high => high,
+                    }
+                }
+                _ => low,
+            };
+            if high < low {
+                return Err(format!("invalid range `{}-{}`", low, high));
+            }
+            items.push(ClassItem::Range(low, high));
+        }
+        Ok(Node::Class { items, negated })
+    }
+}
+
+const NAMED_CLASSES: &[&str] = &[
+    "alnum", "alpha", "blank", "cntrl", "digit", "graph", "lower", "print", "punct", "space", "upper", "word", "xdigit",
+];
+
+fn named_escape(c: char) -> Option<ClassItem> {
+    let (name, negated) = match c {
+        'd' => ("digit", false),
+        'D' => ("digit", true),
+        'w' => ("word", false),
+        'W' => ("word", true),
+        's' => ("space", false),
+        'S' => ("space", true),
+        _ => return None,
+    };
+    Some(ClassItem::Named { name, negated })
+}
+
+/// The character `\c` stands for.
+fn literal_escape(c: char) -> Result<char, String> {
+    match c {
+        'n' => Ok('\n'),
+        't' => Ok('\t'),
+        'r' => Ok('\r'),
+        '1'..='9' => Err("back-references are not supported".to_string()),
+        c if c.is_ascii_alphanumeric() => Err(format!("`\\{}` is not supported", c)),
+        c => Ok(c),
+    }
+}
+
+fn in_named_class(name: &str, c: char) -> bool {
+    match name {
+        "alnum" => c.is_alphanumeric(),
+        "alpha" => c.is_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "cntrl" => c.is_control(),
+        "digit" => c.is_ascii_digit(),
+        "graph" => !c.is_control() && !c.is_whitespace(),
+        "lower" => c.is_lowercase(),
+        "print" => !c.is_control(),
+        "punct" => c.is_ascii_punctuation(),
+        "space" => c.is_whitespace(),
+        "upper" => c.is_uppercase(),
+        "word" => c.is_alphanumeric() || c == '_',
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+struct Matcher<'t> {
+    text: &'t [char],
+    ignore_case: bool,
+}
+
+impl Matcher<'_> {
+    /// Matches `nodes` at `pos` and calls `k` with each position a match
+    /// can end at, in order of preference, until it returns `true`.
+    fn sequence(&self, nodes: &[Node], pos: usize, k: &mut dyn FnMut(usize) -> bool) -> bool {
+        let Some((node, rest)) = nodes.split_first() else {
+            return k(pos);
+        };
+        match node {
+            Node::Group(alternatives) => alternatives
+                .iter()
+                .any(|alternative| self.sequence(alternative, pos, &mut |next| self.sequence(rest, next, k))),
+            Node::Repeat(repetition) => self.repeat(repetition, 0, rest, pos, k),
+            node => self.step(node, pos).is_some_and(|next| self.sequence(rest, next, k)),
+        }
+    }
+
+    fn repeat(
+        &self,
+        repetition: &Repetition,
+        count: usize,
+        rest: &[Node],
+        pos: usize,
+        k: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        let can_stop = count >= repetition.min;
+        let can_go_on = repetition.max.is_none_or(|max| count < max);
+        if repetition.lazy && can_stop && self.sequence(rest, pos, k) {
+            return true;
+        }
+        // An empty match only counts towards the minimum, or the same
+        // position would be tried forever.
+        if can_go_on
+            && self.sequence(std::slice::from_ref(&repetition.node), pos, &mut |next| {
+                (next != pos || !can_stop) && self.repeat(repetition, count + 1, rest, next, k)
+            })
+        {
+            return true;
+        }
+        !repetition.lazy && can_stop && self.sequence(rest, pos, k)
+    }
+
+    /// The position after `node` matched at `pos`, for the nodes that are
+    /// neither groups nor repetitions.
+    fn step(&self, node: &Node, pos: usize) -> Option<usize> {
+        let here = self.text.get(pos).copied();
+        let matches = match node {
+            Node::Char(c) => here.is_some_and(|here| self.same(here, *c)),
+            Node::Any => here.is_some_and(|here| here != '\n'),
+            Node::Class { items, negated } => here.is_some_and(|here| self.in_class(items, here) != *negated),
+            Node::Start => return (pos == 0).then_some(pos),
+            Node::End => return (pos == self.text.len()).then_some(pos),
+            Node::WordBoundary(boundary) => {
+                let word = |c: Option<&char>| c.is_some_and(|c| in_named_class("word", *c));
+                let before = pos.checked_sub(1).and_then(|p| self.text.get(p));
+                return ((word(before) != word(self.text.get(pos))) == *boundary).then_some(pos);
+            }
+            Node::Group(_) | Node::Repeat(_) => unreachable!("handled by Matcher::sequence"),
+        };
+        matches.then_some(pos + 1)
+    }
+
+    fn same(&self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    fn in_class(&self, items: &[ClassItem], c: char) -> bool {
+        let variants: Vec<char> = match self.ignore_case {
+            true => [c]
+                .into_iter()
+                .chain(c.to_lowercase())
+                .chain(c.to_uppercase())
+                .collect(),
+            false => vec![c],
+        };
+        items.iter().any(|item| {
+            variants.iter().any(|&c| match item {
+                ClassItem::Range(low, high) => (*low..=*high).contains(&c),
+                ClassItem::Named { name, negated } => in_named_class(name, c) != *negated,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(pattern: &str, text: &'a str) -> Option<&'a str> {
+        let regex = Regex::new(pattern, false).unwrap();
+        regex.find(text).map(|span| &text[span])
+    }
+
+    #[test]
+    fn finds_the_leftmost_match() {
+        assert_eq!(find("b+", "abbbc"), Some("bbb"));
+        assert_eq!(find("b+?", "abbbc"), Some("b"));
+        assert_eq!(find("^a.c$", "abc"), Some("abc"));
+        assert_eq!(find("^b", "abc"), None);
+        assert_eq!(find("(foo|ba[rz])+", "xbazfoo!"), Some("bazfoo"));
+        assert_eq!(find("a{2,3}", "aaaa"), Some("aaa"));
+        assert_eq!(find("x{2}", "x{2}"), None);
+        assert_eq!(find("\\d+\\.\\d+", "version 4.10 is out"), Some("4.10"));
+        assert_eq!(find("[^[:space:]]+$", "failed for root"), Some("root"));
+        assert_eq!(find("\\bcat\\b", "concat cat"), Some("cat"));
+        assert_eq!(find("a{", "a{"), Some("a{"));
+    }
+
+    #[test]
+    fn backtracks_into_groups_and_repetitions() {
+        assert_eq!(find("(a|ab)c", "abc"), Some("abc"));
+        assert_eq!(find("a*a", "aaa"), Some("aaa"));
+        assert_eq!(find("(a*)*b", "aab"), Some("aab"));
+        assert_eq!(find("(x?)+y", "y"), Some("y"));
+    }
+
+    #[test]
+    fn ignores_case_when_asked() {
+        let regex = Regex::new("^error: [a-z]+", true).unwrap();
+        assert!(regex.is_match("ERROR: Disk"));
+        assert!(!Regex::new("^error", false).unwrap().is_match("ERROR"));
+    }
+
+    #[test]
+    fn finds_later_matches() {
+        let regex = Regex::new("o", false).unwrap();
+        assert_eq!(regex.find_from("foo bar", 2), Some(2..3));
+        assert_eq!(regex.find_from("foo bar", 3), None);
+        assert_eq!(Regex::new("é", false).unwrap().find("café"), Some(3..5));
+    }
+
+    #[test]
+    fn rejects_what_it_does_not_support() {
+        assert!(Regex::new("(a)\\1", false).is_err());
+        assert!(Regex::new("(?=a)", false).is_err());
+        assert!(Regex::new("(a", false).is_err());
+        assert!(Regex::new("a)", false).is_err());
+        assert!(Regex::new("[a", false).is_err());
+        assert!(Regex::new("*a", false).is_err());
+        assert!(Regex::new("[z-a]", false).is_err());
+    }
+}
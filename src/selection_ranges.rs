@@ -0,0 +1,93 @@
+//! `textDocument/selectionRange`: the nested syntax around a position,
+//! from a value out to its option, driver, object and the whole file.
+
+use crate::document_store::offset_to_position;
+use crate::language_types::objects::{Driver, Object, Parameter, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Position, Range, SelectionRange};
+
+pub fn selection_range(text: &str, parsed: &ParsedConfiguration, position: Position) -> SelectionRange {
+    let mut ranges = vec![Range::new(Position::default(), offset_to_position(text, text.len()))];
+    if let Some(annotation) = parsed.annotations.iter().find(|a| a.range().contains(position)) {
+        ranges.push(annotation.range());
+    }
+    if let Some(object) = parsed.objects.iter().find(|o| o.location.contains(position)) {
+        collect_object(object, position, &mut ranges);
+    }
+
+    // Innermost last; equal ranges would make expanding a no-op.
+    ranges.dedup();
+    let mut ranges = ranges.into_iter();
+    let mut selection = SelectionRange {
+        range: ranges.next().unwrap(),
+        parent: None,
+    };
+    for range in ranges {
+        selection = SelectionRange {
+            range,
+            parent: Some(Box::new(selection)),
+        };
+    }
+    selection
+}
+
+fn collect_object(object: &Object, position: Position, ranges: &mut Vec<Range>) {
+    ranges.push(object.location);
+    if collect_statements(object.statements(), position, ranges) {
+        return;
+    }
+    if let Some(id) = object.id.as_ref().filter(|id| id.range.contains(position)) {
+        ranges.push(id.range);
+    }
+}
+
+/// Pushes the ranges inside the statement under `position`, if any, and
+/// reports whether there was one.
+fn collect_statements(statements: &[Statement], position: Position, ranges: &mut Vec<Range>) -> bool {
+    for statement in statements {
+        match statement {
+            Statement::Object(inline) if inline.location.contains(position) => {
+                collect_object(inline, position, ranges);
+                return true;
+            }
+            Statement::Driver(driver) if driver.location.contains(position) => {
+                collect_driver(driver, position, ranges);
+                return true;
+            }
+            Statement::Conditional(conditional) if conditional.location.contains(position) => {
+                ranges.push(conditional.location);
+                if let Some(branch) = conditional.branches.iter().find(|b| b.location.contains(position)) {
+                    ranges.push(branch.location);
+                    collect_statements(&branch.statements, position, ranges);
+                }
+                return true;
+            }
+            Statement::Junction(junction) if junction.location.contains(position) => {
+                ranges.push(junction.location);
+                if let Some(channel) = junction.channels.iter().find(|c| c.location.contains(position)) {
+                    ranges.push(channel.location);
+                    collect_statements(&channel.statements, position, ranges);
+                }
+                return true;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+fn collect_driver(driver: &Driver, position: Position, ranges: &mut Vec<Range>) {
+    ranges.push(driver.location);
+    for parameter in &driver.parameters {
+        match parameter {
+            Parameter::Option(option) if option.location.contains(position) => {
+                return collect_driver(option, position, ranges)
+            }
+            Parameter::Literal(literal) if literal.range.contains(position) => return ranges.push(literal.range),
+            _ => {}
+        }
+    }
+    if driver.name.range.contains(position) {
+        ranges.push(driver.name.range);
+    }
+}
@@ -0,0 +1,276 @@
+//! `textDocument/semanticTokens` classification.
+//!
+//! Tokens come from the lexer, so strings, numbers, comments and
+//! annotations are highlighted even while the document has syntax errors.
+//! Words are classified from the AST when the document parses, and by
+//! what follows them otherwise.
+
+use std::collections::HashMap;
+
+use crate::language_types::objects::{Driver, Object, ObjectBody, ObjectKind, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Position, Range};
+use crate::parser::lexer::{tokenize_with_comments, Token, TokenKind};
+use crate::parser::parse_conf;
+
+/// Token types in legend order; the index is what goes on the wire.
+pub const TOKEN_TYPES: &[&str] = &[
+    "macro", "keyword", "variable", "function", "property", "string", "number", "comment", "operator",
+];
+
+pub const TOKEN_MODIFIERS: &[&str] = &["declaration"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenType {
+    Macro,
+    Keyword,
+    Variable,
+    Function,
+    Property,
+    String,
+    Number,
+    Comment,
+    Operator,
+}
+
+const DECLARATION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Classification {
+    token_type: TokenType,
+    modifiers: u32,
+}
+
+impl From<TokenType> for Classification {
+    fn from(token_type: TokenType) -> Self {
+        Classification {
+            token_type,
+            modifiers: 0,
+        }
+    }
+}
+
+/// A classified piece of a single line.
+struct SemanticToken {
+    start: Position,
+    length: u32,
+    classification: Classification,
+}
+
+/// Encoded tokens of `text`, limited to those overlapping `range` if given.
+pub fn semantic_tokens(text: &str, range: Option<Range>) -> Vec<u32> {
+    let Ok(tokens) = tokenize_with_comments(text) else {
+        return Vec::new();
+    };
+    let words = parse_conf(text).map(|parsed| classify_words(&parsed)).ok();
+
+    let mut pieces = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if range.is_some_and(|r| token.range.end < r.start || token.range.start > r.end) {
+            continue;
+        }
+        if token.kind == TokenKind::Pragma {
+            push_pragma(text, token, &mut pieces);
+            continue;
+        }
+        let classification = match token.kind {
+            TokenKind::String => TokenType::String.into(),
+            TokenKind::Number => TokenType::Number.into(),
+            TokenKind::Comment => TokenType::Comment.into(),
+            TokenKind::Operator => TokenType::Operator.into(),
+            TokenKind::Word => match &words {
+                Some(words) => match words.get(&token.range.start) {
+                    Some(classification) => *classification,
+                    None => continue,
+                },
+                None if tokens.get(i + 1).is_some_and(|t| t.is(TokenKind::LParen)) => TokenType::Function.into(),
+                None if tokens.get(i + 1).is_some_and(|t| t.is(TokenKind::LBrace)) => TokenType::Keyword.into(),
+                None => continue,
+            },
+            _ => continue,
+        };
+        push_split(
+            token.range.start,
+            &text[token.start..token.end],
+            classification,
+            &mut pieces,
+        );
+    }
+    encode(&pieces)
+}
+
+/// `@version` / `@include` as a macro, quoted arguments as strings and the
+/// version as a number.
+fn push_pragma(text: &str, token: &Token, pieces: &mut Vec<SemanticToken>) {
+    let raw = &text[token.start..token.end];
+    let name_len = raw.find(|c: char| c.is_whitespace() || c == ':').unwrap_or(raw.len());
+    push_split(token.range.start, &raw[..name_len], TokenType::Macro.into(), pieces);
+    if let (Some(open), Some(close)) = (raw.find('"'), raw.rfind('"')) {
+        if close > open {
+            let start = Position::new(
+                token.range.start.line,
+                token.range.start.character + utf16_len(&raw[..open]),
+            );
+            let quoted = &raw[open..=close];
+            push_split(start, quoted, TokenType::String.into(), pieces);
+        }
+    } else if let Some(colon) = raw.find(':') {
+        // `@version: 4.0`
+        let value = raw[colon + 1..].trim_start();
+        let offset = raw.len() - value.len();
+        let start = Position::new(
+            token.range.start.line,
+            token.range.start.character + utf16_len(&raw[..offset]),
+        );
+        push_split(start, value.trim_end(), TokenType::Number.into(), pieces);
+    }
+}
+
+/// Pushes `piece`, split at line breaks since not every client supports
+/// tokens spanning lines.
+fn push_split(start: Position, piece: &str, classification: Classification, pieces: &mut Vec<SemanticToken>) {
+    for (i, line) in piece.split('\n').enumerate() {
+        let line = line.trim_end_matches('\r');
+        let start = if i == 0 {
+            start
+        } else {
+            Position::new(start.line + i as u32, 0)
+        };
+        let length = utf16_len(line);
+        if length > 0 {
+            pieces.push(SemanticToken {
+                start,
+                length,
+                classification,
+            });
+        }
+    }
+}
+
+fn utf16_len(text: &str) -> u32 {
+    text.chars().map(|c| c.len_utf16() as u32).sum()
+}
+
+/// Relative encoding: delta line, delta start, length, type, modifiers.
+fn encode(pieces: &[SemanticToken]) -> Vec<u32> {
+    let mut data = Vec::with_capacity(pieces.len() * 5);
+    let mut previous = Position::default();
+    for piece in pieces {
+        let delta_line = piece.start.line - previous.line;
+        let delta_start = if delta_line == 0 {
+            piece.start.character - previous.character
+        } else {
+            piece.start.character
+        };
+        data.extend([
+            delta_line,
+            delta_start,
+            piece.length,
+            piece.classification.token_type as u32,
+            piece.classification.modifiers,
+        ]);
+        previous = piece.start;
+    }
+    data
+}
+
+/// Classification of every word the AST knows about, keyed by start position.
+fn classify_words(parsed: &ParsedConfiguration) -> HashMap<Position, Classification> {
+    let mut words = HashMap::new();
+    for object in &parsed.objects {
+        classify_object(object, &mut words);
+    }
+    for block in &parsed.blocks {
+        words.insert(block.keyword_range.start, TokenType::Keyword.into());
+        words.insert(block.context.range.start, TokenType::Keyword.into());
+        words.insert(
+            block.name.range.start,
+            Classification {
+                token_type: TokenType::Function,
+                modifiers: DECLARATION,
+            },
+        );
+        for parameter in &block.parameters {
+            words.insert(parameter.name.range.start, TokenType::Property.into());
+        }
+    }
+    for reference in &parsed.references {
+        words.insert(reference.range.start, TokenType::Variable.into());
+    }
+    words
+}
+
+fn classify_object(object: &Object, words: &mut HashMap<Position, Classification>) {
+    words.insert(object.keyword_range.start, TokenType::Keyword.into());
+    if let Some(id) = &object.id {
+        words.insert(
+            id.range.start,
+            Classification {
+                token_type: TokenType::Variable,
+                modifiers: DECLARATION,
+            },
+        );
+    }
+    match &object.body {
+        ObjectBody::Expression(expression) => classify_expression(&expression.tokens, words),
+        ObjectBody::Statements(statements) => {
+            // The statements of `options { ... };` are options themselves.
+            let statement_type = match object.kind {
+                ObjectKind::Options => TokenType::Property,
+                _ => TokenType::Function,
+            };
+            classify_statements(statements, statement_type, words);
+        }
+    }
+}
+
+fn classify_statements(
+    statements: &[Statement],
+    statement_type: TokenType,
+    words: &mut HashMap<Position, Classification>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Object(inline) => classify_object(inline, words),
+            Statement::Driver(driver) => classify_driver(driver, statement_type, words),
+            Statement::Conditional(conditional) => {
+                for branch in &conditional.branches {
+                    words.insert(branch.keyword.range.start, TokenType::Keyword.into());
+                    classify_expression(branch.condition_tokens(), words);
+                    classify_statements(&branch.statements, TokenType::Function, words);
+                }
+            }
+            Statement::Junction(junction) => {
+                words.insert(junction.keyword.range.start, TokenType::Keyword.into());
+                for channel in &junction.channels {
+                    words.insert(channel.keyword.range.start, TokenType::Keyword.into());
+                    classify_statements(&channel.statements, TokenType::Function, words);
+                }
+            }
+        }
+    }
+}
+
+/// Filter expressions: `and`, `or` and `not` as keywords, calls as functions.
+fn classify_expression(tokens: &[Token], words: &mut HashMap<Position, Classification>) {
+    for (i, token) in tokens.iter().enumerate() {
+        if !token.is(TokenKind::Word) {
+            continue;
+        }
+        let token_type = if matches!(token.text.as_str(), "and" | "or" | "not") {
+            TokenType::Keyword
+        } else if tokens.get(i + 1).is_some_and(|t| t.is(TokenKind::LParen)) {
+            TokenType::Function
+        } else {
+            continue;
+        };
+        words.insert(token.range.start, token_type.into());
+    }
+}
+
+fn classify_driver(driver: &Driver, token_type: TokenType, words: &mut HashMap<Position, Classification>) {
+    words.insert(driver.name.range.start, token_type.into());
+    for option in driver.options() {
+        classify_driver(option, TokenType::Property, words);
+    }
+}
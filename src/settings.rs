@@ -0,0 +1,179 @@
+//! Settings sent by the client, either as `initializationOptions` or
+//! through `workspace/didChangeConfiguration`.
+
+use crate::configuration::MAX_INCLUDE_DEPTH;
+use crate::grammar::Platform;
+use crate::json::Value;
+
+/// Which diagnostics are published while editing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationMode {
+    /// Every check.
+    Full,
+    /// Only syntax errors, from the parser and from `syslog-ng --syntax-only`.
+    Syntax,
+    /// Nothing; the check commands still report everything.
+    Off,
+}
+
+impl ValidationMode {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "full" => Some(ValidationMode::Full),
+            "syntax" => Some(ValidationMode::Syntax),
+            "off" => Some(ValidationMode::Off),
+            _ => None,
+        }
+    }
+}
+
+/// How much completion items say about themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompletionVerbosity {
+    /// Labels only.
+    Minimal,
+    /// A one-line detail; the documentation on `completionItem/resolve`.
+    #[default]
+    Normal,
+    /// The documentation right in the list, for clients that do not resolve.
+    Full,
+}
+
+impl CompletionVerbosity {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "minimal" => Some(CompletionVerbosity::Minimal),
+            "normal" => Some(CompletionVerbosity::Normal),
+            "full" => Some(CompletionVerbosity::Full),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerSettings {
+    /// Include an excerpt of the document in the log when a request fails
+    /// internally, and document text in the trace of received messages.
+    /// Can be turned off for confidential configurations.
+    pub log_document_content: bool,
+    /// Completing a driver or block also inserts its closing `)`, `);` or
+    /// `};`, with the cursor placed inside.
+    pub insert_closing_syntax: bool,
+    /// Path prefixes of includes replaced before resolving them, e.g.
+    /// `/etc/syslog-ng` by a mirror in the workspace. Relative replacements
+    /// are relative to the workspace folder of the including file.
+    pub include_path_map: Vec<(String, String)>,
+    /// Read included files that lie outside the workspace, e.g. system
+    /// files under `/etc`. They are analysed read-only.
+    pub read_outside_includes: bool,
+    /// Configuration version assumed for documents without `@version`, and
+    /// written by the fix adding one.
+    pub syslogng_version: Option<String>,
+    /// The syslog-ng binary asked for its version when neither the
+    /// configuration nor the settings give one.
+    pub syslogng_binary: Option<String>,
+    /// Milliseconds completion and hover may spend reading workspace files
+    /// before answering with what they have; 0 waits for all of them.
+    pub request_time_budget_ms: u64,
+    /// Also check saved documents with `syslog-ng --syntax-only`, using
+    /// [`ServerSettings::syslogng_binary`].
+    pub external_validation: bool,
+    /// Directory relative `@include` paths are looked up in when they are
+    /// not found next to the including file, like syslog-ng's
+    /// `include-path()`. Relative to the workspace folder of the including
+    /// file if relative.
+    pub include_root: Option<String>,
+    pub validation_mode: ValidationMode,
+    /// Includes nested deeper than this are not followed.
+    pub max_include_depth: usize,
+    pub completion_verbosity: CompletionVerbosity,
+    /// Also publish the checks of how the configuration will run, such as
+    /// an unprivileged `user()` with sources binding privileged ports.
+    pub deployment_lints: bool,
+    /// Diagnostics published for one file at most, the most severe first,
+    /// so that a file that is not a configuration at all does not flood
+    /// the client; 0 publishes all of them.
+    pub max_diagnostics_per_file: usize,
+    /// The platform the configuration is deployed on. Drivers of other
+    /// platforms are left out of completion and reported where used.
+    pub target_platform: Option<Platform>,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            log_document_content: true,
+            insert_closing_syntax: true,
+            include_path_map: Vec::new(),
+            read_outside_includes: true,
+            syslogng_version: None,
+            syslogng_binary: None,
+            request_time_budget_ms: 200,
+            external_validation: false,
+            include_root: None,
+            validation_mode: ValidationMode::Full,
+            max_include_depth: MAX_INCLUDE_DEPTH,
+            completion_verbosity: CompletionVerbosity::Normal,
+            deployment_lints: false,
+            max_diagnostics_per_file: 1000,
+            target_platform: None,
+        }
+    }
+}
+
+impl ServerSettings {
+    /// Applies the settings present in `value`, keeping the others.
+    pub fn update(&mut self, value: &Value) {
+        if let Some(log_document_content) = value["logDocumentContent"].as_bool() {
+            self.log_document_content = log_document_content;
+        }
+        if let Some(insert_closing_syntax) = value["insertClosingSyntax"].as_bool() {
+            self.insert_closing_syntax = insert_closing_syntax;
+        }
+        if let Some(map) = value["includePathMap"].as_object() {
+            self.include_path_map = map
+                .iter()
+                .filter_map(|(from, to)| to.as_str().map(|to| (from.clone(), to.to_string())))
+                .collect();
+        }
+        if let Some(read_outside_includes) = value["readOutsideIncludes"].as_bool() {
+            self.read_outside_includes = read_outside_includes;
+        }
+        if let Some(version) = value.get("syslogngVersion") {
+            self.syslogng_version = version.as_str().filter(|v| !v.is_empty()).map(str::to_string);
+        }
+        if let Some(binary) = value.get("syslogngBinary") {
+            self.syslogng_binary = binary.as_str().filter(|b| !b.is_empty()).map(str::to_string);
+        }
+        if let Some(budget) = value["requestTimeBudgetMs"].as_u64() {
+            self.request_time_budget_ms = budget;
+        }
+        if let Some(external_validation) = value["externalValidation"].as_bool() {
+            self.external_validation = external_validation;
+        }
+        if let Some(root) = value.get("includeRoot") {
+            self.include_root = root.as_str().filter(|r| !r.is_empty()).map(str::to_string);
+        }
+        if let Some(mode) = value["validationMode"].as_str().and_then(ValidationMode::from_name) {
+            self.validation_mode = mode;
+        }
+        if let Some(depth) = value["maxIncludeDepth"].as_u64() {
+            self.max_include_depth = depth as usize;
+        }
+        if let Some(verbosity) = value["completionVerbosity"]
+            .as_str()
+            .and_then(CompletionVerbosity::from_name)
+        {
+            self.completion_verbosity = verbosity;
+        }
+        if let Some(deployment_lints) = value["deploymentLints"].as_bool() {
+            self.deployment_lints = deployment_lints;
+        }
+        if let Some(max) = value["maxDiagnosticsPerFile"].as_u64() {
+            self.max_diagnostics_per_file = max as usize;
+        }
+        if let Some(platform) = value.get("targetPlatform") {
+            self.target_platform = platform.as_str().and_then(Platform::from_name);
+        }
+    }
+}
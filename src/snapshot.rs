@@ -0,0 +1,142 @@
+//! Analysis of one revision of a document.
+//!
+//! The backend builds a [`ParseSnapshot`] whenever an open document
+//! changes and every feature reads from it, so that completion, hover,
+//! symbols and diagnostics all agree on the same parse of the same text
+//! and on the same set of included files.
+//!
+//! Snapshots are never modified: [`ParseSnapshot::apply_edit`] derives the
+//! next revision, so readers holding the previous one keep a consistent
+//! view while it is built.
+
+use crate::configuration::{resolve_snippets, IncludeOptions, Snippet, SnippetCache, TextLoader};
+use crate::diagnostics::{analyse_configuration, syntax_error_report, DiagnosticReport};
+use crate::document_store::{apply_change, LineIndex};
+use crate::file_utilities::uri_to_path;
+use crate::language_types::annotations::Annotation;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Position, TextDocumentContentChangeEvent};
+use crate::parser::{parse_annotations, parse_conf, parse_conf_recovering, SngSyntaxError};
+use crate::symbols::SymbolTable;
+
+#[derive(Debug)]
+pub struct ParseSnapshot {
+    pub uri: String,
+    /// Version of the editor buffer, `None` for a file read from disk.
+    pub version: Option<i64>,
+    pub text: String,
+    pub ast: Result<ParsedConfiguration, SngSyntaxError>,
+    /// The objects and annotations that parse when `ast` is an error, see
+    /// [`parse_conf_recovering`]; empty otherwise.
+    pub recovered: ParsedConfiguration,
+    /// Definitions of the document and of the snippets it includes.
+    pub symbols: SymbolTable,
+    /// Every snippet reachable through `@include`, in the order syslog-ng
+    /// processes them. Followed from the `@include` lines that parse while
+    /// the document does not, so that what they define stays known.
+    pub include_graph: Vec<Snippet>,
+    pub diagnostics: DiagnosticReport,
+    pub line_index: LineIndex,
+}
+
+impl ParseSnapshot {
+    pub fn build(
+        uri: &str,
+        text: String,
+        version: Option<i64>,
+        load: &TextLoader,
+        cache: &SnippetCache,
+        options: &IncludeOptions,
+    ) -> Self {
+        let line_index = LineIndex::new(&text);
+        let ast = parse_conf(&text);
+        let (include_graph, diagnostics, recovered) = match &ast {
+            Ok(parsed) => {
+                let snippets = resolve_snippets(uri, parsed, load, cache, options);
+                let report = analyse_configuration(uri, parsed, &snippets);
+                (snippets, report, ParsedConfiguration::default())
+            }
+            Err(_) => {
+                let (recovered, errors) = parse_conf_recovering(&text);
+                let snippets = resolve_snippets(uri, &recovered, load, cache, options);
+                (snippets, syntax_error_report(&errors), recovered)
+            }
+        };
+        let mut snapshot = ParseSnapshot {
+            uri: uri.to_string(),
+            version,
+            text,
+            ast,
+            recovered,
+            symbols: SymbolTable::default(),
+            include_graph,
+            diagnostics,
+            line_index,
+        };
+        snapshot.symbols = SymbolTable::from_files(snapshot.merged_files(), &options.workspace_folders);
+        snapshot
+    }
+
+    /// The snapshot of the revision `version` that `changes` make of this
+    /// one, applied in order as in `textDocument/didChange`. The text is
+    /// parsed again in full; the included snippets that did not change are
+    /// taken from `cache` rather than read and parsed again.
+    pub fn apply_edit(
+        &self,
+        changes: &[TextDocumentContentChangeEvent],
+        version: Option<i64>,
+        load: &TextLoader,
+        cache: &SnippetCache,
+        options: &IncludeOptions,
+    ) -> Self {
+        let mut text = self.text.clone();
+        for change in changes {
+            apply_change(&mut text, change);
+        }
+        Self::build(&self.uri, text, version, load, cache, options)
+    }
+
+    /// Builds the snapshot of a file that is not open in the editor.
+    pub fn from_disk(uri: &str, load: &TextLoader, cache: &SnippetCache, options: &IncludeOptions) -> Option<Self> {
+        let loaded = load(&uri_to_path(uri)?)?;
+        Some(Self::build(uri, loaded.text, None, load, cache, options))
+    }
+
+    pub fn parsed(&self) -> Option<&ParsedConfiguration> {
+        self.ast.as_ref().ok()
+    }
+
+    /// The document as far as it parses: all of it, or the objects and
+    /// annotations outside the parts with syntax errors.
+    pub fn partial(&self) -> &ParsedConfiguration {
+        self.ast.as_ref().unwrap_or(&self.recovered)
+    }
+
+    /// Whether the document has `@include` lines, including ones that
+    /// match no file at the moment.
+    pub fn has_includes(&self) -> bool {
+        let is_include = |a: &Annotation| matches!(a, Annotation::Include(_));
+        match self.parsed() {
+            Some(parsed) => parsed.annotations.iter().any(is_include),
+            None => parse_annotations(&self.text).iter().any(is_include),
+        }
+    }
+
+    /// The document as far as it parses, followed by the included snippets
+    /// that parse.
+    pub fn merged_files(&self) -> impl Iterator<Item = (&str, &ParsedConfiguration)> {
+        let snippets = self
+            .include_graph
+            .iter()
+            .filter_map(|s| s.parsed.as_ref().ok().map(|p| (s.uri.as_str(), p)));
+        std::iter::once((self.uri.as_str(), self.partial())).chain(snippets)
+    }
+
+    pub fn offset(&self, position: Position) -> usize {
+        self.line_index.offset(&self.text, position)
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        self.line_index.position(&self.text, offset)
+    }
+}
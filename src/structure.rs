@@ -0,0 +1,160 @@
+//! `syslogng/documentStructure`: a plain tree of what a document contains,
+//! for tools embedding the server that render structure themselves rather
+//! than implementing symbols, folding and the like one by one.
+//!
+//! Every node has a `kind`, an optional `name`, a `range` and `children`.
+//! The kinds are `version`, `include`, `define` and `annotation` for
+//! directives, the object keywords (`source`, ..., `log`, `options`),
+//! `block` with its `parameter`s, `branch` for `if`/`elif`/`else`,
+//! `junction` with its `channel`s, `condition` and `expression` for filter
+//! expressions, `driver`, `option` and `value`.
+
+use crate::json::Value;
+use crate::json_object;
+use crate::language_types::annotations::Annotation;
+use crate::language_types::objects::{Driver, Object, ObjectBody, Parameter, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{Range, ToJson};
+use crate::parser::lexer::Token;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructureNode {
+    pub kind: &'static str,
+    pub name: Option<String>,
+    pub range: Range,
+    pub children: Vec<StructureNode>,
+}
+
+impl StructureNode {
+    fn leaf(kind: &'static str, name: Option<String>, range: Range) -> Self {
+        StructureNode {
+            kind,
+            name,
+            range,
+            children: Vec::new(),
+        }
+    }
+}
+
+impl ToJson for StructureNode {
+    fn to_json(&self) -> Value {
+        json_object! {
+            "kind" => self.kind,
+            "name" => self.name.as_deref().map_or(Value::Null, Value::from),
+            "range" => self.range.to_json(),
+            "children" => self.children.to_json(),
+        }
+    }
+}
+
+/// The directives and objects of `parsed` in document order.
+pub fn document_structure(parsed: &ParsedConfiguration) -> Vec<StructureNode> {
+    let mut nodes: Vec<StructureNode> = annotation_nodes(&parsed.annotations);
+    nodes.extend(parsed.objects.iter().map(object_node));
+    nodes.extend(parsed.blocks.iter().map(|block| {
+        StructureNode {
+            kind: "block",
+            name: Some(block.name.name.clone()),
+            range: block.location,
+            children: block
+                .parameters
+                .iter()
+                .map(|p| StructureNode::leaf("parameter", Some(p.name.name.clone()), p.location))
+                .collect(),
+        }
+    }));
+    nodes.sort_by_key(|node| node.range.start);
+    nodes
+}
+
+/// Nodes for directives alone, for a document whose objects do not parse.
+pub fn annotation_nodes(annotations: &[Annotation]) -> Vec<StructureNode> {
+    annotations
+        .iter()
+        .map(|annotation| match annotation {
+            Annotation::Version(a) => StructureNode::leaf("version", Some(a.version.clone()), a.range),
+            Annotation::Include(a) => StructureNode::leaf("include", Some(a.path.clone()), a.range),
+            Annotation::Define(a) => StructureNode::leaf("define", Some(a.name.clone()), a.range),
+            Annotation::Module(a) => StructureNode::leaf("module", Some(a.name.clone()), a.range),
+            Annotation::Requires(a) => StructureNode::leaf("requires", Some(a.module.clone()), a.range),
+            Annotation::Other(a) => StructureNode::leaf("annotation", Some(a.name.clone()), a.range),
+        })
+        .collect()
+}
+
+fn object_node(object: &Object) -> StructureNode {
+    let children = match &object.body {
+        ObjectBody::Statements(statements) => statement_nodes(statements),
+        ObjectBody::Expression(expression) => expression_node("expression", &expression.tokens).into_iter().collect(),
+    };
+    StructureNode {
+        kind: object.kind.keyword(),
+        name: object.id.as_ref().map(|id| id.name.clone()),
+        range: object.location,
+        children,
+    }
+}
+
+fn statement_nodes(statements: &[Statement]) -> Vec<StructureNode> {
+    let mut nodes = Vec::new();
+    for statement in statements {
+        match statement {
+            Statement::Object(inline) => nodes.push(object_node(inline)),
+            Statement::Driver(driver) => nodes.push(driver_node("driver", driver)),
+            Statement::Conditional(conditional) => nodes.extend(conditional.branches.iter().map(|branch| {
+                let condition = branch.condition_tokens();
+                let mut children: Vec<StructureNode> = expression_node("condition", condition).into_iter().collect();
+                children.extend(statement_nodes(&branch.statements));
+                StructureNode {
+                    kind: "branch",
+                    name: Some(branch.keyword.name.clone()),
+                    range: branch.location,
+                    children,
+                }
+            })),
+            Statement::Junction(junction) => nodes.push(StructureNode {
+                kind: "junction",
+                name: None,
+                range: junction.location,
+                children: junction
+                    .channels
+                    .iter()
+                    .map(|channel| StructureNode {
+                        kind: "channel",
+                        name: None,
+                        range: channel.location,
+                        children: statement_nodes(&channel.statements),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+    nodes
+}
+
+fn driver_node(kind: &'static str, driver: &Driver) -> StructureNode {
+    StructureNode {
+        kind,
+        name: Some(driver.name.name.clone()),
+        range: driver.location,
+        children: driver
+            .parameters
+            .iter()
+            .map(|parameter| match parameter {
+                Parameter::Option(option) => driver_node("option", option),
+                Parameter::Literal(literal) => StructureNode::leaf("value", Some(literal.value.clone()), literal.range),
+            })
+            .collect(),
+    }
+}
+
+/// The range covered by the raw tokens of a filter expression; `None` when
+/// there are none.
+fn expression_node(kind: &'static str, tokens: &[Token]) -> Option<StructureNode> {
+    let (first, last) = (tokens.first()?, tokens.last()?);
+    Some(StructureNode::leaf(
+        kind,
+        None,
+        Range::new(first.range.start, last.range.end),
+    ))
+}
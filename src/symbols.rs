@@ -0,0 +1,353 @@
+//! Document outline and workspace symbol search.
+
+use std::path::PathBuf;
+
+use crate::file_utilities::uri_to_path;
+use crate::language_types::objects::{Block, Driver, LiteralKind, Object, ObjectKind, Statement};
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{
+    DocumentHighlight, DocumentHighlightKind, DocumentSymbol, Location, Position, SymbolInformation, SymbolKind,
+};
+
+/// One symbol per top-level object, with its drivers and their options as
+/// children, and one per block definition with its parameters.
+pub fn document_symbols(parsed: &ParsedConfiguration) -> Vec<DocumentSymbol> {
+    let mut symbols: Vec<DocumentSymbol> = parsed.objects.iter().map(object_symbol).collect();
+    symbols.extend(parsed.blocks.iter().map(block_symbol));
+    symbols.sort_by_key(|symbol| symbol.range.start);
+    symbols
+}
+
+/// Icon of block definitions, which are used like drivers.
+pub const BLOCK_SYMBOL_KIND: SymbolKind = SymbolKind::Function;
+
+fn block_symbol(block: &Block) -> DocumentSymbol {
+    DocumentSymbol {
+        name: block.name.name.clone(),
+        detail: Some(format!("block {}", block.context.name)),
+        kind: BLOCK_SYMBOL_KIND,
+        range: block.location,
+        selection_range: block.name.range,
+        children: block
+            .parameters
+            .iter()
+            .map(|parameter| DocumentSymbol {
+                name: parameter.name.name.clone(),
+                detail: None,
+                kind: SymbolKind::Property,
+                range: parameter.location,
+                selection_range: parameter.name.range,
+                children: Vec::new(),
+            })
+            .collect(),
+    }
+}
+
+fn object_symbol(object: &Object) -> DocumentSymbol {
+    let (name, selection_range, detail) = match &object.id {
+        Some(id) => (id.name.clone(), id.range, Some(object.kind.to_string())),
+        None => (object.kind.to_string(), object.keyword_range, None),
+    };
+    // The statements of `options { ... };` are options themselves.
+    let statement_kind = match object.kind {
+        ObjectKind::Options => SymbolKind::Property,
+        _ => SymbolKind::Function,
+    };
+    DocumentSymbol {
+        name,
+        detail,
+        kind: symbol_kind(object.kind),
+        range: object.location,
+        selection_range,
+        children: statement_symbols(object.statements(), statement_kind),
+    }
+}
+
+/// Every branch of an `if` block is a symbol of its own, shown like a
+/// nested log path; a junction is one with its channels as children,
+/// named by their flags.
+fn statement_symbols(statements: &[Statement], driver_kind: SymbolKind) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    for statement in statements {
+        match statement {
+            Statement::Object(inline) => symbols.push(object_symbol(inline)),
+            Statement::Driver(driver) => symbols.push(driver_symbol(driver, driver_kind)),
+            Statement::Conditional(conditional) => {
+                symbols.extend(conditional.branches.iter().map(|branch| DocumentSymbol {
+                    name: branch.keyword.name.clone(),
+                    detail: None,
+                    kind: symbol_kind(ObjectKind::Log),
+                    range: branch.location,
+                    selection_range: branch.keyword.range,
+                    children: statement_symbols(&branch.statements, SymbolKind::Function),
+                }))
+            }
+            Statement::Junction(junction) => symbols.push(DocumentSymbol {
+                name: junction.keyword.name.clone(),
+                detail: None,
+                kind: symbol_kind(ObjectKind::Log),
+                range: junction.location,
+                selection_range: junction.keyword.range,
+                children: junction
+                    .channels
+                    .iter()
+                    .map(|channel| {
+                        let flags: Vec<&str> = channel.flags().map(|f| f.value.as_str()).collect();
+                        DocumentSymbol {
+                            name: channel.keyword.name.clone(),
+                            detail: (!flags.is_empty()).then(|| format!("flags({})", flags.join(", "))),
+                            kind: symbol_kind(ObjectKind::Log),
+                            range: channel.location,
+                            selection_range: channel.keyword.range,
+                            children: statement_symbols(&channel.statements, SymbolKind::Function),
+                        }
+                    })
+                    .collect(),
+            }),
+        }
+    }
+    symbols
+}
+
+/// Icon of an object kind in symbol pickers.
+pub fn symbol_kind(kind: ObjectKind) -> SymbolKind {
+    match kind {
+        ObjectKind::Source => SymbolKind::Event,
+        ObjectKind::Destination => SymbolKind::Interface,
+        ObjectKind::Filter => SymbolKind::Boolean,
+        ObjectKind::Parser => SymbolKind::Class,
+        ObjectKind::Rewrite => SymbolKind::Operator,
+        ObjectKind::Template => SymbolKind::String,
+        ObjectKind::Log => SymbolKind::Namespace,
+        ObjectKind::Options => SymbolKind::Module,
+    }
+}
+
+fn driver_symbol(driver: &Driver, kind: SymbolKind) -> DocumentSymbol {
+    let values: Vec<String> = driver
+        .literals()
+        .map(|l| {
+            let value = l.raw.as_ref().unwrap_or(&l.value);
+            match l.kind {
+                LiteralKind::String => format!("\"{}\"", value),
+                LiteralKind::Number | LiteralKind::Word => value.clone(),
+            }
+        })
+        .collect();
+    DocumentSymbol {
+        name: driver.name.name.clone(),
+        detail: (!values.is_empty()).then(|| values.join(" ")),
+        kind,
+        range: driver.location,
+        selection_range: driver.name.range,
+        children: driver
+            .options()
+            .map(|o| driver_symbol(o, SymbolKind::Property))
+            .collect(),
+    }
+}
+
+/// The definition and the uses in `parsed` of the object identifier under
+/// `position`, the definition marked as written and the uses as read.
+pub fn document_highlights(parsed: &ParsedConfiguration, position: Position) -> Vec<DocumentHighlight> {
+    let Some((kind, name, _)) = parsed.symbol_at(position) else {
+        return Vec::new();
+    };
+    let definitions = parsed
+        .definitions()
+        .filter(|(k, id)| *k == kind && id.name == name)
+        .map(|(_, id)| DocumentHighlight {
+            range: id.range,
+            kind: DocumentHighlightKind::Write,
+        });
+    let references = parsed
+        .references
+        .iter()
+        .filter(|r| r.kind == kind && r.name == name)
+        .map(|r| DocumentHighlight {
+            range: r.range,
+            kind: DocumentHighlightKind::Read,
+        });
+    definitions.chain(references).collect()
+}
+
+/// The definitions of the `kind` object `name` in `files`.
+pub fn definition_locations<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a ParsedConfiguration)>,
+    kind: ObjectKind,
+    name: &str,
+) -> Vec<Location> {
+    let mut locations = Vec::new();
+    for (file_uri, config) in files {
+        let definitions = config.definitions().filter(|(k, id)| *k == kind && id.name == name);
+        locations.extend(definitions.map(|(_, id)| Location {
+            uri: file_uri.to_string(),
+            range: id.range,
+        }));
+    }
+    locations
+}
+
+/// Every definition (if requested) and reference of the `kind` object `name`.
+pub fn symbol_locations(
+    files: &[(String, ParsedConfiguration)],
+    kind: ObjectKind,
+    name: &str,
+    include_declaration: bool,
+) -> Vec<Location> {
+    let mut locations = Vec::new();
+    for (file_uri, config) in files {
+        if include_declaration {
+            let definitions = config.definitions().filter(|(k, id)| *k == kind && id.name == name);
+            locations.extend(definitions.map(|(_, id)| Location {
+                uri: file_uri.clone(),
+                range: id.range,
+            }));
+        }
+        let references = config.references.iter().filter(|r| r.kind == kind && r.name == name);
+        locations.extend(references.map(|r| Location {
+            uri: file_uri.clone(),
+            range: r.range,
+        }));
+    }
+    locations
+}
+
+/// The named objects of a set of files, typically the whole workspace
+/// together with every snippet the open documents include.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    entries: Vec<SymbolEntry>,
+    blocks: Vec<BlockEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolEntry {
+    pub kind: ObjectKind,
+    pub name: String,
+    /// Location of the identifier.
+    pub location: Location,
+    /// The defining file, relative to the workspace folder holding it.
+    pub file: String,
+}
+
+/// A `block` definition in a [`SymbolTable`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntry {
+    /// `source`, `root` and the like, see [`Block::context`].
+    pub context: String,
+    pub name: String,
+    /// Location of the block name.
+    pub location: Location,
+    /// The defining file, relative to the workspace folder holding it.
+    pub file: String,
+}
+
+impl SymbolTable {
+    pub fn build(files: &[(String, ParsedConfiguration)], workspace_folders: &[PathBuf]) -> Self {
+        Self::from_files(
+            files.iter().map(|(uri, parsed)| (uri.as_str(), parsed)),
+            workspace_folders,
+        )
+    }
+
+    pub fn from_files<'a>(
+        files: impl IntoIterator<Item = (&'a str, &'a ParsedConfiguration)>,
+        workspace_folders: &[PathBuf],
+    ) -> Self {
+        let mut table = SymbolTable::default();
+        for (uri, parsed) in files {
+            let file = display_name(uri, workspace_folders);
+            table.entries.extend(parsed.definitions().map(|(kind, id)| SymbolEntry {
+                kind,
+                name: id.name.clone(),
+                location: Location {
+                    uri: uri.to_string(),
+                    range: id.range,
+                },
+                file: file.clone(),
+            }));
+            table.blocks.extend(parsed.blocks.iter().map(|block| BlockEntry {
+                context: block.context.name.clone(),
+                name: block.name.name.clone(),
+                location: Location {
+                    uri: uri.to_string(),
+                    range: block.name.range,
+                },
+                file: file.clone(),
+            }));
+        }
+        table
+    }
+
+    /// Entries whose name contains `query`, ignoring case. The client does
+    /// the fuzzy ranking, so an empty query returns everything.
+    pub fn search<'a>(&'a self, query: &str) -> impl Iterator<Item = &'a SymbolEntry> + 'a {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .filter(move |e| e.name.to_lowercase().contains(&query))
+    }
+
+    /// Blocks whose name contains `query`, like [`Self::search`].
+    pub fn search_blocks<'a>(&'a self, query: &str) -> impl Iterator<Item = &'a BlockEntry> + 'a {
+        let query = query.to_lowercase();
+        self.blocks
+            .iter()
+            .filter(move |b| b.name.to_lowercase().contains(&query))
+    }
+
+    /// Where blocks called `name` are defined, in file order.
+    pub fn block_definitions(&self, name: &str) -> Vec<Location> {
+        self.blocks
+            .iter()
+            .filter(|b| b.name == name)
+            .map(|b| b.location.clone())
+            .collect()
+    }
+
+    /// Where the `kind` object `name` is defined, in file order.
+    pub fn definitions(&self, kind: ObjectKind, name: &str) -> Vec<Location> {
+        self.entries
+            .iter()
+            .filter(|e| e.kind == kind && e.name == name)
+            .map(|e| e.location.clone())
+            .collect()
+    }
+}
+
+impl SymbolEntry {
+    pub fn to_symbol_information(&self) -> SymbolInformation {
+        SymbolInformation {
+            name: self.name.clone(),
+            kind: symbol_kind(self.kind),
+            location: self.location.clone(),
+            container_name: Some(format!("{} in {}", self.kind, self.file)),
+        }
+    }
+}
+
+impl BlockEntry {
+    pub fn to_symbol_information(&self) -> SymbolInformation {
+        SymbolInformation {
+            name: self.name.clone(),
+            kind: BLOCK_SYMBOL_KIND,
+            location: self.location.clone(),
+            container_name: Some(format!("block {} in {}", self.context, self.file)),
+        }
+    }
+}
+
+/// Path of `uri` relative to the workspace folder containing it; files
+/// outside the workspace, e.g. includes from `/etc`, keep their full path.
+pub fn display_name(uri: &str, workspace_folders: &[PathBuf]) -> String {
+    let Some(path) = uri_to_path(uri) else {
+        return uri.to_string();
+    };
+    workspace_folders
+        .iter()
+        .find_map(|folder| path.strip_prefix(folder).ok())
+        .unwrap_or(&path)
+        .display()
+        .to_string()
+}
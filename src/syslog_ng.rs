@@ -0,0 +1,180 @@
+//! Interaction with an installed syslog-ng binary.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::lsp_types::{Position, Range};
+
+/// Binary looked up on `PATH` when no other is configured.
+pub const DEFAULT_BINARY: &str = "syslog-ng";
+
+/// `source` of the diagnostics reported by [`syntax_check`].
+pub const SYNTAX_CHECK_SOURCE: &str = "syslog-ng --syntax-only";
+
+/// The configuration version `binary` supports, from the `Config version:`
+/// line of `syslog-ng --version`. `None` if it cannot be run.
+pub fn detect_config_version(binary: &str) -> Option<String> {
+    let output = Command::new(binary).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Config version:"))
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+}
+
+/// A problem reported by `syslog-ng --syntax-only`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxCheckError {
+    pub path: PathBuf,
+    pub range: Range,
+    pub message: String,
+}
+
+/// Checks the configuration at `path` with `binary --syntax-only`. Returns
+/// the errors it reported, or why it could not be run.
+pub fn syntax_check(binary: &str, path: &Path) -> Result<Vec<SyntaxCheckError>, String> {
+    let output = Command::new(binary)
+        .arg("--syntax-only")
+        .arg("--cfgfile")
+        .arg(path)
+        .output()
+        .map_err(|error| format!("cannot run {}: {}", binary, error))?;
+    if output.status.success() {
+        return Ok(Vec::new());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors: Vec<SyntaxCheckError> = stderr.lines().filter_map(parse_error_line).collect();
+    if !errors.is_empty() {
+        return Ok(errors);
+    }
+    // Failures without a location, e.g. a plugin that is not installed.
+    let message = stderr.lines().map(str::trim).find(|line| !line.is_empty());
+    Ok(vec![SyntaxCheckError {
+        path: path.to_path_buf(),
+        range: Range::default(),
+        message: message.unwrap_or("syntax check failed").to_string(),
+    }])
+}
+
+/// `Error parsing config, syntax error, unexpected '}' in /etc/x.conf:12:5-12:6:`
+/// with 1-based lines and columns.
+fn parse_error_line(line: &str) -> Option<SyntaxCheckError> {
+    let (message, location) = line.trim_end().strip_suffix(':')?.rsplit_once(" in ")?;
+    let (start, end) = location.rsplit_once('-')?;
+    let (start, start_column) = start.rsplit_once(':')?;
+    let (path, start_line) = start.rsplit_once(':')?;
+    let (end_line, end_column) = end.split_once(':')?;
+    let position = |line: &str, column: &str| -> Option<Position> {
+        let line: u32 = line.parse().ok()?;
+        let column: u32 = column.parse().ok()?;
+        Some(Position::new(line.saturating_sub(1), column.saturating_sub(1)))
+    };
+    let start = position(start_line, start_column)?;
+    let end = position(end_line, end_column)?.max(Position::new(start.line, start.character + 1));
+    Some(SyntaxCheckError {
+        path: PathBuf::from(path),
+        range: Range::new(start, end),
+        message: message.trim().to_string(),
+    })
+}
+
+/// Copies of configuration files in a new private directory, laid out as
+/// the files are relative to each other so that relative includes between
+/// them resolve. The directory is removed when the tree is dropped.
+#[derive(Debug)]
+pub struct TemporaryTree {
+    root: PathBuf,
+    /// The directory of the originals that `root` stands for.
+    base: PathBuf,
+}
+
+impl TemporaryTree {
+    /// A tree holding `files`, each a path with the text to write for it.
+    pub fn create(files: &[(PathBuf, String)]) -> io::Result<TemporaryTree> {
+        let base = common_directory(files.iter().map(|(path, _)| path.as_path()));
+        let tree = TemporaryTree {
+            root: create_private_directory()?,
+            base,
+        };
+        for (path, text) in files {
+            let copy = tree.copy(path).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is outside {}", path.display(), tree.base.display()),
+                )
+            })?;
+            if let Some(parent) = copy.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&copy, text)?;
+        }
+        Ok(tree)
+    }
+
+    /// Where the copy of `path` goes; `None` for paths that are not below
+    /// the directory of the originals.
+    pub fn copy(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.base).ok()?;
+        relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+            .then(|| self.root.join(relative))
+    }
+
+    /// The original of the copy at `path`; other paths are returned as they
+    /// are.
+    pub fn original(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.root) {
+            Ok(relative) => self.base.join(relative),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+impl Drop for TemporaryTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// A new directory under the system temporary directory that only the
+/// current user can read.
+fn create_private_directory() -> io::Result<PathBuf> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let root = std::env::temp_dir().join(format!("sng-lsp-{}-{}", std::process::id(), n));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&root) {
+            Ok(()) => return Ok(root),
+            // Left over by an earlier process with the same id.
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// The deepest directory containing every one of `files`.
+fn common_directory<'a>(files: impl Iterator<Item = &'a Path>) -> PathBuf {
+    let mut common: Option<PathBuf> = None;
+    for directory in files.filter_map(Path::parent) {
+        common = Some(match common {
+            None => directory.to_path_buf(),
+            Some(common) => common
+                .components()
+                .zip(directory.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    common.unwrap_or_default()
+}
@@ -0,0 +1,1376 @@
+//! Where a sample message goes in a configuration, for the
+//! `syslogng.traceMessage` command.
+//!
+//! Log paths are tried in the order syslog-ng reads them, honouring
+//! `flags(final)`, `flags(fallback)` and `flags(catch-all)`. Along a path,
+//! filters and `if` conditions are evaluated on the message, rewrite rules
+//! change it and destinations receive it. What cannot be evaluated here,
+//! such as parsers, template functions or `in-list()`, is reported as
+//! unknown: the message is assumed to get past it, and whatever it reaches
+//! after that is marked uncertain.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+
+use crate::configuration::{merged_items, MergedItem, Snippet};
+use crate::file_utilities::wildcard_match;
+use crate::grammar::normalize_name;
+use crate::json::Value;
+use crate::json_object;
+use crate::language_types::filter_expression::{Expression, STRING_COMPARISONS};
+use crate::language_types::objects::{
+    Conditional, Driver, Junction, Literal, LiteralKind, Object, ObjectBody, ObjectKind, Parameter, Statement,
+};
+use crate::language_types::rewrite::{RewriteOperation, RewriteRule};
+use crate::language_types::templates::TemplatePart;
+use crate::language_types::ParsedConfiguration;
+use crate::lsp_types::{FromJson, Location, Range, ToJson};
+use crate::parser::object_parser::match_object_kind;
+use crate::parser::template_parser::parse_template;
+use crate::regexp::Regex;
+
+/// The message to trace.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SampleMessage {
+    /// Values by macro name, e.g. `MESSAGE`, `PROGRAM`, `LEVEL` or
+    /// `.SDATA.meta.id`. Missing ones are empty, as in syslog-ng.
+    pub fields: BTreeMap<String, String>,
+    pub tags: Vec<String>,
+    /// The source object the message arrives from. Without one, the message
+    /// enters every log path that reads a source.
+    pub source: Option<String>,
+}
+
+impl FromJson for SampleMessage {
+    /// `{ "fields": { "MESSAGE": "..." }, "tags": [...], "source": "s_net" }`
+    fn from_json(value: &Value) -> Option<Self> {
+        let mut fields = BTreeMap::new();
+        for (name, field) in value["fields"].as_object()? {
+            let text = match field {
+                Value::String(s) => s.clone(),
+                Value::Number(_) | Value::Bool(_) => field.to_string(),
+                _ => return None,
+            };
+            fields.insert(canonical_name(name).to_string(), text);
+        }
+        Some(SampleMessage {
+            fields,
+            tags: value["tags"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|t| t.as_str().map(str::to_string))
+                .collect(),
+            source: value["source"].as_str().map(str::to_string),
+        })
+    }
+}
+
+/// Where the message went, see [`trace_message`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// The top-level log paths, in the order they appear.
+    pub paths: Vec<PathTrace>,
+    /// The destinations the message reached, each once.
+    pub destinations: Vec<Delivery>,
+}
+
+/// A destination the message reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delivery {
+    /// `None` for a destination defined inline in a log path.
+    pub name: Option<String>,
+    /// The definition of the destination.
+    pub location: Location,
+    /// Every step before it could be evaluated.
+    pub certain: bool,
+}
+
+/// A log path, an embedded log path, an `if` branch or a junction
+/// channel, as the message went along it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTrace {
+    pub location: Location,
+    pub steps: Vec<Step>,
+    /// The message came out at the end of the path.
+    pub matched: bool,
+    /// Every step of the path, and of those it is in, could be evaluated.
+    pub certain: bool,
+    /// Why the message did not enter the path at all.
+    pub skipped: Option<String>,
+}
+
+/// One element of a log path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// `filter`, `rewrite`, `parser`, `destination`, `log`, `junction`,
+    /// `if`, `elif` or `else`.
+    pub kind: String,
+    /// The object referenced, `None` for inline objects.
+    pub name: Option<String>,
+    pub location: Location,
+    pub result: StepResult,
+    /// What could not be evaluated.
+    pub reason: Option<String>,
+    /// What a rewrite changed.
+    pub changes: Vec<Change>,
+    /// The embedded log paths, branches or channels the message went into.
+    pub paths: Vec<PathTrace>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepResult {
+    Accepted,
+    Rejected,
+    Unknown,
+    Rewritten,
+    Delivered,
+}
+
+impl StepResult {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepResult::Accepted => "accepted",
+            StepResult::Rejected => "rejected",
+            StepResult::Unknown => "unknown",
+            StepResult::Rewritten => "rewritten",
+            StepResult::Delivered => "delivered",
+        }
+    }
+}
+
+/// A change a rewrite rule made to the message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Set {
+        field: String,
+        value: String,
+    },
+    /// The field was set to something that could not be evaluated.
+    Unknown {
+        field: String,
+    },
+    Unset {
+        field: String,
+    },
+    SetTag(String),
+    ClearTag(String),
+}
+
+impl ToJson for Trace {
+    fn to_json(&self) -> Value {
+        json_object! { "paths" => self.paths.to_json(), "destinations" => self.destinations.to_json() }
+    }
+}
+
+impl ToJson for Delivery {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! { "location" => self.location.to_json(), "certain" => self.certain };
+        if let Some(name) = &self.name {
+            value.insert("name", name);
+        }
+        value
+    }
+}
+
+impl ToJson for PathTrace {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "location" => self.location.to_json(),
+            "matched" => self.matched,
+            "certain" => self.certain,
+            "steps" => self.steps.to_json(),
+        };
+        if let Some(skipped) = &self.skipped {
+            value.insert("skipped", skipped);
+        }
+        value
+    }
+}
+
+impl ToJson for Step {
+    fn to_json(&self) -> Value {
+        let mut value = json_object! {
+            "kind" => &self.kind,
+            "location" => self.location.to_json(),
+            "result" => self.result.as_str(),
+        };
+        if let Some(name) = &self.name {
+            value.insert("name", name);
+        }
+        if let Some(reason) = &self.reason {
+            value.insert("reason", reason);
+        }
+        if !self.changes.is_empty() {
+            value.insert("changes", self.changes.to_json());
+        }
+        if !self.paths.is_empty() {
+            value.insert("paths", self.paths.to_json());
+        }
+        value
+    }
+}
+
+impl ToJson for Change {
+    fn to_json(&self) -> Value {
+        match self {
+            Change::Set { field, value } => json_object! { "action" => "set", "name" => field, "value" => value },
+            Change::Unknown { field } => json_object! { "action" => "unknown", "name" => field },
+            Change::Unset { field } => json_object! { "action" => "unset", "name" => field },
+            Change::SetTag(tag) => json_object! { "action" => "set-tag", "name" => tag },
+            Change::ClearTag(tag) => json_object! { "action" => "clear-tag", "name" => tag },
+        }
+    }
+}
+
+impl PathTrace {
+    fn new(uri: &str, range: Range, certain: bool) -> Self {
+        PathTrace {
+            location: location(uri, range),
+            steps: Vec::new(),
+            matched: false,
+            certain,
+            skipped: None,
+        }
+    }
+
+    fn push(&mut self, step: Step) {
+        if step.result == StepResult::Unknown {
+            self.certain = false;
+        }
+        self.steps.push(step);
+    }
+}
+
+impl Step {
+    fn new(kind: &str, name: Option<&str>, location: Location, result: StepResult) -> Self {
+        Step {
+            kind: kind.to_string(),
+            name: name.map(str::to_string),
+            location,
+            result,
+            reason: None,
+            changes: Vec::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    fn verdict(kind: &str, name: Option<&str>, location: Location, verdict: &Verdict) -> Self {
+        let result = match verdict {
+            Verdict::Accepted => StepResult::Accepted,
+            Verdict::Rejected => StepResult::Rejected,
+            Verdict::Unknown(_) => StepResult::Unknown,
+        };
+        let mut step = Step::new(kind, name, location, result);
+        if let Verdict::Unknown(reason) = verdict {
+            step.reason = Some(reason.clone());
+        }
+        step
+    }
+
+    fn unknown(kind: &str, name: Option<&str>, location: Location, reason: String) -> Self {
+        Step::verdict(kind, name, location, &Verdict::Unknown(reason))
+    }
+
+    /// Marks the step unknown, keeping the first reason.
+    fn uncertain(&mut self, reason: String) {
+        self.result = StepResult::Unknown;
+        self.reason.get_or_insert(reason);
+    }
+}
+
+fn location(uri: &str, range: Range) -> Location {
+    Location {
+        uri: uri.to_string(),
+        range,
+    }
+}
+
+/// The name a field is kept under: `MSG` is `MESSAGE` and `PRIORITY` is
+/// `LEVEL`.
+fn canonical_name(name: &str) -> &str {
+    match name {
+        "MSG" => "MESSAGE",
+        "PRIORITY" => "LEVEL",
+        _ => name,
+    }
+}
+
+/// The message along a log path. A field is `None` once a rewrite set it
+/// to something that could not be evaluated.
+#[derive(Debug, Clone)]
+struct Message {
+    fields: BTreeMap<String, Option<String>>,
+    tags: Vec<String>,
+}
+
+impl Message {
+    fn new(sample: &SampleMessage) -> Self {
+        let mut fields: BTreeMap<String, Option<String>> = sample
+            .fields
+            .iter()
+            .map(|(n, v)| (n.clone(), Some(v.clone())))
+            .collect();
+        if let Some(source) = &sample.source {
+            fields
+                .entry("SOURCE".to_string())
+                .or_insert_with(|| Some(source.clone()));
+        }
+        Message {
+            fields,
+            tags: sample.tags.clone(),
+        }
+    }
+
+    /// The value of the macro `name`, or why it is unknown.
+    fn get(&self, name: &str) -> Result<String, String> {
+        let name = canonical_name(name);
+        if let Some(value) = self.fields.get(name) {
+            return value
+                .clone()
+                .ok_or_else(|| format!("`{}` is unknown after a rewrite", name));
+        }
+        match name {
+            "MSGHDR" => {
+                let program = self.get("PROGRAM")?;
+                let pid = self.get("PID")?;
+                Ok(match (program.is_empty(), pid.is_empty()) {
+                    (true, _) => String::new(),
+                    (false, true) => format!("{}: ", program),
+                    (false, false) => format!("{}[{}]: ", program, pid),
+                })
+            }
+            "TAGS" => Ok(self.tags.join(",")),
+            _ if name.chars().all(|c| c.is_ascii_digit()) => {
+                Err(format!("the match group `${}` is not evaluated", name))
+            }
+            _ => Ok(String::new()),
+        }
+    }
+
+    fn set(&mut self, name: &str, value: Option<String>) {
+        self.fields.insert(canonical_name(name).to_string(), value);
+    }
+}
+
+/// The outcome of a filter: `Unknown` (with the reason) when it could not
+/// be evaluated, combined the way three-valued logic does: `Rejected` and
+/// anything is `Rejected`, `Accepted` or anything is `Accepted`.
+#[derive(Debug, Clone, PartialEq)]
+enum Verdict {
+    Accepted,
+    Rejected,
+    Unknown(String),
+}
+
+impl Verdict {
+    fn from_bool(accepted: bool) -> Self {
+        if accepted {
+            Verdict::Accepted
+        } else {
+            Verdict::Rejected
+        }
+    }
+
+    fn not(self) -> Self {
+        match self {
+            Verdict::Accepted => Verdict::Rejected,
+            Verdict::Rejected => Verdict::Accepted,
+            unknown => unknown,
+        }
+    }
+
+    fn and(self, other: Verdict) -> Self {
+        match (self, other) {
+            (Verdict::Rejected, _) | (_, Verdict::Rejected) => Verdict::Rejected,
+            (Verdict::Unknown(reason), _) | (_, Verdict::Unknown(reason)) => Verdict::Unknown(reason),
+            _ => Verdict::Accepted,
+        }
+    }
+
+    fn or(self, other: Verdict) -> Self {
+        match (self, other) {
+            (Verdict::Accepted, _) | (_, Verdict::Accepted) => Verdict::Accepted,
+            (Verdict::Unknown(reason), _) | (_, Verdict::Unknown(reason)) => Verdict::Unknown(reason),
+            _ => Verdict::Rejected,
+        }
+    }
+}
+
+/// Traces `sample` through the log paths of the configuration of `uri`
+/// and the files it includes.
+pub fn trace_message(uri: &str, parsed: &ParsedConfiguration, snippets: &[Snippet], sample: &SampleMessage) -> Trace {
+    let mut tracer = Tracer {
+        objects: BTreeMap::new(),
+        source: sample.source.clone(),
+        destinations: Vec::new(),
+        filters: Vec::new(),
+    };
+    let mut logs = Vec::new();
+    for (file, item) in merged_items(uri, parsed, snippets) {
+        let MergedItem::Object(object) = item else {
+            continue;
+        };
+        match &object.id {
+            Some(id) => {
+                tracer
+                    .objects
+                    .entry((object.kind, id.name.as_str()))
+                    .or_insert((file, object));
+            }
+            None if object.kind == ObjectKind::Log => logs.push((file, object.statements(), object.location)),
+            None => {}
+        }
+    }
+    let (paths, _) = tracer.route(&logs, &Message::new(sample), true, true);
+    Trace {
+        paths,
+        destinations: tracer.destinations,
+    }
+}
+
+struct Tracer<'a> {
+    /// The objects with an identifier, with the file each is in.
+    objects: BTreeMap<(ObjectKind, &'a str), (&'a str, &'a Object)>,
+    source: Option<String>,
+    destinations: Vec<Delivery>,
+    /// The filter objects being evaluated, to stop at `filter()` cycles.
+    filters: Vec<&'a str>,
+}
+
+fn has_flag(statements: &[Statement], flag: &str) -> bool {
+    statements.iter().any(|s| match s {
+        Statement::Driver(driver) if driver.name.name == "flags" => driver.literals().any(|l| {
+            let value = normalize_name(&l.value);
+            value == flag || (flag == "catch-all" && value == "catchall")
+        }),
+        _ => false,
+    })
+}
+
+impl<'a> Tracer<'a> {
+    /// Sends `message` into each of `paths` in turn, honouring their
+    /// `final` and `fallback` flags. Returns the traces, in the order of
+    /// `paths`, and the message as it came out of the first path it went
+    /// all the way through.
+    fn route(
+        &mut self,
+        paths: &[(&'a str, &'a [Statement], Range)],
+        message: &Message,
+        certain: bool,
+        from_source: bool,
+    ) -> (Vec<PathTrace>, Option<Message>) {
+        let mut traces: Vec<Option<PathTrace>> = vec![None; paths.len()];
+        // Fallback paths only get what no other path took, so they go last.
+        let (fallbacks, others): (Vec<usize>, Vec<usize>) =
+            (0..paths.len()).partition(|&i| has_flag(paths[i].1, "fallback"));
+        let mut taken = None;
+        let mut finished = false;
+        for i in others.into_iter().chain(fallbacks) {
+            let (file, statements, range) = paths[i];
+            let mut trace = PathTrace::new(file, range, certain);
+            if finished {
+                trace.skipped = Some("an earlier path with flags(final) took the message".to_string());
+            } else if taken.is_some() && has_flag(statements, "fallback") {
+                trace.skipped = Some("another path took the message".to_string());
+            } else if let Some(reason) = from_source.then(|| self.entry(statements)).flatten() {
+                trace.skipped = Some(reason);
+            } else {
+                let mut copy = message.clone();
+                self.walk(file, statements, &mut copy, &mut trace);
+                if trace.matched {
+                    taken.get_or_insert(copy);
+                    finished = has_flag(statements, "final");
+                }
+            }
+            traces[i] = Some(trace);
+        }
+        (traces.into_iter().flatten().collect(), taken)
+    }
+
+    /// Why a top-level log path does not get the message, if it does not.
+    fn entry(&self, statements: &[Statement]) -> Option<String> {
+        if has_flag(statements, "catch-all") {
+            return None;
+        }
+        let mut named = Vec::new();
+        let mut inline = false;
+        for statement in statements {
+            match statement {
+                Statement::Driver(driver) if driver.name.name == "source" => {
+                    named.extend(driver.literals().map(|l| l.value.as_str()))
+                }
+                Statement::Object(object) if object.kind == ObjectKind::Source => inline = true,
+                _ => {}
+            }
+        }
+        match &self.source {
+            None if named.is_empty() && !inline => Some("the log path reads no source".to_string()),
+            None => None,
+            Some(source) if named.contains(&source.as_str()) => None,
+            Some(source) => Some(format!("the log path does not read from `{}`", source)),
+        }
+    }
+
+    /// Takes `message` along `statements`, recording the steps in `path`.
+    fn walk(&mut self, file: &'a str, statements: &'a [Statement], message: &mut Message, path: &mut PathTrace) {
+        let mut i = 0;
+        while i < statements.len() {
+            let passed = match &statements[i] {
+                Statement::Object(object) if object.kind == ObjectKind::Log => {
+                    // Neighbouring embedded log paths share the message
+                    // like the channels of a junction, but the log path
+                    // goes on after them either way.
+                    let embedded: Vec<_> = statements[i..]
+                        .iter()
+                        .map_while(|s| match s {
+                            Statement::Object(object) if object.kind == ObjectKind::Log => {
+                                Some((file, object.statements(), object.location))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    i += embedded.len() - 1;
+                    let (paths, taken) = self.route(&embedded, message, path.certain, false);
+                    let range = Range::new(embedded[0].2.start, embedded[embedded.len() - 1].2.end);
+                    let result = if taken.is_some() {
+                        StepResult::Accepted
+                    } else {
+                        StepResult::Rejected
+                    };
+                    let mut step = Step::new("log", None, location(file, range), result);
+                    step.paths = paths;
+                    path.push(step);
+                    true
+                }
+                Statement::Object(object) => self.inline(file, object, message, path),
+                Statement::Driver(driver) => self.reference(file, driver, message, path),
+                Statement::Conditional(conditional) => self.conditional(file, conditional, message, path),
+                Statement::Junction(junction) => self.junction(file, junction, message, path),
+            };
+            if !passed {
+                return;
+            }
+            i += 1;
+        }
+        path.matched = true;
+    }
+
+    /// `filter(f_name)`, `destination(d_name)` and the like; `false` if a
+    /// filter rejects the message.
+    fn reference(&mut self, file: &'a str, driver: &'a Driver, message: &mut Message, path: &mut PathTrace) -> bool {
+        let Some(kind) = match_object_kind(&driver.name.name) else {
+            return true;
+        };
+        if !matches!(
+            kind,
+            ObjectKind::Filter | ObjectKind::Rewrite | ObjectKind::Parser | ObjectKind::Destination
+        ) {
+            return true;
+        }
+        for literal in driver.literals() {
+            let name = literal.value.as_str();
+            let at = location(file, literal.range);
+            let Some(&(defined_in, object)) = self.objects.get(&(kind, name)) else {
+                let reason = format!("{} `{}` is not defined", kind, name);
+                path.push(Step::unknown(kind.keyword(), Some(name), at, reason));
+                continue;
+            };
+            match kind {
+                ObjectKind::Filter => {
+                    let verdict = self.filter_object(name, object, message);
+                    path.push(Step::verdict(kind.keyword(), Some(name), at, &verdict));
+                    if verdict == Verdict::Rejected {
+                        return false;
+                    }
+                }
+                ObjectKind::Rewrite => {
+                    let mut step = Step::new(kind.keyword(), Some(name), at, StepResult::Rewritten);
+                    self.rewrite(object.drivers(), message, &mut step);
+                    path.push(step);
+                }
+                ObjectKind::Parser => {
+                    let reason = "parsers are not evaluated".to_string();
+                    path.push(Step::unknown(kind.keyword(), Some(name), at, reason));
+                }
+                _ => {
+                    self.deliver(Some(name), location(defined_in, object.location), path.certain);
+                    path.push(Step::new(kind.keyword(), Some(name), at, StepResult::Delivered));
+                }
+            }
+        }
+        true
+    }
+
+    /// An object defined inside the log path; `false` if it is a filter
+    /// rejecting the message.
+    fn inline(&mut self, file: &'a str, object: &'a Object, message: &mut Message, path: &mut PathTrace) -> bool {
+        let at = location(file, object.location);
+        match object.kind {
+            ObjectKind::Filter => {
+                let ObjectBody::Expression(expression) = &object.body else {
+                    return true;
+                };
+                let verdict = self.evaluate(&expression.root, message);
+                path.push(Step::verdict("filter", None, at, &verdict));
+                verdict != Verdict::Rejected
+            }
+            ObjectKind::Rewrite => {
+                let mut step = Step::new("rewrite", None, at, StepResult::Rewritten);
+                self.rewrite(object.drivers(), message, &mut step);
+                path.push(step);
+                true
+            }
+            ObjectKind::Parser => {
+                path.push(Step::unknown(
+                    "parser",
+                    None,
+                    at,
+                    "parsers are not evaluated".to_string(),
+                ));
+                true
+            }
+            ObjectKind::Destination => {
+                self.deliver(None, at.clone(), path.certain);
+                path.push(Step::new("destination", None, at, StepResult::Delivered));
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// The message takes the first branch it goes all the way through; if
+    /// there is none, it is dropped when there is an `else` and goes on
+    /// unchanged otherwise.
+    fn conditional(
+        &mut self,
+        file: &'a str,
+        conditional: &'a Conditional,
+        message: &mut Message,
+        path: &mut PathTrace,
+    ) -> bool {
+        for branch in &conditional.branches {
+            let at = location(file, branch.location);
+            let verdict = match &branch.condition {
+                Some(condition) => self.evaluate(&condition.root, message),
+                None => Verdict::Accepted,
+            };
+            let mut step = Step::verdict(&branch.keyword.name, None, at, &verdict);
+            if verdict == Verdict::Rejected {
+                path.push(step);
+                continue;
+            }
+            let mut trace = PathTrace::new(file, branch.location, path.certain && verdict == Verdict::Accepted);
+            let mut copy = message.clone();
+            self.walk(file, &branch.statements, &mut copy, &mut trace);
+            let matched = trace.matched;
+            step.paths.push(trace);
+            path.push(step);
+            if matched {
+                *message = copy;
+                return true;
+            }
+        }
+        !conditional.branches.iter().any(|b| b.keyword.name == "else")
+    }
+
+    /// The message goes on as it came out of the first channel it went all
+    /// the way through, and is dropped if there is none.
+    fn junction(&mut self, file: &'a str, junction: &'a Junction, message: &mut Message, path: &mut PathTrace) -> bool {
+        let channels: Vec<_> = junction
+            .channels
+            .iter()
+            .map(|c| (file, c.statements.as_slice(), c.location))
+            .collect();
+        let (paths, taken) = self.route(&channels, message, path.certain, false);
+        let result = if taken.is_some() {
+            StepResult::Accepted
+        } else {
+            StepResult::Rejected
+        };
+        let mut step = Step::new("junction", None, location(file, junction.location), result);
+        step.paths = paths;
+        path.push(step);
+        match taken {
+            Some(taken) => {
+                *message = taken;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn deliver(&mut self, name: Option<&str>, location: Location, certain: bool) {
+        match self.destinations.iter_mut().find(|d| d.location == location) {
+            Some(delivery) => delivery.certain |= certain,
+            None => self.destinations.push(Delivery {
+                name: name.map(str::to_string),
+                location,
+                certain,
+            }),
+        }
+    }
+
+    fn filter_object(&mut self, name: &'a str, object: &'a Object, message: &Message) -> Verdict {
+        if self.filters.contains(&name) {
+            return Verdict::Unknown(format!("filter `{}` refers to itself", name));
+        }
+        let ObjectBody::Expression(expression) = &object.body else {
+            return Verdict::Accepted;
+        };
+        self.filters.push(name);
+        let verdict = self.evaluate(&expression.root, message);
+        self.filters.pop();
+        verdict
+    }
+
+    fn evaluate(&mut self, expression: &'a Expression, message: &Message) -> Verdict {
+        match expression {
+            Expression::Call(call) => self.call(call, message),
+            Expression::Comparison(comparison) => {
+                compare(&comparison.left, &comparison.operator.name, &comparison.right, message)
+            }
+            Expression::Not { operand, .. } => self.evaluate(operand, message).not(),
+            Expression::Binary { operator, left, right } => {
+                let left = self.evaluate(left, message);
+                let right = self.evaluate(right, message);
+                if operator.name == "and" {
+                    left.and(right)
+                } else {
+                    left.or(right)
+                }
+            }
+            Expression::Group { expression, .. } => self.evaluate(expression, message),
+        }
+    }
+
+    /// A filter function such as `level(info..err)` or `filter(f_other)`.
+    fn call(&mut self, call: &'a Driver, message: &Message) -> Verdict {
+        match normalize_name(&call.name.name).as_str() {
+            "level" | "priority" => in_ranges(call, message, "LEVEL", level_number),
+            "facility" => in_ranges(call, message, "FACILITY", facility_number),
+            "program" => pattern(call, Some("PROGRAM"), message),
+            "host" => pattern(call, Some("HOST"), message),
+            "message" => pattern(call, Some("MESSAGE"), message),
+            "match" => pattern(call, None, message),
+            "tags" => Verdict::from_bool(call.literals().any(|l| message.tags.contains(&l.value))),
+            "filter" => {
+                let Some(name) = call.literals().next() else {
+                    return Verdict::Unknown("`filter()` names no filter".to_string());
+                };
+                match self.objects.get(&(ObjectKind::Filter, name.value.as_str())) {
+                    Some(&(_, object)) => self.filter_object(&name.value, object, message),
+                    None => Verdict::Unknown(format!("filter `{}` is not defined", name.value)),
+                }
+            }
+            "source" => match &self.source {
+                Some(source) => Verdict::from_bool(call.literals().any(|l| l.value == *source)),
+                None => Verdict::Unknown("the sample message names no source".to_string()),
+            },
+            "netmask" => netmask(call, message),
+            _ => Verdict::Unknown(format!("`{}()` is not evaluated", call.name.name)),
+        }
+    }
+
+    /// Applies the rules of a rewrite object to `message`, recording what
+    /// they change in `step`.
+    fn rewrite(&mut self, rules: impl Iterator<Item = &'a Driver>, message: &mut Message, step: &mut Step) {
+        for driver in rules {
+            let mut problems = Vec::new();
+            let Some(rule) = RewriteRule::from_driver(driver, &mut problems) else {
+                step.uncertain(format!("`{}()` is not evaluated", driver.name.name));
+                continue;
+            };
+            if let Some(condition) = rule.condition {
+                let mut reader = ConditionReader {
+                    parameters: &condition.parameters,
+                    position: 0,
+                };
+                let verdict = match self.condition_or(&mut reader, message) {
+                    _ if reader.position < reader.parameters.len() => {
+                        Verdict::Unknown("cannot read the condition() of the rule".to_string())
+                    }
+                    verdict => verdict,
+                };
+                match verdict {
+                    Verdict::Accepted => {}
+                    Verdict::Rejected => continue,
+                    Verdict::Unknown(reason) => step.uncertain(reason),
+                }
+            }
+            if let Some(reason) = apply(&rule.operation, message, &mut step.changes) {
+                step.uncertain(reason);
+            }
+        }
+    }
+
+    fn condition_or(&mut self, reader: &mut ConditionReader<'a>, message: &Message) -> Verdict {
+        let mut verdict = self.condition_and(reader, message);
+        while reader.eat("or") {
+            verdict = verdict.or(self.condition_and(reader, message));
+        }
+        verdict
+    }
+
+    fn condition_and(&mut self, reader: &mut ConditionReader<'a>, message: &Message) -> Verdict {
+        let mut verdict = self.condition_not(reader, message);
+        while reader.eat("and") {
+            verdict = verdict.and(self.condition_not(reader, message));
+        }
+        verdict
+    }
+
+    fn condition_not(&mut self, reader: &mut ConditionReader<'a>, message: &Message) -> Verdict {
+        if reader.eat("not") {
+            return self.condition_not(reader, message).not();
+        }
+        let parameters = reader.parameters;
+        match &parameters[reader.position.min(parameters.len())..] {
+            [Parameter::Option(call), ..] => {
+                reader.position += 1;
+                self.call(call, message)
+            }
+            [Parameter::Literal(left), Parameter::Literal(operator), Parameter::Literal(right), ..] => {
+                reader.position += 3;
+                compare(left, &operator.value, right, message)
+            }
+            _ => {
+                reader.position = parameters.len() + 1;
+                Verdict::Unknown("cannot read the condition() of the rule".to_string())
+            }
+        }
+    }
+}
+
+/// `condition()` of a rewrite rule: a filter expression kept as the
+/// parameters of the option, read here the way the filter parser reads
+/// tokens.
+struct ConditionReader<'a> {
+    parameters: &'a [Parameter],
+    position: usize,
+}
+
+impl ConditionReader<'_> {
+    fn eat(&mut self, word: &str) -> bool {
+        let found = matches!(
+            self.parameters.get(self.position),
+            Some(Parameter::Literal(literal)) if literal.kind == LiteralKind::Word && literal.value == word
+        );
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+}
+
+const LEVELS: &[&str] = &["emerg", "alert", "crit", "err", "warning", "notice", "info", "debug"];
+
+const FACILITIES: &[&str] = &[
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+fn level_number(name: &str) -> Option<usize> {
+    match name {
+        "panic" => Some(0),
+        "error" => Some(3),
+        "warn" => Some(4),
+        _ => position_or_number(LEVELS, name),
+    }
+}
+
+fn facility_number(name: &str) -> Option<usize> {
+    position_or_number(FACILITIES, name)
+}
+
+fn position_or_number(names: &[&str], name: &str) -> Option<usize> {
+    names
+        .iter()
+        .position(|n| *n == name)
+        .or_else(|| name.parse().ok().filter(|n| *n < names.len()))
+}
+
+/// `level(info..err)` and `facility(mail, local0)`: whether `field` is one
+/// of the values or in one of the ranges.
+fn in_ranges(call: &Driver, message: &Message, field: &str, number: fn(&str) -> Option<usize>) -> Verdict {
+    let value = match message.get(field) {
+        Ok(value) if value.is_empty() => {
+            return Verdict::Unknown(format!("the sample message has no {}", field));
+        }
+        Ok(value) => value.to_lowercase(),
+        Err(reason) => return Verdict::Unknown(reason),
+    };
+    let Some(actual) = number(&value) else {
+        return Verdict::Unknown(format!("`{}` is not a {}", value, field.to_lowercase()));
+    };
+    for literal in call.literals() {
+        for item in literal.value.split(',').filter(|i| !i.is_empty()) {
+            let (low, high) = item.split_once("..").unwrap_or((item, item));
+            let (Some(low), Some(high)) = (number(low), number(high)) else {
+                return Verdict::Unknown(format!("`{}` is not a {}", item, field.to_lowercase()));
+            };
+            if (low.min(high)..=low.max(high)).contains(&actual) {
+                return Verdict::Accepted;
+            }
+        }
+    }
+    Verdict::Rejected
+}
+
+/// `program()`, `host()`, `message()` and `match()`, which without
+/// `value()` or `template()` looks at the header and the message together.
+fn pattern(call: &Driver, field: Option<&str>, message: &Message) -> Verdict {
+    let Some(pattern) = call.literals().next() else {
+        return Verdict::Unknown(format!("`{}()` has no pattern", call.name.name));
+    };
+    let option = |name: &str| {
+        call.options()
+            .find(|o| normalize_name(&o.name.name) == name)
+            .and_then(|o| o.literals().next())
+    };
+    let text = match (option("template"), option("value"), field) {
+        (Some(template), _, _) => expand(&template.value, message),
+        (None, Some(value), _) => message.get(&value.value),
+        (None, None, Some(field)) => message.get(field),
+        (None, None, None) => message
+            .get("MSGHDR")
+            .and_then(|header| Ok(header + &message.get("MESSAGE")?)),
+    };
+    let text = match text {
+        Ok(text) => text,
+        Err(reason) => return Verdict::Unknown(reason),
+    };
+    let kind = option("type").map_or("pcre".to_string(), |t| t.value.to_lowercase());
+    let flags: Vec<String> = call
+        .options()
+        .filter(|o| normalize_name(&o.name.name) == "flags")
+        .flat_map(|o| o.literals())
+        .map(|l| normalize_name(&l.value))
+        .collect();
+    let flag = |name: &str| flags.iter().any(|f| f == name);
+    let (pattern_text, text) = if flag("ignore-case") && kind != "pcre" && kind != "posix" {
+        (pattern.value.to_lowercase(), text.to_lowercase())
+    } else {
+        (pattern.value.clone(), text)
+    };
+    match kind.as_str() {
+        "string" if flag("prefix") => Verdict::from_bool(text.starts_with(&pattern_text)),
+        "string" if flag("substring") => Verdict::from_bool(text.contains(&pattern_text)),
+        "string" => Verdict::from_bool(text == pattern_text),
+        "glob" => Verdict::from_bool(wildcard_match(&pattern_text, &text)),
+        "pcre" | "posix" => match Regex::new(&pattern_text, flag("ignore-case")) {
+            Ok(regex) => Verdict::from_bool(regex.is_match(&text)),
+            Err(error) => Verdict::Unknown(format!("cannot evaluate the pattern `{}`: {}", pattern_text, error)),
+        },
+        _ => Verdict::Unknown(format!("unknown pattern type `{}`", kind)),
+    }
+}
+
+/// `netmask("10.0.0.0/8")` against `SOURCEIP`.
+fn netmask(call: &Driver, message: &Message) -> Verdict {
+    let Some(literal) = call.literals().next() else {
+        return Verdict::Unknown("`netmask()` has no network".to_string());
+    };
+    let address = match message.get("SOURCEIP") {
+        Ok(address) if address.is_empty() => {
+            return Verdict::Unknown("the sample message has no SOURCEIP".to_string());
+        }
+        Ok(address) => address,
+        Err(reason) => return Verdict::Unknown(reason),
+    };
+    let (network, mask) = literal.value.split_once('/').unwrap_or((&literal.value, "32"));
+    let mask = match mask.parse::<u32>() {
+        Ok(0) => Some(0),
+        Ok(bits) if bits <= 32 => Some(u32::MAX << (32 - bits)),
+        _ => mask.parse::<Ipv4Addr>().ok().map(u32::from),
+    };
+    match (network.parse::<Ipv4Addr>(), address.parse::<Ipv4Addr>(), mask) {
+        (Ok(network), Ok(address), Some(mask)) => {
+            Verdict::from_bool(u32::from(network) & mask == u32::from(address) & mask)
+        }
+        _ => Verdict::Unknown(format!("cannot compare `{}` with `{}`", address, literal.value)),
+    }
+}
+
+/// `"${PID}" == 1` and `"${HOST}" eq "server"`.
+fn compare(left: &Literal, operator: &str, right: &Literal, message: &Message) -> Verdict {
+    let value = |literal: &Literal| match literal.kind {
+        LiteralKind::String => expand(&literal.value, message),
+        LiteralKind::Number | LiteralKind::Word => Ok(literal.value.clone()),
+    };
+    let (left, right) = match (value(left), value(right)) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(reason), _) | (_, Err(reason)) => return Verdict::Unknown(reason),
+    };
+    let ordering = if STRING_COMPARISONS.contains(&operator) || matches!(operator, "===" | "!==") {
+        left.cmp(&right)
+    } else {
+        let number = |text: &str| text.trim().parse::<f64>().ok();
+        match (number(&left), number(&right)) {
+            (Some(l), Some(r)) => l.partial_cmp(&r).unwrap_or(Ordering::Equal),
+            _ => return Verdict::Unknown(format!("`{}` {} `{}` compares numbers", left, operator, right)),
+        }
+    };
+    Verdict::from_bool(match operator {
+        "==" | "===" | "eq" => ordering == Ordering::Equal,
+        "!=" | "!==" | "ne" => ordering != Ordering::Equal,
+        "<" | "lt" => ordering == Ordering::Less,
+        "<=" | "le" => ordering != Ordering::Greater,
+        ">" | "gt" => ordering == Ordering::Greater,
+        ">=" | "ge" => ordering != Ordering::Less,
+        _ => return Verdict::Unknown(format!("unknown operator `{}`", operator)),
+    })
+}
+
+/// The value of `template` for `message`, or why it cannot be evaluated.
+fn expand(template: &str, message: &Message) -> Result<String, String> {
+    let mut problems = Vec::new();
+    let parts = parse_template(template, &mut problems);
+    if let Some(problem) = problems.first() {
+        return Err(format!("cannot read the template `{}`: {}", template, problem.message));
+    }
+    let mut text = String::new();
+    for part in &parts {
+        match part {
+            TemplatePart::Text { text: t, .. } => text.push_str(t),
+            TemplatePart::Macro(reference) => text.push_str(&message.get(&reference.name)?),
+            TemplatePart::Function(function) => {
+                return Err(format!("the template function `$({})` is not evaluated", function.name));
+            }
+        }
+    }
+    Ok(text)
+}
+
+/// Applies one rewrite rule; returns why the result is unknown, if it is.
+/// Rules missing an argument are left out, the diagnostics report them.
+fn apply(operation: &RewriteOperation, message: &mut Message, changes: &mut Vec<Change>) -> Option<String> {
+    let field =
+        |field: &Option<&Literal>| field.map_or("MESSAGE".to_string(), |f| canonical_name(&f.value).to_string());
+    match operation {
+        RewriteOperation::Set {
+            template: Some(template),
+            field: name,
+        } => {
+            let value = expand(&template.value, message);
+            set(message, changes, &field(name), value)
+        }
+        RewriteOperation::Subst {
+            pattern: Some(pattern),
+            replacement: Some(replacement),
+            field: name,
+            pattern_type,
+            flags,
+        } => {
+            let name = field(name);
+            let flag = |wanted: &str| flags.iter().any(|f| normalize_name(&f.value) == wanted);
+            let kind = pattern_type.map_or("pcre".to_string(), |t| t.value.to_lowercase());
+            let value = message.get(&name).and_then(|text| {
+                let replacement = expand(&replacement.value, message)?;
+                let regex = match kind.as_str() {
+                    "string" => Regex::new(&escape(&pattern.value), flag("ignore-case")),
+                    "pcre" | "posix" => Regex::new(&pattern.value, flag("ignore-case")),
+                    _ => Err(format!("{} patterns are not evaluated", kind)),
+                };
+                let regex = regex.map_err(|e| format!("cannot evaluate the pattern `{}`: {}", pattern.value, e))?;
+                Ok(substitute(&regex, &text, &replacement, flag("global")))
+            });
+            set(message, changes, &name, value)
+        }
+        RewriteOperation::Unset { field: Some(name) } => {
+            let name = canonical_name(&name.value).to_string();
+            message.fields.remove(&name);
+            changes.push(Change::Unset { field: name });
+            None
+        }
+        RewriteOperation::Rename {
+            old: Some(old),
+            new: Some(new),
+        } => {
+            let old = canonical_name(&old.value).to_string();
+            let value = message.fields.remove(&old)?;
+            changes.push(Change::Unset { field: old });
+            set(
+                message,
+                changes,
+                &new.value,
+                value.ok_or_else(|| format!("`{}` is unknown", new.value)),
+            )
+        }
+        RewriteOperation::SetTag(Some(tag)) => {
+            if !message.tags.contains(&tag.value) {
+                message.tags.push(tag.value.clone());
+            }
+            changes.push(Change::SetTag(tag.value.clone()));
+            None
+        }
+        RewriteOperation::ClearTag(Some(tag)) => {
+            message.tags.retain(|t| *t != tag.value);
+            changes.push(Change::ClearTag(tag.value.clone()));
+            None
+        }
+        RewriteOperation::Groupset {
+            template: Some(template),
+            fields,
+        } => {
+            let names: Vec<String> = message
+                .fields
+                .keys()
+                .filter(|name| fields.iter().any(|f| wildcard_match(&f.value, name)))
+                .cloned()
+                .collect();
+            let mut unknown = None;
+            for name in names {
+                // `$_` is the value being replaced.
+                let mut scoped = message.clone();
+                scoped.set("_", message.fields[&name].clone());
+                let value = expand(&template.value, &scoped);
+                unknown = unknown.or(set(message, changes, &name, value));
+            }
+            unknown
+        }
+        _ => None,
+    }
+}
+
+fn set(message: &mut Message, changes: &mut Vec<Change>, field: &str, value: Result<String, String>) -> Option<String> {
+    let field = canonical_name(field).to_string();
+    match value {
+        Ok(value) => {
+            message.set(&field, Some(value.clone()));
+            changes.push(Change::Set { field, value });
+            None
+        }
+        Err(reason) => {
+            message.set(&field, None);
+            changes.push(Change::Unknown { field });
+            Some(reason)
+        }
+    }
+}
+
+/// `text` matching itself as a regular expression.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\.^$|?*+()[]{}".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn substitute(regex: &Regex, text: &str, replacement: &str, global: bool) -> String {
+    let mut result = String::new();
+    let mut copied = 0;
+    let mut from = 0;
+    while let Some(found) = regex.find_from(text, from) {
+        result.push_str(&text[copied..found.start]);
+        result.push_str(replacement);
+        copied = found.end;
+        if !global {
+            break;
+        }
+        from = match text[found.end..].chars().next() {
+            _ if found.end > found.start => found.end,
+            Some(c) => found.end + c.len_utf8(),
+            None => break,
+        };
+    }
+    result.push_str(&text[copied..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use crate::parser::parse_conf;
+
+    fn trace(config: &str, sample: &str) -> Trace {
+        let parsed = parse_conf(config).unwrap();
+        let sample = SampleMessage::from_json(&json::parse(sample).unwrap()).unwrap();
+        trace_message("file:///syslog-ng.conf", &parsed, &[], &sample)
+    }
+
+    fn delivered(trace: &Trace) -> Vec<(&str, bool)> {
+        trace
+            .destinations
+            .iter()
+            .map(|d| (d.name.as_deref().unwrap_or("inline"), d.certain))
+            .collect()
+    }
+
+    #[test]
+    fn follows_filters_and_final_paths() {
+        let config = r#"
+filter f_errors { level(err..emerg) and program("^sshd"); };
+log { source(s_net); filter(f_errors); destination(d_alerts); flags(final); };
+log { source(s_net); destination(d_all); };
+log { source(s_local); destination(d_local); };
+destination d_alerts { file("/var/log/alerts"); };
+destination d_all { file("/var/log/all"); };
+"#;
+        let error = r#"{ "fields": { "LEVEL": "crit", "PROGRAM": "sshd" }, "source": "s_net" }"#;
+        let traced = trace(config, error);
+        assert_eq!(delivered(&traced), [("d_alerts", true)]);
+        assert_eq!(
+            traced.paths[1].skipped.as_deref(),
+            Some("an earlier path with flags(final) took the message")
+        );
+
+        let notice = r#"{ "fields": { "LEVEL": "notice", "PROGRAM": "sshd" }, "source": "s_net" }"#;
+        let traced = trace(config, notice);
+        assert_eq!(delivered(&traced), [("d_all", true)]);
+        assert_eq!(traced.paths[0].steps[0].result, StepResult::Rejected);
+        assert!(!traced.paths[0].matched);
+        assert_eq!(
+            traced.paths[2].skipped.as_deref(),
+            Some("the log path does not read from `s_net`")
+        );
+    }
+
+    #[test]
+    fn gives_fallback_paths_what_no_other_path_took() {
+        let config = r#"
+log { source(s); destination(d_rest); flags(fallback); };
+log { source(s); filter { host("web*" type(glob)); }; destination(d_web); };
+destination d_rest { file("/var/log/rest"); };
+destination d_web { file("/var/log/web"); };
+"#;
+        assert_eq!(
+            delivered(&trace(config, r#"{ "fields": { "HOST": "web1" } }"#)),
+            [("d_web", true)]
+        );
+        let traced = trace(config, r#"{ "fields": { "HOST": "db1" } }"#);
+        assert_eq!(delivered(&traced), [("d_rest", true)]);
+        assert!(traced.paths[0].matched);
+    }
+
+    #[test]
+    fn rewrites_the_message_for_what_comes_after() {
+        let config = r#"
+rewrite r_host { subst("\.example\.com$", "" value("HOST")); set-tag("short"); };
+log {
+    source(s);
+    rewrite(r_host);
+    rewrite { set("${HOST}: $MSG" condition(tags("short"))); };
+    if ("${HOST}" eq "web1") { destination(d_web); } else { destination(d_other); };
+    filter { message("^web1: up"); };
+    destination(d_up);
+};
+destination d_web { file("/var/log/web"); };
+destination d_other { file("/var/log/other"); };
+destination d_up { file("/var/log/up"); };
+"#;
+        let traced = trace(config, r#"{ "fields": { "HOST": "web1.example.com", "MSG": "up" } }"#);
+        assert_eq!(delivered(&traced), [("d_web", true), ("d_up", true)]);
+        let steps = &traced.paths[0].steps;
+        assert_eq!(
+            steps[0].changes,
+            [
+                Change::Set {
+                    field: "HOST".to_string(),
+                    value: "web1".to_string()
+                },
+                Change::SetTag("short".to_string())
+            ]
+        );
+        assert_eq!(
+            steps[1].changes,
+            [Change::Set {
+                field: "MESSAGE".to_string(),
+                value: "web1: up".to_string()
+            }]
+        );
+        assert_eq!(steps[2].kind, "if");
+        assert_eq!(steps[2].result, StepResult::Accepted);
+    }
+
+    #[test]
+    fn takes_the_first_channel_of_a_junction_the_message_gets_through() {
+        let config = r#"
+log {
+    source(s);
+    junction {
+        channel { filter { facility(mail); }; destination(d_mail); flags(final); };
+        channel { filter { facility(auth, authpriv); }; destination(d_auth); flags(final); };
+    };
+    destination(d_all);
+};
+destination d_mail { file("/var/log/mail"); };
+destination d_auth { file("/var/log/auth"); };
+destination d_all { file("/var/log/all"); };
+"#;
+        let traced = trace(config, r#"{ "fields": { "FACILITY": "authpriv" } }"#);
+        assert_eq!(delivered(&traced), [("d_auth", true), ("d_all", true)]);
+        let traced = trace(config, r#"{ "fields": { "FACILITY": "kern" } }"#);
+        assert!(traced.destinations.is_empty());
+        assert_eq!(traced.paths[0].steps[0].result, StepResult::Rejected);
+    }
+
+    #[test]
+    fn reports_what_it_cannot_evaluate() {
+        let config = r#"
+log {
+    source(s);
+    parser { kv-parser(); };
+    filter { in-list("/etc/hosts.allow" value("HOST")); };
+    rewrite { set("$(uppercase $HOST)" value("HOST")); };
+    destination { file("/var/log/$HOST"); };
+    filter { host("web"); };
+    destination(d_undefined);
+};
+"#;
+        let traced = trace(config, r#"{ "fields": { "HOST": "web" } }"#);
+        let path = &traced.paths[0];
+        let reasons: Vec<_> = path.steps.iter().filter_map(|s| s.reason.as_deref()).collect();
+        assert_eq!(
+            reasons,
+            [
+                "parsers are not evaluated",
+                "`in-list()` is not evaluated",
+                "the template function `$(uppercase)` is not evaluated",
+                "`HOST` is unknown after a rewrite",
+                "destination `d_undefined` is not defined",
+            ]
+        );
+        assert!(path.matched && !path.certain);
+        assert_eq!(delivered(&traced), [("inline", false)]);
+    }
+
+    #[test]
+    fn reads_the_sample_message() {
+        let value = json::parse(r#"{ "fields": { "MSG": "up", "PID": 42 }, "tags": ["a"] }"#).unwrap();
+        let sample = SampleMessage::from_json(&value).unwrap();
+        assert_eq!(sample.fields["MESSAGE"], "up");
+        assert_eq!(sample.fields["PID"], "42");
+        assert_eq!(sample.tags, ["a"]);
+        assert_eq!(sample.source, None);
+        assert!(SampleMessage::from_json(&json::parse(r#"{ "tags": [] }"#).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,148 @@
+//! JSON-RPC framing (`Content-Length` headers) and the outgoing client handle.
+
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::json::{self, Value};
+use crate::json_object;
+use crate::log_event;
+use crate::logging::{self, Level};
+use crate::lsp_types::{Diagnostic, MessageType, Range, ToJson, WorkspaceEdit};
+
+/// Reads one framed message. Returns `Ok(None)` when the input is closed.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    let text = String::from_utf8(body).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    json::parse(&text)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message<W: Write + ?Sized>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+/// Handle for sending notifications and responses to the editor.
+#[derive(Clone)]
+pub struct Client {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+    next_request_id: Arc<AtomicI64>,
+}
+
+impl Client {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Client {
+            writer: Arc::new(Mutex::new(writer)),
+            next_request_id: Arc::new(AtomicI64::new(1)),
+        }
+    }
+
+    pub fn send(&self, message: &Value) {
+        log_event!(Level::Trace, "sent {}", message);
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        // A broken pipe means the editor is gone; the read loop will notice.
+        let _ = write_message(&mut **writer, message);
+    }
+
+    pub fn send_notification(&self, method: &str, params: Value) {
+        self.send(&json_object! {
+            "jsonrpc" => "2.0",
+            "method" => method,
+            "params" => params,
+        });
+    }
+
+    /// Sends a server-to-client request. Replies are not awaited.
+    pub fn send_request(&self, method: &str, params: Value) {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        self.send(&json_object! {
+            "jsonrpc" => "2.0",
+            "id" => id,
+            "method" => method,
+            "params" => params,
+        });
+    }
+
+    /// Registers `method` dynamically, e.g. file watchers for
+    /// `workspace/didChangeWatchedFiles`.
+    pub fn register_capability(&self, id: &str, method: &str, register_options: Value) {
+        self.send_request(
+            "client/registerCapability",
+            json_object! {
+                "registrations" => vec![json_object! {
+                    "id" => id,
+                    "method" => method,
+                    "registerOptions" => register_options,
+                }],
+            },
+        );
+    }
+
+    pub fn apply_edit(&self, label: &str, edit: &WorkspaceEdit) {
+        self.send_request(
+            "workspace/applyEdit",
+            json_object! { "label" => label, "edit" => edit.to_json() },
+        );
+    }
+
+    /// Asks the client to open `uri` with `selection` selected.
+    pub fn show_document(&self, uri: &str, selection: Range) {
+        self.send_request(
+            "window/showDocument",
+            json_object! { "uri" => uri, "takeFocus" => true, "selection" => selection.to_json() },
+        );
+    }
+
+    /// Shows `message` in the log of the client and writes it to the log
+    /// of the server.
+    pub fn log_message(&self, typ: MessageType, message: impl Into<String>) {
+        let message = message.into();
+        logging::write(typ.into(), module_path!(), &message);
+        self.send_notification(
+            "window/logMessage",
+            json_object! { "type" => typ as u32, "message" => message },
+        );
+    }
+
+    pub fn show_message(&self, typ: MessageType, message: impl Into<String>) {
+        self.send_notification(
+            "window/showMessage",
+            json_object! { "type" => typ as u32, "message" => message.into() },
+        );
+    }
+
+    pub fn publish_diagnostics(&self, uri: &str, diagnostics: &[Diagnostic], version: Option<i64>) {
+        let mut params = json_object! {
+            "uri" => uri,
+            "diagnostics" => diagnostics.to_vec().to_json(),
+        };
+        if let Some(version) = version {
+            params.insert("version", version);
+        }
+        self.send_notification("textDocument/publishDiagnostics", params);
+    }
+}